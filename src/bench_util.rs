@@ -0,0 +1,470 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Workloads in the style of RocksDB's `db_bench`, to compare the performance
+//! of versions of this crate or of options without leaving Rust. Enabled by the
+//! `bench-util` feature.
+//!
+//! ```
+//! use rocksdb::bench_util::{Workload, WorkloadRunner};
+//! use rocksdb::DB;
+//!
+//! let tempdir = tempfile::Builder::new()
+//!     .prefix("_path_for_bench_util")
+//!     .tempdir()
+//!     .expect("Failed to create temporary path for the _path_for_bench_util");
+//! let db = DB::open_default(tempdir.path()).unwrap();
+//!
+//! let mut runner = WorkloadRunner::new(Workload::FillSeq);
+//! runner.set_num_keys(1000);
+//! println!("{}", runner.run(&db).unwrap());
+//!
+//! let mut runner = WorkloadRunner::new(Workload::ReadRandom);
+//! runner.set_num_keys(1000);
+//! runner.set_threads(2);
+//! let report = runner.run(&db).unwrap();
+//! assert_eq!(report.found, 1000);
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{
+    db::DBInner,
+    statistics::{Statistics, Ticker},
+    DBCommon, Error, ThreadMode, WriteOptions,
+};
+
+/// The operations a [`WorkloadRunner`] measures.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Workload {
+    /// Writes the keys in ascending order.
+    FillSeq,
+    /// Writes keys picked at random, so some are written more than once.
+    FillRandom,
+    /// Reads keys picked at random.
+    ReadRandom,
+    /// Reads keys picked at random while one more thread overwrites keys picked
+    /// at random. Only the reads are measured.
+    ReadWhileWriting,
+}
+
+impl Workload {
+    /// Returns the name `db_bench` gives the workload.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Workload::FillSeq => "fillseq",
+            Workload::FillRandom => "fillrandom",
+            Workload::ReadRandom => "readrandom",
+            Workload::ReadWhileWriting => "readwhilewriting",
+        }
+    }
+
+    fn writes(self) -> bool {
+        matches!(self, Workload::FillSeq | Workload::FillRandom)
+    }
+}
+
+/// Runs a [`Workload`] against a DB and reports its throughput and latencies.
+///
+/// Key `i` of the key space is `i` in decimal, padded with zeros to the key size.
+/// Values are random bytes.
+#[derive(Clone)]
+pub struct WorkloadRunner {
+    workload: Workload,
+    key_size: usize,
+    value_size: usize,
+    num_keys: u64,
+    num_ops: Option<u64>,
+    threads: usize,
+    rate_limit: u64,
+    write_rate_limit: u64,
+    disable_wal: bool,
+    seed: u64,
+    statistics: Option<Statistics>,
+}
+
+impl WorkloadRunner {
+    pub fn new(workload: Workload) -> Self {
+        Self {
+            workload,
+            key_size: 16,
+            value_size: 100,
+            num_keys: 10_000,
+            num_ops: None,
+            threads: 1,
+            rate_limit: 0,
+            write_rate_limit: 0,
+            disable_wal: false,
+            seed: 301,
+            statistics: None,
+        }
+    }
+
+    /// Sets the size of the keys in bytes, which must fit the largest key of the
+    /// key space.
+    ///
+    /// Default: 16
+    pub fn set_key_size(&mut self, size: usize) {
+        self.key_size = size;
+    }
+
+    /// Sets the size of the values in bytes.
+    ///
+    /// Default: 100
+    pub fn set_value_size(&mut self, size: usize) {
+        self.value_size = size;
+    }
+
+    /// Sets the number of keys of the key space. Values below 1 are treated as 1.
+    ///
+    /// Default: 10000
+    pub fn set_num_keys(&mut self, num_keys: u64) {
+        self.num_keys = num_keys.max(1);
+    }
+
+    /// Sets the number of operations measured, shared by the threads.
+    ///
+    /// Default: the number of keys
+    pub fn set_num_ops(&mut self, num_ops: u64) {
+        self.num_ops = Some(num_ops);
+    }
+
+    /// Sets the number of threads running the measured operations. A value of 0 is
+    /// treated as 1.
+    ///
+    /// Default: 1
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Sets the number of measured operations per second of all threads together,
+    /// 0 for no limit.
+    ///
+    /// Default: 0
+    pub fn set_rate_limit(&mut self, ops_per_sec: u64) {
+        self.rate_limit = ops_per_sec;
+    }
+
+    /// Sets the number of writes per second of the writing thread of
+    /// [`Workload::ReadWhileWriting`], 0 for no limit.
+    ///
+    /// Default: 0
+    pub fn set_write_rate_limit(&mut self, ops_per_sec: u64) {
+        self.write_rate_limit = ops_per_sec;
+    }
+
+    /// Sets whether the writes skip the WAL.
+    ///
+    /// Default: false
+    pub fn set_disable_wal(&mut self, disable: bool) {
+        self.disable_wal = disable;
+    }
+
+    /// Sets the seed the random keys and values are generated from. Runs with the
+    /// same seed and number of threads pick the same keys.
+    ///
+    /// Default: 301
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Sets the statistics the DB was opened with, e.g. from
+    /// [`Options::with_statistics`], to report how their tickers changed during
+    /// the run.
+    ///
+    /// [`Options::with_statistics`]: crate::Options::with_statistics
+    pub fn set_statistics(&mut self, statistics: &Statistics) {
+        self.statistics = Some(statistics.clone());
+    }
+
+    /// Runs the workload against the default column family of `db`, returning
+    /// once all operations are done. Fails with the first error of an operation.
+    ///
+    /// The latency of each operation is kept until the end of the run, which takes
+    /// 8 bytes of memory per operation.
+    pub fn run<T: ThreadMode, D: DBInner>(
+        &self,
+        db: &DBCommon<T, D>,
+    ) -> Result<WorkloadReport, Error> {
+        let max_key = self.num_keys - 1;
+        if max_key.to_string().len() > self.key_size {
+            return Err(Error::new(format!(
+                "Invalid argument: key {max_key} does not fit in {} bytes",
+                self.key_size
+            )));
+        }
+        let num_ops = self.num_ops.unwrap_or(self.num_keys);
+        let threads = self.threads as u64;
+        let tickers_before = self.statistics.as_ref().map(ticker_counts);
+
+        let stop = AtomicBool::new(false);
+        let started = Instant::now();
+        let (results, background_writes) = thread::scope(|scope| {
+            let writer = (self.workload == Workload::ReadWhileWriting)
+                .then(|| scope.spawn(|| self.write_until(db, &stop)));
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| {
+                    // the first threads take the remainder
+                    let ops = num_ops / threads + u64::from(thread < num_ops % threads);
+                    let first_op = thread * (num_ops / threads) + thread.min(num_ops % threads);
+                    scope.spawn(move || self.run_thread(db, thread, first_op, ops))
+                })
+                .collect();
+            let results: Vec<_> = workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect();
+            stop.store(true, Ordering::Relaxed);
+            let background_writes = writer.map(|writer| writer.join().unwrap());
+            (results, background_writes)
+        });
+        let elapsed = started.elapsed();
+
+        let mut latencies = Vec::with_capacity(num_ops as usize);
+        let mut found = 0;
+        for result in results {
+            let result = result?;
+            latencies.extend(result.latencies);
+            found += result.found;
+        }
+        latencies.sort_unstable();
+        let percentile = |pct: usize| -> Duration {
+            let rank = (latencies.len() * pct + 99) / 100;
+            Duration::from_nanos(latencies.get(rank.saturating_sub(1)).copied().unwrap_or(0))
+        };
+
+        let tickers = match (&self.statistics, tickers_before) {
+            (Some(statistics), Some(before)) => Ticker::iter()
+                .zip(ticker_counts(statistics).into_iter().zip(before))
+                .filter(|(_, (after, before))| after > before)
+                .map(|(ticker, (after, before))| (*ticker, after - before))
+                .collect(),
+            _ => vec![],
+        };
+
+        Ok(WorkloadReport {
+            workload: self.workload,
+            ops: latencies.len() as u64,
+            found,
+            background_writes: background_writes.transpose()?.unwrap_or(0),
+            elapsed,
+            latency: LatencyPercentiles {
+                p50: percentile(50),
+                p95: percentile(95),
+                p99: percentile(99),
+                max: percentile(100),
+            },
+            tickers,
+        })
+    }
+
+    fn run_thread<T: ThreadMode, D: DBInner>(
+        &self,
+        db: &DBCommon<T, D>,
+        thread: u64,
+        first_op: u64,
+        ops: u64,
+    ) -> Result<ThreadResult, Error> {
+        let mut rng = Rng::new(self.seed, thread);
+        let write_opts = self.write_options();
+        let rate_limit = (self.rate_limit + self.threads as u64 - 1) / self.threads as u64;
+        let mut value = vec![0; self.value_size];
+        let mut result = ThreadResult {
+            latencies: Vec::with_capacity(ops as usize),
+            found: 0,
+        };
+
+        let started = Instant::now();
+        for op in 0..ops {
+            pace(started, op, rate_limit);
+            let key = match self.workload {
+                Workload::FillSeq => self.key((first_op + op) % self.num_keys),
+                _ => self.key(rng.below(self.num_keys)),
+            };
+            if self.workload.writes() {
+                rng.fill(&mut value);
+            }
+
+            let op_started = Instant::now();
+            if self.workload.writes() {
+                db.put_opt(&key, &value, &write_opts)?;
+            } else if db.get_pinned(&key)?.is_some() {
+                result.found += 1;
+            }
+            result
+                .latencies
+                .push(op_started.elapsed().as_nanos() as u64);
+        }
+        Ok(result)
+    }
+
+    /// Overwrites random keys until `stop` is set, returning the number of writes.
+    fn write_until<T: ThreadMode, D: DBInner>(
+        &self,
+        db: &DBCommon<T, D>,
+        stop: &AtomicBool,
+    ) -> Result<u64, Error> {
+        let mut rng = Rng::new(self.seed, u64::MAX);
+        let write_opts = self.write_options();
+        let mut value = vec![0; self.value_size];
+        let started = Instant::now();
+        let mut writes = 0;
+        // at least one write, however fast the readers are
+        loop {
+            rng.fill(&mut value);
+            db.put_opt(self.key(rng.below(self.num_keys)), &value, &write_opts)?;
+            writes += 1;
+            if stop.load(Ordering::Relaxed) {
+                return Ok(writes);
+            }
+            pace(started, writes, self.write_rate_limit);
+        }
+    }
+
+    fn key(&self, index: u64) -> String {
+        format!("{index:0width$}", width = self.key_size)
+    }
+
+    fn write_options(&self) -> WriteOptions {
+        let mut write_opts = WriteOptions::default();
+        write_opts.disable_wal(self.disable_wal);
+        write_opts
+    }
+}
+
+struct ThreadResult {
+    latencies: Vec<u64>,
+    found: u64,
+}
+
+/// Sleeps until `done` operations are due since `started` at `ops_per_sec`.
+fn pace(started: Instant, done: u64, ops_per_sec: u64) {
+    if ops_per_sec == 0 {
+        return;
+    }
+    let due =
+        Duration::from_nanos((u128::from(done) * 1_000_000_000 / u128::from(ops_per_sec)) as u64);
+    if let Some(wait) = due.checked_sub(started.elapsed()) {
+        thread::sleep(wait);
+    }
+}
+
+fn ticker_counts(statistics: &Statistics) -> Vec<u64> {
+    Ticker::iter()
+        .map(|ticker| statistics.get_ticker_count(*ticker))
+        .collect()
+}
+
+/// A xorshift64* generator, one per thread.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64, stream: u64) -> Self {
+        // splitmix64 spreads the seeds of the threads apart, xorshift needs a state other than 0
+        let mut z =
+            (seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15)).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn fill(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next().to_le_bytes()[..chunk.len()]);
+        }
+    }
+}
+
+/// Latencies of the operations of a run, by percentile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyPercentiles {
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+/// The results of [`WorkloadRunner::run`]. Displayed like a line of `db_bench`.
+#[derive(Debug, Clone)]
+pub struct WorkloadReport {
+    pub workload: Workload,
+    /// Number of measured operations: writes for the fill workloads, reads for
+    /// the others
+    pub ops: u64,
+    /// Number of the reads that found their key
+    pub found: u64,
+    /// Number of writes of the writing thread of [`Workload::ReadWhileWriting`]
+    pub background_writes: u64,
+    /// Wall time of the run
+    pub elapsed: Duration,
+    pub latency: LatencyPercentiles,
+    /// The tickers of the statistics set with
+    /// [`set_statistics`](WorkloadRunner::set_statistics) that grew during the
+    /// run, with how much. Other DBs sharing the statistics count too.
+    pub tickers: Vec<(Ticker, u64)>,
+}
+
+impl WorkloadReport {
+    #[allow(clippy::cast_precision_loss)]
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns how much `ticker` grew during the run, 0 if no statistics were set.
+    pub fn ticker(&self, ticker: Ticker) -> u64 {
+        self.tickers
+            .iter()
+            .find(|(t, _)| *t == ticker)
+            .map_or(0, |(_, count)| *count)
+    }
+}
+
+impl fmt::Display for WorkloadReport {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let micros = |d: Duration| d.as_secs_f64() * 1e6;
+        write!(
+            f,
+            "{:<16} : {:>10.3} micros/op {:.0} ops/sec {:.3} seconds {} operations;",
+            self.workload.name(),
+            micros(self.elapsed) / self.ops.max(1) as f64,
+            self.ops_per_sec(),
+            self.elapsed.as_secs_f64(),
+            self.ops,
+        )?;
+        if !self.workload.writes() {
+            write!(f, " ({} of {} found)", self.found, self.ops)?;
+        }
+        write!(
+            f,
+            " P50: {:.3} P95: {:.3} P99: {:.3} Max: {:.3} micros",
+            micros(self.latency.p50),
+            micros(self.latency.p95),
+            micros(self.latency.p99),
+            micros(self.latency.max),
+        )
+    }
+}
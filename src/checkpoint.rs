@@ -17,17 +17,40 @@
 //!
 //! [1]: https://github.com/facebook/rocksdb/wiki/Checkpoints
 
-use crate::{db::DBInner, ffi, ffi_util::to_cpath, DBCommon, Error, ThreadMode};
-use std::{marker::PhantomData, path::Path};
+use crate::{db::DBInner, ffi, ffi_util::to_cpath, AsColumnFamilyRef, DBCommon, Error, ThreadMode};
+use std::path::{Path, PathBuf};
 
 /// Undocumented parameter for `ffi::rocksdb_checkpoint_create` function. Zero by default.
 const LOG_SIZE_FOR_FLUSH: u64 = 0_u64;
 
+/// The SST files holding the data of an exported column family, as consumed by
+/// [`DBWithThreadMode::create_cf_with_import`].
+///
+/// [`DBWithThreadMode::create_cf_with_import`]: crate::DBWithThreadMode::create_cf_with_import
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportImportFilesMetaData {
+    /// Name of the comparator the keys of the files are sorted with.
+    pub db_comparator_name: String,
+    /// Paths of the SST files, whose key ranges must not overlap.
+    pub files: Vec<PathBuf>,
+}
+
+/// Dumps the data of a column family into SST files, implemented by the
+/// database a [`Checkpoint`] is created for.
+pub(crate) trait DumpColumnFamily {
+    fn dump_column_family(
+        &self,
+        cf: &dyn AsColumnFamilyRef,
+        export_dir: &Path,
+    ) -> Result<ExportImportFilesMetaData, Error>;
+}
+
 /// Database's checkpoint object.
 /// Used to create checkpoints of the specified DB from time to time.
 pub struct Checkpoint<'db> {
     inner: *mut ffi::rocksdb_checkpoint_t,
-    _db: PhantomData<&'db ()>,
+    db: &'db dyn DumpColumnFamily,
 }
 
 impl<'db> Checkpoint<'db> {
@@ -48,7 +71,7 @@ impl<'db> Checkpoint<'db> {
 
         Ok(Self {
             inner: checkpoint,
-            _db: PhantomData,
+            db,
         })
     }
 
@@ -64,6 +87,26 @@ impl<'db> Checkpoint<'db> {
         }
         Ok(())
     }
+
+    /// Dumps the data of the column family `cf` into SST files in `dump_dir`,
+    /// which must not exist yet, returning the metadata to recreate the column
+    /// family with [`DBWithThreadMode::create_cf_with_import`], e.g. in another DB.
+    ///
+    /// This is not RocksDB's `Checkpoint::ExportColumnFamily`, which the C API does
+    /// not expose: the files are written from an iterator over `cf` rather than
+    /// linked from the live SST files of the column family, so the dump takes time
+    /// and space linear in its size. Only column families using the default
+    /// bytewise comparator can be dumped. An empty column family dumps no files.
+    /// `dump_dir` is removed again if the dump fails.
+    ///
+    /// [`DBWithThreadMode::create_cf_with_import`]: crate::DBWithThreadMode::create_cf_with_import
+    pub fn dump_column_family<P: AsRef<Path>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        dump_dir: P,
+    ) -> Result<ExportImportFilesMetaData, Error> {
+        self.db.dump_column_family(cf, dump_dir.as_ref())
+    }
 }
 
 impl<'db> Drop for Checkpoint<'db> {
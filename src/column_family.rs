@@ -12,9 +12,12 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{db::MultiThreaded, ffi, Options};
+use crate::{db::MultiThreaded, db_options::OptionsMustOutliveDB, ffi, Options};
 
-use std::sync::Arc;
+use libc::{c_void, size_t};
+use std::slice;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
 
 /// The name of the default column family.
 ///
@@ -22,6 +25,9 @@ use std::sync::Arc;
 /// families are used.
 pub const DEFAULT_COLUMN_FAMILY_NAME: &str = "default";
 
+/// The id RocksDB gives the default column family.
+pub(crate) const DEFAULT_COLUMN_FAMILY_ID: u32 = 0;
+
 /// A descriptor for a RocksDB column family.
 ///
 /// A description of the column family, containing the name and `Options`.
@@ -55,12 +61,15 @@ pub struct ColumnFamily {
 }
 
 /// A specialized opaque type used to represent a column family by the [`MultiThreaded`]
-/// mode. Clone (and Copy) is derived to behave like `&ColumnFamily` (this is used for
-/// single-threaded mode). `Clone`/`Copy` is safe because this lifetime is bound to DB like
-/// iterators/snapshots. On top of it, this is as cheap and small as `&ColumnFamily` because
-/// this only has a single pointer-wide field.
+/// mode. It is handed out behind an `Arc`, which plays the role of `&ColumnFamily` in the
+/// single-threaded mode, and its lifetime is bound to DB like iterators/snapshots. Besides
+/// the handle, it records whether the column family has been dropped and keeps the options
+/// it was opened with alive until the handle itself is destroyed.
+#[repr(C)]
 pub struct BoundColumnFamily<'a> {
     pub(crate) inner: *mut ffi::rocksdb_column_family_handle_t,
+    pub(crate) dropped: AtomicBool,
+    pub(crate) outlive: OnceLock<OptionsMustOutliveDB>,
     pub(crate) multi_threaded_cfs: std::marker::PhantomData<&'a MultiThreaded>,
 }
 
@@ -68,11 +77,38 @@ pub struct BoundColumnFamily<'a> {
 // but its memory will be exposed after transmute()-ing to BoundColumnFamily.
 // ColumnFamily's lifetime should be bound to DB. But, db holds cfs and cfs can't easily
 // self-reference DB as its lifetime due to rust's type system
-pub(crate) struct UnboundColumnFamily {
+// `pub` only to appear in `AsColumnFamilyRef::shared_handle`, the module is private.
+#[repr(C)]
+pub struct UnboundColumnFamily {
     pub(crate) inner: *mut ffi::rocksdb_column_family_handle_t,
+    // Set by `drop_cf`, while handles to the column family may still be alive.
+    pub(crate) dropped: AtomicBool,
+    // Set by `drop_cf` to what RocksDB borrows from the options the column family
+    // was created with, which the handles and iterators still alive may use.
+    pub(crate) outlive: OnceLock<OptionsMustOutliveDB>,
 }
 
 impl UnboundColumnFamily {
+    pub(crate) fn new(inner: *mut ffi::rocksdb_column_family_handle_t) -> Self {
+        Self {
+            inner,
+            dropped: AtomicBool::new(false),
+            outlive: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn mark_dropped(&self) {
+        self.dropped.store(true, Ordering::Release);
+    }
+
+    /// Keeps `outlive`, released by the DB when the column family was dropped,
+    /// until the handle is destroyed.
+    pub(crate) fn retain_options(&self, outlive: Option<OptionsMustOutliveDB>) {
+        if let Some(outlive) = outlive {
+            let _ = self.outlive.set(outlive);
+        }
+    }
+
     pub(crate) fn bound_column_family<'a>(self: Arc<Self>) -> Arc<BoundColumnFamily<'a>> {
         // SAFETY: the new BoundColumnFamily here just adding lifetime,
         // so that column family handle won't outlive db.
@@ -88,6 +124,43 @@ fn destroy_handle(handle: *mut ffi::rocksdb_column_family_handle_t) {
     }
 }
 
+/// Returns the name of the column family behind `handle`.
+pub(crate) fn column_family_name(handle: *mut ffi::rocksdb_column_family_handle_t) -> String {
+    unsafe {
+        let mut name_len: size_t = 0;
+        let name = ffi::rocksdb_column_family_handle_get_name(handle, &mut name_len);
+        let result = String::from_utf8_lossy(slice::from_raw_parts(name as *const u8, name_len))
+            .into_owned();
+        ffi::rocksdb_free(name as *mut c_void);
+        result
+    }
+}
+
+/// Returns the id of the column family behind `handle`, the one written batches
+/// refer to it with.
+pub(crate) fn column_family_id(handle: *mut ffi::rocksdb_column_family_handle_t) -> u32 {
+    unsafe { ffi::rocksdb_column_family_handle_get_id(handle) }
+}
+
+impl ColumnFamily {
+    /// Returns the id of the column family, the one its operations are written
+    /// with in a [`WriteBatch`](crate::WriteBatch), see
+    /// [`DBCommon::cf_handle_by_id`](crate::DBCommon::cf_handle_by_id).
+    ///
+    /// Ids are never reused within a DB: a column family dropped and created
+    /// again under the same name gets a new id.
+    pub fn id(&self) -> u32 {
+        column_family_id(self.inner)
+    }
+}
+
+impl<'a> BoundColumnFamily<'a> {
+    /// Returns the id of the column family, see [`ColumnFamily::id`].
+    pub fn id(&self) -> u32 {
+        column_family_id(self.inner)
+    }
+}
+
 impl Drop for ColumnFamily {
     fn drop(&mut self) {
         destroy_handle(self.inner);
@@ -120,6 +193,20 @@ pub type ColumnFamilyRef<'a> = Arc<BoundColumnFamily<'a>>;
 /// (`&ColumnFamily` and `BoundColumnFamily`)
 pub trait AsColumnFamilyRef {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t;
+
+    /// Returns `true` if the column family has been dropped with `drop_cf` while
+    /// this handle was alive.
+    fn is_dropped(&self) -> bool {
+        false
+    }
+
+    /// Returns a new reference to the handle if it is reference counted, as the
+    /// ones of the multi-threaded mode are, which `drop_cf` leaves to be
+    /// destroyed with the last reference.
+    #[doc(hidden)]
+    fn shared_handle(&self) -> Option<Arc<UnboundColumnFamily>> {
+        None
+    }
 }
 
 impl AsColumnFamilyRef for ColumnFamily {
@@ -143,6 +230,57 @@ impl<'a> AsColumnFamilyRef for Arc<BoundColumnFamily<'a>> {
     fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
         self.inner
     }
+
+    fn is_dropped(&self) -> bool {
+        self.dropped.load(Ordering::Acquire)
+    }
+
+    fn shared_handle(&self) -> Option<Arc<UnboundColumnFamily>> {
+        // SAFETY: the reverse of `UnboundColumnFamily::bound_column_family`, it only
+        // drops the lifetime, which the caller must bound to the DB instead.
+        Some(unsafe { Arc::from_raw(Arc::into_raw(Arc::clone(self)).cast()) })
+    }
+}
+
+/// Borrows a column family handle of any kind as an [`AsColumnFamilyRef`].
+pub(crate) struct DynColumnFamily<'a>(pub(crate) &'a dyn AsColumnFamilyRef);
+
+impl<'a> AsColumnFamilyRef for DynColumnFamily<'a> {
+    fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
+        self.0.inner()
+    }
+
+    fn is_dropped(&self) -> bool {
+        self.0.is_dropped()
+    }
+
+    fn shared_handle(&self) -> Option<Arc<UnboundColumnFamily>> {
+        self.0.shared_handle()
+    }
+}
+
+/// A column family handle kept by what recreates RocksDB objects from it after
+/// it was created, e.g. an iterator changing its bounds. It keeps a reference
+/// counted handle alive, which the caller could otherwise drop after `drop_cf`
+/// while it is still used.
+pub(crate) struct RetainedColumnFamily {
+    inner: *mut ffi::rocksdb_column_family_handle_t,
+    _shared: Option<Arc<UnboundColumnFamily>>,
+}
+
+impl RetainedColumnFamily {
+    pub(crate) fn new(cf: &impl AsColumnFamilyRef) -> Self {
+        Self {
+            inner: cf.inner(),
+            _shared: cf.shared_handle(),
+        }
+    }
+}
+
+impl AsColumnFamilyRef for RetainedColumnFamily {
+    fn inner(&self) -> *mut ffi::rocksdb_column_family_handle_t {
+        self.inner
+    }
 }
 
 unsafe impl Send for ColumnFamily {}
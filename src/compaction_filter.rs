@@ -17,6 +17,8 @@ use libc::{c_char, c_int, c_uchar, c_void, size_t};
 use std::ffi::{CStr, CString};
 use std::slice;
 
+use crate::ffi_util::abort_on_panic;
+
 /// Decision about how to handle compacting an object
 ///
 /// This is returned by a compaction filter callback. Depending
@@ -53,6 +55,17 @@ pub trait CompactionFilter {
     /// compaction finishes. If you use snapshots, think twice about whether you
     /// want to use compaction filter and whether you are using it in a safe way.
     ///
+    /// With blob files enabled (see `Options::set_enable_blob_files`), values
+    /// stored in blob files are read back before this method is invoked, so
+    /// `value` is always the user value rather than a blob reference. Removing
+    /// such a key turns its blob into garbage, which is reclaimed once the
+    /// whole blob file becomes garbage or is relocated by blob GC.
+    ///
+    /// Wide-column entities are passed through their default column only, and
+    /// entities without a default column are kept; the C API RocksDB is bound
+    /// through does not expose `FilterV3`, so the filter can neither tell value
+    /// types apart nor rewrite the columns of an entity.
+    ///
     /// If the CompactionFilter was created by a factory, then it will only ever
     /// be used by a single thread that is doing the compaction run, and this
     /// call does not need to be thread-safe.  However, multiple filters may be
@@ -100,15 +113,19 @@ pub unsafe extern "C" fn destructor_callback<F>(raw_cb: *mut c_void)
 where
     F: CompactionFilter,
 {
-    drop(Box::from_raw(raw_cb as *mut F));
+    abort_on_panic("compaction filter", || {
+        drop(Box::from_raw(raw_cb as *mut F));
+    });
 }
 
 pub unsafe extern "C" fn name_callback<F>(raw_cb: *mut c_void) -> *const c_char
 where
     F: CompactionFilter,
 {
-    let cb = &*(raw_cb as *mut F);
-    cb.name().as_ptr()
+    abort_on_panic("compaction filter", || {
+        let cb = &*(raw_cb as *mut F);
+        cb.name().as_ptr()
+    })
 }
 
 pub unsafe extern "C" fn filter_callback<F>(
@@ -125,22 +142,24 @@ pub unsafe extern "C" fn filter_callback<F>(
 where
     F: CompactionFilter,
 {
-    use self::Decision::{Change, Keep, Remove};
+    abort_on_panic("compaction filter", || {
+        use self::Decision::{Change, Keep, Remove};
 
-    let cb = &mut *(raw_cb as *mut F);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_length);
-    let oldval = slice::from_raw_parts(existing_value as *const u8, value_length);
-    let result = cb.filter(level as u32, key, oldval);
-    match result {
-        Keep => 0,
-        Remove => 1,
-        Change(newval) => {
-            *new_value = newval.as_ptr() as *mut c_char;
-            *new_value_length = newval.len() as size_t;
-            *value_changed = 1_u8;
-            0
+        let cb = &mut *(raw_cb as *mut F);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_length);
+        let oldval = slice::from_raw_parts(existing_value as *const u8, value_length);
+        let result = cb.filter(level as u32, key, oldval);
+        match result {
+            Keep => 0,
+            Remove => 1,
+            Change(newval) => {
+                *new_value = newval.as_ptr() as *mut c_char;
+                *new_value_length = newval.len() as size_t;
+                *value_changed = 1_u8;
+                0
+            }
         }
-    }
+    })
 }
 
 #[cfg(test)]
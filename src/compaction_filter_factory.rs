@@ -1,10 +1,12 @@
 use std::ffi::CStr;
+use std::sync::Mutex;
 
 use libc::{self, c_char, c_void};
 
 use crate::{
     compaction_filter::{self, CompactionFilter},
     ffi,
+    ffi_util::abort_on_panic,
 };
 
 /// Each compaction will create a new CompactionFilter allowing the
@@ -30,18 +32,28 @@ pub unsafe extern "C" fn destructor_callback<F>(raw_self: *mut c_void)
 where
     F: CompactionFilterFactory,
 {
-    drop(Box::from_raw(raw_self as *mut F));
+    abort_on_panic("compaction filter factory", || {
+        drop(Box::from_raw(raw_self as *mut Mutex<F>));
+    });
 }
 
 pub unsafe extern "C" fn name_callback<F>(raw_self: *mut c_void) -> *const c_char
 where
     F: CompactionFilterFactory,
 {
-    let self_ = &*(raw_self.cast_const() as *const F);
-    self_.name().as_ptr()
+    abort_on_panic("compaction filter factory", || {
+        let self_ = &*(raw_self.cast_const() as *const Mutex<F>);
+        self_.lock().unwrap().name().as_ptr()
+    })
 }
 
 /// Context information of a compaction run
+///
+/// The input files of the compaction are not available through RocksDB's C API.
+/// Factories that depend on the properties of the compacted files can be fed
+/// through [`DBCommon::properties_for_files`] before a manual compaction.
+///
+/// [`DBCommon::properties_for_files`]: crate::DBCommon::properties_for_files
 pub struct CompactionFilterContext {
     /// Does this compaction run include all data files
     pub is_full_compaction: bool,
@@ -70,18 +82,22 @@ pub unsafe extern "C" fn create_compaction_filter_callback<F>(
 where
     F: CompactionFilterFactory,
 {
-    let self_ = &mut *(raw_self as *mut F);
-    let context = CompactionFilterContext::from_raw(context);
-    let filter = Box::new(self_.create(context));
-
-    let filter_ptr = Box::into_raw(filter);
-
-    ffi::rocksdb_compactionfilter_create(
-        filter_ptr as *mut c_void,
-        Some(compaction_filter::destructor_callback::<F::Filter>),
-        Some(compaction_filter::filter_callback::<F::Filter>),
-        Some(compaction_filter::name_callback::<F::Filter>),
-    )
+    abort_on_panic("compaction filter factory", || {
+        // concurrent compactions, also of DBs opened with cloned options, share
+        // the factory
+        let self_ = &*(raw_self.cast_const() as *const Mutex<F>);
+        let context = CompactionFilterContext::from_raw(context);
+        let filter = Box::new(self_.lock().unwrap().create(context));
+
+        let filter_ptr = Box::into_raw(filter);
+
+        ffi::rocksdb_compactionfilter_create(
+            filter_ptr as *mut c_void,
+            Some(compaction_filter::destructor_callback::<F::Filter>),
+            Some(compaction_filter::filter_callback::<F::Filter>),
+            Some(compaction_filter::name_callback::<F::Filter>),
+        )
+    })
 }
 
 #[cfg(test)]
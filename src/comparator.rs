@@ -13,11 +13,13 @@
 // limitations under the License.
 //
 
-use libc::{c_char, c_int, c_void, size_t};
+use libc::{c_char, c_int, c_uchar, c_void, size_t};
 use std::cmp::Ordering;
 use std::ffi::CString;
 use std::slice;
 
+use crate::ffi_util::abort_on_panic;
+
 pub type CompareFn = dyn Fn(&[u8], &[u8]) -> Ordering;
 
 pub struct ComparatorCallback {
@@ -26,7 +28,9 @@ pub struct ComparatorCallback {
 }
 
 pub unsafe extern "C" fn destructor_callback(raw_cb: *mut c_void) {
-    drop(Box::from_raw(raw_cb as *mut ComparatorCallback));
+    abort_on_panic("comparator", || {
+        drop(Box::from_raw(raw_cb as *mut ComparatorCallback));
+    });
 }
 
 pub unsafe extern "C" fn name_callback(raw_cb: *mut c_void) -> *const c_char {
@@ -42,12 +46,190 @@ pub unsafe extern "C" fn compare_callback(
     b_raw: *const c_char,
     b_len: size_t,
 ) -> c_int {
-    let cb: &mut ComparatorCallback = &mut *(raw_cb as *mut ComparatorCallback);
-    let a: &[u8] = slice::from_raw_parts(a_raw as *const u8, a_len);
-    let b: &[u8] = slice::from_raw_parts(b_raw as *const u8, b_len);
-    match (cb.f)(a, b) {
+    abort_on_panic("comparator", || {
+        let cb: &mut ComparatorCallback = &mut *(raw_cb as *mut ComparatorCallback);
+        let a: &[u8] = slice::from_raw_parts(a_raw as *const u8, a_len);
+        let b: &[u8] = slice::from_raw_parts(b_raw as *const u8, b_len);
+        match (cb.f)(a, b) {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        }
+    })
+}
+
+pub type CompareTsFn = dyn Fn(&[u8], &[u8]) -> Ordering;
+
+pub type CompareWithoutTsFn = dyn Fn(&[u8], bool, &[u8], bool) -> Ordering;
+
+pub struct ComparatorWithTsCallback {
+    pub name: CString,
+    pub compare_fn: Box<CompareFn>,
+    pub compare_ts_fn: Box<CompareTsFn>,
+    pub compare_without_ts_fn: Box<CompareWithoutTsFn>,
+}
+
+pub unsafe extern "C" fn destructor_with_ts_callback(raw_cb: *mut c_void) {
+    abort_on_panic("comparator", || {
+        drop(Box::from_raw(raw_cb as *mut ComparatorWithTsCallback));
+    });
+}
+
+pub unsafe extern "C" fn name_with_ts_callback(raw_cb: *mut c_void) -> *const c_char {
+    let cb: &mut ComparatorWithTsCallback = &mut *(raw_cb as *mut ComparatorWithTsCallback);
+    let ptr = cb.name.as_ptr();
+    ptr as *const c_char
+}
+
+pub unsafe extern "C" fn compare_with_ts_callback(
+    raw_cb: *mut c_void,
+    a_raw: *const c_char,
+    a_len: size_t,
+    b_raw: *const c_char,
+    b_len: size_t,
+) -> c_int {
+    abort_on_panic("comparator", || {
+        let cb: &mut ComparatorWithTsCallback = &mut *(raw_cb as *mut ComparatorWithTsCallback);
+        let a: &[u8] = slice::from_raw_parts(a_raw as *const u8, a_len);
+        let b: &[u8] = slice::from_raw_parts(b_raw as *const u8, b_len);
+        ordering_to_int((cb.compare_fn)(a, b))
+    })
+}
+
+pub unsafe extern "C" fn compare_ts_callback(
+    raw_cb: *mut c_void,
+    a_ts_raw: *const c_char,
+    a_ts_len: size_t,
+    b_ts_raw: *const c_char,
+    b_ts_len: size_t,
+) -> c_int {
+    abort_on_panic("comparator", || {
+        let cb: &mut ComparatorWithTsCallback = &mut *(raw_cb as *mut ComparatorWithTsCallback);
+        let a_ts: &[u8] = slice::from_raw_parts(a_ts_raw as *const u8, a_ts_len);
+        let b_ts: &[u8] = slice::from_raw_parts(b_ts_raw as *const u8, b_ts_len);
+        ordering_to_int((cb.compare_ts_fn)(a_ts, b_ts))
+    })
+}
+
+pub unsafe extern "C" fn compare_without_ts_callback(
+    raw_cb: *mut c_void,
+    a_raw: *const c_char,
+    a_len: size_t,
+    a_has_ts: c_uchar,
+    b_raw: *const c_char,
+    b_len: size_t,
+    b_has_ts: c_uchar,
+) -> c_int {
+    abort_on_panic("comparator", || {
+        let cb: &mut ComparatorWithTsCallback = &mut *(raw_cb as *mut ComparatorWithTsCallback);
+        let a: &[u8] = slice::from_raw_parts(a_raw as *const u8, a_len);
+        let b: &[u8] = slice::from_raw_parts(b_raw as *const u8, b_len);
+        ordering_to_int((cb.compare_without_ts_fn)(
+            a,
+            a_has_ts != 0,
+            b,
+            b_has_ts != 0,
+        ))
+    })
+}
+
+fn ordering_to_int(ordering: Ordering) -> c_int {
+    match ordering {
         Ordering::Less => -1,
         Ordering::Equal => 0,
         Ordering::Greater => 1,
     }
 }
+
+/// A timestamp encoded as 8 little-endian bytes, the format expected by
+/// [`U64Comparator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U64Timestamp([u8; U64Timestamp::SIZE]);
+
+impl U64Timestamp {
+    /// The size of an encoded timestamp, to be passed as `timestamp_size` to
+    /// [`Options::set_comparator_with_ts`](crate::Options::set_comparator_with_ts).
+    pub const SIZE: usize = 8;
+
+    pub fn new(ts: u64) -> Self {
+        Self(ts.to_le_bytes())
+    }
+
+    /// Decodes a timestamp, e.g. one returned by an iterator.
+    ///
+    /// Returns `None` if `bytes` is not exactly [`SIZE`](Self::SIZE) bytes long.
+    pub fn decode(bytes: &[u8]) -> Option<u64> {
+        let mut buf = [0; Self::SIZE];
+        if bytes.len() != Self::SIZE {
+            return None;
+        }
+        buf.copy_from_slice(bytes);
+        Some(u64::from_le_bytes(buf))
+    }
+}
+
+impl From<u64> for U64Timestamp {
+    fn from(ts: u64) -> Self {
+        Self::new(ts)
+    }
+}
+
+impl AsRef<[u8]> for U64Timestamp {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// A bytewise comparator for keys suffixed with a [`U64Timestamp`], ordering
+/// versions of the same user key from the newest to the oldest timestamp. It
+/// orders keys the same way as RocksDB's built-in
+/// `leveldb.BytewiseComparator.u64ts`.
+///
+/// ```
+/// use rocksdb::{Options, U64Comparator, U64Timestamp};
+///
+/// let mut opts = Options::default();
+/// opts.set_comparator_with_ts(
+///     U64Comparator::NAME,
+///     U64Timestamp::SIZE,
+///     Box::new(U64Comparator::compare),
+///     Box::new(U64Comparator::compare_ts),
+///     Box::new(U64Comparator::compare_without_ts),
+/// );
+/// ```
+pub struct U64Comparator;
+
+impl U64Comparator {
+    pub const NAME: &'static str = "rust-rocksdb.U64Comparator";
+
+    /// Compares two keys including their timestamp suffix. Keys too short to
+    /// have one are compared bytewise.
+    pub fn compare(a: &[u8], b: &[u8]) -> Ordering {
+        if a.len() < U64Timestamp::SIZE || b.len() < U64Timestamp::SIZE {
+            return a.cmp(b);
+        }
+        Self::compare_without_ts(a, true, b, true).then_with(|| {
+            let a_ts = &a[a.len() - U64Timestamp::SIZE..];
+            let b_ts = &b[b.len() - U64Timestamp::SIZE..];
+            // Newer versions sort first.
+            Self::compare_ts(a_ts, b_ts).reverse()
+        })
+    }
+
+    /// Compares two encoded timestamps.
+    pub fn compare_ts(a_ts: &[u8], b_ts: &[u8]) -> Ordering {
+        U64Timestamp::decode(a_ts).cmp(&U64Timestamp::decode(b_ts))
+    }
+
+    /// Compares the user keys of two keys, stripping the timestamp suffix of the
+    /// keys which have one. Keys too short to have one are kept whole.
+    pub fn compare_without_ts(a: &[u8], a_has_ts: bool, b: &[u8], b_has_ts: bool) -> Ordering {
+        fn strip(key: &[u8], has_ts: bool) -> &[u8] {
+            match key.len().checked_sub(U64Timestamp::SIZE) {
+                Some(len) if has_ts => &key[..len],
+                _ => key,
+            }
+        }
+        strip(a, a_has_ts).cmp(strip(b, b_has_ts))
+    }
+}
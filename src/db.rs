@@ -14,32 +14,54 @@
 //
 
 use crate::{
-    column_family::{AsColumnFamilyRef, BoundColumnFamily, UnboundColumnFamily},
-    db_options::OptionsMustOutliveDB,
+    checkpoint::{DumpColumnFamily, ExportImportFilesMetaData},
+    column_family::{
+        column_family_id, column_family_name, AsColumnFamilyRef, BoundColumnFamily,
+        DynColumnFamily, UnboundColumnFamily, DEFAULT_COLUMN_FAMILY_ID,
+    },
+    db_options::{OptionsMustOutliveDB, PluginNames, BYTEWISE_COMPARATOR_NAME},
     ffi,
-    ffi_util::{from_cstr, opt_bytes_to_ptr, raw_data, to_cpath, CStrLike},
-    table_properties::TablePropertiesCollection,
-    ColumnFamily, ColumnFamilyDescriptor, CompactOptions, DBIteratorWithThreadMode,
-    DBPinnableSlice, DBRawIteratorWithThreadMode, DBWALIterator, Direction, Error, FlushOptions,
-    IngestExternalFileOptions, IteratorMode, Options, ReadOptions, SnapshotWithThreadMode,
-    WaitForCompactOptions, WriteBatch, WriteOptions, DEFAULT_COLUMN_FAMILY_NAME,
+    ffi_util::{error_message, from_cstr, opt_bytes_to_ptr, raw_data, to_cpath, CStrLike},
+    iter_range::next_key,
+    perf::PerfContext,
+    properties,
+    retry::{self, RetryPolicy},
+    table_properties::{
+        decode_range_tombstones, decode_seqno_range, HyperLogLog, KeyRange, RangeTombstone,
+        TableProperties, TablePropertiesCollection, HLL_KEY_COUNT_PROPERTY,
+        RANGE_TOMBSTONES_PROPERTY, SEQNO_RANGE_PROPERTY,
+    },
+    write_batch::{decode_batch_ops, summarize_batch, BatchOp},
+    BatchSummary, BottommostLevelCompaction, ColumnFamily, ColumnFamilyDescriptor, CompactOptions,
+    DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode, DBScan, DBWALIterator,
+    Direction, Durability, Env, Error, ExternalSstFileInfo, FlushOptions,
+    ImportColumnFamilyOptions, IngestExternalFileOptions, IterateBounds, IteratorMode, OpenError,
+    Options, ReadOptions, ScanOptions, SizeApproximationOptions, SnapshotWithThreadMode,
+    SstFileWriter, WaitForCompactOptions, WriteBatch, WriteOptions, WriteValidationOptions,
+    DEFAULT_COLUMN_FAMILY_NAME, HISTORY_UNAVAILABLE, MERGE_OPERAND_THRESHOLD_EXCEEDED,
 };
 
 use crate::ffi_util::CSlice;
 use libc::{self, c_char, c_int, c_uchar, c_void, size_t};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::convert::TryFrom;
 use std::ffi::{CStr, CString};
 use std::fmt;
 use std::fs;
 use std::iter;
+use std::mem;
+use std::ops::{BitOr, BitOrAssign, Bound, RangeBounds};
 use std::path::Path;
 use std::path::PathBuf;
 use std::ptr;
 use std::slice;
 use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
 use std::sync::Arc;
-use std::sync::RwLock;
-use std::time::Duration;
+use std::sync::{Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 
 /// Marker trait to specify single or multi threaded column family alternations for
 /// [`DBWithThreadMode<T>`]
@@ -52,12 +74,19 @@ use std::time::Duration;
 /// [`SingleThreaded`] and [`MultiThreaded`].  These methods aren't expected to be
 /// called and defined externally.
 pub trait ThreadMode {
+    /// The column family handles returned by [`DBCommon::cf_handles`]
+    type CfHandle<'a>: AsColumnFamilyRef
+    where
+        Self: 'a;
     /// Internal implementation for storing column family handles
     fn new_cf_map_internal(
         cf_map: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
     ) -> Self;
     /// Internal implementation for dropping column family handles
     fn drop_all_cfs_internal(&mut self);
+    /// Internal implementation for listing column family handles, returning the
+    /// first column family ordered after `name`, or the first one without a name
+    fn cf_handle_after_internal(&self, name: Option<&str>) -> Option<(String, Self::CfHandle<'_>)>;
 }
 
 /// Actual marker type for the marker trait `ThreadMode`, which holds
@@ -80,6 +109,8 @@ pub struct MultiThreaded {
 }
 
 impl ThreadMode for SingleThreaded {
+    type CfHandle<'a> = &'a ColumnFamily;
+
     fn new_cf_map_internal(
         cfs: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
     ) -> Self {
@@ -95,16 +126,26 @@ impl ThreadMode for SingleThreaded {
         // Cause all ColumnFamily objects to be Drop::drop()-ed.
         self.cfs.clear();
     }
+
+    fn cf_handle_after_internal(&self, name: Option<&str>) -> Option<(String, &ColumnFamily)> {
+        let after = name.map_or(Bound::Unbounded, Bound::Excluded);
+        self.cfs
+            .range::<str, _>((after, Bound::Unbounded))
+            .next()
+            .map(|(name, cf)| (name.clone(), cf))
+    }
 }
 
 impl ThreadMode for MultiThreaded {
+    type CfHandle<'a> = Arc<BoundColumnFamily<'a>>;
+
     fn new_cf_map_internal(
         cfs: BTreeMap<String, *mut ffi::rocksdb_column_family_handle_t>,
     ) -> Self {
         Self {
             cfs: RwLock::new(
                 cfs.into_iter()
-                    .map(|(n, c)| (n, Arc::new(UnboundColumnFamily { inner: c })))
+                    .map(|(n, c)| (n, Arc::new(UnboundColumnFamily::new(c))))
                     .collect(),
             ),
         }
@@ -114,8 +155,41 @@ impl ThreadMode for MultiThreaded {
         // Cause all UnboundColumnFamily objects to be Drop::drop()-ed.
         self.cfs.write().unwrap().clear();
     }
+
+    fn cf_handle_after_internal(
+        &self,
+        name: Option<&str>,
+    ) -> Option<(String, Arc<BoundColumnFamily>)> {
+        let after = name.map_or(Bound::Unbounded, Bound::Excluded);
+        self.cfs
+            .read()
+            .unwrap()
+            .range::<str, _>((after, Bound::Unbounded))
+            .next()
+            .map(|(name, cf)| (name.clone(), cf.clone().bound_column_family()))
+    }
+}
+
+/// An iterator over the open column families of a database with their handles,
+/// returned by [`DBCommon::cf_handles`].
+pub struct CfHandles<'a, T: ThreadMode> {
+    cfs: &'a T,
+    last: Option<String>,
+}
+
+impl<'a, T: ThreadMode> Iterator for CfHandles<'a, T> {
+    type Item = (String, T::CfHandle<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (name, cf) = self.cfs.cf_handle_after_internal(self.last.as_deref())?;
+        self.last = Some(name.clone());
+        Some((name, cf))
+    }
 }
 
+/// The user-collected properties of a table file, by name.
+type UserProperties = BTreeMap<Box<[u8]>, Box<[u8]>>;
+
 /// Get underlying `rocksdb_t`.
 pub trait DBInner {
     fn inner(&self) -> *mut ffi::rocksdb_t;
@@ -129,7 +203,30 @@ pub struct DBCommon<T: ThreadMode, D: DBInner> {
     pub(crate) inner: D,
     cfs: T, // Column families are held differently depending on thread mode
     path: PathBuf,
-    _outlive: Vec<OptionsMustOutliveDB>,
+    // TTLs the column families were opened with through the TTL open paths.
+    ttls: BTreeMap<String, Duration>,
+    // Limits checked before each write, see `Options::set_write_validation`.
+    pub(crate) write_validation: Option<WriteValidationOptions>,
+    // Durability policies and frozen column families, checked before each write
+    // under a single lock.
+    cf_write_rules: RwLock<CfWriteRules>,
+    // Number of `disable_manual_compaction` calls not yet matched by
+    // `enable_manual_compaction`, and of all calls, so a manual compaction can
+    // tell whether it was paused while it ran.
+    manual_compaction_paused: Mutex<(usize, u64)>,
+    // Entries recorded by `tick` with `TickTasks::RECORD_STATS`, in memory only.
+    stats_history: Mutex<StatsHistory>,
+    // Keeps what RocksDB borrows from the options the DB was opened with, e.g.
+    // comparators.
+    outlive: Vec<OptionsMustOutliveDB>,
+    // The same for the column families created since, by column family id,
+    // released when they are dropped.
+    cf_outlive: Mutex<BTreeMap<u32, OptionsMustOutliveDB>>,
+    // Names of the comparator and merge operator each column family was opened or
+    // created with, by column family id, which the C API offers no way to read.
+    cf_plugin_names: RwLock<BTreeMap<u32, PluginNames>>,
+    // The limit on open files the DB was opened with, as sanitized by RocksDB.
+    max_open_files: i32,
 }
 
 /// Minimal set of DB-related methods, intended to be generic over
@@ -338,9 +435,18 @@ unsafe impl<T: ThreadMode, I: DBInner> Sync for DBCommon<T, I> {}
 // Specifies whether open DB for read only.
 enum AccessType<'a> {
     ReadWrite,
-    ReadOnly { error_if_log_file_exist: bool },
-    Secondary { secondary_path: &'a Path },
-    WithTTL { ttl: Duration },
+    ReadOnly {
+        error_if_log_file_exist: bool,
+    },
+    Secondary {
+        secondary_path: &'a Path,
+    },
+    WithTTL {
+        ttl: Duration,
+    },
+    WithCfTTLs {
+        ttls: &'a BTreeMap<String, Duration>,
+    },
 }
 
 /// Methods of `DBWithThreadMode`.
@@ -357,6 +463,34 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         Self::open_cf(opts, path, None::<&str>)
     }
 
+    /// Same as [`open`](Self::open), but tells apart the failure to lock the DB,
+    /// e.g. because another process or a DB still open in this one uses it.
+    pub fn try_open<P: AsRef<Path>>(opts: &Options, path: P) -> Result<Self, OpenError> {
+        Self::open(opts, path).map_err(OpenError::from)
+    }
+
+    /// Same as [`try_open`](Self::try_open), but waits for up to `timeout` for the
+    /// lock of the DB to be released, retrying the open with backoff.
+    pub fn open_with_lock_wait<P: AsRef<Path>>(
+        opts: &Options,
+        path: P,
+        timeout: Duration,
+    ) -> Result<Self, OpenError> {
+        const MAX_DELAY: Duration = Duration::from_millis(100);
+
+        let deadline = Instant::now() + timeout;
+        let mut delay = Duration::from_millis(1);
+        loop {
+            match Self::try_open(opts, path.as_ref()) {
+                Err(OpenError::Locked { .. }) if Instant::now() < deadline => {
+                    thread::sleep(delay.min(deadline.saturating_duration_since(Instant::now())));
+                    delay = (delay * 2).min(MAX_DELAY);
+                }
+                result => return result,
+            }
+        }
+    }
+
     /// Opens the database for read only with the specified options.
     pub fn open_for_read_only<P: AsRef<Path>>(
         opts: &Options,
@@ -376,6 +510,10 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
     }
 
     /// Opens the database with a Time to Live compaction filter.
+    ///
+    /// A compaction filter or compaction filter factory set on the options is not
+    /// replaced: it runs after the TTL filter on the entries that did not expire, and
+    /// sees their values without the timestamp suffix the TTL filter relies on.
     pub fn open_with_ttl<P: AsRef<Path>>(
         opts: &Options,
         path: P,
@@ -405,6 +543,28 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         Self::open_cf_descriptors_with_ttl(opts, path, cfs, ttl)
     }
 
+    /// Opens the database with a Time to Live compaction filter and a separate TTL for
+    /// every listed column family.
+    ///
+    /// Column families not listed, including `default`, are opened without a TTL. Column
+    /// families opened using this function will be created with default `Options`.
+    pub fn open_cf_with_ttls<P, I, N>(opts: &Options, path: P, cfs: I) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = (N, Duration)>,
+        N: AsRef<str>,
+    {
+        let ttls: BTreeMap<String, Duration> = cfs
+            .into_iter()
+            .map(|(name, ttl)| (name.as_ref().to_owned(), ttl))
+            .collect();
+        let cfs = ttls
+            .keys()
+            .map(|name| ColumnFamilyDescriptor::new(name, Options::default()));
+
+        Self::open_cf_descriptors_internal(opts, path, cfs, &AccessType::WithCfTTLs { ttls: &ttls })
+    }
+
     /// Opens a database with the given database with a Time to Live compaction filter and
     /// column family descriptors.
     /// *NOTE*: `default` column family is opened with `Options::default()`.
@@ -482,6 +642,66 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         )
     }
 
+    /// Opens the checkpoint at `path`, e.g. created from this DB with
+    /// [`Checkpoint::create_checkpoint`], read-only with all its column families.
+    /// Meant for analytical reads next to the primary without doubling the memory
+    /// of the block cache.
+    ///
+    /// The options are loaded from the OPTIONS file of the checkpoint, so the
+    /// table options of each column family are the ones of this DB, and every
+    /// column family uses the block cache of this DB: its capacity and usage are
+    /// shared by both DBs. Column families with a custom comparator or merge
+    /// operator, which cannot be loaded from an OPTIONS file, need
+    /// [`open_cf_descriptors_read_only`](Self::open_cf_descriptors_read_only)
+    /// instead.
+    ///
+    /// Reads fill the shared cache as usual, scans should use the read options of
+    /// [`analytical_read_options`](Self::analytical_read_options) so that they do
+    /// not evict the blocks of the primary.
+    ///
+    /// Fails with `InvalidArgument` if this DB was not opened with a block cache
+    /// set with [`BlockBasedOptions::set_block_cache`].
+    ///
+    /// [`Checkpoint::create_checkpoint`]: crate::checkpoint::Checkpoint::create_checkpoint
+    /// [`BlockBasedOptions::set_block_cache`]: crate::BlockBasedOptions::set_block_cache
+    pub fn open_checkpoint_read_only<P: AsRef<Path>>(&self, path: P) -> Result<Self, Error> {
+        let cache = self
+            .outlive
+            .iter()
+            .find_map(OptionsMustOutliveDB::block_cache)
+            .cloned();
+        let env = self
+            .outlive
+            .iter()
+            .find_map(OptionsMustOutliveDB::env)
+            .cloned();
+        let Some(cache) = cache else {
+            return Err(Error::new(
+                "Invalid argument: the DB has no block cache set with \
+                 BlockBasedOptions::set_block_cache to share"
+                    .to_owned(),
+            ));
+        };
+        let env = match env {
+            Some(env) => env,
+            None => Env::new()?,
+        };
+
+        let (opts, cfs) = Options::load_latest(&path, env, false, cache)?;
+        Self::open_cf_descriptors_read_only(&opts, &path, cfs, false)
+    }
+
+    /// Returns the read options for the analytical scans of a DB opened with
+    /// [`open_checkpoint_read_only`](Self::open_checkpoint_read_only), e.g. with
+    /// [`iterator_opt`](DBCommon::iterator_opt). They do not fill the block cache,
+    /// as with [`ReadOptions::fill_cache`], so that scans do not evict the blocks
+    /// of the primary.
+    pub fn analytical_read_options() -> ReadOptions {
+        let mut opts = ReadOptions::default();
+        opts.fill_cache(false);
+        opts
+    }
+
     /// Opens a database for read only with the given database options and column family names.
     /// *NOTE*: `default` column family is opened with `Options::default()`.
     /// If you want to open `default` cf with different options, set them explicitly in `cfs`.
@@ -623,9 +843,11 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
 
         let db: *mut ffi::rocksdb_t;
         let mut cf_map = BTreeMap::new();
+        let mut cf_plugin_names = BTreeMap::new();
 
         if cfs.is_empty() {
             db = Self::open_raw(opts, &cpath, access_type)?;
+            cf_plugin_names.insert(DEFAULT_COLUMN_FAMILY_ID, opts.plugin_names.clone());
         } else {
             let mut cfs_v = cfs;
             // Always open the default column family.
@@ -670,6 +892,10 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
             }
 
             for (cf_desc, inner) in cfs_v.iter().zip(cfhandles) {
+                cf_plugin_names.insert(
+                    column_family_id(inner),
+                    cf_desc.options.plugin_names.clone(),
+                );
                 cf_map.insert(cf_desc.name.clone(), inner);
             }
         }
@@ -678,11 +904,31 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
             return Err(Error::new("Could not initialize database.".to_owned()));
         }
 
+        let ttls = match access_type {
+            AccessType::WithTTL { ttl } => {
+                let mut names: Vec<_> = cf_map.keys().cloned().collect();
+                if names.is_empty() {
+                    names.push(DEFAULT_COLUMN_FAMILY_NAME.to_owned());
+                }
+                names.into_iter().map(|name| (name, *ttl)).collect()
+            }
+            AccessType::WithCfTTLs { ttls } => (*ttls).clone(),
+            _ => BTreeMap::new(),
+        };
+
         Ok(Self {
             inner: DBWithThreadModeInner { inner: db },
             path: path.as_ref().to_path_buf(),
             cfs: T::new_cf_map_internal(cf_map),
-            _outlive: outlive,
+            ttls,
+            write_validation: opts.write_validation,
+            cf_write_rules: RwLock::new(CfWriteRules::default()),
+            manual_compaction_paused: Mutex::new((0, 0)),
+            stats_history: Mutex::new(StatsHistory::default()),
+            outlive,
+            cf_outlive: Mutex::new(BTreeMap::new()),
+            cf_plugin_names: RwLock::new(cf_plugin_names),
+            max_open_files: opts.effective_max_open_files(),
         })
     }
 
@@ -715,6 +961,10 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
                     cpath.as_ptr(),
                     ttl.as_secs() as c_int,
                 )),
+                // a TTL of 0 disables expiration
+                AccessType::WithCfTTLs { .. } => {
+                    ffi_try!(ffi::rocksdb_open_with_ttl(opts.inner, cpath.as_ptr(), 0))
+                }
             }
         };
         Ok(db)
@@ -774,6 +1024,21 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
                         ttls_v.as_ptr(),
                     ))
                 }
+                AccessType::WithCfTTLs { ttls } => {
+                    let ttls_v: Vec<_> = cfs_v
+                        .iter()
+                        .map(|cf| ttls.get(&cf.name).map_or(0, |ttl| ttl.as_secs() as c_int))
+                        .collect();
+                    ffi_try!(ffi::rocksdb_open_column_families_with_ttl(
+                        opts.inner,
+                        cpath.as_ptr(),
+                        cfs_v.len() as c_int,
+                        cfnames.as_ptr(),
+                        cfopts.as_ptr(),
+                        cfhandles.as_mut_ptr(),
+                        ttls_v.as_ptr(),
+                    ))
+                }
             }
         };
         Ok(db)
@@ -789,6 +1054,11 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
     ) -> Result<(), Error> {
         let from = from.as_ref();
         let to = to.as_ref();
+        self.check_delete(from)?;
+        self.check_delete(to)?;
+
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_delete_range_cf(
@@ -815,6 +1085,15 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
     }
 
     pub fn write_opt(&self, batch: WriteBatch, writeopts: &WriteOptions) -> Result<(), Error> {
+        self.write_batch_opt(&batch, writeopts)
+    }
+
+    fn write_batch_opt(&self, batch: &WriteBatch, writeopts: &WriteOptions) -> Result<(), Error> {
+        if let Some(validation) = &self.write_validation {
+            validation.check_batch(batch.data())?;
+        }
+        self.check_batch_cf_write(batch, writeopts)?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_write(
                 self.inner.inner(),
@@ -834,19 +1113,235 @@ impl<T: ThreadMode> DBWithThreadMode<T> {
         wo.disable_wal(true);
         self.write_opt(batch, &wo)
     }
+
+    /// Like [`write_opt`](Self::write_opt), retrying transient failures
+    /// according to `policy`. The batch is copied for each attempt.
+    pub fn write_opt_with_retry(
+        &self,
+        policy: &RetryPolicy,
+        batch: &WriteBatch,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        retry::with_backoff(policy, || self.write_opt(batch.duplicate(), writeopts))
+    }
+
+    /// Sets whether writes to `cf` must, or must not, go through the WAL. Writes
+    /// violating the policy fail with [`ErrorKind::InvalidArgument`] before
+    /// reaching RocksDB, instead of silently skipping the WAL of a column family
+    /// other code expects to be durable.
+    ///
+    /// While any column family has a policy, write batches are decoded to check
+    /// them against the column families of their operations, and a batch that
+    /// cannot be decoded is checked against the policies of all column families.
+    /// The policy of the default column family also applies to writes that do
+    /// not name a column family. [`Durability::DontCare`] removes the policy, and
+    /// dropping the column family removes it as well.
+    ///
+    /// [`ErrorKind::InvalidArgument`]: crate::ErrorKind::InvalidArgument
+    pub fn set_cf_durability_policy(&self, cf: &impl AsColumnFamilyRef, policy: Durability) {
+        let cf = column_family_id(cf.inner());
+        let policies = &mut self.cf_write_rules.write().unwrap().durability_policies;
+        if policy == Durability::DontCare {
+            policies.remove(&cf);
+        } else {
+            policies.insert(cf, policy);
+        }
+    }
+
+    /// Makes the column family read-only through this DB: puts, merges, deletes,
+    /// ingestions and write batches touching it fail with
+    /// [`ErrorKind::InvalidArgument`] until [`unfreeze_cf`](Self::unfreeze_cf) is
+    /// called, e.g. to catch stray writes once a migration is done. Reads,
+    /// flushes and compactions are not affected.
+    ///
+    /// Write batches are checked like for
+    /// [`set_cf_durability_policy`](Self::set_cf_durability_policy): a batch
+    /// with any operation on a frozen column family is rejected as a whole, and
+    /// a batch that cannot be decoded is rejected while any column family is
+    /// frozen. Freezing the default column family also covers the writes
+    /// that do not name a column family.
+    ///
+    /// [`ErrorKind::InvalidArgument`]: crate::ErrorKind::InvalidArgument
+    pub fn freeze_cf(&self, cf: &impl AsColumnFamilyRef) {
+        self.cf_write_rules
+            .write()
+            .unwrap()
+            .frozen_cfs
+            .insert(column_family_id(cf.inner()));
+    }
+
+    /// Reverts [`freeze_cf`](Self::freeze_cf).
+    pub fn unfreeze_cf(&self, cf: &impl AsColumnFamilyRef) {
+        self.cf_write_rules
+            .write()
+            .unwrap()
+            .frozen_cfs
+            .remove(&column_family_id(cf.inner()));
+    }
+
+    /// Returns `true` if `cf` was frozen with [`freeze_cf`](Self::freeze_cf).
+    pub fn is_cf_frozen(&self, cf: &impl AsColumnFamilyRef) -> bool {
+        self.cf_write_rules
+            .read()
+            .unwrap()
+            .frozen_cfs
+            .contains(&column_family_id(cf.inner()))
+    }
+
+    /// Writes the batch and invokes `callback` with the sequence number assigned
+    /// to the first operation of the batch once the write has been applied.
+    ///
+    /// The callback runs after the batch has been written to the WAL (and synced,
+    /// if requested by `writeopts`) and inserted into the memtables, so the data
+    /// is visible to readers by the time it is invoked. It is not invoked if the
+    /// write fails.
+    ///
+    /// The C API does not return the sequence number of a write, so it is read
+    /// from the header of the batch, where RocksDB stores it while applying the
+    /// batch. It is thus exact even with concurrent writes, but callbacks of
+    /// concurrent calls may run out of sequence number order.
+    pub fn write_with_callback_opt<F>(
+        &self,
+        batch: WriteBatch,
+        writeopts: &WriteOptions,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce(u64),
+    {
+        self.write_batch_opt(&batch, writeopts)?;
+        // the serialized batch starts with its 8-byte sequence number
+        let mut seqno = [0; 8];
+        seqno.copy_from_slice(&batch.data()[..8]);
+        callback(u64::from_le_bytes(seqno));
+        Ok(())
+    }
+
+    /// Same as [`write_with_callback_opt`](Self::write_with_callback_opt) with
+    /// default write options.
+    pub fn write_with_callback<F>(&self, batch: WriteBatch, callback: F) -> Result<(), Error>
+    where
+        F: FnOnce(u64),
+    {
+        self.write_with_callback_opt(batch, &WriteOptions::default(), callback)
+    }
+
+    /// Replaces the value of `key` in the column family with the one `f` returns
+    /// for its current value, or deletes the key if `f` returns `None`, and
+    /// returns the value written.
+    ///
+    /// This is not atomic: the key is read, then written, so a write of the key
+    /// by another thread in between is overwritten and concurrent updates can be
+    /// lost. Use [`OptimisticTransactionDB::update_cf`] or
+    /// [`TransactionDB::update_cf`] for keys updated by several threads.
+    ///
+    /// [`OptimisticTransactionDB::update_cf`]: crate::OptimisticTransactionDB::update_cf
+    /// [`TransactionDB::update_cf`]: crate::TransactionDB::update_cf
+    pub fn update_cf<K, F>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        f: F,
+    ) -> Result<Option<Vec<u8>>, Error>
+    where
+        K: AsRef<[u8]>,
+        F: FnOnce(Option<&[u8]>) -> Option<Vec<u8>>,
+    {
+        let key = key.as_ref();
+        let current = self.get_pinned_cf(cf, key)?;
+        let new = f(current.as_deref());
+        match &new {
+            Some(value) => self.put_cf(cf, key, value)?,
+            None => self.delete_cf(cf, key)?,
+        }
+        Ok(new)
+    }
 }
 
 /// Common methods of `DBWithThreadMode` and `OptimisticTransactionDB`.
 impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
-    pub(crate) fn new(inner: D, cfs: T, path: PathBuf, outlive: Vec<OptionsMustOutliveDB>) -> Self {
+    pub(crate) fn new(
+        inner: D,
+        cfs: T,
+        path: PathBuf,
+        write_validation: Option<WriteValidationOptions>,
+        outlive: Vec<OptionsMustOutliveDB>,
+        cf_plugin_names: BTreeMap<u32, PluginNames>,
+        max_open_files: i32,
+    ) -> Self {
         Self {
             inner,
             cfs,
             path,
-            _outlive: outlive,
+            ttls: BTreeMap::new(),
+            write_validation,
+            cf_write_rules: RwLock::new(CfWriteRules::default()),
+            manual_compaction_paused: Mutex::new((0, 0)),
+            stats_history: Mutex::new(StatsHistory::default()),
+            outlive,
+            cf_outlive: Mutex::new(BTreeMap::new()),
+            cf_plugin_names: RwLock::new(cf_plugin_names),
+            max_open_files,
         }
     }
 
+    /// Checks a put or merge against the limits set with `Options::set_write_validation`.
+    fn check_write(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_write(key, value))
+    }
+
+    /// Checks the key of a delete against the limits set with
+    /// `Options::set_write_validation`.
+    fn check_delete(&self, key: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_key(key.len()))
+    }
+
+    /// Checks a write to the column families with the ids `cfs` against the
+    /// frozen column families and their durability policies.
+    fn check_cf_write(
+        &self,
+        cfs: impl IntoIterator<Item = u32>,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        self.cf_write_rules.read().unwrap().check(cfs, writeopts)
+    }
+
+    /// Checks a write batch like `check_cf_write`, decoding it to find the column
+    /// families of its operations only while a column family is frozen or has a
+    /// durability policy. A batch that cannot be decoded is checked against all
+    /// of them.
+    fn check_batch_cf_write(
+        &self,
+        batch: &WriteBatch,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        let rules = self.cf_write_rules.read().unwrap();
+        if rules.is_empty() {
+            return Ok(());
+        }
+        if let Some(ops) = decode_batch_ops(batch.data()) {
+            return rules.check(ops.into_iter().map(|(cf, _)| cf), writeopts);
+        }
+        if !rules.frozen_cfs.is_empty() {
+            return Err(frozen_cf_error());
+        }
+        rules
+            .durability_policies
+            .values()
+            .try_for_each(|policy| policy.check(writeopts.wal_disabled))
+    }
+
+    /// Checks a write to the column family with the id `cf` that does not go
+    /// through write options, e.g. an ingestion.
+    fn check_not_frozen(&self, cf: u32) -> Result<(), Error> {
+        if self.cf_write_rules.read().unwrap().frozen_cfs.contains(&cf) {
+            return Err(frozen_cf_error());
+        }
+        Ok(())
+    }
+
     pub fn list_cf<P: AsRef<Path>>(opts: &Options, path: P) -> Result<Vec<String>, Error> {
         let cpath = to_cpath(path)?;
         let mut length = 0;
@@ -883,10 +1378,63 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
+    /// Returns the path the database was opened at.
     pub fn path(&self) -> &Path {
         self.path.as_path()
     }
 
+    /// Returns an iterator over the open column families, including the default
+    /// one, with their handles, ordered by name, e.g. to flush or compact all of
+    /// them.
+    ///
+    /// The column families are looked up one at a time as the iterator advances,
+    /// without holding a lock in between, so in multi-threaded mode the column
+    /// families created or dropped meanwhile are reflected if they come after
+    /// the last one returned.
+    pub fn cf_handles(&self) -> CfHandles<'_, T> {
+        CfHandles {
+            cfs: &self.cfs,
+            last: None,
+        }
+    }
+
+    /// Counts the operations of `batch` by column family, and lists the ones on
+    /// column family ids the DB has no column family for, e.g. to map or reject a
+    /// batch received from another node before writing it. Writing such a batch
+    /// fails, or skips those operations with
+    /// [`WriteOptions::set_ignore_missing_column_families`].
+    ///
+    /// Fails with an invalid argument error if the batch cannot be decoded.
+    pub fn validate_write_batch(&self, batch: &WriteBatch) -> Result<BatchSummary, Error> {
+        let cf_names = self
+            .cf_handles()
+            .map(|(name, cf)| (column_family_id(cf.inner()), name))
+            .collect();
+        summarize_batch(batch, &cf_names)
+    }
+
+    /// Returns the name of the database directory, i.e. the last component of
+    /// [`path`](Self::path), to tell databases apart in logs. Empty if that
+    /// component is missing or not valid UTF-8.
+    pub fn name(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(std::ffi::OsStr::to_str)
+            .unwrap_or_default()
+    }
+
+    /// Returns the TTL the default column family was opened with, `None` if the DB
+    /// was not opened with a TTL for it.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttls.get(DEFAULT_COLUMN_FAMILY_NAME).copied()
+    }
+
+    /// Returns the TTL the column family was opened with, `None` if it was not
+    /// opened with a TTL. Column families created after opening have no TTL.
+    pub fn ttl_cf(&self, cf: &impl AsColumnFamilyRef) -> Option<Duration> {
+        self.ttls.get(&column_family_name(cf.inner())).copied()
+    }
+
     /// Flushes the WAL buffer. If `sync` is set to `true`, also syncs
     /// the data to disk.
     pub fn flush_wal(&self, sync: bool) -> Result<(), Error> {
@@ -899,7 +1447,82 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
-    /// Flushes database memtables to SST files on the disk.
+    /// Starts a thread calling [`flush_wal(false)`](Self::flush_wal) every
+    /// `interval`, bounding how long writes stay in the WAL buffer of a DB opened
+    /// with [`Options::set_manual_wal_flush`] before reaching the OS.
+    ///
+    /// The thread only holds a weak reference to the DB, and stops once the DB
+    /// is dropped or when the returned handle is dropped, which joins it. A
+    /// failed flush is tried again at the next interval.
+    pub fn spawn_wal_flusher(self: &Arc<Self>, interval: Duration) -> WalFlusherHandle
+    where
+        Self: Send + 'static,
+    {
+        let db = Arc::downgrade(self);
+        let (sender, receiver) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = receiver.recv_timeout(interval) {
+                let Some(db) = db.upgrade() else {
+                    break;
+                };
+                let _ = db.flush_wal(false);
+            }
+        });
+        WalFlusherHandle {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    /// Runs the periodic `tasks` RocksDB otherwise schedules on its own timer
+    /// thread, so they can be driven by the scheduler of the application, e.g.
+    /// when its thread budget rules out the timer thread. The timer thread is
+    /// not started if [`Options::set_stats_dump_period_sec`] and
+    /// [`Options::set_stats_persist_period_sec`] are both set to 0.
+    ///
+    /// [`TickTasks::DUMP_STATS`] returns the `rocksdb.stats` text in the report
+    /// instead of writing it to the LOG, for the application to log it.
+    /// [`TickTasks::RECORD_STATS`] records an in-memory entry of
+    /// [`stats_history`](Self::stats_history), and requires the DB to be opened
+    /// with [`Options::enable_statistics`]. RocksDB's own stats history, in
+    /// memory or persisted to disk, is not involved.
+    pub fn tick(&self, tasks: TickTasks) -> Result<TickReport, Error> {
+        let mut report = TickReport::default();
+        if tasks.contains(TickTasks::FLUSH_WAL) {
+            self.flush_wal(false)?;
+        }
+        if tasks.contains(TickTasks::DUMP_STATS) {
+            report.stats_dump = self.property_value(properties::STATS)?;
+        }
+        if tasks.contains(TickTasks::RECORD_STATS) {
+            let statistics = self
+                .property_value(properties::OPTIONS_STATISTICS)?
+                .ok_or_else(|| {
+                    Error::new("Invalid argument: statistics are not enabled".to_owned())
+                })?;
+            self.stats_history
+                .lock()
+                .unwrap()
+                .record(parse_ticker_counts(&statistics));
+        }
+        Ok(report)
+    }
+
+    /// Returns the entries recorded by [`tick`](Self::tick) with
+    /// [`TickTasks::RECORD_STATS`], oldest first. The most recent
+    /// [`STATS_HISTORY_LEN`] entries are kept, in memory only, so the history
+    /// starts over when the DB is reopened.
+    pub fn stats_history(&self) -> Vec<StatsHistoryEntry> {
+        self.stats_history
+            .lock()
+            .unwrap()
+            .entries
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Flushes the memtables of the default column family to SST files on the disk.
     pub fn flush_opt(&self, flushopts: &FlushOptions) -> Result<(), Error> {
         unsafe {
             ffi_try!(ffi::rocksdb_flush(self.inner.inner(), flushopts.inner));
@@ -907,7 +1530,8 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
-    /// Flushes database memtables to SST files on the disk using default options.
+    /// Flushes the memtables of the default column family to SST files on the disk
+    /// using default options, without looking up its handle.
     pub fn flush(&self) -> Result<(), Error> {
         self.flush_opt(&FlushOptions::default())
     }
@@ -928,6 +1552,44 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         Ok(())
     }
 
+    /// Flushes the column family, waiting for the flush to finish, and returns the
+    /// table files it wrote.
+    ///
+    /// RocksDB's C API has no event listeners, so the files are found by comparing
+    /// the live files of the column family before and after the flush, which races
+    /// with the background flushes and compactions running meanwhile: the files
+    /// they add, and their key ranges, are reported as written by this flush, while
+    /// files of this flush that a compaction already consumed are missing. The
+    /// results are only exact while no other flush or compaction of the column
+    /// family runs, e.g. with automatic compactions disabled.
+    pub fn flush_cf_with_info(&self, cf: &impl AsColumnFamilyRef) -> Result<FlushedFiles, Error> {
+        let (cf_name, _, outputs) =
+            self.live_files_diff_cf(cf, || self.flush_cf_opt(cf, &FlushOptions::default()))?;
+        Ok(FlushedFiles {
+            smallest_key: outputs.iter().filter_map(|f| f.start_key.clone()).min(),
+            largest_key: outputs.iter().filter_map(|f| f.end_key.clone()).max(),
+            bytes_written: outputs.iter().map(|f| f.size as u64).sum(),
+            output_files: outputs.into_iter().map(|f| f.name).collect(),
+            column_family_name: cf_name,
+        })
+    }
+
+    /// Flushes the column family with `opts` and returns the metadata of the table
+    /// files the flush wrote, e.g. to ship them elsewhere.
+    ///
+    /// As with [`flush_cf_with_info`](Self::flush_cf_with_info), the files are found
+    /// by comparing the live files of the column family before and after the flush,
+    /// so files of background jobs finishing at the same time are included. If
+    /// `opts` does not wait for the flush, its files may be missing.
+    pub fn flush_cf_and_get_files(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        opts: &FlushOptions,
+    ) -> Result<Vec<LiveFile>, Error> {
+        let (_, _, outputs) = self.live_files_diff_cf(cf, || self.flush_cf_opt(cf, opts))?;
+        Ok(outputs)
+    }
+
     /// Flushes multiple column families.
     ///
     /// If atomic flush is not enabled, it is equivalent to calling flush_cf multiple times.
@@ -999,6 +1661,48 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         self.get_cf_opt(cf, key.as_ref(), &ReadOptions::default())
     }
 
+    /// Returns the value associated with a key, failing if more than
+    /// `max_operands` merge operands were merged to read it, see
+    /// [`get_with_merge_limit_cf`](Self::get_with_merge_limit_cf).
+    pub fn get_with_merge_limit<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        max_operands: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let context = PerfContext::default();
+        let merged_before = context.internal_merge_count();
+        let iter = self.raw_iterator_opt(ReadOptions::default());
+        read_with_merge_limit(iter, key.as_ref(), &context, merged_before, max_operands)
+    }
+
+    /// Returns the value associated with a key in a column family, failing if
+    /// more than `max_operands` merge operands were merged to read it.
+    ///
+    /// The error is an incomplete error for which
+    /// [`Error::is_merge_operand_threshold_exceeded`] returns `true`, so that the
+    /// caller can compact the range of the key to fold its operands into a value.
+    /// The C API does not expose `ReadOptions::merge_operand_count_threshold`, so
+    /// the key is read through an iterator and the operands it merged are counted
+    /// with the [`PerfContext`] of the calling thread once the read completes: the
+    /// operands are still read and merged, the check only tells the caller that
+    /// the key is due for a compaction. The count needs the perf stats level of
+    /// the thread to be at least [`PerfStatsLevel::EnableCount`], RocksDB's
+    /// default; with [`PerfStatsLevel::Disable`] no read fails.
+    ///
+    /// [`PerfStatsLevel::EnableCount`]: crate::perf::PerfStatsLevel::EnableCount
+    /// [`PerfStatsLevel::Disable`]: crate::perf::PerfStatsLevel::Disable
+    pub fn get_with_merge_limit_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        max_operands: usize,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let context = PerfContext::default();
+        let merged_before = context.internal_merge_count();
+        let iter = self.raw_iterator_cf_opt(cf, ReadOptions::default());
+        read_with_merge_limit(iter, key.as_ref(), &context, merged_before, max_operands)
+    }
+
     /// Return the value associated with a key using RocksDB's PinnableSlice
     /// so as to avoid unnecessary memory copy.
     pub fn get_pinned_opt<K: AsRef<[u8]>>(
@@ -1055,6 +1759,8 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
 
         let key = key.as_ref();
+        check_cf_dropped(cf)?;
+
         unsafe {
             let val = ffi_try!(ffi::rocksdb_get_pinned_cf(
                 self.inner.inner(),
@@ -1082,6 +1788,36 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         self.get_pinned_cf_opt(cf, key, &ReadOptions::default())
     }
 
+    /// Reads the value associated with a key into `buf`, reusing its allocation,
+    /// and returns the length of the value.
+    ///
+    /// `buf` is cleared and then grown as needed to hold the value. If the key is
+    /// not found, `Ok(None)` is returned and `buf` is left untouched.
+    pub fn get_into_cf_opt<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        buf: &mut Vec<u8>,
+        readopts: &ReadOptions,
+    ) -> Result<Option<usize>, Error> {
+        Ok(self.get_pinned_cf_opt(cf, key, readopts)?.map(|value| {
+            buf.clear();
+            buf.extend_from_slice(&value);
+            value.len()
+        }))
+    }
+
+    /// Reads the value associated with a key into `buf` using default read options.
+    /// See [`get_into_cf_opt`](Self::get_into_cf_opt).
+    pub fn get_into_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        buf: &mut Vec<u8>,
+    ) -> Result<Option<usize>, Error> {
+        self.get_into_cf_opt(cf, key, buf, &ReadOptions::default())
+    }
+
     /// Return the values associated with the given keys.
     pub fn multi_get<K, I>(&self, keys: I) -> Vec<Result<Option<Vec<u8>>, Error>>
     where
@@ -1363,40 +2099,232 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
                 "Failed to convert path to CString when creating cf: {err}"
             ))
         })?;
-        Ok(unsafe {
+        let handle = unsafe {
             ffi_try!(ffi::rocksdb_create_column_family(
                 self.inner.inner(),
                 opts.inner,
                 cf_name.as_ptr(),
             ))
-        })
-    }
-
-    pub fn iterator<'a: 'b, 'b>(
-        &'a self,
-        mode: IteratorMode,
-    ) -> DBIteratorWithThreadMode<'b, Self> {
-        let readopts = ReadOptions::default();
-        self.iterator_opt(mode, readopts)
+        };
+        self.cf_outlive
+            .lock()
+            .unwrap()
+            .insert(column_family_id(handle), opts.outlive.clone());
+        self.cf_plugin_names
+            .write()
+            .unwrap()
+            .insert(column_family_id(handle), opts.plugin_names.clone());
+        Ok(handle)
     }
 
-    pub fn iterator_opt<'a: 'b, 'b>(
-        &'a self,
-        mode: IteratorMode,
-        readopts: ReadOptions,
-    ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new(self, readopts, mode)
+    /// Creates a column family and ingests `paths` into it, dropping the column family
+    /// again if the ingestion fails.
+    fn create_cf_handle_with_ingest<P: AsRef<Path>>(
+        &self,
+        name: &str,
+        opts: &Options,
+        paths: Vec<P>,
+        ingest_opts: &IngestExternalFileOptions,
+    ) -> Result<ColumnFamily, Error> {
+        let cf = ColumnFamily {
+            inner: self.create_inner_cf_handle(name, opts)?,
+        };
+        if let Err(err) = self.ingest_external_file_cf_opts(&cf, ingest_opts, paths) {
+            return match self.drop_column_family(cf.inner, cf) {
+                Ok(_) => Err(err),
+                Err(drop_err) => Err(Error::new(format!(
+                    "{err} (failed to drop column family {name}: {drop_err})"
+                ))),
+            };
+        }
+        Ok(cf)
     }
 
-    /// Opens an iterator using the provided ReadOptions.
-    /// This is used when you want to iterate over a specific ColumnFamily with a modified ReadOptions
-    pub fn iterator_cf_opt<'a: 'b, 'b>(
-        &'a self,
-        cf_handle: &impl AsColumnFamilyRef,
-        readopts: ReadOptions,
-        mode: IteratorMode,
-    ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+    /// Creates the column family `name` from the files of `metadata`.
+    ///
+    /// The C API does not expose `CreateColumnFamilyWithImport`, so the files are
+    /// ingested into the new column family instead, after checking that they are
+    /// sorted with the comparator of `opts`.
+    fn create_cf_handle_with_import(
+        &self,
+        name: &str,
+        opts: &Options,
+        import_opts: &ImportColumnFamilyOptions,
+        metadata: &ExportImportFilesMetaData,
+    ) -> Result<ColumnFamily, Error> {
+        let comparator = opts
+            .plugin_names
+            .comparator
+            .as_deref()
+            .unwrap_or(BYTEWISE_COMPARATOR_NAME);
+        if metadata.db_comparator_name != comparator {
+            return Err(Error::new(format!(
+                "Invalid argument: files sorted with comparator `{}` instead of `{comparator}`",
+                metadata.db_comparator_name
+            )));
+        }
+        if metadata.files.is_empty() {
+            return Ok(ColumnFamily {
+                inner: self.create_inner_cf_handle(name, opts)?,
+            });
+        }
+        let mut ingest_opts = IngestExternalFileOptions::default();
+        ingest_opts.set_move_files(import_opts.move_files);
+        self.create_cf_handle_with_ingest(name, opts, metadata.files.clone(), &ingest_opts)
+    }
+
+    /// Creates the column family `name` holding the entries of `cf` visible at the time
+    /// of the call.
+    ///
+    /// The C API exposes neither `Checkpoint::ExportColumnFamily` nor
+    /// `CreateColumnFamilyWithImport`, so the entries are written from an iterator over
+    /// `cf` to an SST file in a temporary directory of the system, which is ingested
+    /// into the new column family and removed afterwards, also on failure.
+    fn copy_cf_handle(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        name: &str,
+        opts: &Options,
+    ) -> Result<ColumnFamily, Error> {
+        static NEXT_COPY_ID: AtomicUsize = AtomicUsize::new(0);
+
+        check_cf_dropped(cf)?;
+        let dir = std::env::temp_dir().join(format!(
+            "rocksdb-copy-cf-{}-{}.tmp",
+            std::process::id(),
+            NEXT_COPY_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&dir).map_err(|err| {
+            Error::new(format!(
+                "Failed to create export directory {}: {err}",
+                dir.display()
+            ))
+        })?;
+        let result = self
+            .export_to_sst_cf(
+                cf,
+                ..,
+                &dir.join("export.sst"),
+                opts,
+                ReadOptions::default(),
+            )
+            .and_then(|info| match info {
+                Some(info) => {
+                    let mut ingest_opts = IngestExternalFileOptions::default();
+                    ingest_opts.set_move_files(true);
+                    self.create_cf_handle_with_ingest(
+                        name,
+                        opts,
+                        vec![info.file_path],
+                        &ingest_opts,
+                    )
+                }
+                None => Ok(ColumnFamily {
+                    inner: self.create_inner_cf_handle(name, opts)?,
+                }),
+            });
+        // the ingested file has been moved or copied into the column family, so a
+        // failed cleanup only leaves the temporary directory behind
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    /// Creates the column families `names` in a single call, returning the handles of
+    /// the ones created. Creation stops at the first failure, whose error lists the
+    /// names that were not created.
+    fn create_inner_cf_handles<N: AsRef<str>>(
+        &self,
+        names: &[N],
+        opts: &Options,
+    ) -> (
+        Vec<*mut ffi::rocksdb_column_family_handle_t>,
+        Result<(), Error>,
+    ) {
+        let c_names = match names
+            .iter()
+            .map(|name| CString::new(name.as_ref()))
+            .collect::<Result<Vec<_>, _>>()
+        {
+            Ok(c_names) => c_names,
+            Err(err) => {
+                return (
+                    vec![],
+                    Err(Error::new(format!(
+                        "Failed to convert path to CString when creating cf: {err}"
+                    ))),
+                )
+            }
+        };
+        let c_name_ptrs: Vec<_> = c_names.iter().map(|name| name.as_ptr()).collect();
+
+        let mut created: size_t = 0;
+        let mut err: *mut c_char = ptr::null_mut();
+        let handles = unsafe {
+            let list = ffi::rocksdb_create_column_families(
+                self.inner.inner(),
+                opts.inner,
+                c_name_ptrs.len() as c_int,
+                c_name_ptrs.as_ptr(),
+                &mut created,
+                &mut err,
+            );
+            let handles = if list.is_null() {
+                vec![]
+            } else {
+                slice::from_raw_parts(list, created).to_vec()
+            };
+            ffi::rocksdb_create_column_families_destroy(list);
+            handles
+        };
+        self.cf_outlive.lock().unwrap().extend(
+            handles
+                .iter()
+                .map(|&handle| (column_family_id(handle), opts.outlive.clone())),
+        );
+        self.cf_plugin_names.write().unwrap().extend(
+            handles
+                .iter()
+                .map(|&handle| (column_family_id(handle), opts.plugin_names.clone())),
+        );
+
+        let result = if err.is_null() {
+            Ok(())
+        } else {
+            let failed: Vec<_> = names[handles.len()..].iter().map(AsRef::as_ref).collect();
+            Err(Error::new(format!(
+                "{} (column families not created: {})",
+                error_message(err),
+                failed.join(", ")
+            )))
+        };
+        (handles, result)
+    }
+
+    pub fn iterator<'a: 'b, 'b>(
+        &'a self,
+        mode: IteratorMode,
+    ) -> DBIteratorWithThreadMode<'b, Self> {
+        let readopts = ReadOptions::default();
+        self.iterator_opt(mode, readopts)
+    }
+
+    pub fn iterator_opt<'a: 'b, 'b>(
+        &'a self,
+        mode: IteratorMode,
+        readopts: ReadOptions,
+    ) -> DBIteratorWithThreadMode<'b, Self> {
+        DBIteratorWithThreadMode::new(self, readopts, mode)
+    }
+
+    /// Opens an iterator using the provided ReadOptions.
+    /// This is used when you want to iterate over a specific ColumnFamily with a modified ReadOptions
+    pub fn iterator_cf_opt<'a: 'b, 'b>(
+        &'a self,
+        cf_handle: &impl AsColumnFamilyRef,
+        readopts: ReadOptions,
+        mode: IteratorMode,
+    ) -> DBIteratorWithThreadMode<'b, Self> {
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -1424,13 +2352,21 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         )
     }
 
+    /// Opens an iterator over the column family with the default read options.
+    ///
+    /// If the column family has a prefix extractor, seeks with
+    /// [`IteratorMode::From`] are prefix seeks and may skip the keys of other
+    /// prefixes. Scans across prefixes should use [`full_iterator_cf`], or an
+    /// iterator opened with [`ReadOptions::set_total_order_seek`].
+    ///
+    /// [`full_iterator_cf`]: Self::full_iterator_cf
     pub fn iterator_cf<'a: 'b, 'b>(
         &'a self,
         cf_handle: &impl AsColumnFamilyRef,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -1440,7 +2376,43 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
+    }
+
+    /// Opens an iterator over the keys within `range`, in the given direction.
+    /// See [`range_cf`](Self::range_cf).
+    pub fn range<'a: 'b, 'b, K, R>(
+        &'a self,
+        range: R,
+        direction: Direction,
+    ) -> DBIteratorWithThreadMode<'b, Self>
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let mode = range_iterator_mode(direction);
+        DBIteratorWithThreadMode::new(self, range_read_options(&range), mode)
+    }
+
+    /// Opens an iterator over the keys of the column family within `range`, in the
+    /// given direction.
+    ///
+    /// The bounds are copied into the read options of the iterator, so `range` does
+    /// not need to outlive it. Keys are assumed to be sorted in lexicographical
+    /// order: an excluded lower bound or an included upper bound `k` is turned into
+    /// the bound `k\0`, the key immediately following `k`.
+    pub fn range_cf<'a: 'b, 'b, K, R>(
+        &'a self,
+        cf_handle: &impl AsColumnFamilyRef,
+        range: R,
+        direction: Direction,
+    ) -> DBIteratorWithThreadMode<'b, Self>
+    where
+        K: AsRef<[u8]> + ?Sized,
+        R: RangeBounds<K>,
+    {
+        let mode = range_iterator_mode(direction);
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, range_read_options(&range), mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -1452,12 +2424,31 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
     }
 
+    /// Same as `prefix_iterator_cf` but with custom read options, on top of which
+    /// `prefix_same_as_start` is set. The iterator stops at the last key with the
+    /// prefix of the column family's prefix extractor, unless `total_order_seek` is
+    /// set in `readopts`.
+    pub fn prefix_iterator_cf_opt<'a, P: AsRef<[u8]>>(
+        &'a self,
+        cf_handle: &impl AsColumnFamilyRef,
+        prefix: P,
+        mut readopts: ReadOptions,
+    ) -> DBIteratorWithThreadMode<'a, Self> {
+        readopts.set_prefix_same_as_start(true);
+        DBIteratorWithThreadMode::<'a, Self>::new_cf(
+            self,
+            cf_handle,
+            readopts,
+            IteratorMode::From(prefix.as_ref(), Direction::Forward),
+        )
+    }
+
     /// Opens a raw iterator over the database, using the default read options
     pub fn raw_iterator<'a: 'b, 'b>(&'a self) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
@@ -1470,7 +2461,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -1487,13 +2478,54 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
     }
 
     pub fn snapshot(&self) -> SnapshotWithThreadMode<Self> {
         SnapshotWithThreadMode::<Self>::new(self)
     }
 
+    /// Creates an iterator over each of the `[from, to)` ranges of the column family,
+    /// all reading the DB as of `snapshot`, which must be a snapshot of this DB.
+    ///
+    /// As the iterators share one consistent view, the ranges can be scanned in
+    /// parallel, e.g. one per thread, and together return the same entries as a
+    /// single scan of the snapshot over the union of the ranges. The iterators
+    /// borrow the snapshot, so they cannot outlive it.
+    pub fn iterators_for_ranges_cf<'a: 'b, 'b>(
+        &'a self,
+        cf_handle: &impl AsColumnFamilyRef,
+        ranges: &[(&[u8], &[u8])],
+        snapshot: &'b SnapshotWithThreadMode<'a, Self>,
+    ) -> Vec<DBIteratorWithThreadMode<'b, Self>> {
+        ranges
+            .iter()
+            .map(|&(from, to)| {
+                let mut readopts = ReadOptions::default();
+                readopts.set_snapshot(snapshot);
+                readopts.set_iterate_range(from..to);
+                self.iterator_cf_opt(cf_handle, readopts, IteratorMode::Start)
+            })
+            .collect()
+    }
+
+    /// Scans the keys of the column family within `range` in chunks of
+    /// [`ScanOptions::batch_size`] keys, recreating the underlying iterator for
+    /// every chunk so that long scans don't keep old memtables and SST files
+    /// pinned.
+    ///
+    /// Each chunk resumes right after the last key returned, so no key is returned
+    /// twice. With [`ScanOptions::use_snapshot`], the whole scan sees the DB as of
+    /// the call, regardless of concurrent writes.
+    pub fn scan_cf<'a, C: AsColumnFamilyRef>(
+        &'a self,
+        cf: &'a C,
+        range: impl IterateBounds,
+        opts: ScanOptions,
+    ) -> DBScan<'a, Self> {
+        DBScan::new(self, cf, range, opts)
+    }
+
     pub fn put_opt<K, V>(&self, key: K, value: V, writeopts: &WriteOptions) -> Result<(), Error>
     where
         K: AsRef<[u8]>,
@@ -1501,6 +2533,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     {
         let key = key.as_ref();
         let value = value.as_ref();
+        self.check_write(key, value)?;
+
+        self.check_cf_write([DEFAULT_COLUMN_FAMILY_ID], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_put(
@@ -1528,6 +2563,10 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     {
         let key = key.as_ref();
         let value = value.as_ref();
+        self.check_write(key, value)?;
+
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_put_cf(
@@ -1550,6 +2589,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     {
         let key = key.as_ref();
         let value = value.as_ref();
+        self.check_write(key, value)?;
+
+        self.check_cf_write([DEFAULT_COLUMN_FAMILY_ID], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_merge(
@@ -1577,6 +2619,10 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
     {
         let key = key.as_ref();
         let value = value.as_ref();
+        self.check_write(key, value)?;
+
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_merge_cf(
@@ -1598,6 +2644,9 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
         let key = key.as_ref();
+        self.check_delete(key)?;
+
+        self.check_cf_write([DEFAULT_COLUMN_FAMILY_ID], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_delete(
@@ -1617,6 +2666,10 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
         let key = key.as_ref();
+        self.check_delete(key)?;
+
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
 
         unsafe {
             ffi_try!(ffi::rocksdb_delete_cf(
@@ -1646,6 +2699,65 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         self.put_cf_opt(cf, key.as_ref(), value.as_ref(), &WriteOptions::default())
     }
 
+    /// Like [`put_opt`](Self::put_opt), retrying transient failures such as
+    /// write stalls with `no_slowdown` according to `policy`.
+    pub fn put_opt_with_retry<K, V>(
+        &self,
+        policy: &RetryPolicy,
+        key: K,
+        value: V,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        retry::with_backoff(policy, || {
+            self.put_opt(key.as_ref(), value.as_ref(), writeopts)
+        })
+    }
+
+    /// Like [`put_cf_opt`](Self::put_cf_opt), retrying transient failures
+    /// according to `policy`.
+    pub fn put_cf_opt_with_retry<K, V>(
+        &self,
+        policy: &RetryPolicy,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        value: V,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        retry::with_backoff(policy, || {
+            self.put_cf_opt(cf, key.as_ref(), value.as_ref(), writeopts)
+        })
+    }
+
+    pub fn put_with_retry<K, V>(&self, policy: &RetryPolicy, key: K, value: V) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.put_opt_with_retry(policy, key, value, &WriteOptions::default())
+    }
+
+    pub fn put_cf_with_retry<K, V>(
+        &self,
+        policy: &RetryPolicy,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        value: V,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.put_cf_opt_with_retry(policy, cf, key, value, &WriteOptions::default())
+    }
+
     pub fn merge<K, V>(&self, key: K, value: V) -> Result<(), Error>
     where
         K: AsRef<[u8]>,
@@ -1674,82 +2786,301 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         self.delete_cf_opt(cf, key.as_ref(), &WriteOptions::default())
     }
 
-    /// Runs a manual compaction on the Range of keys given. This is not likely to be needed for typical usage.
-    pub fn compact_range<S: AsRef<[u8]>, E: AsRef<[u8]>>(&self, start: Option<S>, end: Option<E>) {
-        unsafe {
-            let start = start.as_ref().map(AsRef::as_ref);
-            let end = end.as_ref().map(AsRef::as_ref);
+    /// Sets the value of `key` at the user-defined timestamp `ts`. Requires a
+    /// comparator set with [`Options::set_comparator_with_ts`].
+    pub fn put_with_ts_opt<K, Ts, V>(
+        &self,
+        key: K,
+        ts: Ts,
+        value: V,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        Ts: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        let ts = ts.as_ref();
+        let value = value.as_ref();
+        self.check_write(key, value)?;
 
-            ffi::rocksdb_compact_range(
-                self.inner.inner(),
-                opt_bytes_to_ptr(start),
-                start.map_or(0, <[u8]>::len) as size_t,
-                opt_bytes_to_ptr(end),
-                end.map_or(0, <[u8]>::len) as size_t,
-            );
-        }
-    }
+        self.check_cf_write([DEFAULT_COLUMN_FAMILY_ID], writeopts)?;
 
-    /// Same as `compact_range` but with custom options.
-    pub fn compact_range_opt<S: AsRef<[u8]>, E: AsRef<[u8]>>(
-        &self,
-        start: Option<S>,
-        end: Option<E>,
-        opts: &CompactOptions,
-    ) {
         unsafe {
-            let start = start.as_ref().map(AsRef::as_ref);
-            let end = end.as_ref().map(AsRef::as_ref);
-
-            ffi::rocksdb_compact_range_opt(
+            ffi_try!(ffi::rocksdb_put_with_ts(
                 self.inner.inner(),
-                opts.inner,
-                opt_bytes_to_ptr(start),
-                start.map_or(0, <[u8]>::len) as size_t,
-                opt_bytes_to_ptr(end),
-                end.map_or(0, <[u8]>::len) as size_t,
-            );
+                writeopts.inner,
+                key.as_ptr() as *const c_char,
+                key.len() as size_t,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+                value.as_ptr() as *const c_char,
+                value.len() as size_t,
+            ));
+            Ok(())
         }
     }
 
-    /// Runs a manual compaction on the Range of keys given on the
-    /// given column family. This is not likely to be needed for typical usage.
-    pub fn compact_range_cf<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+    pub fn put_cf_with_ts_opt<K, Ts, V>(
         &self,
         cf: &impl AsColumnFamilyRef,
-        start: Option<S>,
-        end: Option<E>,
-    ) {
-        unsafe {
-            let start = start.as_ref().map(AsRef::as_ref);
-            let end = end.as_ref().map(AsRef::as_ref);
+        key: K,
+        ts: Ts,
+        value: V,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        Ts: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let key = key.as_ref();
+        let ts = ts.as_ref();
+        let value = value.as_ref();
+        self.check_write(key, value)?;
 
-            ffi::rocksdb_compact_range_cf(
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
+
+        unsafe {
+            ffi_try!(ffi::rocksdb_put_cf_with_ts(
                 self.inner.inner(),
+                writeopts.inner,
                 cf.inner(),
-                opt_bytes_to_ptr(start),
-                start.map_or(0, <[u8]>::len) as size_t,
-                opt_bytes_to_ptr(end),
-                end.map_or(0, <[u8]>::len) as size_t,
-            );
+                key.as_ptr() as *const c_char,
+                key.len() as size_t,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+                value.as_ptr() as *const c_char,
+                value.len() as size_t,
+            ));
+            Ok(())
         }
     }
 
-    /// Same as `compact_range_cf` but with custom options.
-    pub fn compact_range_cf_opt<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+    pub fn put_with_ts<K, Ts, V>(&self, key: K, ts: Ts, value: V) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        Ts: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.put_with_ts_opt(key, ts, value, &WriteOptions::default())
+    }
+
+    pub fn put_cf_with_ts<K, Ts, V>(
         &self,
         cf: &impl AsColumnFamilyRef,
-        start: Option<S>,
-        end: Option<E>,
-        opts: &CompactOptions,
-    ) {
-        unsafe {
-            let start = start.as_ref().map(AsRef::as_ref);
-            let end = end.as_ref().map(AsRef::as_ref);
+        key: K,
+        ts: Ts,
+        value: V,
+    ) -> Result<(), Error>
+    where
+        K: AsRef<[u8]>,
+        Ts: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        self.put_cf_with_ts_opt(cf, key, ts, value, &WriteOptions::default())
+    }
 
-            ffi::rocksdb_compact_range_cf_opt(
-                self.inner.inner(),
-                cf.inner(),
+    /// Deletes `key` at the user-defined timestamp `ts`. Requires a comparator set
+    /// with [`Options::set_comparator_with_ts`].
+    pub fn delete_with_ts_opt<K: AsRef<[u8]>, Ts: AsRef<[u8]>>(
+        &self,
+        key: K,
+        ts: Ts,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        let key = key.as_ref();
+        let ts = ts.as_ref();
+        self.check_delete(key)?;
+
+        self.check_cf_write([DEFAULT_COLUMN_FAMILY_ID], writeopts)?;
+
+        unsafe {
+            ffi_try!(ffi::rocksdb_delete_with_ts(
+                self.inner.inner(),
+                writeopts.inner,
+                key.as_ptr() as *const c_char,
+                key.len() as size_t,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+            ));
+            Ok(())
+        }
+    }
+
+    pub fn delete_cf_with_ts_opt<K: AsRef<[u8]>, Ts: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        ts: Ts,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        let key = key.as_ref();
+        let ts = ts.as_ref();
+        self.check_delete(key)?;
+
+        check_cf_dropped(cf)?;
+        self.check_cf_write([column_family_id(cf.inner())], writeopts)?;
+
+        unsafe {
+            ffi_try!(ffi::rocksdb_delete_cf_with_ts(
+                self.inner.inner(),
+                writeopts.inner,
+                cf.inner(),
+                key.as_ptr() as *const c_char,
+                key.len() as size_t,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+            ));
+            Ok(())
+        }
+    }
+
+    pub fn delete_with_ts<K: AsRef<[u8]>, Ts: AsRef<[u8]>>(
+        &self,
+        key: K,
+        ts: Ts,
+    ) -> Result<(), Error> {
+        self.delete_with_ts_opt(key, ts, &WriteOptions::default())
+    }
+
+    pub fn delete_cf_with_ts<K: AsRef<[u8]>, Ts: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        ts: Ts,
+    ) -> Result<(), Error> {
+        self.delete_cf_with_ts_opt(cf, key, ts, &WriteOptions::default())
+    }
+
+    /// Raises the timestamp below which the history of the column family may be
+    /// garbage-collected to `ts`, for a column family with a comparator set with
+    /// [`Options::set_comparator_with_ts`].
+    ///
+    /// Compactions then only keep, of the versions of a key with a timestamp
+    /// below `ts`, the newest one, and reads at a timestamp below `ts` fail with
+    /// an invalid argument error. Lowering it also fails with an invalid argument
+    /// error.
+    pub fn increase_full_history_ts_low_cf<Ts: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        ts: Ts,
+    ) -> Result<(), Error> {
+        let ts = ts.as_ref();
+        check_cf_dropped(cf)?;
+        unsafe {
+            ffi_try!(ffi::rocksdb_increase_full_history_ts_low(
+                self.inner.inner(),
+                cf.inner(),
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the timestamp set with
+    /// [`increase_full_history_ts_low_cf`](Self::increase_full_history_ts_low_cf),
+    /// or an empty one if it was never set.
+    pub fn get_full_history_ts_low_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+    ) -> Result<Vec<u8>, Error> {
+        check_cf_dropped(cf)?;
+        unsafe {
+            let mut ts_len: size_t = 0;
+            let ts = ffi_try!(ffi::rocksdb_get_full_history_ts_low(
+                self.inner.inner(),
+                cf.inner(),
+                &mut ts_len,
+            ));
+            if ts.is_null() {
+                return Ok(vec![]);
+            }
+            let result = slice::from_raw_parts(ts as *const u8, ts_len).to_vec();
+            ffi::rocksdb_free(ts as *mut c_void);
+            Ok(result)
+        }
+    }
+
+    /// Runs a manual compaction on the Range of keys given. This is not likely to be needed for typical usage.
+    pub fn compact_range<S: AsRef<[u8]>, E: AsRef<[u8]>>(&self, start: Option<S>, end: Option<E>) {
+        unsafe {
+            let start = start.as_ref().map(AsRef::as_ref);
+            let end = end.as_ref().map(AsRef::as_ref);
+
+            ffi::rocksdb_compact_range(
+                self.inner.inner(),
+                opt_bytes_to_ptr(start),
+                start.map_or(0, <[u8]>::len) as size_t,
+                opt_bytes_to_ptr(end),
+                end.map_or(0, <[u8]>::len) as size_t,
+            );
+        }
+    }
+
+    /// Same as `compact_range` but with custom options.
+    pub fn compact_range_opt<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+        &self,
+        start: Option<S>,
+        end: Option<E>,
+        opts: &CompactOptions,
+    ) {
+        unsafe {
+            let start = start.as_ref().map(AsRef::as_ref);
+            let end = end.as_ref().map(AsRef::as_ref);
+
+            ffi::rocksdb_compact_range_opt(
+                self.inner.inner(),
+                opts.inner,
+                opt_bytes_to_ptr(start),
+                start.map_or(0, <[u8]>::len) as size_t,
+                opt_bytes_to_ptr(end),
+                end.map_or(0, <[u8]>::len) as size_t,
+            );
+        }
+    }
+
+    /// Runs a manual compaction on the Range of keys given on the
+    /// given column family. This is not likely to be needed for typical usage.
+    pub fn compact_range_cf<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        start: Option<S>,
+        end: Option<E>,
+    ) {
+        unsafe {
+            let start = start.as_ref().map(AsRef::as_ref);
+            let end = end.as_ref().map(AsRef::as_ref);
+
+            ffi::rocksdb_compact_range_cf(
+                self.inner.inner(),
+                cf.inner(),
+                opt_bytes_to_ptr(start),
+                start.map_or(0, <[u8]>::len) as size_t,
+                opt_bytes_to_ptr(end),
+                end.map_or(0, <[u8]>::len) as size_t,
+            );
+        }
+    }
+
+    /// Same as `compact_range_cf` but with custom options.
+    pub fn compact_range_cf_opt<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        start: Option<S>,
+        end: Option<E>,
+        opts: &CompactOptions,
+    ) {
+        unsafe {
+            let start = start.as_ref().map(AsRef::as_ref);
+            let end = end.as_ref().map(AsRef::as_ref);
+
+            ffi::rocksdb_compact_range_cf_opt(
+                self.inner.inner(),
+                cf.inner(),
                 opts.inner,
                 opt_bytes_to_ptr(start),
                 start.map_or(0, <[u8]>::len) as size_t,
@@ -1759,6 +3090,206 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Same as `compact_range_cf_opt` but fails with an incomplete error if manual
+    /// compactions are disabled with [`disable_manual_compaction`] before or while
+    /// it runs, in which case the compaction is abandoned.
+    ///
+    /// The C API does not return the status of a manual compaction, so other
+    /// failures are not reported.
+    ///
+    /// [`disable_manual_compaction`]: Self::disable_manual_compaction
+    pub fn try_compact_range_cf_opt<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        start: Option<S>,
+        end: Option<E>,
+        opts: &CompactOptions,
+    ) -> Result<(), Error> {
+        let paused = || Error::new("Result incomplete: Manual compaction paused".to_owned());
+
+        let (depth, pauses) = *self.manual_compaction_paused.lock().unwrap();
+        if depth > 0 {
+            return Err(paused());
+        }
+        self.compact_range_cf_opt(cf, start, end, opts);
+        if self.manual_compaction_paused.lock().unwrap().1 != pauses {
+            return Err(paused());
+        }
+        Ok(())
+    }
+
+    /// Stops the running manual compactions and makes new ones return right away
+    /// until [`enable_manual_compaction`](Self::enable_manual_compaction) is
+    /// called, e.g. so a long manual compaction does not block shutdown.
+    ///
+    /// The compactions stop at their next check, which happens as each key is
+    /// processed. Only [`try_compact_range_cf_opt`](Self::try_compact_range_cf_opt)
+    /// reports them as incomplete. Calls nest: manual compactions are only enabled
+    /// again once `enable_manual_compaction` has been called as many times.
+    pub fn disable_manual_compaction(&self) {
+        let mut paused = self.manual_compaction_paused.lock().unwrap();
+        unsafe {
+            ffi::rocksdb_disable_manual_compaction(self.inner.inner());
+        }
+        paused.0 += 1;
+        paused.1 += 1;
+    }
+
+    /// Reverts a call to [`disable_manual_compaction`](Self::disable_manual_compaction).
+    /// Does nothing if manual compactions are not disabled.
+    pub fn enable_manual_compaction(&self) {
+        let mut paused = self.manual_compaction_paused.lock().unwrap();
+        if paused.0 > 0 {
+            unsafe {
+                ffi::rocksdb_enable_manual_compaction(self.inner.inner());
+            }
+            paused.0 -= 1;
+        }
+    }
+
+    /// Same as `compact_range_cf_opt` but returns the files the compaction consumed
+    /// and produced. Like [`try_compact_range_cf_opt`](Self::try_compact_range_cf_opt),
+    /// fails if manual compactions are disabled.
+    ///
+    /// RocksDB's C API has no event listeners, so the files are found by comparing
+    /// the live files of the column family before and after the compaction, which
+    /// races with the background flushes and compactions running meanwhile: the
+    /// files they add are reported as outputs and the files they remove as inputs,
+    /// while outputs of this compaction that one of them already consumed are
+    /// missing. The results are only exact while no other flush or compaction of
+    /// the column family runs, e.g. with automatic compactions disabled.
+    pub fn compact_range_cf_opt_with_info<S: AsRef<[u8]>, E: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        start: Option<S>,
+        end: Option<E>,
+        opts: &CompactOptions,
+    ) -> Result<CompactedFiles, Error> {
+        let (cf_name, inputs, outputs) =
+            self.live_files_diff_cf(cf, || self.try_compact_range_cf_opt(cf, start, end, opts))?;
+        Ok(CompactedFiles {
+            output_level: outputs.iter().map(|f| f.level).max(),
+            bytes_read: inputs.iter().map(|f| f.size as u64).sum(),
+            bytes_written: outputs.iter().map(|f| f.size as u64).sum(),
+            input_files: inputs.into_iter().map(|f| f.name).collect(),
+            output_files: outputs.into_iter().map(|f| f.name).collect(),
+            column_family_name: cf_name,
+        })
+    }
+
+    /// Moves all L0 files of the column family to `target_level` without rewriting
+    /// them, like RocksDB's `PromoteL0`. This saves the I/O of a compaction when the
+    /// data was written in sorted, disjoint batches, e.g. by a bulk load.
+    ///
+    /// Fails with an invalid argument error, leaving the files where they are, if
+    /// `target_level` is not a level below L0 of the column family, if the key
+    /// ranges of the L0 files overlap, or if any level below L0 holds files. The
+    /// key ranges are compared bytewise. Does nothing if L0 is empty; the memtables
+    /// are not flushed first.
+    ///
+    /// The C API has no `PromoteL0`, so the files are moved by a manual compaction
+    /// that RocksDB turns into trivial moves. Unlike `PromoteL0`, the levels below
+    /// `target_level` must be empty too, as the compaction would move their files
+    /// along. A column family with a compaction filter gets its files rewritten
+    /// instead, which is reported as an incomplete error once the data has been
+    /// compacted to `target_level`.
+    pub fn promote_l0_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        target_level: i32,
+    ) -> Result<(), Error> {
+        let invalid = |msg: String| Error::new(format!("Invalid argument: {msg}"));
+
+        // the property is missing for the levels the column family does not have
+        let level_exists = match usize::try_from(target_level) {
+            Ok(level) if level >= 1 => self
+                .property_value_cf(cf, properties::num_files_at_level(level))?
+                .is_some(),
+            _ => false,
+        };
+        if !level_exists {
+            return Err(invalid(format!("Invalid target level {target_level}")));
+        }
+
+        let cf_name = column_family_name(cf.inner());
+        let files: Vec<LiveFile> = self
+            .live_files()?
+            .into_iter()
+            .filter(|f| f.column_family_name == cf_name)
+            .collect();
+        if let Some(f) = files.iter().find(|f| f.level > 0) {
+            return Err(invalid(format!(
+                "Level {} is not empty, holding {}",
+                f.level, f.name
+            )));
+        }
+        let mut l0: Vec<&LiveFile> = files.iter().filter(|f| f.level == 0).collect();
+        if l0.is_empty() {
+            return Ok(());
+        }
+        l0.sort_by(|a, b| a.start_key.cmp(&b.start_key));
+        if let Some(pair) = l0
+            .windows(2)
+            .find(|pair| pair[0].end_key >= pair[1].start_key)
+        {
+            return Err(invalid(format!(
+                "Files {} and {} in L0 overlap",
+                pair[0].name, pair[1].name
+            )));
+        }
+
+        let mut opts = CompactOptions::default();
+        opts.set_change_level(true);
+        opts.set_target_level(target_level);
+        opts.set_bottommost_level_compaction(BottommostLevelCompaction::Skip);
+        self.try_compact_range_cf_opt(cf, None::<&[u8]>, None::<&[u8]>, &opts)?;
+
+        let moved = self
+            .live_files()?
+            .into_iter()
+            .filter(|f| f.column_family_name == cf_name && f.level == target_level)
+            .map(|f| f.name)
+            .collect::<BTreeSet<_>>();
+        if let Some(f) = l0.iter().find(|f| !moved.contains(&f.name)) {
+            return Err(Error::new(format!(
+                "Result incomplete: {} was rewritten instead of moved to level {target_level}",
+                f.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Runs `job` and returns the name of the column family with the table files of
+    /// it removed and added in the meantime.
+    fn live_files_diff_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        job: impl FnOnce() -> Result<(), Error>,
+    ) -> Result<(String, Vec<LiveFile>, Vec<LiveFile>), Error> {
+        let cf_name = column_family_name(cf.inner());
+        let cf_files = |files: Vec<LiveFile>| -> Vec<LiveFile> {
+            files
+                .into_iter()
+                .filter(|f| f.column_family_name == cf_name)
+                .collect()
+        };
+
+        let before = cf_files(self.live_files()?);
+        job()?;
+        let after = cf_files(self.live_files()?);
+
+        let removed = before
+            .iter()
+            .filter(|f| !after.iter().any(|a| a.name == f.name))
+            .cloned()
+            .collect();
+        let added = after
+            .into_iter()
+            .filter(|f| !before.iter().any(|b| b.name == f.name))
+            .collect();
+        Ok((cf_name, removed, added))
+    }
+
     /// Wait for all flush and compactions jobs to finish. Jobs to wait include the
     /// unscheduled (queued, but not scheduled yet).
     ///
@@ -1918,55 +3449,290 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         )
     }
 
-    /// The sequence number of the most recent transaction.
-    pub fn latest_sequence_number(&self) -> u64 {
-        unsafe { ffi::rocksdb_get_latest_sequence_number(self.inner.inner()) }
+    /// Returns the number of compactions currently running.
+    pub fn num_running_compactions(&self) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value(properties::NUM_RUNNING_COMPACTIONS)?
+            .unwrap_or_default())
     }
 
-    /// Iterate over batches of write operations since a given sequence.
-    ///
-    /// Produce an iterator that will provide the batches of write operations
-    /// that have occurred since the given sequence (see
-    /// `latest_sequence_number()`). Use the provided iterator to retrieve each
-    /// (`u64`, `WriteBatch`) tuple, and then gather the individual puts and
-    /// deletes using the `WriteBatch::iterate()` function.
-    ///
-    /// Calling `get_updates_since()` with a sequence number that is out of
-    /// bounds will return an error.
-    pub fn get_updates_since(&self, seq_number: u64) -> Result<DBWALIterator, Error> {
-        unsafe {
-            // rocksdb_wal_readoptions_t does not appear to have any functions
-            // for creating and destroying it; fortunately we can pass a nullptr
-            // here to get the default behavior
-            let opts: *const ffi::rocksdb_wal_readoptions_t = ptr::null();
-            let iter = ffi_try!(ffi::rocksdb_get_updates_since(
-                self.inner.inner(),
-                seq_number,
-                opts
-            ));
-            Ok(DBWALIterator {
-                inner: iter,
-                start_seq_number: seq_number,
-            })
-        }
+    /// Returns the number of flushes currently running.
+    pub fn num_running_flushes(&self) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value(properties::NUM_RUNNING_FLUSHES)?
+            .unwrap_or_default())
     }
 
-    /// Tries to catch up with the primary by reading as much as possible from the
-    /// log files.
-    pub fn try_catch_up_with_primary(&self) -> Result<(), Error> {
-        unsafe {
-            ffi_try!(ffi::rocksdb_try_catch_up_with_primary(self.inner.inner()));
-        }
-        Ok(())
+    /// Returns the estimated memory held by the readers of the open SST files of the
+    /// default column family, e.g. their index and filter blocks when they are not
+    /// cached in the block cache.
+    pub fn estimate_table_readers_mem(&self) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value(properties::ESTIMATE_TABLE_READERS_MEM)?
+            .unwrap_or_default())
     }
 
-    /// Loads a list of external SST files created with SstFileWriter into the DB with default opts
-    pub fn ingest_external_file<P: AsRef<Path>>(&self, paths: Vec<P>) -> Result<(), Error> {
-        let opts = IngestExternalFileOptions::default();
-        self.ingest_external_file_opts(&opts, paths)
+    /// Returns the estimated memory held by the readers of the open SST files of the
+    /// column family.
+    pub fn estimate_table_readers_mem_cf(&self, cf: &impl AsColumnFamilyRef) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value_cf(cf, properties::ESTIMATE_TABLE_READERS_MEM)?
+            .unwrap_or_default())
     }
 
-    /// Loads a list of external SST files created with SstFileWriter into the DB
+    /// Returns what fills the block cache of the DB, by the role of the cached
+    /// entries. Fails with a not supported error if the default column family has
+    /// no block cache.
+    ///
+    /// RocksDB scans the cache for the stats when they are asked for, but reuses
+    /// the results of the previous scan if it happened less than 10 seconds ago.
+    pub fn block_cache_entry_stats(&self) -> Result<BlockCacheEntryStats, Error> {
+        let text = self
+            .property_value(properties::BLOCK_CACHE_ENTRY_STATS)?
+            .ok_or_else(|| Error::new("Not implemented: the DB has no block cache".to_owned()))?;
+        parse_block_cache_entry_stats(&text)
+    }
+
+    /// Returns the number of SST files at `level` of the default column family.
+    pub fn num_files_at_level(&self, level: usize) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value(properties::num_files_at_level(level))?
+            .unwrap_or_default())
+    }
+
+    /// Returns the number of SST files at `level` of the column family.
+    pub fn num_files_at_level_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        level: usize,
+    ) -> Result<u64, Error> {
+        Ok(self
+            .property_int_value_cf(cf, properties::num_files_at_level(level))?
+            .unwrap_or_default())
+    }
+
+    /// Returns the limit on open files the DB runs with, `-1` if all files are kept
+    /// open.
+    ///
+    /// RocksDB raises a limit below 20 to 20 and lowers it to the limit of open
+    /// files of the process, so the value can differ from the one set with
+    /// [`Options::set_max_open_files`].
+    pub fn max_open_files(&self) -> i32 {
+        self.max_open_files
+    }
+
+    /// Returns the number of SST file readers the table cache of the DB holds at
+    /// most, derived from [`max_open_files`](Self::max_open_files) the way RocksDB
+    /// sizes it: 10 less than the limit, as other files are kept open too, or
+    /// 4194304 when all files are kept open.
+    ///
+    /// The C API does not expose the table cache, so its usage is not available.
+    /// [`estimate_table_readers_mem`](Self::estimate_table_readers_mem) reports the
+    /// memory held by the cached readers.
+    pub fn table_cache_capacity(&self) -> usize {
+        const INFINITE_CAPACITY: usize = 0x40_0000;
+        const NUM_NON_TABLE_CACHE_FILES: usize = 10;
+
+        match usize::try_from(self.max_open_files) {
+            Ok(max_open_files) => max_open_files.saturating_sub(NUM_NON_TABLE_CACHE_FILES),
+            Err(_) => INFINITE_CAPACITY,
+        }
+    }
+
+    /// Returns the name of the comparator the column family was opened or created
+    /// with.
+    ///
+    /// The C API cannot read the comparator back from RocksDB, so only the names
+    /// given to [`Options::set_comparator`] or [`Options::set_comparator_with_ts`]
+    /// are known. For a column family whose options only name their comparator,
+    /// e.g. options loaded with [`Options::load_latest`], this reports the default
+    /// bytewise comparator regardless.
+    pub fn comparator_name_cf(&self, cf: &impl AsColumnFamilyRef) -> Result<String, Error> {
+        self.plugin_names_cf(cf).map(|names| {
+            names
+                .comparator
+                .unwrap_or_else(|| BYTEWISE_COMPARATOR_NAME.to_owned())
+        })
+    }
+
+    /// Returns the name of the merge operator the column family was opened or created
+    /// with, or `None` if it has none.
+    ///
+    /// As with [`comparator_name_cf`](Self::comparator_name_cf), only the names
+    /// given to the merge operator setters of [`Options`] are known, so this
+    /// returns `None` for a merge operator the options only name, e.g. options
+    /// loaded with [`Options::load_latest`].
+    pub fn merge_operator_name_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+    ) -> Result<Option<String>, Error> {
+        self.plugin_names_cf(cf).map(|names| names.merge_operator)
+    }
+
+    fn plugin_names_cf(&self, cf: &impl AsColumnFamilyRef) -> Result<PluginNames, Error> {
+        check_cf_dropped(cf)?;
+        let cf_id = column_family_id(cf.inner());
+        self.cf_plugin_names
+            .read()
+            .unwrap()
+            .get(&cf_id)
+            .cloned()
+            .ok_or_else(|| Error::new(format!("Invalid column family: {cf_id}")))
+    }
+
+    /// The sequence number of the most recent transaction.
+    pub fn latest_sequence_number(&self) -> u64 {
+        unsafe { ffi::rocksdb_get_latest_sequence_number(self.inner.inner()) }
+    }
+
+    /// Returns the value of `key` in the column family as it was at sequence
+    /// number `seqno`, for debugging, starting from `snapshot`, which must be a
+    /// snapshot of this DB taken at or before `seqno`.
+    ///
+    /// RocksDB only keeps the history needed by the live snapshots, and the C API
+    /// cannot read at an arbitrary sequence number, so the value is read in
+    /// `snapshot` and the writes of the WAL up to `seqno` are replayed on top of
+    /// it. This fails with an incomplete error for which
+    /// [`Error::is_history_unavailable`] returns `true` if `snapshot` was taken
+    /// after `seqno`, or if the WAL misses some of those writes, e.g. because they
+    /// were written without the WAL or the WAL was purged after a flush. Merge
+    /// operands are not replayed either, nor range deletions in column families
+    /// with a custom comparator, which fail with a not supported error.
+    pub fn get_at_seqno_cf_with_snapshot<K: AsRef<[u8]>>(
+        &self,
+        snapshot: &SnapshotWithThreadMode<Self>,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        seqno: u64,
+    ) -> Result<Option<Vec<u8>>, Error> {
+        let key = key.as_ref();
+        let latest = self.latest_sequence_number();
+        if seqno > latest {
+            return Err(Error::new(format!(
+                "Invalid argument: sequence number {seqno} is after the latest one {latest}"
+            )));
+        }
+
+        let base = snapshot.sequence_number();
+        if base > seqno {
+            return Err(history_unavailable(
+                seqno,
+                &format!("the snapshot was taken at {base}"),
+            ));
+        }
+        let mut readopts = ReadOptions::default();
+        readopts.set_snapshot(snapshot);
+        let mut value = self.get_cf_opt(cf, key, &readopts)?;
+
+        let cf_id = column_family_id(cf.inner());
+        let mut bytewise = None;
+        let mut next_seqno = base + 1;
+        if next_seqno <= seqno {
+            let updates = self
+                .get_updates_since(base)
+                .map_err(|err| history_unavailable(seqno, err.as_ref()))?;
+            for update in updates {
+                let (batch_seqno, batch) = update?;
+                // the WAL starts at the batch holding `base`, which the snapshot sees
+                if batch_seqno + batch.len() as u64 <= next_seqno {
+                    continue;
+                }
+                if batch_seqno > seqno || batch_seqno != next_seqno {
+                    break;
+                }
+                let ops = decode_batch_ops(batch.data()).ok_or_else(|| {
+                    Error::new(format!(
+                        "Not implemented: the write batch at sequence number {batch_seqno} \
+                         cannot be replayed"
+                    ))
+                })?;
+                next_seqno += ops.len() as u64;
+                for (op_seqno, (op_cf, op)) in (batch_seqno..=seqno).zip(ops) {
+                    if op_cf != cf_id {
+                        continue;
+                    }
+                    match op {
+                        BatchOp::Put { key: k, value: v } if k == key => value = Some(v.to_vec()),
+                        BatchOp::Delete { key: k } if k == key => value = None,
+                        BatchOp::DeleteRange { from, to } => {
+                            if bytewise.is_none() {
+                                bytewise =
+                                    Some(self.comparator_name_cf(cf)? == BYTEWISE_COMPARATOR_NAME);
+                            }
+                            if bytewise == Some(false) {
+                                return Err(Error::new(format!(
+                                    "Not implemented: the range deletion at sequence number \
+                                     {op_seqno} cannot be replayed with a custom comparator"
+                                )));
+                            }
+                            if from <= key && key < to {
+                                value = None;
+                            }
+                        }
+                        BatchOp::Merge { key: k, .. } | BatchOp::Other { key: k } if k == key => {
+                            return Err(Error::new(format!(
+                                "Not implemented: the write at sequence number {op_seqno} \
+                                 cannot be replayed"
+                            )));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if next_seqno <= seqno {
+            return Err(history_unavailable(
+                seqno,
+                &format!("the WAL misses the writes after {}", next_seqno - 1),
+            ));
+        }
+        Ok(value)
+    }
+
+    /// Iterate over batches of write operations since a given sequence.
+    ///
+    /// Produce an iterator that will provide the batches of write operations
+    /// that have occurred since the given sequence (see
+    /// `latest_sequence_number()`). Use the provided iterator to retrieve each
+    /// (`u64`, `WriteBatch`) tuple, and then gather the individual puts and
+    /// deletes using the `WriteBatch::iterate()` function.
+    ///
+    /// Calling `get_updates_since()` with a sequence number that is out of
+    /// bounds will return an error.
+    pub fn get_updates_since(&self, seq_number: u64) -> Result<DBWALIterator, Error> {
+        unsafe {
+            // rocksdb_wal_readoptions_t does not appear to have any functions
+            // for creating and destroying it; fortunately we can pass a nullptr
+            // here to get the default behavior
+            let opts: *const ffi::rocksdb_wal_readoptions_t = ptr::null();
+            let iter = ffi_try!(ffi::rocksdb_get_updates_since(
+                self.inner.inner(),
+                seq_number,
+                opts
+            ));
+            Ok(DBWALIterator {
+                inner: iter,
+                start_seq_number: seq_number,
+            })
+        }
+    }
+
+    /// Tries to catch up with the primary by reading as much as possible from the
+    /// log files.
+    pub fn try_catch_up_with_primary(&self) -> Result<(), Error> {
+        unsafe {
+            ffi_try!(ffi::rocksdb_try_catch_up_with_primary(self.inner.inner()));
+        }
+        Ok(())
+    }
+
+    /// Loads a list of external SST files created with SstFileWriter into the DB with default opts
+    pub fn ingest_external_file<P: AsRef<Path>>(&self, paths: Vec<P>) -> Result<(), Error> {
+        let opts = IngestExternalFileOptions::default();
+        self.ingest_external_file_opts(&opts, paths)
+    }
+
+    /// Loads a list of external SST files created with SstFileWriter into the DB
     pub fn ingest_external_file_opts<P: AsRef<Path>>(
         &self,
         opts: &IngestExternalFileOptions,
@@ -2002,12 +3768,193 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         self.ingest_external_file_raw_cf(cf, opts, &paths_v, &cpaths)
     }
 
+    /// Loads a list of SST files held in memory (e.g. produced with
+    /// [`SstFileWriter::finish_to_buffer`]) into the DB.
+    ///
+    /// The buffers are written with `std::fs` to a temporary directory under
+    /// [`std::env::temp_dir`], outside the DB directory and bypassing the DB's
+    /// `Env`, and ingested from there. The directory is removed once the
+    /// ingestion finishes, whether it succeeded or not, so `move_files` only
+    /// decides whether the temporary files are moved or copied into the DB.
+    /// RocksDB copies them anyway if the temporary directory is on another file
+    /// system than the DB.
+    ///
+    /// Fails with an invalid argument error if the DB was opened with
+    /// [`Env::mem_env`], which cannot read the temporary files.
+    ///
+    /// [`SstFileWriter::finish_to_buffer`]: crate::SstFileWriter::finish_to_buffer
+    pub fn ingest_external_file_from_buffers(
+        &self,
+        opts: &IngestExternalFileOptions,
+        buffers: &[&[u8]],
+    ) -> Result<(), Error> {
+        self.with_buffers_as_files(buffers, |paths| self.ingest_external_file_opts(opts, paths))
+    }
+
+    /// Loads a list of SST files held in memory into the DB for given Column Family.
+    ///
+    /// See [`ingest_external_file_from_buffers`](Self::ingest_external_file_from_buffers).
+    pub fn ingest_external_file_from_buffers_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        opts: &IngestExternalFileOptions,
+        buffers: &[&[u8]],
+    ) -> Result<(), Error> {
+        self.with_buffers_as_files(buffers, |paths| {
+            self.ingest_external_file_cf_opts(cf, opts, paths)
+        })
+    }
+
+    /// Writes the entries of the column family with keys in `[from, to)` to a new
+    /// SST file at `path`, returning its description. The file can be loaded into
+    /// another DB with [`ingest_external_file_cf`](Self::ingest_external_file_cf).
+    ///
+    /// The entries are read from an iterator, so they are a consistent view of the
+    /// column family as of the start of the export, and are streamed to the file
+    /// without being held in memory. `readopts` is used to create the iterator,
+    /// except for its bounds, which are replaced by the range: set e.g. its
+    /// readahead size to control the reads of a large export. `opts` configures the
+    /// SST file writer and must use the comparator of the column family, which is
+    /// checked against the latest OPTIONS file of the DB, and its table properties
+    /// collectors are applied to the new file.
+    ///
+    /// If the range holds no entries, an invalid argument error is returned and no
+    /// file is created, as an SST file cannot be empty. If the export fails midway,
+    /// the partially written file is removed.
+    pub fn export_range_to_sst_cf<K, P>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        from: K,
+        to: K,
+        path: P,
+        opts: &Options,
+        readopts: ReadOptions,
+    ) -> Result<ExternalSstFileInfo, Error>
+    where
+        K: Into<Vec<u8>>,
+        P: AsRef<Path>,
+    {
+        self.export_to_sst_cf(cf, from.into()..to.into(), path.as_ref(), opts, readopts)?
+            .ok_or_else(|| {
+                Error::new("Invalid argument: no entries in the range to export".to_owned())
+            })
+    }
+
+    /// Writes the entries of `cf` in `range` to a new SST file at `path`, returning
+    /// `None` without creating the file if the range holds no entries.
+    fn export_to_sst_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        range: impl IterateBounds,
+        path: &Path,
+        opts: &Options,
+        mut readopts: ReadOptions,
+    ) -> Result<Option<ExternalSstFileInfo>, Error> {
+        check_cf_dropped(cf)?;
+        let comparator = opts
+            .plugin_names
+            .comparator
+            .as_deref()
+            .unwrap_or(BYTEWISE_COMPARATOR_NAME);
+        let cf_comparator = self.comparator_name_cf(cf)?;
+        if comparator != cf_comparator {
+            return Err(Error::new(format!(
+                "Invalid argument: the options use comparator `{comparator}` while the \
+                 column family uses `{cf_comparator}`"
+            )));
+        }
+
+        readopts.set_iterate_range(range);
+        let mut iter = self.raw_iterator_cf_opt(cf, readopts);
+        iter.seek_to_first();
+        let Some(smallest_key) = iter.key().map(<[u8]>::to_vec) else {
+            return iter.status().map(|()| None);
+        };
+
+        let mut writer = SstFileWriter::create(opts);
+        let mut write = || -> Result<(Vec<u8>, u64), Error> {
+            writer.open(path)?;
+            let mut largest_key = vec![];
+            let mut num_entries = 0;
+            while let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+                writer.put(key, value)?;
+                largest_key.clear();
+                largest_key.extend_from_slice(key);
+                num_entries += 1;
+                iter.next();
+            }
+            iter.status()?;
+            writer.finish()?;
+            Ok((largest_key, num_entries))
+        };
+        match write() {
+            Ok((largest_key, num_entries)) => Ok(Some(ExternalSstFileInfo {
+                file_path: path.to_path_buf(),
+                smallest_key,
+                largest_key,
+                num_entries,
+                file_size: writer.file_size(),
+            })),
+            Err(err) => {
+                let _ = fs::remove_file(path);
+                Err(err)
+            }
+        }
+    }
+
+    fn with_buffers_as_files<F>(&self, buffers: &[&[u8]], f: F) -> Result<(), Error>
+    where
+        F: FnOnce(Vec<PathBuf>) -> Result<(), Error>,
+    {
+        static NEXT_INGEST_ID: AtomicUsize = AtomicUsize::new(0);
+
+        if self
+            .outlive
+            .iter()
+            .find_map(OptionsMustOutliveDB::env)
+            .is_some_and(Env::is_in_memory)
+        {
+            return Err(Error::new(
+                "Invalid argument: SST buffers cannot be ingested into a DB using an \
+                 in-memory Env"
+                    .to_owned(),
+            ));
+        }
+        let dir = std::env::temp_dir().join(format!(
+            "rocksdb-ingest-{}-{}.tmp",
+            std::process::id(),
+            NEXT_INGEST_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        let write_files = || -> std::io::Result<Vec<PathBuf>> {
+            fs::create_dir(&dir)?;
+            buffers
+                .iter()
+                .enumerate()
+                .map(|(i, buffer)| {
+                    let file = dir.join(format!("{i:06}.sst"));
+                    fs::write(&file, buffer)?;
+                    Ok(file)
+                })
+                .collect()
+        };
+        let result = match write_files() {
+            Ok(paths) => f(paths),
+            Err(e) => Err(Error::new(format!(
+                "Failed to write SST buffers to `{}`: {e}",
+                dir.display()
+            ))),
+        };
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
     fn ingest_external_file_raw(
         &self,
         opts: &IngestExternalFileOptions,
         paths_v: &[CString],
         cpaths: &[*const c_char],
     ) -> Result<(), Error> {
+        self.check_not_frozen(DEFAULT_COLUMN_FAMILY_ID)?;
         unsafe {
             ffi_try!(ffi::rocksdb_ingest_external_file(
                 self.inner.inner(),
@@ -2026,6 +3973,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         paths_v: &[CString],
         cpaths: &[*const c_char],
     ) -> Result<(), Error> {
+        self.check_not_frozen(column_family_id(cf.inner()))?;
         unsafe {
             ffi_try!(ffi::rocksdb_ingest_external_file_cf(
                 self.inner.inner(),
@@ -2043,11 +3991,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         unsafe {
             let ptr = ffi::rocksdb_get_column_family_metadata(self.inner.inner());
 
-            let metadata = ColumnFamilyMetaData {
-                size: ffi::rocksdb_column_family_metadata_get_size(ptr),
-                name: from_cstr(ffi::rocksdb_column_family_metadata_get_name(ptr)),
-                file_count: ffi::rocksdb_column_family_metadata_get_file_count(ptr),
-            };
+            let metadata = ColumnFamilyMetaData::from_c(ptr);
 
             // destroy
             ffi::rocksdb_column_family_metadata_destroy(ptr);
@@ -2065,11 +4009,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         unsafe {
             let ptr = ffi::rocksdb_get_column_family_metadata_cf(self.inner.inner(), cf.inner());
 
-            let metadata = ColumnFamilyMetaData {
-                size: ffi::rocksdb_column_family_metadata_get_size(ptr),
-                name: from_cstr(ffi::rocksdb_column_family_metadata_get_name(ptr)),
-                file_count: ffi::rocksdb_column_family_metadata_get_file_count(ptr),
-            };
+            let metadata = ColumnFamilyMetaData::from_c(ptr);
 
             // destroy
             ffi::rocksdb_column_family_metadata_destroy(ptr);
@@ -2079,6 +4019,57 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Returns the stats of each level of the column family, from level 0 to the
+    /// last one, including the empty ones, e.g. to tell a write stall is coming
+    /// from the compaction scores.
+    ///
+    /// The files and sizes come from the metadata of the column family, the
+    /// scores and the files being compacted from the `rocksdb.cfstats` text, as
+    /// the C API has no map properties. The two are read one after the other, so
+    /// they may disagree if a flush or compaction finishes in between.
+    pub fn level_stats_cf(&self, cf: &impl AsColumnFamilyRef) -> Result<Vec<LevelStats>, Error> {
+        let text = self
+            .property_value_cf(cf, properties::CFSTATS_NO_FILE_HISTOGRAM)?
+            .ok_or_else(|| Error::new("Not implemented: no compaction stats".to_owned()))?;
+        let scores = parse_compaction_scores(&text)?;
+        Ok(self
+            .get_column_family_metadata_cf(cf)
+            .levels
+            .into_iter()
+            .map(|level| {
+                let (score, being_compacted_files) =
+                    scores.get(&level.level).copied().unwrap_or_default();
+                LevelStats {
+                    level: level.level,
+                    num_files: level.file_count,
+                    size_bytes: level.size,
+                    score,
+                    being_compacted_files,
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the statistics of `cf`, see [`CfStats`].
+    ///
+    /// The sizes of the memtables and of the pending compactions come from integer
+    /// properties, the files and sizes of the levels from the metadata of the
+    /// column family.
+    pub fn cf_stats_snapshot(&self, cf: &impl AsColumnFamilyRef) -> Result<CfStats, Error> {
+        check_cf_dropped(cf)?;
+        let levels = self.get_column_family_metadata_cf(cf).levels;
+        Ok(CfStats {
+            num_files: levels.iter().map(|level| level.file_count as u64).collect(),
+            level_bytes: levels.iter().map(|level| level.size).collect(),
+            memtable_bytes: self
+                .property_int_value_cf(cf, properties::CUR_SIZE_ALL_MEM_TABLES)?
+                .unwrap_or_default(),
+            pending_compaction_bytes: self
+                .property_int_value_cf(cf, properties::ESTIMATE_PENDING_COMPACTION_BYTES)?
+                .unwrap_or_default(),
+        })
+    }
+
     /// Returns a list of all table files with their level, start key
     /// and end key
     pub fn live_files(&self) -> Result<Vec<LiveFile>, Error> {
@@ -2180,11 +4171,14 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Drops the column family and destroys its handle `cf`, returning what RocksDB
+    /// borrows from its options if it was created after the DB was opened, for the
+    /// caller to keep as long as other handles to it are alive.
     fn drop_column_family<C>(
         &self,
         cf_inner: *mut ffi::rocksdb_column_family_handle_t,
         cf: C,
-    ) -> Result<(), Error> {
+    ) -> Result<Option<OptionsMustOutliveDB>, Error> {
         unsafe {
             // first mark the column family as dropped
             ffi_try!(ffi::rocksdb_drop_column_family(
@@ -2192,10 +4186,18 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
                 cf_inner
             ));
         }
+        let cf_id = column_family_id(cf_inner);
+        {
+            let mut rules = self.cf_write_rules.write().unwrap();
+            rules.durability_policies.remove(&cf_id);
+            rules.frozen_cfs.remove(&cf_id);
+        }
+        let outlive = self.cf_outlive.lock().unwrap().remove(&cf_id);
+        self.cf_plugin_names.write().unwrap().remove(&cf_id);
         // then finally reclaim any resources (mem, files) by destroying the only single column
         // family handle by drop()-ing it
         drop(cf);
-        Ok(())
+        Ok(outlive)
     }
 
     pub fn get_properties_of_all_range(
@@ -2254,13 +4256,259 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
         }
     }
 
+    /// Folds the user-collected properties of every table file of the column family
+    /// into one map, calling `reducer` with the accumulated map and the properties of
+    /// each table in turn, e.g. to sum a per-file key count across the column family.
+    pub fn aggregate_user_properties_cf<F>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        mut reducer: F,
+    ) -> Result<UserProperties, Error>
+    where
+        F: FnMut(&mut UserProperties, &UserProperties),
+    {
+        let mut aggregate = BTreeMap::new();
+        for table in self.get_properties_of_all_range(cf)?.tables {
+            reducer(&mut aggregate, &table.user_collected_properties());
+        }
+        Ok(aggregate)
+    }
+
+    /// Returns the properties of the table files `files` of the column family, keyed
+    /// by the names given in `files`. Names are matched on their file name, so both
+    /// the names reported by [`live_files`](Self::live_files) and
+    /// [`CompactedFiles`] and plain names such as `000012.sst` are accepted.
+    /// Files that are no longer live are missing from the result.
+    ///
+    /// Only the tables overlapping the key ranges of `files` are read. This lets a
+    /// [`CompactionFilterFactory`] base its decision on the user-collected
+    /// properties of the files about to be compacted, e.g. the live files of the
+    /// column family before a full manual compaction, as RocksDB's C API does not
+    /// pass the input files of a compaction to the factory.
+    ///
+    /// [`CompactionFilterFactory`]: crate::compaction_filter_factory::CompactionFilterFactory
+    pub fn properties_for_files(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        files: &[&str],
+    ) -> Result<BTreeMap<String, TableProperties>, Error> {
+        fn file_name(path: &str) -> Option<&std::ffi::OsStr> {
+            Path::new(path).file_name()
+        }
+
+        let cf_name = column_family_name(cf.inner());
+        let ranges: Vec<_> = self
+            .live_files()?
+            .into_iter()
+            .filter(|f| {
+                f.column_family_name == cf_name
+                    && files
+                        .iter()
+                        .any(|name| file_name(name) == file_name(&f.name))
+            })
+            .filter_map(|f| Some((f.start_key?, next_key(&f.end_key?))))
+            .collect();
+        if ranges.is_empty() {
+            return Ok(BTreeMap::new());
+        }
+
+        let mut properties = BTreeMap::new();
+        for table in self.get_properties_of_tables_in_range(cf, &ranges)?.tables {
+            let table_name = table.name().to_string_lossy().into_owned();
+            if let Some(name) = files
+                .iter()
+                .find(|name| file_name(name) == file_name(&table_name))
+            {
+                properties.insert((*name).to_owned(), table);
+            }
+        }
+        Ok(properties)
+    }
+
+    /// Returns the names of the live table files of the column family whose writing
+    /// started before `cutoff`, named as in [`live_files`](Self::live_files), e.g. to
+    /// drop their key ranges with
+    /// [`delete_file_in_range_cf`](Self::delete_file_in_range_cf).
+    ///
+    /// The times are the ones recorded by [`WriteTimesCollectorFactory`], see
+    /// [`TableProperties::recorded_write_start_time`]. A compaction output counts as
+    /// written when the compaction started, however old its data. Files without a
+    /// recorded time, such as the ones written before the factory was registered,
+    /// are never reported.
+    ///
+    /// [`WriteTimesCollectorFactory`]: crate::table_properties::WriteTimesCollectorFactory
+    /// [`TableProperties::recorded_write_start_time`]: crate::table_properties::TableProperties::recorded_write_start_time
+    pub fn files_written_before_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        cutoff: SystemTime,
+    ) -> Result<Vec<String>, Error> {
+        let old_tables: Vec<_> = self
+            .get_properties_of_all_range(cf)?
+            .tables
+            .iter()
+            .filter(|table| {
+                table
+                    .recorded_write_start_time()
+                    .is_some_and(|time| time < cutoff)
+            })
+            .map(|table| table.name().to_string_lossy().into_owned())
+            .collect();
+
+        let cf_name = column_family_name(cf.inner());
+        Ok(self
+            .live_files()?
+            .into_iter()
+            .filter(|f| {
+                f.column_family_name == cf_name
+                    && old_tables
+                        .iter()
+                        .any(|table| Path::new(table).file_name() == Path::new(&f.name).file_name())
+            })
+            .map(|f| f.name)
+            .collect())
+    }
+
+    /// Returns the range tombstones persisted in the SST files of the column family,
+    /// ordered by start key and sequence number.
+    ///
+    /// Tombstones are read from the properties recorded by
+    /// [`RangeTombstoneCollectorFactory`], which must have been registered on the
+    /// column family options when the files were written. This fails with
+    /// [`ErrorKind::InvalidArgument`] if any SST file of the column family was
+    /// written without it.
+    ///
+    /// Tombstones still held in memtables are not reported until they are
+    /// flushed, call [`flush_cf`](Self::flush_cf) first to include them.
+    /// Tombstones dropped by compaction are no longer reported.
+    ///
+    /// [`ErrorKind::InvalidArgument`]: crate::ErrorKind::InvalidArgument
+    /// [`RangeTombstoneCollectorFactory`]: crate::table_properties::RangeTombstoneCollectorFactory
+    pub fn range_tombstones_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+    ) -> Result<Vec<RangeTombstone>, Error> {
+        let collection = self.get_properties_of_all_range(cf)?;
+        let mut tombstones = vec![];
+        for table in &collection.tables {
+            // the collector records the property for every file, even without tombstones
+            let data = table
+                .user_collected_properties()
+                .remove(RANGE_TOMBSTONES_PROPERTY)
+                .ok_or_else(|| {
+                    Error::new(format!(
+                        "Invalid argument: SST file `{}` was written without the \
+                         RangeTombstoneCollectorFactory",
+                        table.name().to_string_lossy()
+                    ))
+                })?;
+            tombstones.extend(decode_range_tombstones(&data));
+        }
+        tombstones.sort();
+        // A tombstone spanning several output files of a compaction is recorded
+        // once per file.
+        tombstones.dedup();
+        Ok(tombstones)
+    }
+
+    /// Returns the key ranges of the column family that may hold data written after
+    /// sequence number `seq`, ordered by key, with overlapping ranges merged.
+    ///
+    /// Ranges are read from the properties recorded by [`SeqnoRangeCollectorFactory`],
+    /// which must have been registered on the column family options when the files
+    /// were written, and are ordered bytewise. Writes still held in memtables are not
+    /// reported until they are flushed. Compaction into the bottommost level resets
+    /// the sequence numbers of keys not protected by a snapshot, so keep a snapshot
+    /// taken at `seq` alive to not miss them.
+    ///
+    /// [`SeqnoRangeCollectorFactory`]: crate::table_properties::SeqnoRangeCollectorFactory
+    pub fn changed_key_ranges_since_cf(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        seq: u64,
+    ) -> Result<Vec<KeyRange>, Error> {
+        let collection = self.get_properties_of_all_range(cf)?;
+        let mut ranges: Vec<_> = collection
+            .tables
+            .iter()
+            .filter_map(|table| {
+                table
+                    .user_collected_properties()
+                    .get(SEQNO_RANGE_PROPERTY)
+                    .and_then(|data| decode_seqno_range(data))
+            })
+            .filter(|range| range.largest_seqno > seq)
+            .collect();
+        ranges.sort();
+
+        let mut merged: Vec<KeyRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.smallest_key <= last.largest_key => {
+                    if range.largest_key > last.largest_key {
+                        last.largest_key = range.largest_key;
+                    }
+                    last.largest_seqno = last.largest_seqno.max(range.largest_seqno);
+                }
+                _ => merged.push(range),
+            }
+        }
+        Ok(merged)
+    }
+
+    /// Returns the estimated number of distinct keys with a value in the SST files
+    /// of the column family, merging the sketches recorded by
+    /// [`HllKeyCountCollectorFactory`], which must have been registered on the
+    /// column family options when the files were written.
+    ///
+    /// A key written to several files is counted once. Keys still held in memtables
+    /// are not counted until they are flushed, and deleted keys are counted until
+    /// compaction drops their values. Files written without the collector are
+    /// skipped, and sketches of different precisions are merged at the lowest one.
+    ///
+    /// [`HllKeyCountCollectorFactory`]: crate::table_properties::HllKeyCountCollectorFactory
+    pub fn estimate_unique_keys_cf(&self, cf: &impl AsColumnFamilyRef) -> Result<u64, Error> {
+        let collection = self.get_properties_of_all_range(cf)?;
+        let mut merged: Option<HyperLogLog> = None;
+        for table in &collection.tables {
+            let properties = table.user_collected_properties();
+            let Some(data) = properties.get(HLL_KEY_COUNT_PROPERTY) else {
+                continue;
+            };
+            let sketch = HyperLogLog::decode(data).ok_or_else(|| {
+                Error::new(
+                    "Corruption: malformed HyperLogLog sketch in table properties".to_owned(),
+                )
+            })?;
+            match &mut merged {
+                Some(merged) => merged.merge(&sketch),
+                None => merged = Some(sketch),
+            }
+        }
+        Ok(merged.map_or(0, |sketch| sketch.estimate().round() as u64))
+    }
+
     /// Get the approximate sizes of the ranges.
     ///
-    /// For now it only get the file stats approximation.
+    /// For now it only get the file stats approximation, see
+    /// [`get_approximate_sizes_opt`](Self::get_approximate_sizes_opt) to include the
+    /// memtables.
     pub fn get_approximate_sizes<K: AsRef<[u8]>>(
         &self,
         cf: &impl AsColumnFamilyRef,
         ranges: &[(K, K)],
+    ) -> Result<Vec<u64>, Error> {
+        self.get_approximate_sizes_opt(cf, ranges, &SizeApproximationOptions::default())
+    }
+
+    /// Get the approximate sizes of the ranges, including the data in the memtables
+    /// and the SST files as set in `opts`. Fails with an invalid argument error if
+    /// both are excluded.
+    pub fn get_approximate_sizes_opt<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        ranges: &[(K, K)],
+        opts: &SizeApproximationOptions,
     ) -> Result<Vec<u64>, Error> {
         unsafe {
             let num_ranges = ranges.len();
@@ -2284,7 +4532,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
             let mut sizes = vec![064; num_ranges];
 
             let db = self.inner.inner();
-            ffi_try!(ffi::rocksdb_approximate_sizes_cf(
+            ffi_try!(ffi::rocksdb_approximate_sizes_cf_with_flags(
                 db,
                 cf.inner(),
                 num_ranges as i32,
@@ -2292,6 +4540,7 @@ impl<T: ThreadMode, D: DBInner> DBCommon<T, D> {
                 range_start_lens.as_ptr(),
                 range_limit_keys.as_ptr(),
                 range_limit_lens.as_ptr(),
+                opts.flags(),
                 sizes.as_mut_ptr()
             ));
             Ok(sizes)
@@ -2312,16 +4561,113 @@ impl<I: DBInner> DBCommon<SingleThreaded, I> {
     /// Drops the column family with the given name
     pub fn drop_cf(&mut self, name: &str) -> Result<(), Error> {
         if let Some(cf) = self.cfs.cfs.remove(name) {
-            self.drop_column_family(cf.inner, cf)
+            // no iterator can be alive, so the options are released with the handle
+            self.drop_column_family(cf.inner, cf).map(drop)
         } else {
             Err(Error::new(format!("Invalid column family: {name}")))
         }
     }
 
+    /// Creates several column families with the same options in a single call.
+    ///
+    /// Creation stops at the first failure. The column families created before it
+    /// stay usable, and the returned error lists the names that were not created.
+    pub fn create_cfs<N: AsRef<str>>(&mut self, names: &[N], opts: &Options) -> Result<(), Error> {
+        let (handles, result) = self.create_inner_cf_handles(names, opts);
+        for (name, inner) in names.iter().zip(handles) {
+            self.cfs
+                .cfs
+                .insert(name.as_ref().to_string(), ColumnFamily { inner });
+        }
+        result
+    }
+
+    /// Creates a column family and ingests the external SST files `paths` into it
+    /// before making it available through [`cf_handle`](Self::cf_handle).
+    ///
+    /// If the ingestion fails the column family is dropped again, so that no empty
+    /// column family is left behind.
+    pub fn create_cf_with_ingest<N: AsRef<str>, P: AsRef<Path>>(
+        &mut self,
+        name: N,
+        opts: &Options,
+        paths: Vec<P>,
+        ingest_opts: &IngestExternalFileOptions,
+    ) -> Result<(), Error> {
+        let cf = self.create_cf_handle_with_ingest(name.as_ref(), opts, paths, ingest_opts)?;
+        self.cfs.cfs.insert(name.as_ref().to_string(), cf);
+        Ok(())
+    }
+
+    /// Creates the column family `name` populated with the SST files of `metadata`,
+    /// e.g. exported from a column family of another DB, before making it available
+    /// through [`cf_handle`](Self::cf_handle).
+    ///
+    /// `opts` must use the comparator the files are sorted with. If the import
+    /// fails, no column family is left behind.
+    pub fn create_cf_with_import<N: AsRef<str>>(
+        &mut self,
+        name: N,
+        opts: &Options,
+        import_opts: &ImportColumnFamilyOptions,
+        metadata: &ExportImportFilesMetaData,
+    ) -> Result<(), Error> {
+        let cf = self.create_cf_handle_with_import(name.as_ref(), opts, import_opts, metadata)?;
+        self.cfs.cfs.insert(name.as_ref().to_string(), cf);
+        Ok(())
+    }
+
+    /// Creates the column family `name` with a copy of the entries the column family
+    /// `src` holds at the time of the call, flushed or not. Later writes to either
+    /// column family do not affect the other.
+    ///
+    /// `opts` are the options of the new column family and must use the comparator
+    /// of `src`. The copy holds the latest value of each key: deleted keys, older
+    /// versions and merge operands are resolved away.
+    ///
+    /// This is not RocksDB's export and import of a column family, which the C API
+    /// does not expose: the entries are read with an iterator and written to a new
+    /// SST file, so the copy takes time and space linear in the size of `src`.
+    pub fn copy_cf<N: AsRef<str>>(
+        &mut self,
+        src: &str,
+        name: N,
+        opts: &Options,
+    ) -> Result<(), Error> {
+        let src = self
+            .cf_handle(src)
+            .ok_or_else(|| Error::new(format!("Invalid column family: {src}")))?;
+        let cf = self.copy_cf_handle(src, name.as_ref(), opts)?;
+        self.cfs.cfs.insert(name.as_ref().to_string(), cf);
+        Ok(())
+    }
+
+    /// Drops several column families, one after the other. Every column family is
+    /// dropped even if dropping another one failed, and the returned error lists
+    /// the names that could not be dropped.
+    ///
+    /// This is not atomic: a failure, or a crash midway, leaves the other column
+    /// families dropped. RocksDB's C API has no `DropColumnFamilies`, which drops
+    /// them in a single manifest write.
+    pub fn drop_cfs<N: AsRef<str>>(&mut self, names: &[N]) -> Result<(), Error> {
+        collect_drop_errors(names.iter().map(|name| {
+            let name = name.as_ref();
+            (name, self.drop_cf(name))
+        }))
+    }
+
     /// Returns the underlying column family handle
     pub fn cf_handle(&self, name: &str) -> Option<&ColumnFamily> {
         self.cfs.cfs.get(name)
     }
+
+    /// Returns the handle of the column family with the id `id`, e.g. to apply
+    /// the operations of a batch passed to
+    /// [`WriteBatch::iterate_cf`](crate::WriteBatchWithTransaction::iterate_cf) to it.
+    /// Returns `None` once the column family is dropped.
+    pub fn cf_handle_by_id(&self, id: u32) -> Option<&ColumnFamily> {
+        self.cfs.cfs.values().find(|cf| cf.id() == id)
+    }
 }
 
 impl<I: DBInner> DBCommon<MultiThreaded, I> {
@@ -2330,21 +4676,138 @@ impl<I: DBInner> DBCommon<MultiThreaded, I> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
         self.cfs.cfs.write().unwrap().insert(
             name.as_ref().to_string(),
-            Arc::new(UnboundColumnFamily { inner }),
+            Arc::new(UnboundColumnFamily::new(inner)),
         );
         Ok(())
     }
 
     /// Drops the column family with the given name by internally locking the inner column
     /// family map. This avoids needing `&mut self` reference
+    ///
+    /// Handles to the column family obtained before remain valid, the column
+    /// family handle is destroyed once the last of them is dropped. Point reads
+    /// and writes through them, i.e. `get_cf`, `get_pinned_cf`, `put_cf`,
+    /// `merge_cf`, `delete_cf`, `delete_range_cf`, their `_opt` variants and the
+    /// `_with_ts` writes, fail
+    /// with [`ErrorKind::ColumnFamilyDropped`](crate::ErrorKind::ColumnFamilyDropped),
+    /// as do exports of the column family, `cf_stats_snapshot` and the
+    /// `full_history_ts_low` getter and setter. Other operations, e.g. iterators, which
+    /// keep working until dropped, properties, ingestion, compactions and write
+    /// batches, are passed on to RocksDB, which handles the dropped column family
+    /// on its own terms.
     pub fn drop_cf(&self, name: &str) -> Result<(), Error> {
         if let Some(cf) = self.cfs.cfs.write().unwrap().remove(name) {
-            self.drop_column_family(cf.inner, cf)
+            let outlive = self.drop_column_family(cf.inner, Arc::clone(&cf))?;
+            cf.retain_options(outlive);
+            cf.mark_dropped();
+            Ok(())
         } else {
             Err(Error::new(format!("Invalid column family: {name}")))
         }
     }
 
+    /// Creates several column families with the same options in a single call.
+    ///
+    /// Creation stops at the first failure. The column families created before it
+    /// stay usable, and the returned error lists the names that were not created.
+    pub fn create_cfs<N: AsRef<str>>(&self, names: &[N], opts: &Options) -> Result<(), Error> {
+        let (handles, result) = self.create_inner_cf_handles(names, opts);
+        let mut cfs = self.cfs.cfs.write().unwrap();
+        for (name, inner) in names.iter().zip(handles) {
+            cfs.insert(
+                name.as_ref().to_string(),
+                Arc::new(UnboundColumnFamily::new(inner)),
+            );
+        }
+        result
+    }
+
+    /// Creates a column family and ingests the external SST files `paths` into it
+    /// before making it available through [`cf_handle`](Self::cf_handle).
+    ///
+    /// If the ingestion fails the column family is dropped again, so that no empty
+    /// column family is left behind.
+    pub fn create_cf_with_ingest<N: AsRef<str>, P: AsRef<Path>>(
+        &self,
+        name: N,
+        opts: &Options,
+        paths: Vec<P>,
+        ingest_opts: &IngestExternalFileOptions,
+    ) -> Result<(), Error> {
+        let cf = self.create_cf_handle_with_ingest(name.as_ref(), opts, paths, ingest_opts)?;
+        // the map takes over the ownership of the handle
+        let cf = mem::ManuallyDrop::new(cf);
+        self.cfs.cfs.write().unwrap().insert(
+            name.as_ref().to_string(),
+            Arc::new(UnboundColumnFamily::new(cf.inner)),
+        );
+        Ok(())
+    }
+
+    /// Creates the column family `name` populated with the SST files of `metadata`,
+    /// e.g. exported from a column family of another DB, before making it available
+    /// through [`cf_handle`](Self::cf_handle).
+    ///
+    /// `opts` must use the comparator the files are sorted with. If the import
+    /// fails, no column family is left behind.
+    pub fn create_cf_with_import<N: AsRef<str>>(
+        &self,
+        name: N,
+        opts: &Options,
+        import_opts: &ImportColumnFamilyOptions,
+        metadata: &ExportImportFilesMetaData,
+    ) -> Result<(), Error> {
+        let cf = self.create_cf_handle_with_import(name.as_ref(), opts, import_opts, metadata)?;
+        // the map takes over the ownership of the handle
+        let cf = mem::ManuallyDrop::new(cf);
+        self.cfs.cfs.write().unwrap().insert(
+            name.as_ref().to_string(),
+            Arc::new(UnboundColumnFamily::new(cf.inner)),
+        );
+        Ok(())
+    }
+
+    /// Creates the column family `name` with a copy of the entries the column family
+    /// `src` holds at the time of the call, flushed or not. Later writes to either
+    /// column family do not affect the other.
+    ///
+    /// `opts` are the options of the new column family and must use the comparator
+    /// of `src`. The copy holds the latest value of each key: deleted keys, older
+    /// versions and merge operands are resolved away.
+    ///
+    /// This is not RocksDB's export and import of a column family, which the C API
+    /// does not expose: the entries are read with an iterator and written to a new
+    /// SST file, so the copy takes time and space linear in the size of `src`.
+    pub fn copy_cf<N: AsRef<str>>(&self, src: &str, name: N, opts: &Options) -> Result<(), Error> {
+        let src = self
+            .cf_handle(src)
+            .ok_or_else(|| Error::new(format!("Invalid column family: {src}")))?;
+        let cf = self.copy_cf_handle(&src, name.as_ref(), opts)?;
+        // the map takes over the ownership of the handle
+        let cf = mem::ManuallyDrop::new(cf);
+        self.cfs.cfs.write().unwrap().insert(
+            name.as_ref().to_string(),
+            Arc::new(UnboundColumnFamily::new(cf.inner)),
+        );
+        Ok(())
+    }
+
+    /// Drops several column families, one after the other. Every column family is
+    /// dropped even if dropping another one failed, and the returned error lists
+    /// the names that could not be dropped.
+    ///
+    /// This is not atomic: a failure, or a crash midway, leaves the other column
+    /// families dropped. RocksDB's C API has no `DropColumnFamilies`, which drops
+    /// them in a single manifest write.
+    ///
+    /// See [`drop_cf`](Self::drop_cf) for handles still held to them.
+    pub fn drop_cfs<N: AsRef<str>>(&self, names: &[N]) -> Result<(), Error> {
+        collect_drop_errors(names.iter().map(|name| {
+            let name = name.as_ref();
+            (name, self.drop_cf(name))
+        }))
+    }
+
     /// Returns the underlying column family handle
     pub fn cf_handle(&self, name: &str) -> Option<Arc<BoundColumnFamily>> {
         self.cfs
@@ -2355,6 +4818,66 @@ impl<I: DBInner> DBCommon<MultiThreaded, I> {
             .cloned()
             .map(UnboundColumnFamily::bound_column_family)
     }
+
+    /// Returns the handle of the column family with the id `id`, e.g. to apply
+    /// the operations of a batch passed to
+    /// [`WriteBatch::iterate_cf`](crate::WriteBatchWithTransaction::iterate_cf) to it.
+    /// Returns `None` once the column family is dropped.
+    pub fn cf_handle_by_id(&self, id: u32) -> Option<Arc<BoundColumnFamily>> {
+        self.cfs
+            .cfs
+            .read()
+            .unwrap()
+            .values()
+            .find(|cf| column_family_id(cf.inner) == id)
+            .cloned()
+            .map(UnboundColumnFamily::bound_column_family)
+    }
+}
+
+impl<T: ThreadMode, I: DBInner> DumpColumnFamily for DBCommon<T, I> {
+    fn dump_column_family(
+        &self,
+        cf: &dyn AsColumnFamilyRef,
+        dump_dir: &Path,
+    ) -> Result<ExportImportFilesMetaData, Error> {
+        let cf = DynColumnFamily(cf);
+        check_cf_dropped(&cf)?;
+        let comparator = self.comparator_name_cf(&cf)?;
+        if comparator != BYTEWISE_COMPARATOR_NAME {
+            return Err(Error::new(format!(
+                "Not implemented: dumping a column family sorted with comparator `{comparator}`"
+            )));
+        }
+        if dump_dir.exists() {
+            return Err(Error::new(format!(
+                "Invalid argument: dump directory {} exists",
+                dump_dir.display()
+            )));
+        }
+        fs::create_dir_all(dump_dir).map_err(|err| {
+            Error::new(format!(
+                "Failed to create dump directory {}: {err}",
+                dump_dir.display()
+            ))
+        })?;
+        match self.export_to_sst_cf(
+            &cf,
+            ..,
+            &dump_dir.join("dump.sst"),
+            &Options::default(),
+            ReadOptions::default(),
+        ) {
+            Ok(info) => Ok(ExportImportFilesMetaData {
+                db_comparator_name: comparator,
+                files: info.into_iter().map(|info| info.file_path).collect(),
+            }),
+            Err(err) => {
+                let _ = fs::remove_dir_all(dump_dir);
+                Err(err)
+            }
+        }
+    }
 }
 
 impl<T: ThreadMode, I: DBInner> Drop for DBCommon<T, I> {
@@ -2370,7 +4893,10 @@ impl<T: ThreadMode, I: DBInner> fmt::Debug for DBCommon<T, I> {
 }
 
 /// The metadata that describes a column family.
+///
+/// It is only created by the DB, and fields may be added in minor releases.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ColumnFamilyMetaData {
     // The size of this column family in bytes, which is equal to the sum of
     // the file size of its "levels".
@@ -2379,6 +4905,313 @@ pub struct ColumnFamilyMetaData {
     pub name: String,
     // The number of files in this column family.
     pub file_count: usize,
+    // The metadata of each level, from level 0 to the last one, including the
+    // empty ones.
+    pub levels: Vec<LevelMetaData>,
+}
+
+impl ColumnFamilyMetaData {
+    /// Copies the metadata out of `ptr`, which is still owned by the caller.
+    unsafe fn from_c(ptr: *mut ffi::rocksdb_column_family_metadata_t) -> Self {
+        let level_count = ffi::rocksdb_column_family_metadata_get_level_count(ptr);
+        let levels = (0..level_count)
+            .map(|i| {
+                let level = ffi::rocksdb_column_family_metadata_get_level_metadata(ptr, i);
+                let metadata = LevelMetaData {
+                    level: ffi::rocksdb_level_metadata_get_level(level),
+                    size: ffi::rocksdb_level_metadata_get_size(level),
+                    file_count: ffi::rocksdb_level_metadata_get_file_count(level),
+                };
+                ffi::rocksdb_level_metadata_destroy(level);
+                metadata
+            })
+            .collect();
+
+        Self {
+            size: ffi::rocksdb_column_family_metadata_get_size(ptr),
+            name: from_cstr(ffi::rocksdb_column_family_metadata_get_name(ptr)),
+            file_count: ffi::rocksdb_column_family_metadata_get_file_count(ptr),
+            levels,
+        }
+    }
+}
+
+/// The metadata that describes a level of a column family.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct LevelMetaData {
+    // The level.
+    pub level: i32,
+    // The size of this level in bytes, which is equal to the sum of the file
+    // size of its files.
+    pub size: u64,
+    // The number of files in this level.
+    pub file_count: usize,
+}
+
+/// The stats of a level of a column family, as returned by
+/// [`level_stats_cf`](DBCommon::level_stats_cf).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelStats {
+    /// The level
+    pub level: i32,
+    /// Number of table files at the level
+    pub num_files: usize,
+    /// Total size of the table files at the level
+    pub size_bytes: u64,
+    /// Compaction score of the level, rounded to one decimal. Compaction picks
+    /// the levels scoring 1.0 or more, and level 0 scores 1.0 once it holds
+    /// `level0_file_num_compaction_trigger` files. A score growing well beyond
+    /// that means compaction falls behind and writes are about to stall.
+    pub score: f64,
+    /// Number of the table files at the level being compacted
+    pub being_compacted_files: usize,
+}
+
+/// The thread started by [`DBCommon::spawn_wal_flusher`], stopped and joined
+/// when dropped.
+pub struct WalFlusherHandle {
+    sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WalFlusherHandle {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The periodic jobs run by [`tick`](DBCommon::tick), combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickTasks(u8);
+
+impl TickTasks {
+    /// Writes the WAL buffer of a DB opened with [`Options::set_manual_wal_flush`]
+    /// to its file.
+    pub const FLUSH_WAL: TickTasks = TickTasks(1);
+    /// Returns the `rocksdb.stats` text RocksDB dumps to its LOG every
+    /// `stats_dump_period_sec`.
+    pub const DUMP_STATS: TickTasks = TickTasks(1 << 1);
+    /// Records the change of the tickers of the statistics, as RocksDB does
+    /// every `stats_persist_period_sec`. The entry is kept in memory by the
+    /// wrapper only, and never written to the `___rocksdb_stats_history___`
+    /// column family RocksDB uses with `persist_stats_to_disk`.
+    pub const RECORD_STATS: TickTasks = TickTasks(1 << 2);
+    pub const ALL: TickTasks = TickTasks(0b111);
+
+    pub const fn empty() -> Self {
+        TickTasks(0)
+    }
+
+    /// Returns `true` if all the tasks of `other` are in `self`.
+    pub const fn contains(self, other: TickTasks) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for TickTasks {
+    type Output = TickTasks;
+
+    fn bitor(self, other: TickTasks) -> TickTasks {
+        TickTasks(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for TickTasks {
+    fn bitor_assign(&mut self, other: TickTasks) {
+        self.0 |= other.0;
+    }
+}
+
+/// The outcome of a [`tick`](DBCommon::tick).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TickReport {
+    /// The `rocksdb.stats` text, if [`TickTasks::DUMP_STATS`] was run.
+    pub stats_dump: Option<String>,
+}
+
+/// The maximum number of entries kept by [`stats_history`](DBCommon::stats_history).
+pub const STATS_HISTORY_LEN: usize = 1024;
+
+/// An entry of [`stats_history`](DBCommon::stats_history).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsHistoryEntry {
+    /// When the entry was recorded.
+    pub time: SystemTime,
+    /// The increase of each ticker since the previous entry, or since the DB was
+    /// opened for the first one, by ticker name, e.g. `rocksdb.number.keys.written`.
+    pub tickers: BTreeMap<String, u64>,
+}
+
+/// The column families frozen with `freeze_cf` and the policies set with
+/// `set_cf_durability_policy`, by column family id.
+#[derive(Default)]
+struct CfWriteRules {
+    durability_policies: BTreeMap<u32, Durability>,
+    frozen_cfs: BTreeSet<u32>,
+}
+
+impl CfWriteRules {
+    fn is_empty(&self) -> bool {
+        self.durability_policies.is_empty() && self.frozen_cfs.is_empty()
+    }
+
+    fn check(
+        &self,
+        cfs: impl IntoIterator<Item = u32>,
+        writeopts: &WriteOptions,
+    ) -> Result<(), Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        cfs.into_iter().try_for_each(|cf| {
+            if self.frozen_cfs.contains(&cf) {
+                return Err(frozen_cf_error());
+            }
+            self.durability_policies
+                .get(&cf)
+                .map_or(Ok(()), |policy| policy.check(writeopts.wal_disabled))
+        })
+    }
+}
+
+#[derive(Default)]
+struct StatsHistory {
+    last: BTreeMap<String, u64>,
+    entries: VecDeque<StatsHistoryEntry>,
+}
+
+impl StatsHistory {
+    fn record(&mut self, counts: BTreeMap<String, u64>) {
+        let tickers = counts
+            .iter()
+            .map(|(name, &count)| {
+                let last = self.last.get(name).copied().unwrap_or(0);
+                (name.clone(), count.saturating_sub(last))
+            })
+            .collect();
+        self.last = counts;
+        if self.entries.len() == STATS_HISTORY_LEN {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(StatsHistoryEntry {
+            time: SystemTime::now(),
+            tickers,
+        });
+    }
+}
+
+/// Parses the ticker lines of the `rocksdb.options-statistics` text, such as
+/// `rocksdb.block.cache.miss COUNT : 5`, skipping the histogram lines.
+fn parse_ticker_counts(statistics: &str) -> BTreeMap<String, u64> {
+    statistics
+        .lines()
+        .filter_map(
+            |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                [name, "COUNT", ":", count] => Some((name.to_owned(), count.parse().ok()?)),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+/// Parses the text of the `rocksdb.block-cache-entry-stats` property, such as:
+///
+/// ```text
+/// Block cache LRUCache@0x5627db9b8d60#7004 capacity: 32.00 MB seed: 1 usage: 1.09 KB table_size: 256 occupancy: 2 collections: 1 last_copies: 0 last_secs: 5e-06 secs_since: 0
+/// Block cache entry stats(count,size,portion): DataBlock(1,1.05 KB,0.0032%) Misc(1,0.00 KB,0%)
+/// ```
+fn parse_block_cache_entry_stats(text: &str) -> Result<BlockCacheEntryStats, Error> {
+    let invalid = || Error::new(format!("Failed to parse block cache entry stats: {text}"));
+    // sizes are printed in KB, MB, GB or TB with two decimals
+    let parse_bytes = |value: &str, unit: &str| -> Option<u64> {
+        let exponent = match unit {
+            "KB" => 1,
+            "MB" => 2,
+            "GB" => 3,
+            "TB" => 4,
+            _ => return None,
+        };
+        let value: f64 = value.parse().ok()?;
+        Some((value * f64::from(1024u32).powi(exponent)).round() as u64)
+    };
+
+    let mut stats = BlockCacheEntryStats::default();
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or_else(invalid)?
+        .split_whitespace()
+        .collect();
+    let field = |name: &str| {
+        let at = header.iter().position(|token| *token == name)?;
+        parse_bytes(header.get(at + 1)?, header.get(at + 2)?)
+    };
+    stats.cache_id = (*header.get(2).ok_or_else(invalid)?).to_owned();
+    stats.capacity = field("capacity:").ok_or_else(invalid)?;
+    stats.usage = field("usage:").ok_or_else(invalid)?;
+
+    let roles = lines
+        .next()
+        .and_then(|line| line.split_once("):"))
+        .ok_or_else(invalid)?
+        .1;
+    for role in roles
+        .split(')')
+        .map(str::trim)
+        .filter(|role| !role.is_empty())
+    {
+        let (name, values) = role.split_once('(').ok_or_else(invalid)?;
+        let [count, size, _portion] = values.split(',').collect::<Vec<_>>()[..] else {
+            return Err(invalid());
+        };
+        let (size, unit) = size.split_once(' ').ok_or_else(invalid)?;
+        stats.roles.insert(
+            name.to_owned(),
+            CacheEntryRoleStats {
+                count: count.parse().map_err(|_| invalid())?,
+                charge: parse_bytes(size, unit).ok_or_else(invalid)?,
+            },
+        );
+    }
+    Ok(stats)
+}
+
+/// Returns the rows of the levels listed in the `rocksdb.cfstats` text, split
+/// into their columns. They read e.g. `  L0      3/1    2.95 KB   1.5 ...`, the
+/// files at the level and the ones of them being compacted, the size with its
+/// unit, the compaction score, then the compaction stats of the level. Levels
+/// without files or compactions are left out.
+fn level_rows(text: &str) -> impl Iterator<Item = (i32, Vec<&str>)> {
+    text.lines().filter_map(|line| {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let level = tokens.first()?.strip_prefix('L')?.parse().ok()?;
+        Some((level, tokens))
+    })
+}
+
+/// Parses the compaction score and the number of files being compacted of the
+/// levels listed in the `rocksdb.cfstats` text, by level.
+fn parse_compaction_scores(text: &str) -> Result<BTreeMap<i32, (f64, usize)>, Error> {
+    let invalid = || Error::new(format!("Failed to parse compaction stats: {text}"));
+    let mut scores = BTreeMap::new();
+    for (level, tokens) in level_rows(text) {
+        let [_, files, _size, _unit, score, ..] = tokens[..] else {
+            return Err(invalid());
+        };
+        let (_, being_compacted) = files.split_once('/').ok_or_else(invalid)?;
+        scores.insert(
+            level,
+            (
+                score.parse().map_err(|_| invalid())?,
+                being_compacted.parse().map_err(|_| invalid())?,
+            ),
+        );
+    }
+    Ok(scores)
 }
 
 /// The metadata that describes a SST file
@@ -2402,6 +5235,207 @@ pub struct LiveFile {
     pub num_deletions: u64,
 }
 
+/// Statistics of a column family, as returned by
+/// [`cf_stats_snapshot`](DBCommon::cf_stats_snapshot), reflecting its state at
+/// the time of the snapshot.
+///
+/// The C API has no map properties, and no integer properties count the bytes
+/// flushed, compacted or the write stalls of a column family, which RocksDB
+/// only prints in the `rocksdb.cfstats` text. The DB-wide tickers of
+/// [`Statistics`], e.g. [`Ticker::FlushWriteBytes`] and [`Ticker::StallMicros`],
+/// count them for all column families.
+///
+/// [`Ticker::FlushWriteBytes`]: crate::statistics::Ticker::FlushWriteBytes
+/// [`Ticker::StallMicros`]: crate::statistics::Ticker::StallMicros
+/// [`Statistics`]: crate::statistics::Statistics
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CfStats {
+    /// The number of table files at each level.
+    pub num_files: Vec<u64>,
+    /// The total size of the table files at each level.
+    pub level_bytes: Vec<u64>,
+    /// The size of the memtables.
+    pub memtable_bytes: u64,
+    /// The estimated number of bytes compaction needs to rewrite.
+    pub pending_compaction_bytes: u64,
+}
+
+/// What fills a block cache, as returned by
+/// [`block_cache_entry_stats`](DBCommon::block_cache_entry_stats).
+///
+/// RocksDB only reports the sizes rounded to two decimals of a KB, MB, GB or TB,
+/// so they are approximations.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockCacheEntryStats {
+    /// Identifies the cache, which may be shared with other DBs
+    pub cache_id: String,
+    /// Capacity of the cache in bytes
+    pub capacity: u64,
+    /// Total charge of the entries in the cache in bytes, including the ones of
+    /// the other DBs sharing it
+    pub usage: u64,
+    /// Stats of the entries by role, keyed by the names RocksDB gives the roles,
+    /// such as `DataBlock`, `IndexBlock` or `FilterBlock`. Roles without entries
+    /// are left out.
+    pub roles: BTreeMap<String, CacheEntryRoleStats>,
+}
+
+impl BlockCacheEntryStats {
+    /// Returns the stats of the entries of the role called `name`.
+    pub fn role(&self, name: &str) -> CacheEntryRoleStats {
+        self.roles.get(name).copied().unwrap_or_default()
+    }
+
+    pub fn data_block(&self) -> CacheEntryRoleStats {
+        self.role("DataBlock")
+    }
+
+    pub fn index_block(&self) -> CacheEntryRoleStats {
+        self.role("IndexBlock")
+    }
+
+    pub fn filter_block(&self) -> CacheEntryRoleStats {
+        self.role("FilterBlock")
+    }
+}
+
+/// The entries of one role in a block cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheEntryRoleStats {
+    /// Number of entries
+    pub count: u64,
+    /// Total charge of the entries in bytes
+    pub charge: u64,
+}
+
+/// The files consumed and produced by a manual compaction, as returned by
+/// [`compact_range_cf_opt_with_info`](DBCommon::compact_range_cf_opt_with_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactedFiles {
+    /// Name of the compacted column family
+    pub column_family_name: String,
+    /// Names of the table files the compaction removed
+    pub input_files: Vec<String>,
+    /// Names of the table files the compaction created
+    pub output_files: Vec<String>,
+    /// Total size of the input files
+    pub bytes_read: u64,
+    /// Total size of the output files
+    pub bytes_written: u64,
+    /// Level the output files were written to, `None` if there are no output files
+    pub output_level: Option<i32>,
+}
+
+/// The files written by a flush, as returned by
+/// [`flush_cf_with_info`](DBCommon::flush_cf_with_info).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlushedFiles {
+    /// Name of the flushed column family
+    pub column_family_name: String,
+    /// Names of the table files the flush created, empty if there was nothing to flush
+    pub output_files: Vec<String>,
+    /// Smallest user key of the output files, in lexicographical order
+    pub smallest_key: Option<Vec<u8>>,
+    /// Largest user key of the output files, in lexicographical order
+    pub largest_key: Option<Vec<u8>>,
+    /// Total size of the output files
+    pub bytes_written: u64,
+}
+
+/// Returns read options bounding iteration to `range`.
+fn range_read_options<K, R>(range: &R) -> ReadOptions
+where
+    K: AsRef<[u8]> + ?Sized,
+    R: RangeBounds<K>,
+{
+    let mut opts = ReadOptions::default();
+    match range.start_bound() {
+        Bound::Included(key) => opts.set_iterate_lower_bound(key.as_ref()),
+        Bound::Excluded(key) => opts.set_iterate_lower_bound(next_key(key.as_ref())),
+        Bound::Unbounded => {}
+    }
+    match range.end_bound() {
+        Bound::Included(key) => opts.set_iterate_upper_bound(next_key(key.as_ref())),
+        Bound::Excluded(key) => opts.set_iterate_upper_bound(key.as_ref()),
+        Bound::Unbounded => {}
+    }
+    opts
+}
+
+fn range_iterator_mode(direction: Direction) -> IteratorMode<'static> {
+    match direction {
+        Direction::Forward => IteratorMode::Start,
+        Direction::Reverse => IteratorMode::End,
+    }
+}
+
+/// Turns the results of dropping column families into a single error listing the
+/// column families that could not be dropped.
+fn collect_drop_errors<'a>(
+    results: impl Iterator<Item = (&'a str, Result<(), Error>)>,
+) -> Result<(), Error> {
+    let failed: Vec<_> = results
+        .filter_map(|(name, result)| result.err().map(|err| format!("{name}: {err}")))
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(format!(
+            "Failed to drop column families: {}",
+            failed.join(", ")
+        )))
+    }
+}
+
+fn frozen_cf_error() -> Error {
+    Error::new("Invalid argument: write to a frozen column family".to_owned())
+}
+
+/// Reads `key` with `iter`, failing if the thread merged more than
+/// `max_operands` operands since `context` counted `merged_before` of them.
+fn read_with_merge_limit<D: DBAccess>(
+    mut iter: DBRawIteratorWithThreadMode<D>,
+    key: &[u8],
+    context: &PerfContext,
+    merged_before: u64,
+    max_operands: usize,
+) -> Result<Option<Vec<u8>>, Error> {
+    iter.seek(key);
+    iter.status()?;
+    // The operands merged to position the iterator on a following key are not
+    // those of `key`.
+    if iter.key() != Some(key) {
+        return Ok(None);
+    }
+    let num_operands = context.internal_merge_count() - merged_before;
+    if num_operands > max_operands as u64 {
+        return Err(Error::new(format!(
+            "Result incomplete: {MERGE_OPERAND_THRESHOLD_EXCEEDED}: {num_operands} operands, \
+             at most {max_operands} allowed"
+        )));
+    }
+    Ok(iter.value().map(<[u8]>::to_vec))
+}
+
+fn history_unavailable(seqno: u64, reason: &str) -> Error {
+    Error::new(format!(
+        "Result incomplete: {HISTORY_UNAVAILABLE} at sequence number {seqno}: {reason}"
+    ))
+}
+
+/// Fails with `ErrorKind::ColumnFamilyDropped` if `cf` has been dropped with `drop_cf`.
+/// Only called by the operations listed in the docs of `drop_cf`, keep them in
+/// sync.
+fn check_cf_dropped(cf: &impl AsColumnFamilyRef) -> Result<(), Error> {
+    if cf.is_dropped() {
+        return Err(Error::new(format!(
+            "Column family dropped: {}",
+            column_family_name(cf.inner())
+        )));
+    }
+    Ok(())
+}
+
 fn convert_options(opts: &[(&str, &str)]) -> Result<Vec<(CString, CString)>, Error> {
     opts.iter()
         .map(|(name, value)| {
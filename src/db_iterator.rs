@@ -13,11 +13,15 @@
 // limitations under the License.
 
 use crate::{
+    column_family::RetainedColumnFamily,
     db::{DBAccess, DB},
-    ffi, Error, ReadOptions, WriteBatch,
+    ffi, AsColumnFamilyRef, Error, IterateBounds, ReadOptions, SnapshotWithThreadMode, WriteBatch,
 };
 use libc::{c_char, c_uchar, size_t};
-use std::{marker::PhantomData, slice};
+use std::{
+    slice,
+    time::{Duration, Instant},
+};
 
 /// A type alias to keep compatibility. See [`DBRawIteratorWithThreadMode`] for details
 pub type DBRawIterator<'a> = DBRawIteratorWithThreadMode<'a, DB>;
@@ -74,43 +78,108 @@ pub struct DBRawIteratorWithThreadMode<'a, D: DBAccess> {
     inner: std::ptr::NonNull<ffi::rocksdb_iterator_t>,
 
     /// When iterate_lower_bound or iterate_upper_bound are set, the inner
-    /// C iterator keeps a pointer to the upper bound inside `_readopts`.
+    /// C iterator keeps a pointer to the upper bound inside `readopts`.
     /// Storing this makes sure the upper bound is always alive when the
     /// iterator is being used.
     ///
     /// And yes, we need to store the entire ReadOptions structure since C++
     /// ReadOptions keep reference to C rocksdb_readoptions_t wrapper which
     /// point to vectors we own.  See issue #660.
-    _readopts: ReadOptions,
+    readopts: ReadOptions,
 
-    db: PhantomData<&'a D>,
+    /// The database and column family the iterator was created from, to
+    /// recreate it when its bounds change.
+    db: &'a D,
+    cf_handle: Option<RetainedColumnFamily>,
 }
 
 impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
-    pub(crate) fn new(db: &D, readopts: ReadOptions) -> Self {
-        let inner = unsafe { db.create_iterator(&readopts) };
-        Self::from_inner(inner, readopts)
+    pub(crate) fn new(db: &'a D, readopts: ReadOptions) -> Self {
+        Self::create(db, None, readopts)
     }
 
     pub(crate) fn new_cf(
         db: &'a D,
-        cf_handle: *mut ffi::rocksdb_column_family_handle_t,
+        cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> Self {
-        let inner = unsafe { db.create_iterator_cf(cf_handle, &readopts) };
-        Self::from_inner(inner, readopts)
+        Self::create(db, Some(RetainedColumnFamily::new(cf_handle)), readopts)
     }
 
-    fn from_inner(inner: *mut ffi::rocksdb_iterator_t, readopts: ReadOptions) -> Self {
+    fn create(db: &'a D, cf_handle: Option<RetainedColumnFamily>, readopts: ReadOptions) -> Self {
+        Self {
+            inner: Self::create_inner(db, cf_handle.as_ref(), &readopts),
+            readopts,
+            db,
+            cf_handle,
+        }
+    }
+
+    fn create_inner(
+        db: &D,
+        cf_handle: Option<&RetainedColumnFamily>,
+        readopts: &ReadOptions,
+    ) -> std::ptr::NonNull<ffi::rocksdb_iterator_t> {
+        let inner = unsafe {
+            match cf_handle {
+                Some(cf_handle) => db.create_iterator_cf(cf_handle.inner(), readopts),
+                None => db.create_iterator(readopts),
+            }
+        };
         // This unwrap will never fail since rocksdb_create_iterator and
         // rocksdb_create_iterator_cf functions always return non-null. They
         // use new and deference the result so any nulls would end up with SIGSEGV
         // there and we would have a bigger issue.
-        let inner = std::ptr::NonNull::new(inner).unwrap();
-        Self {
-            inner,
-            _readopts: readopts,
-            db: PhantomData,
+        std::ptr::NonNull::new(inner).unwrap()
+    }
+
+    /// Changes the bounds of the iterator, like [`ReadOptions::set_iterate_range`]
+    /// does for the read options of a new one. The bounds are copied and kept
+    /// alive by the iterator.
+    ///
+    /// The new bounds take effect from the next seek, so the iterator must be
+    /// repositioned before it is used again. A bound the iterator already had is
+    /// changed in place. Adding or removing a bound recreates the underlying
+    /// iterator, which then reads the latest data unless the read options the
+    /// iterator was created with hold a snapshot. The iterator keeps the handle
+    /// of its column family alive for this, also once the column family is
+    /// dropped.
+    ///
+    /// ```
+    /// use rocksdb::{DB, Options};
+    ///
+    /// let tempdir = tempfile::Builder::new()
+    ///     .prefix("_path_for_rocksdb_storage_set_bounds")
+    ///     .tempdir()
+    ///     .expect("Failed to create temporary path for the _path_for_rocksdb_storage_set_bounds");
+    /// let path = tempdir.path();
+    /// {
+    ///     let db = DB::open_default(path).unwrap();
+    ///     for key in [b"a", b"b", b"c", b"d"] {
+    ///         db.put(key, b"").unwrap();
+    ///     }
+    ///     let mut iter = db.raw_iterator();
+    ///     iter.set_bounds(b"b".as_slice()..b"d".as_slice());
+    ///     iter.seek_to_first();
+    ///     assert_eq!(iter.key(), Some(b"b".as_slice()));
+    ///     iter.set_bounds(b"c".as_slice()..);
+    ///     iter.seek_to_last();
+    ///     assert_eq!(iter.key(), Some(b"d".as_slice()));
+    /// }
+    /// let _ = DB::destroy(&Options::default(), path);
+    /// ```
+    pub fn set_bounds(&mut self, range: impl IterateBounds) {
+        let (lower, upper) = range.into_bounds();
+        let recreate = lower.is_some() != self.readopts.iterate_lower_bound.is_some()
+            || upper.is_some() != self.readopts.iterate_upper_bound.is_some();
+        self.readopts.set_lower_bound_impl(lower);
+        self.readopts.set_upper_bound_impl(upper);
+        if recreate {
+            let inner = Self::create_inner(self.db, self.cf_handle.as_ref(), &self.readopts);
+            let old = std::mem::replace(&mut self.inner, inner);
+            unsafe {
+                ffi::rocksdb_iter_destroy(old.as_ptr());
+            }
         }
     }
 
@@ -323,6 +392,21 @@ impl<'a, D: DBAccess> DBRawIteratorWithThreadMode<'a, D> {
         }
     }
 
+    /// Returns a slice of the user-defined timestamp of the current key, when the
+    /// column family uses a comparator with timestamps.
+    pub fn timestamp(&self) -> Option<&[u8]> {
+        if self.valid() {
+            // Safety Note: see key_impl().
+            unsafe {
+                let mut ts_len: size_t = 0;
+                let ts_ptr = ffi::rocksdb_iter_timestamp(self.inner.as_ptr(), &mut ts_len);
+                Some(slice::from_raw_parts(ts_ptr as *const c_uchar, ts_len))
+            }
+        } else {
+            None
+        }
+    }
+
     /// Returns pair with slice of the current key and current value.
     pub fn item(&self) -> Option<(&[u8], &[u8])> {
         if self.valid() {
@@ -410,6 +494,7 @@ pub struct DBIteratorWithThreadMode<'a, D: DBAccess> {
     raw: DBRawIteratorWithThreadMode<'a, D>,
     direction: Direction,
     done: bool,
+    bytes_read: u64,
 }
 
 #[derive(Copy, Clone)]
@@ -428,13 +513,13 @@ pub enum IteratorMode<'a> {
 }
 
 impl<'a, D: DBAccess> DBIteratorWithThreadMode<'a, D> {
-    pub(crate) fn new(db: &D, readopts: ReadOptions, mode: IteratorMode) -> Self {
+    pub(crate) fn new(db: &'a D, readopts: ReadOptions, mode: IteratorMode) -> Self {
         Self::from_raw(DBRawIteratorWithThreadMode::new(db, readopts), mode)
     }
 
     pub(crate) fn new_cf(
         db: &'a D,
-        cf_handle: *mut ffi::rocksdb_column_family_handle_t,
+        cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> Self {
@@ -449,11 +534,21 @@ impl<'a, D: DBAccess> DBIteratorWithThreadMode<'a, D> {
             raw,
             direction: Direction::Forward, // blown away by set_mode()
             done: false,
+            bytes_read: 0,
         };
         rv.set_mode(mode);
         rv
     }
 
+    /// Repositions the iterator as if it was created with `mode`, also after it
+    /// has been exhausted.
+    ///
+    /// The bounds of the read options are honored the same way in both
+    /// directions: the iterator starts at the first key at or after the lower
+    /// bound for [`IteratorMode::Start`], at the last key before the upper bound
+    /// for [`IteratorMode::End`], and a key of [`IteratorMode::From`] outside
+    /// the bounds is moved to the nearest bound. The iteration stops at the other
+    /// bound.
     pub fn set_mode(&mut self, mode: IteratorMode) {
         self.done = false;
         self.direction = match mode {
@@ -475,6 +570,47 @@ impl<'a, D: DBAccess> DBIteratorWithThreadMode<'a, D> {
             }
         };
     }
+
+    /// Changes the bounds of the iterator like
+    /// [`DBRawIteratorWithThreadMode::set_bounds`] and repositions it with
+    /// `mode`, so the new bounds apply from the next item on.
+    ///
+    /// ```
+    /// use rocksdb::{DB, Direction, IteratorMode, Options};
+    ///
+    /// let tempdir = tempfile::Builder::new()
+    ///     .prefix("_path_for_rocksdb_storage_set_bounds_and_mode")
+    ///     .tempdir()
+    ///     .expect("Failed to create temporary path for the _path_for_rocksdb_storage_set_bounds_and_mode");
+    /// let path = tempdir.path();
+    /// {
+    ///     let db = DB::open_default(path).unwrap();
+    ///     for key in [b"a", b"b", b"c", b"d"] {
+    ///         db.put(key, b"").unwrap();
+    ///     }
+    ///     let mut iter = db.iterator(IteratorMode::Start);
+    ///     iter.set_bounds_and_mode(b"b".as_slice()..b"d".as_slice(), IteratorMode::End);
+    ///     let keys: Vec<_> = iter.map(|item| item.unwrap().0.to_vec()).collect();
+    ///     assert_eq!(keys, [b"c".to_vec(), b"b".to_vec()]);
+    /// }
+    /// let _ = DB::destroy(&Options::default(), path);
+    /// ```
+    pub fn set_bounds_and_mode(&mut self, range: impl IterateBounds, mode: IteratorMode) {
+        self.raw.set_bounds(range);
+        self.set_mode(mode);
+    }
+
+    /// Returns the total length of the keys and values returned by the iterator
+    /// so far, also before it was repositioned, e.g. to size or bill a scan.
+    ///
+    /// This is the data handed to the application, not what RocksDB read from
+    /// the memtables and SST files to produce it; see [`PerfContext`] for the
+    /// latter.
+    ///
+    /// [`PerfContext`]: crate::PerfContext
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
 }
 
 impl<'a, D: DBAccess> Iterator for DBIteratorWithThreadMode<'a, D> {
@@ -484,6 +620,7 @@ impl<'a, D: DBAccess> Iterator for DBIteratorWithThreadMode<'a, D> {
         if self.done {
             None
         } else if let Some((key, value)) = self.raw.item() {
+            self.bytes_read += (key.len() + value.len()) as u64;
             let item = (Box::from(key), Box::from(value));
             match self.direction {
                 Direction::Forward => self.raw.next(),
@@ -505,6 +642,160 @@ impl<'a, D: DBAccess> Into<DBRawIteratorWithThreadMode<'a, D>> for DBIteratorWit
     }
 }
 
+/// Options of a chunked scan created by [`DBCommon::scan_cf`].
+///
+/// [`DBCommon::scan_cf`]: crate::DBCommon::scan_cf
+#[derive(Debug, Clone, Copy)]
+pub struct ScanOptions {
+    /// The number of keys read with one RocksDB iterator before it is dropped and
+    /// the scan re-seeks with a fresh one, releasing the resources (memtables,
+    /// SST files) the old iterator pinned. `0` reads everything with one iterator.
+    ///
+    /// Default: 1000
+    pub batch_size: usize,
+    /// The maximum number of keys returned per second, `0` for no limit.
+    ///
+    /// Default: 0
+    pub rate_limit_keys_per_sec: u64,
+    /// Whether the whole scan reads from one snapshot taken when it is created.
+    /// Without a snapshot, each batch sees the writes made before it started.
+    ///
+    /// Default: true
+    pub use_snapshot: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            rate_limit_keys_per_sec: 0,
+            use_snapshot: true,
+        }
+    }
+}
+
+/// A forward scan over a column family that periodically recreates its
+/// underlying iterator, resuming right after the last returned key.
+///
+/// Created by [`DBCommon::scan_cf`].
+///
+/// [`DBCommon::scan_cf`]: crate::DBCommon::scan_cf
+pub struct DBScan<'a, D: DBAccess> {
+    db: &'a D,
+    cf_handle: RetainedColumnFamily,
+    // Declared before `snapshot` so that it is dropped before the snapshot is released.
+    iter: Option<DBRawIteratorWithThreadMode<'a, D>>,
+    snapshot: Option<SnapshotWithThreadMode<'a, D>>,
+    lower_bound: Option<Vec<u8>>,
+    upper_bound: Option<Vec<u8>>,
+    opts: ScanOptions,
+    last_key: Option<Box<[u8]>>,
+    keys_in_batch: usize,
+    keys_returned: u64,
+    started: Instant,
+    done: bool,
+}
+
+impl<'a, D: DBAccess> DBScan<'a, D> {
+    pub(crate) fn new(
+        db: &'a D,
+        cf_handle: &impl AsColumnFamilyRef,
+        range: impl IterateBounds,
+        opts: ScanOptions,
+    ) -> Self {
+        let (lower_bound, upper_bound) = range.into_bounds();
+        Self {
+            db,
+            cf_handle: RetainedColumnFamily::new(cf_handle),
+            iter: None,
+            snapshot: opts.use_snapshot.then(|| SnapshotWithThreadMode::new(db)),
+            lower_bound,
+            upper_bound,
+            opts,
+            last_key: None,
+            keys_in_batch: 0,
+            keys_returned: 0,
+            started: Instant::now(),
+            done: false,
+        }
+    }
+
+    fn start_batch(&mut self) {
+        // Drop the previous iterator first so that it doesn't pin anything while
+        // the new one is created.
+        self.iter = None;
+        let mut readopts = ReadOptions::default();
+        if let Some(snapshot) = &self.snapshot {
+            readopts.set_snapshot(snapshot);
+        }
+        if let Some(lower) = self
+            .last_key
+            .as_deref()
+            .map(<[u8]>::to_vec)
+            .or_else(|| self.lower_bound.clone())
+        {
+            readopts.set_iterate_lower_bound(lower);
+        }
+        if let Some(upper) = &self.upper_bound {
+            readopts.set_iterate_upper_bound(upper.clone());
+        }
+        let mut iter = DBRawIteratorWithThreadMode::new_cf(self.db, &self.cf_handle, readopts);
+        iter.seek_to_first();
+        // The lower bound is inclusive, skip the key returned last.
+        if iter.key().is_some() && iter.key() == self.last_key.as_deref() {
+            iter.next();
+        }
+        self.iter = Some(iter);
+        self.keys_in_batch = 0;
+    }
+
+    fn throttle(&self) {
+        if self.opts.rate_limit_keys_per_sec == 0 {
+            return;
+        }
+        let due = Duration::from_nanos(
+            (u128::from(self.keys_returned) * 1_000_000_000
+                / u128::from(self.opts.rate_limit_keys_per_sec)) as u64,
+        );
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            std::thread::sleep(wait);
+        }
+    }
+}
+
+impl<'a, D: DBAccess> Iterator for DBScan<'a, D> {
+    type Item = Result<KVBytes, Error>;
+
+    fn next(&mut self) -> Option<Result<KVBytes, Error>> {
+        if self.done {
+            return None;
+        }
+        if self.iter.is_none()
+            || (self.opts.batch_size > 0 && self.keys_in_batch >= self.opts.batch_size)
+        {
+            self.start_batch();
+        }
+        self.throttle();
+
+        let iter = self.iter.as_mut().unwrap();
+        if let Some((key, value)) = iter.item() {
+            let item: KVBytes = (Box::from(key), Box::from(value));
+            iter.next();
+            self.keys_in_batch += 1;
+            self.keys_returned += 1;
+            self.last_key = Some(item.0.clone());
+            Some(Ok(item))
+        } else {
+            self.done = true;
+            let status = iter.status();
+            self.iter = None;
+            status.err().map(Result::Err)
+        }
+    }
+}
+
+impl<'a, D: DBAccess> std::iter::FusedIterator for DBScan<'a, D> {}
+
 /// Iterates the batches of writes since a given sequence number.
 ///
 /// `DBWALIterator` is returned by `DB::get_updates_since()` and will return the
@@ -552,9 +843,8 @@ impl Iterator for DBWALIterator {
         }
 
         let mut seq: u64 = 0;
-        let mut batch = WriteBatch {
-            inner: unsafe { ffi::rocksdb_wal_iter_get_batch(self.inner, &mut seq) },
-        };
+        let mut batch =
+            WriteBatch::from_raw(unsafe { ffi::rocksdb_wal_iter_get_batch(self.inner, &mut seq) });
 
         // if the initial sequence number is what was requested we skip it to
         // only provide changes *after* it
@@ -568,9 +858,9 @@ impl Iterator for DBWALIterator {
             }
 
             // this drops which in turn frees the skipped batch
-            batch = WriteBatch {
-                inner: unsafe { ffi::rocksdb_wal_iter_get_batch(self.inner, &mut seq) },
-            };
+            batch = WriteBatch::from_raw(unsafe {
+                ffi::rocksdb_wal_iter_get_batch(self.inner, &mut seq)
+            });
         }
 
         if !self.valid() {
@@ -12,32 +12,60 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::path::Path;
 use std::ptr::{null_mut, NonNull};
 use std::slice;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use libc::{self, c_char, c_double, c_int, c_uchar, c_uint, c_void, size_t};
 
-use crate::statistics::{Histogram, HistogramData, StatsLevel};
+use crate::statistics::{Histogram, HistogramData, Statistics, StatsLevel};
 use crate::{
     compaction_filter::{self, CompactionFilterCallback, CompactionFilterFn},
     compaction_filter_factory::{self, CompactionFilterFactory},
-    comparator::{self, ComparatorCallback, CompareFn},
+    comparator::{
+        self, ComparatorCallback, ComparatorWithTsCallback, CompareFn, CompareTsFn,
+        CompareWithoutTsFn,
+    },
     db::DBAccess,
     env::Env,
     ffi,
-    ffi_util::{from_cstr, to_cpath, CStrLike},
+    ffi_util::{error_message, from_cstr, to_cpath, CStrLike},
     merge_operator::{
         self, full_merge_callback, partial_merge_callback, MergeFn, MergeOperatorCallback,
     },
+    options_file::{object_id, OptionsFile},
     slice_transform::SliceTransform,
     statistics::Ticker,
     table_properties::{self, TablePropertiesCollectorFactory},
-    ColumnFamilyDescriptor, Error, SnapshotWithThreadMode,
+    write_batch::{decode_batch_ops, BatchOp},
+    ColumnFamilyDescriptor, Error, SnapshotWithThreadMode, DEFAULT_COLUMN_FAMILY_NAME,
 };
 
+/// Name of RocksDB's default comparator.
+pub(crate) const BYTEWISE_COMPARATOR_NAME: &str = "leveldb.BytewiseComparator";
+
+/// Returns the limit of open files of the process the way `port::GetMaxOpenFiles`
+/// of RocksDB does, or the limit RocksDB assumes when it is unknown.
+fn process_max_open_files() -> c_int {
+    const UNKNOWN_LIMIT: c_int = 0x40_0000;
+
+    #[cfg(unix)]
+    {
+        use std::convert::TryFrom;
+
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } == 0 {
+            return c_int::try_from(limit.rlim_cur).unwrap_or(c_int::MAX);
+        }
+    }
+    UNKNOWN_LIMIT
+}
+
 pub(crate) struct WriteBufferManagerWrapper {
     pub(crate) inner: NonNull<ffi::rocksdb_write_buffer_manager_t>,
 }
@@ -199,20 +227,74 @@ impl Cache {
         unsafe { ffi::rocksdb_cache_get_pinned_usage(self.0.inner.as_ptr()) }
     }
 
+    /// Returns the number of entries in the cache, which together with
+    /// [`get_usage`](Cache::get_usage) gives the average charge of an entry.
+    pub fn get_occupancy_count(&self) -> usize {
+        unsafe { ffi::rocksdb_cache_get_occupancy_count(self.0.inner.as_ptr()) }
+    }
+
+    /// Returns the number of slots in the hash tables of the cache, entries
+    /// can be stored in.
+    pub fn get_table_address_count(&self) -> usize {
+        unsafe { ffi::rocksdb_cache_get_table_address_count(self.0.inner.as_ptr()) }
+    }
+
     /// Sets cache capacity in bytes.
     pub fn set_capacity(&mut self, capacity: size_t) {
         unsafe {
             ffi::rocksdb_cache_set_capacity(self.0.inner.as_ptr(), capacity);
         }
     }
+
+    /// Makes the cache leak its entries instead of freeing them when it is
+    /// destroyed, to speed up the shutdown of the process.
+    ///
+    /// This leaks memory, so it should only be called right before the process
+    /// exits.
+    pub fn disown_data(&self) {
+        unsafe {
+            ffi::rocksdb_cache_disown_data(self.0.inner.as_ptr());
+        }
+    }
+}
+
+/// A comparator set with `Options::set_comparator`, which RocksDB only refers
+/// to, destroyed once no options or DB use it anymore.
+pub(crate) struct ComparatorWrapper {
+    inner: NonNull<ffi::rocksdb_comparator_t>,
+}
+
+impl Drop for ComparatorWrapper {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rocksdb_comparator_destroy(self.inner.as_ptr());
+        }
+    }
+}
+
+/// A compaction filter set with `Options::set_compaction_filter`, which RocksDB
+/// only refers to, destroyed once no options or DB use it anymore.
+pub(crate) struct CompactionFilterWrapper {
+    inner: NonNull<ffi::rocksdb_compactionfilter_t>,
+}
+
+impl Drop for CompactionFilterWrapper {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::rocksdb_compactionfilter_destroy(self.inner.as_ptr());
+        }
+    }
 }
 
 #[derive(Default)]
 pub(crate) struct OptionsMustOutliveDB {
     env: Option<Env>,
     row_cache: Option<Cache>,
+    blob_cache: Option<Cache>,
     block_based: Option<BlockBasedOptionsMustOutliveDB>,
     write_buffer_manager: Option<WriteBufferManager>,
+    comparator: Option<Arc<ComparatorWrapper>>,
+    compaction_filter: Option<Arc<CompactionFilterWrapper>>,
 }
 
 impl OptionsMustOutliveDB {
@@ -220,13 +302,25 @@ impl OptionsMustOutliveDB {
         Self {
             env: self.env.clone(),
             row_cache: self.row_cache.clone(),
+            blob_cache: self.blob_cache.clone(),
             block_based: self
                 .block_based
                 .as_ref()
                 .map(BlockBasedOptionsMustOutliveDB::clone),
             write_buffer_manager: self.write_buffer_manager.clone(),
+            comparator: self.comparator.clone(),
+            compaction_filter: self.compaction_filter.clone(),
         }
     }
+
+    pub(crate) fn env(&self) -> Option<&Env> {
+        self.env.as_ref()
+    }
+
+    /// Returns the cache set with [`BlockBasedOptions::set_block_cache`].
+    pub(crate) fn block_cache(&self) -> Option<&Cache> {
+        self.block_based.as_ref()?.block_cache.as_ref()
+    }
 }
 
 #[derive(Default)]
@@ -277,6 +371,137 @@ impl BlockBasedOptionsMustOutliveDB {
 pub struct Options {
     pub(crate) inner: *mut ffi::rocksdb_options_t,
     pub(crate) outlive: OptionsMustOutliveDB,
+    pub(crate) plugin_names: PluginNames,
+    pub(crate) write_validation: Option<WriteValidationOptions>,
+}
+
+/// Names of the comparator, merge operator and prefix extractor configured
+/// through this wrapper, which the C API offers no way to read back.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct PluginNames {
+    pub(crate) comparator: Option<String>,
+    pub(crate) merge_operator: Option<String>,
+    pub(crate) prefix_extractor: Option<String>,
+}
+
+/// Limits on the keys and values written through a [`DB`], an
+/// [`OptimisticTransactionDB`] or a [`TransactionDB`] and their transactions,
+/// enforced before the write reaches RocksDB. Configured with
+/// [`Options::set_write_validation`].
+///
+/// Writes that violate a limit fail with [`ErrorKind::InvalidArgument`] and
+/// an error message carrying the offending size.
+///
+/// [`DB`]: crate::DB
+/// [`OptimisticTransactionDB`]: crate::OptimisticTransactionDB
+/// [`TransactionDB`]: crate::TransactionDB
+/// [`ErrorKind::InvalidArgument`]: crate::ErrorKind::InvalidArgument
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WriteValidationOptions {
+    /// The maximum size of a key in bytes, `None` for no limit.
+    ///
+    /// Default: None
+    pub max_key_bytes: Option<usize>,
+    /// The maximum size of a value in bytes, `None` for no limit.
+    ///
+    /// Default: None
+    pub max_value_bytes: Option<usize>,
+    /// Whether writes of an empty key are rejected.
+    ///
+    /// Default: false
+    pub reject_empty_keys: bool,
+}
+
+impl WriteValidationOptions {
+    pub(crate) fn check_key(&self, len: usize) -> Result<(), Error> {
+        if len == 0 && self.reject_empty_keys {
+            return Err(Error::new("Invalid argument: empty key".to_owned()));
+        }
+        match self.max_key_bytes {
+            Some(max) if len > max => Err(Error::new(format!(
+                "Invalid argument: key of {len} bytes exceeds the limit of {max} bytes"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn check_value(&self, len: usize) -> Result<(), Error> {
+        match self.max_value_bytes {
+            Some(max) if len > max => Err(Error::new(format!(
+                "Invalid argument: value of {len} bytes exceeds the limit of {max} bytes"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a put or merge.
+    pub(crate) fn check_write(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.check_key(key.len())?;
+        self.check_value(value.len())
+    }
+
+    /// Checks the operations of the serialized write batch `data`, decoding it.
+    pub(crate) fn check_batch(&self, data: &[u8]) -> Result<(), Error> {
+        let ops = decode_batch_ops(data).ok_or_else(|| {
+            Error::new("Invalid argument: the write batch cannot be decoded".to_owned())
+        })?;
+        ops.iter().try_for_each(|(_, op)| match *op {
+            BatchOp::Put { key, value } | BatchOp::Merge { key, value } => {
+                self.check_write(key, value)
+            }
+            BatchOp::Delete { key } | BatchOp::Other { key } => self.check_key(key.len()),
+            BatchOp::DeleteRange { from, to } => {
+                self.check_key(from.len())?;
+                self.check_key(to.len())
+            }
+        })
+    }
+}
+
+/// Whether writes to a column family must go through the write-ahead log.
+/// Set per column family with [`DBWithThreadMode::set_cf_durability_policy`].
+///
+/// [`DBWithThreadMode::set_cf_durability_policy`]: crate::DBWithThreadMode::set_cf_durability_policy
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// Reject writes with [`WriteOptions::disable_wal`] set.
+    WalRequired,
+    /// Reject writes without [`WriteOptions::disable_wal`] set.
+    WalForbidden,
+    /// Accept any write.
+    #[default]
+    DontCare,
+}
+
+impl Durability {
+    pub(crate) fn check(self, wal_disabled: bool) -> Result<(), Error> {
+        match self {
+            Durability::WalRequired if wal_disabled => Err(Error::new(
+                "Invalid argument: write without WAL to a column family that requires it"
+                    .to_owned(),
+            )),
+            Durability::WalForbidden if !wal_disabled => Err(Error::new(
+                "Invalid argument: write with WAL to a column family that forbids it".to_owned(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A critical column family option whose configured value differs from the
+/// one recorded in the OPTIONS file of an existing database.
+///
+/// See [`Options::validate_against_existing`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionsMismatch {
+    /// The column family the option belongs to.
+    pub column_family: String,
+    /// The option name as written in the OPTIONS file, e.g. `comparator`.
+    pub field: &'static str,
+    /// The value configured on the `Options`, `None` if not set.
+    pub configured: Option<String>,
+    /// The value the existing database was opened with, `None` if not set.
+    pub existing: Option<String>,
 }
 
 /// Optionally disable WAL or sync for this write.
@@ -306,6 +531,8 @@ pub struct Options {
 /// ```
 pub struct WriteOptions {
     pub(crate) inner: *mut ffi::rocksdb_writeoptions_t,
+    // Mirrors `disable_wal` for checking durability policies.
+    pub(crate) wal_disabled: bool,
 }
 
 /// Optionally wait for the memtable flush to be performed.
@@ -340,8 +567,11 @@ pub struct BlockBasedOptions {
 
 pub struct ReadOptions {
     pub(crate) inner: *mut ffi::rocksdb_readoptions_t,
-    iterate_upper_bound: Option<Vec<u8>>,
-    iterate_lower_bound: Option<Vec<u8>>,
+    pub(crate) iterate_upper_bound: Option<Vec<u8>>,
+    pub(crate) iterate_lower_bound: Option<Vec<u8>>,
+    timestamp: Option<Vec<u8>>,
+    iter_start_ts: Option<Vec<u8>>,
+    total_order_seek: bool,
 }
 
 /// Configuration of cuckoo-based storage.
@@ -390,6 +620,8 @@ unsafe impl Send for IngestExternalFileOptions {}
 unsafe impl Send for CacheWrapper {}
 unsafe impl Send for CompactOptions {}
 unsafe impl Send for WriteBufferManagerWrapper {}
+unsafe impl Send for ComparatorWrapper {}
+unsafe impl Send for CompactionFilterWrapper {}
 
 // Sync is similarly safe for many types because they do not expose interior mutability, and their
 // use within the rocksdb library is generally behind a const reference
@@ -403,6 +635,8 @@ unsafe impl Sync for IngestExternalFileOptions {}
 unsafe impl Sync for CacheWrapper {}
 unsafe impl Sync for CompactOptions {}
 unsafe impl Sync for WriteBufferManagerWrapper {}
+unsafe impl Sync for ComparatorWrapper {}
+unsafe impl Sync for CompactionFilterWrapper {}
 
 impl Drop for Options {
     fn drop(&mut self) {
@@ -412,6 +646,12 @@ impl Drop for Options {
     }
 }
 
+/// Cloned options share the callbacks registered on the original, e.g. the
+/// comparator, merge operator, compaction filter and the factories of compaction
+/// filters and table properties collectors: DBs opened with either use the same
+/// instances, which are dropped when the last options or DB using them is.
+/// Factories create one filter or collector at a time, each used by a single
+/// compaction or SST file only.
 impl Clone for Options {
     fn clone(&self) -> Self {
         let inner = unsafe { ffi::rocksdb_options_create_copy(self.inner) };
@@ -420,6 +660,8 @@ impl Clone for Options {
         Self {
             inner,
             outlive: self.outlive.clone(),
+            plugin_names: self.plugin_names.clone(),
+            write_validation: self.write_validation,
         }
     }
 }
@@ -502,6 +744,24 @@ impl BlockBasedOptions {
     ///
     /// Use partitioned full filters for each SST file. This option is
     /// incompatible with block-based filters.
+    ///
+    /// The filter of a file is split into partitions of about
+    /// [`set_metadata_block_size`](Self::set_metadata_block_size) bytes, loaded
+    /// on demand, so only a small top-level index of the partitions has to stay
+    /// in memory. This reduces the memory used by the filters of very large SST
+    /// files, at the cost of an extra block read for the lookups missing the
+    /// cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::{BlockBasedIndexType, BlockBasedOptions};
+    ///
+    /// let mut opts = BlockBasedOptions::default();
+    /// opts.set_bloom_filter(10.0, false);
+    /// opts.set_partition_filters(true);
+    /// opts.set_index_type(BlockBasedIndexType::TwoLevelIndexSearch);
+    /// ```
     pub fn set_partition_filters(&mut self, size: bool) {
         unsafe {
             ffi::rocksdb_block_based_options_set_partition_filters(self.inner, c_uchar::from(size));
@@ -530,6 +790,14 @@ impl BlockBasedOptions {
     /// Sets a [Bloom filter](https://github.com/facebook/rocksdb/wiki/RocksDB-Bloom-Filter)
     /// policy to reduce disk reads.
     ///
+    /// `bits_per_key` sets the false positive rate of the filter and may be
+    /// fractional, e.g. 10 bits per key give a rate of about 1%, and each extra
+    /// 5 bits roughly divide it by 10.
+    ///
+    /// RocksDB no longer builds the deprecated block-based filters, so
+    /// `block_based` is ignored and a full filter is built for each SST file, or
+    /// a partitioned one with [`set_partition_filters`](Self::set_partition_filters).
+    ///
     /// # Examples
     ///
     /// ```
@@ -633,7 +901,9 @@ impl BlockBasedOptions {
     /// If cache_index_and_filter_blocks is true and the below is true, then
     /// filter and index blocks are stored in the cache, but a reference is
     /// held in the "table reader" object so the blocks are pinned and only
-    /// evicted from cache when the table reader is freed.
+    /// evicted from cache when the table reader is freed. This avoids reloading
+    /// them on reads of the level 0 files, which are often compacted away
+    /// before their blocks would stay hot in the cache otherwise.
     ///
     /// Default: false.
     pub fn set_pin_l0_filter_and_index_blocks_in_cache(&mut self, v: bool) {
@@ -651,7 +921,7 @@ impl BlockBasedOptions {
     /// blocks are pinned and only evicted from cache when the table reader is
     /// freed. This is not limited to l0 in LSM tree.
     ///
-    /// Default: false.
+    /// Default: true.
     pub fn set_pin_top_level_index_and_filter(&mut self, v: bool) {
         unsafe {
             ffi::rocksdb_block_based_options_set_pin_top_level_index_and_filter(
@@ -899,17 +1169,31 @@ impl Options {
                 &mut column_family_options,
             ));
         }
-        let options = Options {
-            inner: db_options,
-            outlive: OptionsMustOutliveDB::default(),
+        // the options only refer to the env, and the table options of each column
+        // family share the cache
+        let outlive = OptionsMustOutliveDB {
+            env: Some(env),
+            block_based: Some(BlockBasedOptionsMustOutliveDB {
+                block_cache: Some(cache),
+            }),
+            ..OptionsMustOutliveDB::default()
         };
-        let column_families = unsafe {
+        let mut column_families = unsafe {
             Options::read_column_descriptors(
                 num_column_families,
                 column_family_names,
                 column_family_options,
             )
         };
+        for cf in &mut column_families {
+            cf.options.outlive = outlive.clone();
+        }
+        let options = Options {
+            inner: db_options,
+            outlive,
+            plugin_names: PluginNames::default(),
+            write_validation: None,
+        };
         Ok((options, column_families))
     }
 
@@ -930,6 +1214,8 @@ impl Options {
                 .map(|ptr| Options {
                     inner: *ptr,
                     outlive: OptionsMustOutliveDB::default(),
+                    plugin_names: PluginNames::default(),
+                    write_validation: None,
                 });
         let column_descriptors = column_family_names_iter
             .zip(column_family_options_iter)
@@ -944,6 +1230,73 @@ impl Options {
         column_descriptors
     }
 
+    /// Compares the comparator, merge operator and prefix extractor configured on
+    /// these options with the ones recorded in the latest OPTIONS file of the
+    /// existing database at `path`, for the `default` column family.
+    ///
+    /// This allows detecting a mismatch before opening the database, which would
+    /// otherwise fail late (or, for a merge operator, not at all). Only names set
+    /// through this crate's setters are known, so options obtained from
+    /// [`Options::load_latest`] report their plugins as not configured.
+    ///
+    /// Returns an empty list when everything matches.
+    pub fn validate_against_existing<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<Vec<OptionsMismatch>, Error> {
+        self.validate_cf_against_existing(path, DEFAULT_COLUMN_FAMILY_NAME)
+    }
+
+    /// Same as [`validate_against_existing`](Self::validate_against_existing), for
+    /// the column family named `cf_name`.
+    pub fn validate_cf_against_existing<P: AsRef<Path>>(
+        &self,
+        path: P,
+        cf_name: &str,
+    ) -> Result<Vec<OptionsMismatch>, Error> {
+        self.mismatches(&OptionsFile::load_latest(path)?, cf_name)
+    }
+
+    /// Compares the plugins of these options with the ones of the column family
+    /// `cf_name` in `file`, see `validate_cf_against_existing`.
+    pub(crate) fn mismatches(
+        &self,
+        file: &OptionsFile,
+        cf_name: &str,
+    ) -> Result<Vec<OptionsMismatch>, Error> {
+        let existing = file.cf_options(cf_name).ok_or_else(|| {
+            Error::new(format!(
+                "Invalid argument: column family `{cf_name}` not found in the OPTIONS file"
+            ))
+        })?;
+        let configured = vec![
+            (
+                "comparator",
+                self.plugin_names
+                    .comparator
+                    .clone()
+                    .or_else(|| Some(BYTEWISE_COMPARATOR_NAME.to_owned())),
+            ),
+            ("merge_operator", self.plugin_names.merge_operator.clone()),
+            (
+                "prefix_extractor",
+                self.plugin_names.prefix_extractor.clone(),
+            ),
+        ];
+        Ok(configured
+            .into_iter()
+            .filter_map(|(field, configured)| {
+                let existing = existing.get(field).and_then(|v| object_id(v));
+                (configured != existing).then(|| OptionsMismatch {
+                    column_family: cf_name.to_owned(),
+                    field,
+                    configured,
+                    existing,
+                })
+            })
+            .collect())
+    }
+
     /// By default, RocksDB uses only one background thread for flush and
     /// compaction. Calling this function will set it up such that total of
     /// `total_threads` is used. Good value for `total_threads` is the number of
@@ -1084,6 +1437,95 @@ impl Options {
         }
     }
 
+    /// If true, the number of entries read from the memtables during a flush is
+    /// checked against the number of entries they hold, failing the flush with a
+    /// corruption error on mismatch.
+    ///
+    /// Default: true
+    pub fn set_flush_verify_memtable_count(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set_from_string(&format!("flush_verify_memtable_count={enabled}"))
+    }
+
+    /// If true, the unique ID of each SST file is checked against the one
+    /// recorded in the MANIFEST when the file is opened, failing the open of the
+    /// DB with a corruption error on mismatch, e.g. when a file was replaced.
+    ///
+    /// Default: true
+    pub fn set_verify_sst_unique_id_in_manifest(&mut self, enabled: bool) -> Result<(), Error> {
+        self.set_from_string(&format!("verify_sst_unique_id_in_manifest={enabled}"))
+    }
+
+    /// Sets the maximum number of times RocksDB tries to recover on its own from
+    /// a retryable background error, e.g. an I/O error during a flush, before the
+    /// DB stays in read-only mode. 0 disables the automatic recovery.
+    ///
+    /// Default: `i32::MAX`
+    pub fn set_max_bgerror_resume_count(&mut self, count: i32) -> Result<(), Error> {
+        self.set_from_string(&format!("max_bgerror_resume_count={count}"))
+    }
+
+    /// Sets the delay in microseconds between two attempts of RocksDB to recover
+    /// from a retryable background error, see
+    /// [`set_max_bgerror_resume_count`](Options::set_max_bgerror_resume_count).
+    ///
+    /// Default: 1000000 (1 second)
+    pub fn set_bgerror_resume_retry_interval(&mut self, micros: u64) -> Result<(), Error> {
+        self.set_from_string(&format!("bgerror_resume_retry_interval={micros}"))
+    }
+
+    /// Sets options the C API has no setter for from a string such as
+    /// `"name=value;name=value"`, parsed by RocksDB on top of the current ones.
+    /// Fails if the bundled RocksDB does not know one of the options.
+    fn set_from_string(&mut self, opts: &str) -> Result<(), Error> {
+        let c_opts = CString::new(opts).map_err(|err| {
+            Error::new(format!(
+                "Failed to convert options `{opts}` to CString: {err}"
+            ))
+        })?;
+        unsafe {
+            let new_inner = ffi::rocksdb_options_create();
+            let mut err: *mut c_char = null_mut();
+            ffi::rocksdb_get_options_from_string(self.inner, c_opts.as_ptr(), new_inner, &mut err);
+            if !err.is_null() {
+                ffi::rocksdb_options_destroy(new_inner);
+                return Err(Error::new(error_message(err)));
+            }
+            ffi::rocksdb_options_destroy(self.inner);
+            self.inner = new_inner;
+        }
+        Ok(())
+    }
+
+    /// Sets the limits on key and value sizes checked by a database opened with
+    /// these options before each put, merge, delete and batch write, including
+    /// the ones of its transactions and the ones with a user-defined timestamp.
+    /// Deletes only have their keys checked, and range deletes both bounds.
+    ///
+    /// The check is opt-in: without validation writes are passed to RocksDB as
+    /// is. With it, each written [`WriteBatch`] is decoded to check its keys and
+    /// values, which takes time linear in the size of the batch, and batches that
+    /// cannot be decoded are rejected.
+    ///
+    /// Default: no validation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::{Options, WriteValidationOptions};
+    ///
+    /// let mut opts = Options::default();
+    /// opts.set_write_validation(WriteValidationOptions {
+    ///     max_key_bytes: Some(1024),
+    ///     max_value_bytes: Some(1024 * 1024),
+    ///     reject_empty_keys: true,
+    /// });
+    /// ```
+    ///
+    /// [`WriteBatch`]: crate::WriteBatch
+    pub fn set_write_validation(&mut self, validation: WriteValidationOptions) {
+        self.write_validation = Some(validation);
+    }
+
     /// A list of paths where SST files can be put into, with its target size.
     /// Newer data is placed into paths specified earlier in the vector while
     /// older data gradually moves to paths specified later in the vector.
@@ -1379,7 +1821,13 @@ impl Options {
     /// With this feature turned on, RocksDB will automatically adjust max bytes for each level.
     /// The goal of this feature is to have lower bound on size amplification.
     ///
-    /// Default: false.
+    /// The level sizes are derived from the size of the last level, so data is
+    /// compacted straight from level 0 into the last non-empty level, and the
+    /// levels above it stay empty until the DB grows. The levels in use can be
+    /// seen in [`ColumnFamilyMetaData::levels`](crate::ColumnFamilyMetaData::levels).
+    /// Only takes effect with level compaction.
+    ///
+    /// Default: true.
     pub fn set_level_compaction_dynamic_level_bytes(&mut self, v: bool) {
         unsafe {
             ffi::rocksdb_options_set_level_compaction_dynamic_level_bytes(
@@ -1443,8 +1891,10 @@ impl Options {
         name: impl CStrLike,
         full_merge_fn: F,
     ) {
+        let name = name.into_c_string().unwrap();
+        self.plugin_names.merge_operator = Some(name.to_string_lossy().into_owned());
         let cb = Box::new(MergeOperatorCallback {
-            name: name.into_c_string().unwrap(),
+            name,
             full_merge_fn: full_merge_fn.clone(),
             partial_merge_fn: full_merge_fn,
         });
@@ -1468,8 +1918,10 @@ impl Options {
         full_merge_fn: F,
         partial_merge_fn: PF,
     ) {
+        let name = name.into_c_string().unwrap();
+        self.plugin_names.merge_operator = Some(name.to_string_lossy().into_owned());
         let cb = Box::new(MergeOperatorCallback {
-            name: name.into_c_string().unwrap(),
+            name,
             full_merge_fn,
             partial_merge_fn,
         });
@@ -1522,6 +1974,9 @@ impl Options {
                 Some(compaction_filter::name_callback::<CompactionFilterCallback<F>>),
             );
             ffi::rocksdb_options_set_compaction_filter(self.inner, cf);
+            self.outlive.compaction_filter = Some(Arc::new(CompactionFilterWrapper {
+                inner: NonNull::new(cf).unwrap(),
+            }));
         }
     }
 
@@ -1537,7 +1992,7 @@ impl Options {
     where
         F: CompactionFilterFactory + 'static,
     {
-        let factory = Box::new(factory);
+        let factory = Box::new(Mutex::new(factory));
 
         unsafe {
             let cff = ffi::rocksdb_compactionfilterfactory_create(
@@ -1558,8 +2013,10 @@ impl Options {
     /// name and orders keys *exactly* the same as the comparator provided to
     /// previous open calls on the same DB.
     pub fn set_comparator(&mut self, name: impl CStrLike, compare_fn: Box<CompareFn>) {
+        let name = name.into_c_string().unwrap();
+        self.plugin_names.comparator = Some(name.to_string_lossy().into_owned());
         let cb = Box::new(ComparatorCallback {
-            name: name.into_c_string().unwrap(),
+            name,
             f: compare_fn,
         });
 
@@ -1571,10 +2028,60 @@ impl Options {
                 Some(comparator::name_callback),
             );
             ffi::rocksdb_options_set_comparator(self.inner, cmp);
+            self.outlive.comparator = Some(Arc::new(ComparatorWrapper {
+                inner: NonNull::new(cmp).unwrap(),
+            }));
+        }
+    }
+
+    /// Sets a comparator for keys carrying a user-defined timestamp of
+    /// `timestamp_size` bytes, enabling the `*_with_ts` write methods and
+    /// [`ReadOptions::set_timestamp`].
+    ///
+    /// `compare_fn` compares full keys including their timestamp suffix,
+    /// `compare_ts_fn` compares two timestamps and `compare_without_ts_fn` compares
+    /// the user keys of two keys, each of which may or may not have a timestamp.
+    /// [`U64Comparator`](crate::U64Comparator) implements them for
+    /// [`U64Timestamp`](crate::U64Timestamp)s.
+    ///
+    /// As with [`set_comparator`](Self::set_comparator), the name and ordering must
+    /// stay the same across all opens of the DB.
+    pub fn set_comparator_with_ts(
+        &mut self,
+        name: impl CStrLike,
+        timestamp_size: usize,
+        compare_fn: Box<CompareFn>,
+        compare_ts_fn: Box<CompareTsFn>,
+        compare_without_ts_fn: Box<CompareWithoutTsFn>,
+    ) {
+        let name = name.into_c_string().unwrap();
+        self.plugin_names.comparator = Some(name.to_string_lossy().into_owned());
+        let cb = Box::new(ComparatorWithTsCallback {
+            name,
+            compare_fn,
+            compare_ts_fn,
+            compare_without_ts_fn,
+        });
+
+        unsafe {
+            let cmp = ffi::rocksdb_comparator_with_ts_create(
+                Box::into_raw(cb).cast::<c_void>(),
+                Some(comparator::destructor_with_ts_callback),
+                Some(comparator::compare_with_ts_callback),
+                Some(comparator::compare_ts_callback),
+                Some(comparator::compare_without_ts_callback),
+                Some(comparator::name_with_ts_callback),
+                timestamp_size,
+            );
+            ffi::rocksdb_options_set_comparator(self.inner, cmp);
+            self.outlive.comparator = Some(Arc::new(ComparatorWrapper {
+                inner: NonNull::new(cmp).unwrap(),
+            }));
         }
     }
 
     pub fn set_prefix_extractor(&mut self, prefix_extractor: SliceTransform) {
+        self.plugin_names.prefix_extractor = prefix_extractor.take_name();
         unsafe {
             ffi::rocksdb_options_set_prefix_extractor(self.inner, prefix_extractor.inner);
         }
@@ -1656,8 +2163,35 @@ impl Options {
         }
     }
 
+    /// Returns the value set with [`set_max_open_files`](Options::set_max_open_files).
+    ///
+    /// RocksDB may lower the limit when opening the DB, see
+    /// [`DBWithThreadMode::max_open_files`](crate::DBWithThreadMode::max_open_files)
+    /// for the effective value.
+    pub fn get_max_open_files(&self) -> c_int {
+        unsafe { ffi::rocksdb_options_get_max_open_files(self.inner) }
+    }
+
+    /// Returns the limit on open files a DB opened with these options runs with,
+    /// sanitized the way RocksDB does on open: a limit other than `-1` is lowered
+    /// to the limit of open files of the process and raised to at least 20.
+    pub(crate) fn effective_max_open_files(&self) -> c_int {
+        const MIN_MAX_OPEN_FILES: c_int = 20;
+
+        match self.get_max_open_files() {
+            -1 => -1,
+            max_open_files => max_open_files
+                .min(process_max_open_files())
+                .max(MIN_MAX_OPEN_FILES),
+        }
+    }
+
     /// If max_open_files is -1, DB will open all files on DB::Open(). You can
     /// use this option to increase the number of threads used to open the files.
+    ///
+    /// On DBs with many SST files the open time is dominated by opening them, so
+    /// more threads shorten it. The threads only live for the duration of the open.
+    ///
     /// Default: 16
     pub fn set_max_file_opening_threads(&mut self, nthreads: c_int) {
         unsafe {
@@ -1665,6 +2199,12 @@ impl Options {
         }
     }
 
+    /// Returns the value set with
+    /// [`set_max_file_opening_threads`](Options::set_max_file_opening_threads).
+    pub fn get_max_file_opening_threads(&self) -> c_int {
+        unsafe { ffi::rocksdb_options_get_max_file_opening_threads(self.inner) }
+    }
+
     /// By default, writes to stable storage use fdatasync (on platforms
     /// where this function is available). If this option is true,
     /// fsync is used instead.
@@ -1695,7 +2235,9 @@ impl Options {
     /// If it is empty, the log files will be in the same dir as data.
     /// If it is non empty, the log files will be in the specified dir,
     /// and the db data dir's absolute path will be used as the log file
-    /// name's prefix.
+    /// name's prefix, with the separators replaced by underscores, e.g.
+    /// `..._data_db_LOG` for a DB at `/data/db`. The dir is created when the DB
+    /// is opened, so the info LOG can be kept off a space-constrained data volume.
     ///
     /// Default: empty
     pub fn set_db_log_dir<P: AsRef<Path>>(&mut self, path: P) {
@@ -1770,9 +2312,15 @@ impl Options {
     /// IO and fix the buffer size when using direct IO to ensure alignment of
     /// write requests if the logical sector size is unusual
     ///
+    /// With [`set_use_direct_io_for_flush_and_compaction`], flushes and
+    /// compactions write SST files in chunks of this size, which must then be
+    /// a multiple of the logical block size of the device.
+    ///
     /// Default: 1024 * 1024 (1 MB)
     ///
     /// Dynamically changeable through SetDBOptions() API.
+    ///
+    /// [`set_use_direct_io_for_flush_and_compaction`]: Options::set_use_direct_io_for_flush_and_compaction
     pub fn set_writable_file_max_buffer_size(&mut self, nbytes: u64) {
         unsafe {
             ffi::rocksdb_options_set_writable_file_max_buffer_size(self.inner, nbytes);
@@ -1820,12 +2368,32 @@ impl Options {
         }
     }
 
+    /// Sets the maximum time in microseconds a writer waiting for the write batch
+    /// group leader spins and yields before blocking on a mutex, with
+    /// [`set_enable_write_thread_adaptive_yield`](Options::set_enable_write_thread_adaptive_yield).
+    /// Longer waits trade CPU for lower write latency under contention.
+    ///
+    /// Default: 100
+    pub fn set_write_thread_max_yield_usec(&mut self, micros: u64) -> Result<(), Error> {
+        self.set_from_string(&format!("write_thread_max_yield_usec={micros}"))
+    }
+
     /// Specifies whether an iteration->Next() sequentially skips over keys with the same user-key or not.
     ///
     /// This number specifies the number of keys (with the same userkey)
-    /// that will be sequentially skipped before a reseek is issued.
+    /// that will be sequentially skipped before a reseek is issued. Reseeks are
+    /// counted by the `Ticker::NumberOfReseeksInIteration` ticker.
     ///
     /// Default: 8
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::Options;
+    ///
+    /// let mut opts = Options::default();
+    /// opts.set_max_sequential_skip_in_iterations(16);
+    /// ```
     pub fn set_max_sequential_skip_in_iterations(&mut self, num: u64) {
         unsafe {
             ffi::rocksdb_options_set_max_sequential_skip_in_iterations(self.inner, num);
@@ -2254,7 +2822,30 @@ impl Options {
         }
     }
 
-    /// Sets the options needed to support Universal Style compactions.
+    /// Sets the options needed to support Universal Style compactions. They are
+    /// copied, and only used with [`DBCompactionStyle::Universal`].
+    ///
+    /// In universal compaction, each L0 file and each other non-empty level is a
+    /// sorted run. Once there are at least
+    /// [`set_level_zero_file_num_compaction_trigger`](Self::set_level_zero_file_num_compaction_trigger)
+    /// sorted runs, RocksDB first compacts all of them if the space amplification
+    /// exceeds [`UniversalCompactOptions::set_max_size_amplification_percent`],
+    /// and otherwise merges runs of similar size as set with
+    /// [`UniversalCompactOptions::set_size_ratio`].
+    ///
+    /// ```
+    /// use rocksdb::{DBCompactionStyle, Options, UniversalCompactOptions};
+    ///
+    /// let mut uco = UniversalCompactOptions::default();
+    /// uco.set_size_ratio(1);
+    /// uco.set_min_merge_width(2);
+    /// uco.set_max_merge_width(8);
+    /// uco.set_max_size_amplification_percent(50);
+    ///
+    /// let mut opts = Options::default();
+    /// opts.set_compaction_style(DBCompactionStyle::Universal);
+    /// opts.set_universal_compaction_options(&uco);
+    /// ```
     pub fn set_universal_compaction_options(&mut self, uco: &UniversalCompactOptions) {
         unsafe {
             ffi::rocksdb_options_set_universal_compaction_options(self.inner, uco.inner);
@@ -2291,7 +2882,21 @@ impl Options {
     /// number) that will change the state visible to the snapshot after they are
     /// landed to the memtable.
     ///
+    /// Opening a database fails with `NotSupported` if unordered_write is combined
+    /// with `enable_pipelined_write` or with `allow_concurrent_memtable_write`
+    /// disabled. A `TransactionDB` only accepts it with `two_write_queues` and the
+    /// WRITE_PREPARED write policy, which this crate does not expose.
+    ///
     /// Default: false
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::Options;
+    ///
+    /// let mut opts = Options::default();
+    /// opts.set_unordered_write(true);
+    /// ```
     pub fn set_unordered_write(&mut self, unordered: bool) {
         unsafe {
             ffi::rocksdb_options_set_unordered_write(self.inner, c_uchar::from(unordered));
@@ -2312,6 +2917,10 @@ impl Options {
     /// Sets maximum number of concurrent background jobs
     /// (compactions and flushes).
     ///
+    /// A quarter of the jobs, and at least one, are flushes, the rest are
+    /// compactions. The threads running them are sized per pool with
+    /// [`Env::set_background_threads_with_priority`].
+    ///
     /// Default: 2
     ///
     /// Dynamically changeable through SetDBOptions() API.
@@ -2321,6 +2930,11 @@ impl Options {
         }
     }
 
+    /// Returns the value set with [`set_max_background_jobs`](Self::set_max_background_jobs).
+    pub fn get_max_background_jobs(&self) -> c_int {
+        unsafe { ffi::rocksdb_options_get_max_background_jobs(self.inner) }
+    }
+
     /// Sets the maximum number of concurrent background compaction jobs, submitted to
     /// the default LOW priority thread pool.
     /// We first try to schedule compactions based on
@@ -2332,7 +2946,7 @@ impl Options {
     /// LOW priority thread pool. For more information, see
     /// Env::SetBackgroundThreads
     ///
-    /// Default: `1`
+    /// Default: `-1`, i.e. derived from `max_background_jobs`
     ///
     /// # Examples
     ///
@@ -2353,6 +2967,13 @@ impl Options {
         }
     }
 
+    /// Returns the value set with
+    /// [`set_max_background_compactions`](Self::set_max_background_compactions),
+    /// `-1` if it was not set.
+    pub fn get_max_background_compactions(&self) -> c_int {
+        unsafe { ffi::rocksdb_options_get_max_background_compactions(self.inner) }
+    }
+
     /// Sets the maximum number of concurrent background memtable flush jobs, submitted to
     /// the HIGH priority thread pool.
     ///
@@ -2368,7 +2989,7 @@ impl Options {
     /// HIGH priority thread pool. For more information, see
     /// Env::SetBackgroundThreads
     ///
-    /// Default: `1`
+    /// Default: `-1`, i.e. derived from `max_background_jobs`
     ///
     /// # Examples
     ///
@@ -2389,6 +3010,13 @@ impl Options {
         }
     }
 
+    /// Returns the value set with
+    /// [`set_max_background_flushes`](Self::set_max_background_flushes), `-1` if
+    /// it was not set.
+    pub fn get_max_background_flushes(&self) -> c_int {
+        unsafe { ffi::rocksdb_options_get_max_background_flushes(self.inner) }
+    }
+
     /// Disables automatic compactions. Manual compactions can still
     /// be issued on this column family
     ///
@@ -2420,6 +3048,8 @@ impl Options {
     /// If there isn't enough free huge page available, it will fall back to
     /// malloc.
     ///
+    /// Default: 0
+    ///
     /// Dynamically changeable through SetOptions() API
     pub fn set_memtable_huge_page_size(&mut self, size: size_t) {
         unsafe {
@@ -2427,6 +3057,12 @@ impl Options {
         }
     }
 
+    /// Returns the value set with
+    /// [`set_memtable_huge_page_size`](Self::set_memtable_huge_page_size).
+    pub fn get_memtable_huge_page_size(&self) -> size_t {
+        unsafe { ffi::rocksdb_options_get_memtable_huge_page_size(self.inner) }
+    }
+
     /// Sets the maximum number of successive merge operations on a key in the memtable.
     ///
     /// When a merge operation is added to the memtable and the maximum number of
@@ -2572,6 +3208,31 @@ impl Options {
         }
     }
 
+    /// If true, writes that skip the memtable (e.g. the prepare phase of a
+    /// WRITE_PREPARED transaction) go through a second write queue, so they only
+    /// contend with each other for the WAL instead of waiting for memtable
+    /// writers. Plain writes keep using the main queue and are not affected.
+    ///
+    /// RocksDB's write path does not support a second write queue together with
+    /// pipelined writes, so do not combine this with
+    /// [`set_enable_pipelined_write`](Self::set_enable_pipelined_write).
+    ///
+    /// Default: false
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::Options;
+    ///
+    /// let mut opts = Options::default();
+    /// opts.set_two_write_queues(true);
+    /// ```
+    pub fn set_two_write_queues(&mut self, value: bool) {
+        unsafe {
+            ffi::rocksdb_options_set_two_write_queues(self.inner, c_uchar::from(value));
+        }
+    }
+
     /// Defines the underlying memtable implementation.
     /// See official [wiki](https://github.com/facebook/rocksdb/wiki/MemTable) for more information.
     /// Defaults to using a skiplist.
@@ -2759,6 +3420,26 @@ impl Options {
         }
     }
 
+    /// Creates default options reporting into `statistics`, which can be shared by
+    /// several DBs to aggregate their metrics. Clones of the returned options report
+    /// into the same object.
+    ///
+    /// Statistics cannot be attached to existing options through RocksDB's C API,
+    /// so start from these options before applying any other setting.
+    pub fn with_statistics(statistics: &Statistics) -> Self {
+        statistics.options.clone()
+    }
+
+    /// Returns the statistics object these options report into, if statistics are
+    /// enabled. See [`with_statistics`](Self::with_statistics).
+    ///
+    /// The returned object does not share the [`reset`](Statistics::reset) state of
+    /// the object the options were created from.
+    pub fn statistics(&self) -> Option<Statistics> {
+        self.get_statistics()
+            .map(|_| Statistics::from_options(self.clone()))
+    }
+
     pub fn get_statistics(&self) -> Option<String> {
         unsafe {
             let value = ffi::rocksdb_options_statistics_get_string(self.inner);
@@ -2975,7 +3656,30 @@ impl Options {
         }
     }
 
-    /// Specify the maximal number of info log files to be kept.
+    /// If true, the data recovered from the WAL on open is kept in the memtables
+    /// instead of being flushed to SST files before the open returns, which makes
+    /// restarts faster. The WAL files are then kept until that data is flushed,
+    /// so it is replayed again if the DB is reopened before.
+    ///
+    /// Default: false
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::Options;
+    ///
+    /// let mut options = Options::default();
+    /// options.set_avoid_flush_during_recovery(true);
+    /// ```
+    pub fn set_avoid_flush_during_recovery(&mut self, avoid: bool) {
+        unsafe {
+            ffi::rocksdb_options_set_avoid_flush_during_recovery(self.inner, c_int::from(avoid));
+        }
+    }
+
+    /// Specify the maximal number of info log files to be kept, counting the
+    /// active `LOG` file. The oldest `LOG.old.*` files beyond it are deleted
+    /// when obsolete files are purged, e.g. on open.
     ///
     /// Default: 1000
     ///
@@ -3074,6 +3778,10 @@ impl Options {
 
     /// Sets global cache for table-level rows.
     ///
+    /// The same [`Cache`] can also serve as block cache and blob cache, so that
+    /// one memory budget covers all of them. Its effectiveness is reported by the
+    /// `RowCacheHit` and `RowCacheMiss` tickers.
+    ///
     /// Default: null (disabled)
     /// Not supported in ROCKSDB_LITE mode!
     pub fn set_row_cache(&mut self, cache: &Cache) {
@@ -3174,6 +3882,11 @@ impl Options {
     /// because the blocks are already allocated and fdatasync does not need to
     /// update the inode after each write.
     ///
+    /// RocksDB ignores it when the WAL recovery mode is
+    /// [`TolerateCorruptedTailRecords`](DBRecoveryMode::TolerateCorruptedTailRecords)
+    /// or [`AbsoluteConsistency`](DBRecoveryMode::AbsoluteConsistency), or when
+    /// archived WAL files are kept.
+    ///
     /// Default: 0
     ///
     /// # Examples
@@ -3193,7 +3906,20 @@ impl Options {
     /// Sets the threshold at which all writes will be slowed down to at least delayed_write_rate if estimated
     /// bytes needed to be compaction exceed this threshold.
     ///
+    /// Time spent in slowed down writes is reported by the `Ticker::StallMicros`
+    /// ticker when statistics are enabled. 0 disables the limit.
+    ///
     /// Default: 64GB
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rocksdb::Options;
+    ///
+    /// let mut options = Options::default();
+    /// options.set_soft_pending_compaction_bytes_limit(16 << 30);
+    /// options.set_hard_pending_compaction_bytes_limit(64 << 30);
+    /// ```
     pub fn set_soft_pending_compaction_bytes_limit(&mut self, limit: usize) {
         unsafe {
             ffi::rocksdb_options_set_soft_pending_compaction_bytes_limit(self.inner, limit);
@@ -3203,6 +3929,9 @@ impl Options {
     /// Sets the bytes threshold at which all writes are stopped if estimated bytes needed to be compaction exceed
     /// this threshold.
     ///
+    /// Writes are resumed once compaction brings the estimate back under the
+    /// limit. 0 disables the limit.
+    ///
     /// Default: 256GB
     pub fn set_hard_pending_compaction_bytes_limit(&mut self, limit: usize) {
         unsafe {
@@ -3321,6 +4050,19 @@ impl Options {
         }
     }
 
+    /// Sets the cache for blob values read from blob files.
+    ///
+    /// The same [`Cache`] can also serve as block cache and row cache, so that
+    /// one memory budget covers all of them.
+    ///
+    /// Default: null (disabled)
+    pub fn set_blob_cache(&mut self, cache: &Cache) {
+        unsafe {
+            ffi::rocksdb_options_set_blob_cache(self.inner, cache.0.inner.as_ptr());
+        }
+        self.outlive.blob_cache = Some(cache.clone());
+    }
+
     /// Sets the blob compaction read ahead size.
     ///
     /// Dynamically changeable through SetOptions() API
@@ -3394,6 +4136,8 @@ impl Options {
     /// If true, working thread may avoid doing unnecessary and long-latency
     /// operation (such as deleting obsolete files directly or deleting memtable)
     /// and will instead schedule a background job to do it.
+    /// This includes the cleanup done when the last iterator referencing
+    /// obsolete files is dropped.
     ///
     /// Use it if you're latency-sensitive.
     ///
@@ -3424,6 +4168,8 @@ impl Default for Options {
             Self {
                 inner: opts,
                 outlive: OptionsMustOutliveDB::default(),
+                plugin_names: PluginNames::default(),
+                write_validation: None,
             }
         }
     }
@@ -3490,6 +4236,7 @@ impl WriteOptions {
         unsafe {
             ffi::rocksdb_writeoptions_disable_WAL(self.inner, c_int::from(disable));
         }
+        self.wal_disabled = disable;
     }
 
     /// If true and if user is trying to write to column families that don't exist (they were dropped),
@@ -3554,7 +4301,10 @@ impl Default for WriteOptions {
             "Could not create RocksDB write options"
         );
 
-        Self { inner: write_opts }
+        Self {
+            inner: write_opts,
+            wal_disabled: false,
+        }
     }
 }
 
@@ -3598,12 +4348,18 @@ impl ReadOptions {
     }
 
     /// Sets the lower bound for an iterator.
+    ///
+    /// The bound is owned by the read options and kept alive by the iterators
+    /// created with them.
     pub fn set_iterate_lower_bound<K: Into<Vec<u8>>>(&mut self, key: K) {
         self.set_lower_bound_impl(Some(key.into()));
     }
 
     /// Sets the upper bound for an iterator.
     /// The upper bound itself is not included on the iteration result.
+    ///
+    /// The bound is owned by the read options and kept alive by the iterators
+    /// created with them.
     pub fn set_iterate_upper_bound<K: Into<Vec<u8>>>(&mut self, key: K) {
         self.set_upper_bound_impl(Some(key.into()));
     }
@@ -3649,7 +4405,7 @@ impl ReadOptions {
         self.set_upper_bound_impl(upper);
     }
 
-    fn set_lower_bound_impl(&mut self, bound: Option<Vec<u8>>) {
+    pub(crate) fn set_lower_bound_impl(&mut self, bound: Option<Vec<u8>>) {
         let (ptr, len) = if let Some(ref bound) = bound {
             (bound.as_ptr() as *const c_char, bound.len())
         } else if self.iterate_lower_bound.is_some() {
@@ -3663,7 +4419,7 @@ impl ReadOptions {
         }
     }
 
-    fn set_upper_bound_impl(&mut self, bound: Option<Vec<u8>>) {
+    pub(crate) fn set_upper_bound_impl(&mut self, bound: Option<Vec<u8>>) {
         let (ptr, len) = if let Some(ref bound) = bound {
             (bound.as_ptr() as *const c_char, bound.len())
         } else if self.iterate_upper_bound.is_some() {
@@ -3677,11 +4433,52 @@ impl ReadOptions {
         }
     }
 
+    /// Reads the data visible as of the user-defined timestamp `ts`, i.e. the
+    /// newest version of each key whose timestamp is not newer than `ts`.
+    ///
+    /// Required for every read when the column family uses a comparator set with
+    /// [`Options::set_comparator_with_ts`].
+    pub fn set_timestamp<T: Into<Vec<u8>>>(&mut self, ts: T) {
+        let ts = ts.into();
+        unsafe {
+            ffi::rocksdb_readoptions_set_timestamp(
+                self.inner,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+            );
+        }
+        self.timestamp = Some(ts);
+    }
+
+    /// Makes iterators return every version of each key whose timestamp lies
+    /// between `ts` and the one set with [`set_timestamp`](Self::set_timestamp),
+    /// newest first, instead of only the newest one.
+    ///
+    /// Keys returned by such iterators are internal keys: the user key followed by
+    /// its timestamp and 8 bytes encoding the sequence number and value type.
+    pub fn set_iter_start_ts<T: Into<Vec<u8>>>(&mut self, ts: T) {
+        let ts = ts.into();
+        unsafe {
+            ffi::rocksdb_readoptions_set_iter_start_ts(
+                self.inner,
+                ts.as_ptr() as *const c_char,
+                ts.len() as size_t,
+            );
+        }
+        self.iter_start_ts = Some(ts);
+    }
+
     /// Specify if this read request should process data that ALREADY
     /// resides on a particular cache. If the required data is not
     /// found at the specified cache, then Status::Incomplete is returned.
     ///
+    /// With [`ReadTier::BlockCache`], a read never blocks on the disk, so it can
+    /// be tried first on a latency-sensitive path, falling back to a read with
+    /// [`ReadTier::All`] when it fails with [`ErrorKind::Incomplete`].
+    ///
     /// Default: ::All
+    ///
+    /// [`ErrorKind::Incomplete`]: crate::ErrorKind::Incomplete
     pub fn set_read_tier(&mut self, tier: ReadTier) {
         unsafe {
             ffi::rocksdb_readoptions_set_read_tier(self.inner, tier as c_int);
@@ -3709,12 +4506,26 @@ impl ReadOptions {
     /// If true when calling Get(), we also skip prefix bloom when reading from
     /// block based table. It provides a way to read existing data after
     /// changing implementation of prefix extractor.
+    ///
+    /// If false and the column family has a prefix extractor, a seek is a prefix
+    /// seek: the keys with a different prefix than the seek key may be skipped,
+    /// so a scan across prefixes can miss data. Scans across prefixes should set
+    /// this.
+    ///
+    /// Default: false
     pub fn set_total_order_seek(&mut self, v: bool) {
+        self.total_order_seek = v;
         unsafe {
             ffi::rocksdb_readoptions_set_total_order_seek(self.inner, c_uchar::from(v));
         }
     }
 
+    /// Returns the value set with
+    /// [`set_total_order_seek`](ReadOptions::set_total_order_seek).
+    pub fn get_total_order_seek(&self) -> bool {
+        self.total_order_seek
+    }
+
     /// Sets a threshold for the number of keys that can be skipped
     /// before failing an iterator seek as incomplete. The default value of 0 should be used to
     /// never fail a request as incomplete, even on skipping too many keys.
@@ -3743,6 +4554,9 @@ impl ReadOptions {
     /// readers until they are naturally deleted during compaction. This improves
     /// read performance in DBs with many range deletions.
     ///
+    /// It also lets recovery and debugging tools inspect the keys still
+    /// physically present under a range tombstone.
+    ///
     /// Default: false
     pub fn set_ignore_range_deletions(&mut self, v: bool) {
         unsafe {
@@ -3835,6 +4649,9 @@ impl Default for ReadOptions {
                 inner: ffi::rocksdb_readoptions_create(),
                 iterate_upper_bound: None,
                 iterate_lower_bound: None,
+                timestamp: None,
+                iter_start_ts: None,
+                total_order_seek: false,
             }
         }
     }
@@ -3905,6 +4722,27 @@ impl Default for IngestExternalFileOptions {
     }
 }
 
+/// Options for [`DBWithThreadMode::create_cf_with_import`].
+///
+/// [`DBWithThreadMode::create_cf_with_import`]: crate::DBWithThreadMode::create_cf_with_import
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImportColumnFamilyOptions {
+    pub(crate) move_files: bool,
+}
+
+impl ImportColumnFamilyOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Can be set to true to move the files instead of copying them.
+    ///
+    /// Default: false
+    pub fn set_move_files(&mut self, v: bool) {
+        self.move_files = v;
+    }
+}
+
 /// Used by BlockBasedOptions::set_index_type.
 pub enum BlockBasedIndexType {
     /// A space efficient index block that is optimized for
@@ -3916,7 +4754,18 @@ pub enum BlockBasedIndexType {
     HashSearch,
 
     /// A two-level index implementation. Both levels are binary search indexes.
+    ///
+    /// The index of a file is split into partitions of about
+    /// [`BlockBasedOptions::set_metadata_block_size`] bytes, loaded on demand, so
+    /// only the small top-level index has to stay in memory. This reduces the
+    /// memory used by the indexes of large files.
     TwoLevelIndexSearch,
+
+    /// Like `BinarySearch`, but the index also stores the first key of each data
+    /// block, so iterators can defer reading a block until its entries are
+    /// needed, e.g. when seeking into a range of files. This makes the index
+    /// larger.
+    BinarySearchWithFirstKey,
 }
 
 /// Used by BlockBasedOptions::set_data_block_index_type.
@@ -4117,7 +4966,7 @@ impl UniversalCompactOptions {
         }
     }
 
-    /// sets the size amplification.
+    /// Sets the maximum size amplification.
     ///
     /// It is defined as the amount (in percentage) of
     /// additional storage needed to store a single byte of data in the database.
@@ -4248,6 +5097,43 @@ impl CompactOptions {
     }
 }
 
+/// What [`DBCommon::get_approximate_sizes_opt`] includes in the sizes.
+///
+/// [`DBCommon::get_approximate_sizes_opt`]: crate::DBCommon::get_approximate_sizes_opt
+#[derive(Debug, Clone, Copy)]
+pub struct SizeApproximationOptions {
+    /// Whether the data in the memtables, not flushed yet, is included.
+    ///
+    /// Default: false
+    pub include_memtables: bool,
+    /// Whether the data in the SST files is included.
+    ///
+    /// Default: true
+    pub include_files: bool,
+}
+
+impl Default for SizeApproximationOptions {
+    fn default() -> Self {
+        Self {
+            include_memtables: false,
+            include_files: true,
+        }
+    }
+}
+
+impl SizeApproximationOptions {
+    pub(crate) fn flags(self) -> u8 {
+        let mut flags = ffi::rocksdb_size_approximation_flags_none;
+        if self.include_memtables {
+            flags |= ffi::rocksdb_size_approximation_flags_include_memtable;
+        }
+        if self.include_files {
+            flags |= ffi::rocksdb_size_approximation_flags_include_files;
+        }
+        flags as u8
+    }
+}
+
 pub struct WaitForCompactOptions {
     pub(crate) inner: *mut ffi::rocksdb_wait_for_compact_options_t,
 }
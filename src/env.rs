@@ -14,11 +14,32 @@ use crate::{ffi, Error};
 ///
 /// Note: currently, C API behinds C++ API for various settings.
 /// See also: `rocksdb/include/env.h`
+///
+/// In particular, RocksDB's encrypted env (`NewEncryptedEnv`) cannot be
+/// created through the C API, and the only block cipher RocksDB ships is the
+/// insecure `ROT13BlockCipher` meant for its tests. Encryption at rest has to
+/// be provided below RocksDB, e.g. by an encrypted filesystem or volume.
 #[derive(Clone)]
 pub struct Env(pub(crate) Arc<EnvWrapper>);
 
+/// A thread pool of an [`Env`], running the background jobs of the DBs using it.
+///
+/// With the default options, flushes run in the high priority pool and
+/// compactions in the low priority pool. Compactions into the bottommost level
+/// run in the bottom priority pool once it has threads, so that they do not hold
+/// up the compactions of the upper levels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Priority {
+    Bottom,
+    Low,
+    High,
+}
+
 pub(crate) struct EnvWrapper {
     pub(crate) inner: *mut ffi::rocksdb_env_t,
+    // Whether the env was created with `Env::mem_env`, whose files cannot be
+    // read or written through `std::fs`.
+    in_memory: bool,
 }
 
 impl Drop for EnvWrapper {
@@ -36,7 +57,10 @@ impl Env {
         if env.is_null() {
             Err(Error::new("Could not create mem env".to_owned()))
         } else {
-            Ok(Self(Arc::new(EnvWrapper { inner: env })))
+            Ok(Self(Arc::new(EnvWrapper {
+                inner: env,
+                in_memory: false,
+            })))
         }
     }
 
@@ -47,10 +71,18 @@ impl Env {
         if env.is_null() {
             Err(Error::new("Could not create mem env".to_owned()))
         } else {
-            Ok(Self(Arc::new(EnvWrapper { inner: env })))
+            Ok(Self(Arc::new(EnvWrapper {
+                inner: env,
+                in_memory: true,
+            })))
         }
     }
 
+    /// Returns `true` if the env was created with [`mem_env`](Self::mem_env).
+    pub(crate) fn is_in_memory(&self) -> bool {
+        self.0.in_memory
+    }
+
     /// Sets the number of background worker threads of a specific thread pool for this environment.
     /// `LOW` is the default pool.
     ///
@@ -85,6 +117,36 @@ impl Env {
         }
     }
 
+    /// Sets the number of background worker threads of the `priority` thread pool.
+    ///
+    /// The number of jobs the DB schedules to the pools is limited by
+    /// [`Options::set_max_background_jobs`], so raising it may be needed to keep
+    /// the threads busy.
+    ///
+    /// [`Options::set_max_background_jobs`]: crate::Options::set_max_background_jobs
+    pub fn set_background_threads_with_priority(&mut self, n: c_int, priority: Priority) {
+        match priority {
+            Priority::Bottom => self.set_bottom_priority_background_threads(n),
+            Priority::Low => self.set_low_priority_background_threads(n),
+            Priority::High => self.set_high_priority_background_threads(n),
+        }
+    }
+
+    /// Returns the number of background worker threads of the `priority` thread pool.
+    pub fn get_background_threads(&self, priority: Priority) -> c_int {
+        unsafe {
+            match priority {
+                Priority::Bottom => {
+                    ffi::rocksdb_env_get_bottom_priority_background_threads(self.0.inner)
+                }
+                Priority::Low => ffi::rocksdb_env_get_low_priority_background_threads(self.0.inner),
+                Priority::High => {
+                    ffi::rocksdb_env_get_high_priority_background_threads(self.0.inner)
+                }
+            }
+        }
+    }
+
     /// Wait for all threads started by StartThread to terminate.
     pub fn join_all_threads(&mut self) {
         unsafe {
@@ -15,9 +15,66 @@
 
 use crate::{ffi, Error};
 use libc::{self, c_char, c_void, size_t};
+use std::any::Any;
 use std::ffi::{CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
+use std::process;
 use std::ptr;
+use std::sync::RwLock;
+
+type CallbackPanicHandler = Box<dyn Fn(&str, &str) + Send + Sync>;
+
+static CALLBACK_PANIC_HANDLER: RwLock<Option<CallbackPanicHandler>> = RwLock::new(None);
+
+/// Sets the function called when a callback invoked by RocksDB (a compaction
+/// filter, comparator, merge operator, slice transform, table properties
+/// collector, ...) panics, with the kind of callback and the panic message.
+///
+/// A panic must not unwind into RocksDB, and the database cannot be left in a
+/// consistent state after a callback failed half-way, so the process is aborted
+/// once the handler returns. The handler is the last chance to log or flush
+/// diagnostics. Without a handler the message is written to stderr.
+pub fn set_callback_panic_handler<F>(handler: F)
+where
+    F: Fn(&str, &str) + Send + Sync + 'static,
+{
+    *CALLBACK_PANIC_HANDLER
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(Box::new(handler));
+}
+
+/// Runs the body of a callback invoked by RocksDB, aborting the process if it
+/// panics instead of unwinding across the FFI boundary.
+pub(crate) fn abort_on_panic<R>(callback: &str, f: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = panic_message(payload.as_ref());
+            let handler = CALLBACK_PANIC_HANDLER
+                .read()
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            match handler.as_ref() {
+                // a panicking handler must not unwind either
+                Some(handler) => {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(callback, message)));
+                }
+                None => eprintln!("rocksdb: {callback} callback panicked: {message}; aborting"),
+            }
+            process::abort()
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
 
 pub(crate) unsafe fn from_cstr(ptr: *const c_char) -> String {
     let cstr = CStr::from_ptr(ptr as *const _);
@@ -91,6 +91,15 @@ fn next_prefix(prefix: &[u8]) -> Option<Vec<u8>> {
     }
 }
 
+/// Returns the key immediately following `key` in lexicographical order,
+/// i.e. `key` followed by a `\0` byte.
+pub(crate) fn next_key(key: &[u8]) -> Vec<u8> {
+    let mut next = Vec::with_capacity(key.len() + 1);
+    next.extend_from_slice(key);
+    next.push(0);
+    next
+}
+
 #[test]
 fn test_prefix_range() {
     fn test(start: &[u8], end: Option<&[u8]>) {
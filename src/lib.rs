@@ -76,6 +76,8 @@
 mod ffi_util;
 
 pub mod backup;
+#[cfg(feature = "bench-util")]
+pub mod bench_util;
 pub mod checkpoint;
 mod column_family;
 pub mod compaction_filter;
@@ -88,15 +90,24 @@ mod db_pinnable_slice;
 mod env;
 mod iter_range;
 pub mod merge_operator;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod options_file;
+pub mod options_util;
 pub mod perf;
 mod prop_name;
 pub mod properties;
+pub mod retry;
 mod slice_transform;
 mod snapshot;
 mod sst_file_writer;
 pub mod statistics;
 pub mod table_properties;
+#[cfg(feature = "test-util")]
+pub mod test_util;
 mod transactions;
+#[cfg(feature = "typed")]
+pub mod typed;
 mod write_batch;
 
 pub use crate::{
@@ -105,36 +116,43 @@ pub use crate::{
         ColumnFamilyRef, DEFAULT_COLUMN_FAMILY_NAME,
     },
     compaction_filter::Decision as CompactionDecision,
+    comparator::{U64Comparator, U64Timestamp},
     db::{
-        DBAccess, DBCommon, DBWithThreadMode, LiveFile, MultiThreaded, SingleThreaded, ThreadMode,
-        DB,
+        BlockCacheEntryStats, CacheEntryRoleStats, CfHandles, CfStats, ColumnFamilyMetaData,
+        CompactedFiles, DBAccess, DBCommon, DBWithThreadMode, FlushedFiles, LevelMetaData,
+        LevelStats, LiveFile, MultiThreaded, SingleThreaded, StatsHistoryEntry, ThreadMode,
+        TickReport, TickTasks, WalFlusherHandle, DB, STATS_HISTORY_LEN,
     },
     db_iterator::{
-        DBIterator, DBIteratorWithThreadMode, DBRawIterator, DBRawIteratorWithThreadMode,
-        DBWALIterator, Direction, IteratorMode,
+        DBIterator, DBIteratorWithThreadMode, DBRawIterator, DBRawIteratorWithThreadMode, DBScan,
+        DBWALIterator, Direction, IteratorMode, ScanOptions,
     },
     db_options::{
         BlockBasedIndexType, BlockBasedOptions, BottommostLevelCompaction, Cache, ChecksumType,
         CompactOptions, CuckooTableOptions, DBCompactionStyle, DBCompressionType, DBPath,
-        DBRecoveryMode, DataBlockIndexType, FifoCompactOptions, FlushOptions,
-        IngestExternalFileOptions, KeyEncodingType, LogLevel, MemtableFactory, Options,
-        PlainTableFactoryOptions, ReadOptions, ReadTier, UniversalCompactOptions,
-        UniversalCompactionStopStyle, WaitForCompactOptions, WriteBufferManager, WriteOptions,
+        DBRecoveryMode, DataBlockIndexType, Durability, FifoCompactOptions, FlushOptions,
+        ImportColumnFamilyOptions, IngestExternalFileOptions, KeyEncodingType, LogLevel,
+        MemtableFactory, Options, OptionsMismatch, PlainTableFactoryOptions, ReadOptions, ReadTier,
+        SizeApproximationOptions, UniversalCompactOptions, UniversalCompactionStopStyle,
+        WaitForCompactOptions, WriteBufferManager, WriteOptions, WriteValidationOptions,
     },
     db_pinnable_slice::DBPinnableSlice,
-    env::Env,
-    ffi_util::CStrLike,
+    env::{Env, Priority},
+    ffi_util::{set_callback_panic_handler, CStrLike},
     iter_range::{IterateBounds, PrefixRange},
     merge_operator::MergeOperands,
     perf::{PerfContext, PerfMetric, PerfStatsLevel},
     slice_transform::SliceTransform,
     snapshot::{Snapshot, SnapshotWithThreadMode},
-    sst_file_writer::SstFileWriter,
+    sst_file_writer::{ExternalSstFileInfo, SstFileWriter},
     transactions::{
         OptimisticTransactionDB, OptimisticTransactionOptions, Transaction, TransactionDB,
         TransactionDBOptions, TransactionOptions,
     },
-    write_batch::{WriteBatch, WriteBatchIterator, WriteBatchWithTransaction},
+    write_batch::{
+        BatchOpCounts, BatchSummary, CfBatch, CfBatchStats, WriteBatch, WriteBatchBuilder,
+        WriteBatchIterator, WriteBatchIteratorCf, WriteBatchWithTransaction,
+    },
 };
 
 use librocksdb_sys as ffi;
@@ -200,6 +218,93 @@ impl Error {
             _ => ErrorKind::Unknown,
         }
     }
+
+    /// Returns `true` if the error was caused by the LOCK file of a DB being held,
+    /// by another process or by a DB still open in this one.
+    pub fn is_lock_held(&self) -> bool {
+        self.kind() == ErrorKind::IOError
+            && (self.message.contains(LOCK_HELD_BY_CURRENT_PROCESS)
+                || self.message.contains("While lock file: "))
+    }
+
+    /// Returns `true` if a read refused to merge more operands than allowed, see
+    /// [`DBCommon::get_with_merge_limit_cf`].
+    pub fn is_merge_operand_threshold_exceeded(&self) -> bool {
+        self.kind() == ErrorKind::Incomplete
+            && self.message.contains(MERGE_OPERAND_THRESHOLD_EXCEEDED)
+    }
+
+    /// Returns `true` if a value could not be read at a past sequence number, see
+    /// [`DBCommon::get_at_seqno_cf_with_snapshot`].
+    pub fn is_history_unavailable(&self) -> bool {
+        self.kind() == ErrorKind::Incomplete && self.message.contains(HISTORY_UNAVAILABLE)
+    }
+}
+
+/// Message of the error returned when a value cannot be read at a past sequence
+/// number.
+const HISTORY_UNAVAILABLE: &str = "History unavailable";
+
+/// Message of the error returned when a read has too many merge operands, as
+/// RocksDB words the status of `ReadOptions::merge_operand_count_threshold`.
+const MERGE_OPERAND_THRESHOLD_EXCEEDED: &str = "Merge operand count threshold exceeded";
+
+/// Message of the error returned when opening a DB already open in this process.
+const LOCK_HELD_BY_CURRENT_PROCESS: &str = "lock hold by current process";
+
+/// An error opening a DB, see [`DBWithThreadMode::try_open`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenError {
+    /// The LOCK file of the DB is held. `holder_pid` is the id of the process
+    /// holding it, which RocksDB only reports when it is this process, and
+    /// `error` is the error RocksDB returned.
+    Locked {
+        holder_pid: Option<u32>,
+        error: Error,
+    },
+    Other(Error),
+}
+
+impl From<Error> for OpenError {
+    fn from(e: Error) -> OpenError {
+        if !e.is_lock_held() {
+            return OpenError::Other(e);
+        }
+        let holder_pid = e
+            .message
+            .contains(LOCK_HELD_BY_CURRENT_PROCESS)
+            .then(std::process::id);
+        OpenError::Locked {
+            holder_pid,
+            error: e,
+        }
+    }
+}
+
+impl From<OpenError> for Error {
+    fn from(e: OpenError) -> Error {
+        match e {
+            OpenError::Locked { error, .. } | OpenError::Other(error) => error,
+        }
+    }
+}
+
+impl error::Error for OpenError {}
+
+impl fmt::Display for OpenError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self {
+            OpenError::Locked {
+                holder_pid: Some(pid),
+                error,
+            } => write!(formatter, "{error} (DB is locked by process {pid})"),
+            OpenError::Locked {
+                holder_pid: None,
+                error,
+            } => error.fmt(formatter),
+            OpenError::Other(e) => e.fmt(formatter),
+        }
+    }
 }
 
 impl AsRef<str> for Error {
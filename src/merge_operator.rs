@@ -56,11 +56,14 @@
 //! ```
 
 use libc::{self, c_char, c_int, c_void, size_t};
+use std::convert::TryInto;
 use std::ffi::CString;
 use std::mem;
 use std::ptr;
 use std::slice;
 
+use crate::ffi_util::abort_on_panic;
+
 pub trait MergeFn:
     Fn(&[u8], Option<&[u8]>, &MergeOperands) -> Option<Vec<u8>> + Send + Sync + 'static
 {
@@ -77,7 +80,9 @@ pub struct MergeOperatorCallback<F: MergeFn, PF: MergeFn> {
 }
 
 pub unsafe extern "C" fn destructor_callback<F: MergeFn, PF: MergeFn>(raw_cb: *mut c_void) {
-    drop(Box::from_raw(raw_cb as *mut MergeOperatorCallback<F, PF>));
+    abort_on_panic("merge operator", || {
+        drop(Box::from_raw(raw_cb as *mut MergeOperatorCallback<F, PF>));
+    });
 }
 
 pub unsafe extern "C" fn delete_callback(
@@ -112,29 +117,31 @@ pub unsafe extern "C" fn full_merge_callback<F: MergeFn, PF: MergeFn>(
     success: *mut u8,
     new_value_length: *mut size_t,
 ) -> *mut c_char {
-    let cb = &mut *(raw_cb as *mut MergeOperatorCallback<F, PF>);
-    let operands = &MergeOperands::new(operands_list, operands_list_len, num_operands);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_len);
-    let oldval = if existing_value.is_null() {
-        None
-    } else {
-        Some(slice::from_raw_parts(
-            existing_value as *const u8,
-            existing_value_len,
-        ))
-    };
-    (cb.full_merge_fn)(key, oldval, operands).map_or_else(
-        || {
-            *new_value_length = 0;
-            *success = 0_u8;
-            ptr::null_mut() as *mut c_char
-        },
-        |result| {
-            *new_value_length = result.len() as size_t;
-            *success = 1_u8;
-            Box::into_raw(result.into_boxed_slice()) as *mut c_char
-        },
-    )
+    abort_on_panic("merge operator", || {
+        let cb = &mut *(raw_cb as *mut MergeOperatorCallback<F, PF>);
+        let operands = &MergeOperands::new(operands_list, operands_list_len, num_operands);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_len);
+        let oldval = if existing_value.is_null() {
+            None
+        } else {
+            Some(slice::from_raw_parts(
+                existing_value as *const u8,
+                existing_value_len,
+            ))
+        };
+        (cb.full_merge_fn)(key, oldval, operands).map_or_else(
+            || {
+                *new_value_length = 0;
+                *success = 0_u8;
+                ptr::null_mut() as *mut c_char
+            },
+            |result| {
+                *new_value_length = result.len() as size_t;
+                *success = 1_u8;
+                Box::into_raw(result.into_boxed_slice()) as *mut c_char
+            },
+        )
+    })
 }
 
 pub unsafe extern "C" fn partial_merge_callback<F: MergeFn, PF: MergeFn>(
@@ -147,21 +154,89 @@ pub unsafe extern "C" fn partial_merge_callback<F: MergeFn, PF: MergeFn>(
     success: *mut u8,
     new_value_length: *mut size_t,
 ) -> *mut c_char {
-    let cb = &mut *(raw_cb as *mut MergeOperatorCallback<F, PF>);
-    let operands = &MergeOperands::new(operands_list, operands_list_len, num_operands);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_len);
-    (cb.partial_merge_fn)(key, None, operands).map_or_else(
-        || {
-            *new_value_length = 0;
-            *success = 0_u8;
-            ptr::null_mut::<c_char>()
-        },
-        |result| {
-            *new_value_length = result.len() as size_t;
-            *success = 1_u8;
-            Box::into_raw(result.into_boxed_slice()) as *mut c_char
-        },
-    )
+    abort_on_panic("merge operator", || {
+        let cb = &mut *(raw_cb as *mut MergeOperatorCallback<F, PF>);
+        let operands = &MergeOperands::new(operands_list, operands_list_len, num_operands);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_len);
+        (cb.partial_merge_fn)(key, None, operands).map_or_else(
+            || {
+                *new_value_length = 0;
+                *success = 0_u8;
+                ptr::null_mut::<c_char>()
+            },
+            |result| {
+                *new_value_length = result.len() as size_t;
+                *success = 1_u8;
+                Box::into_raw(result.into_boxed_slice()) as *mut c_char
+            },
+        )
+    })
+}
+
+/// Length of the timestamp prefixed to the values by [`encode_timestamped`].
+const TIMESTAMP_LEN: usize = 8;
+
+/// Prefixes `value` with `timestamp`, for the values merged with
+/// [`last_write_wins`].
+pub fn encode_timestamped(timestamp: u64, value: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(TIMESTAMP_LEN + value.len());
+    encoded.extend_from_slice(&timestamp.to_be_bytes());
+    encoded.extend_from_slice(value);
+    encoded
+}
+
+/// Splits a value encoded with [`encode_timestamped`] into its timestamp and
+/// the original value, or returns `None` if it is too short to hold one.
+pub fn decode_timestamped(value: &[u8]) -> Option<(u64, &[u8])> {
+    if value.len() < TIMESTAMP_LEN {
+        return None;
+    }
+    let (timestamp, value) = value.split_at(TIMESTAMP_LEN);
+    Some((u64::from_be_bytes(timestamp.try_into().unwrap()), value))
+}
+
+/// A merge function keeping the value with the highest timestamp, for values
+/// and operands encoded with [`encode_timestamped`]. Of values with the same
+/// timestamp, the one merged last wins. Values without a timestamp make the
+/// merge fail.
+///
+/// The C API passes neither the sequence numbers nor the user-defined
+/// timestamps of the operands to merge operators, so the timestamps are
+/// carried in the values. As the result only depends on the timestamps, the
+/// function can be used for both full and partial merges, e.g. with
+/// [`Options::set_merge_operator_associative`](crate::Options::set_merge_operator_associative).
+///
+/// ```
+/// use rocksdb::merge_operator::{decode_timestamped, encode_timestamped, last_write_wins};
+/// use rocksdb::{Options, DB};
+///
+/// let path = "_path_for_last_write_wins";
+/// {
+///     let mut opts = Options::default();
+///     opts.create_if_missing(true);
+///     opts.set_merge_operator_associative("last_write_wins", last_write_wins);
+///     let db = DB::open(&opts, path).unwrap();
+///     db.merge(b"k", encode_timestamped(2, b"newer")).unwrap();
+///     // arrives late, but is older
+///     db.merge(b"k", encode_timestamped(1, b"older")).unwrap();
+///     let value = db.get(b"k").unwrap().unwrap();
+///     assert_eq!(decode_timestamped(&value), Some((2, &b"newer"[..])));
+/// }
+/// let _ = DB::destroy(&Options::default(), path);
+/// ```
+pub fn last_write_wins(
+    _key: &[u8],
+    existing_val: Option<&[u8]>,
+    operands: &MergeOperands,
+) -> Option<Vec<u8>> {
+    let mut newest: Option<(u64, &[u8])> = None;
+    for value in existing_val.into_iter().chain(operands) {
+        let (timestamp, _) = decode_timestamped(value)?;
+        if newest.map_or(true, |(newest, _)| timestamp >= newest) {
+            newest = Some((timestamp, value));
+        }
+    }
+    newest.map(|(_, value)| value.to_vec())
 }
 
 pub struct MergeOperands {
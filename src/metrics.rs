@@ -0,0 +1,230 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export of the statistics and properties of DBs through the [`metrics`]
+//! facade, e.g. to Prometheus. Enabled by the `metrics` feature.
+//!
+//! No thread is started: the values are read and emitted to the installed
+//! recorder each time [`MetricsCollector::collect`] is called.
+//!
+//! * Each [`Ticker`] is a counter named after it, e.g. `rocksdb.bytes.written`
+//!   is `rocksdb_bytes_written`.
+//! * Each [`Histogram`] is a set of gauges named after it with a `quantile`
+//!   label of `0.5`, `0.95`, `0.99` or `1`, and the counters `<name>_sum` and
+//!   `<name>_count`.
+//! * Each property of [`CF_PROPERTY_METRICS`] is a gauge with a `cf` label.
+//!
+//! All of them have a `db` label with the name the DB was registered with.
+//!
+//! ```
+//! use rocksdb::{metrics::MetricsCollector, statistics::Statistics, Options, DB};
+//!
+//! let tempdir = tempfile::Builder::new()
+//!     .prefix("_path_for_metrics")
+//!     .tempdir()
+//!     .expect("Failed to create temporary path for the _path_for_metrics");
+//! let path = tempdir.path();
+//! {
+//!     let statistics = Statistics::new();
+//!     let mut opts = Options::with_statistics(&statistics);
+//!     opts.create_if_missing(true);
+//!     let db = DB::open(&opts, path).unwrap();
+//!
+//!     let mut collector = MetricsCollector::new();
+//!     collector.register("main", &db, Some(statistics));
+//!     // e.g. on each scrape of the metrics endpoint
+//!     collector.collect().unwrap();
+//! }
+//! let _ = DB::destroy(&Options::default(), path);
+//! ```
+//!
+//! [`metrics`]: https://docs.rs/metrics
+
+use crate::{
+    properties::{self, PropName},
+    statistics::{Histogram, Statistics, Ticker},
+    DBWithThreadMode, Error, ThreadMode,
+};
+
+/// The properties of each column family emitted as gauges, with their metric
+/// names.
+pub const CF_PROPERTY_METRICS: &[(&PropName, &str)] = &[
+    (properties::ESTIMATE_NUM_KEYS, "rocksdb_estimate_num_keys"),
+    (
+        properties::ESTIMATE_LIVE_DATA_SIZE,
+        "rocksdb_estimate_live_data_size",
+    ),
+    (
+        properties::TOTAL_SST_FILES_SIZE,
+        "rocksdb_total_sst_files_size",
+    ),
+    (
+        properties::LIVE_SST_FILES_SIZE,
+        "rocksdb_live_sst_files_size",
+    ),
+    (
+        properties::CUR_SIZE_ALL_MEM_TABLES,
+        "rocksdb_cur_size_all_mem_tables",
+    ),
+    (
+        properties::SIZE_ALL_MEM_TABLES,
+        "rocksdb_size_all_mem_tables",
+    ),
+    (
+        properties::NUM_IMMUTABLE_MEM_TABLE,
+        "rocksdb_num_immutable_mem_table",
+    ),
+    (
+        properties::MEM_TABLE_FLUSH_PENDING,
+        "rocksdb_mem_table_flush_pending",
+    ),
+    (properties::COMPACTION_PENDING, "rocksdb_compaction_pending"),
+    (
+        properties::ESTIMATE_PENDING_COMPACTION_BYTES,
+        "rocksdb_estimate_pending_compaction_bytes",
+    ),
+    (
+        properties::NUM_RUNNING_FLUSHES,
+        "rocksdb_num_running_flushes",
+    ),
+    (
+        properties::NUM_RUNNING_COMPACTIONS,
+        "rocksdb_num_running_compactions",
+    ),
+    (properties::BACKGROUND_ERRORS, "rocksdb_background_errors"),
+    (
+        properties::ACTUAL_DELAYED_WRITE_RATE,
+        "rocksdb_actual_delayed_write_rate",
+    ),
+    (properties::IS_WRITE_STOPPED, "rocksdb_is_write_stopped"),
+    (properties::NUM_SNAPSHOTS, "rocksdb_num_snapshots"),
+    (properties::NUM_LIVE_VERSIONS, "rocksdb_num_live_versions"),
+    (
+        properties::ESTIMATE_TABLE_READERS_MEM,
+        "rocksdb_estimate_table_readers_mem",
+    ),
+    (properties::BLOCK_CACHE_USAGE, "rocksdb_block_cache_usage"),
+    (
+        properties::BLOCK_CACHE_PINNED_USAGE,
+        "rocksdb_block_cache_pinned_usage",
+    ),
+];
+
+/// Returns the name of the counter of `ticker`.
+pub fn ticker_metric_name(ticker: Ticker) -> String {
+    metric_name(ticker.name())
+}
+
+/// Returns the name of the gauges of `histogram`.
+pub fn histogram_metric_name(histogram: Histogram) -> String {
+    metric_name(histogram.name())
+}
+
+fn metric_name(name: &str) -> String {
+    name.replace(['.', '-'], "_")
+}
+
+/// A DB whose column families can be listed with their properties.
+/// Implemented for [`DBWithThreadMode`] in both thread modes.
+pub trait CfPropertySource {
+    /// Returns the value of the integer property `name` of each column family,
+    /// by column family name.
+    fn cf_int_property(&self, name: &PropName) -> Result<Vec<(String, Option<u64>)>, Error>;
+}
+
+impl<T: ThreadMode> CfPropertySource for DBWithThreadMode<T> {
+    fn cf_int_property(&self, name: &PropName) -> Result<Vec<(String, Option<u64>)>, Error> {
+        self.cf_handles()
+            .map(|(cf_name, cf)| {
+                let value = self.property_int_value_cf(&cf, name)?;
+                Ok((cf_name, value))
+            })
+            .collect()
+    }
+}
+
+struct Registered<'a> {
+    name: String,
+    db: &'a dyn CfPropertySource,
+    statistics: Option<Statistics>,
+}
+
+/// Emits the metrics of the registered DBs on each call to
+/// [`collect`](Self::collect).
+#[derive(Default)]
+pub struct MetricsCollector<'a> {
+    dbs: Vec<Registered<'a>>,
+}
+
+impl<'a> MetricsCollector<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `db` under `name`, the value of the `db` label of its metrics.
+    /// The tickers and histograms are read from `statistics`, which should be
+    /// the one the DB was opened with, e.g. from [`Options::statistics`].
+    ///
+    /// [`Options::statistics`]: crate::Options::statistics
+    pub fn register(
+        &mut self,
+        name: &str,
+        db: &'a dyn CfPropertySource,
+        statistics: Option<Statistics>,
+    ) {
+        self.dbs.push(Registered {
+            name: name.to_owned(),
+            db,
+            statistics,
+        });
+    }
+
+    /// Reads the statistics and properties of the registered DBs and emits
+    /// them to the recorder installed for the `metrics` crate.
+    pub fn collect(&self) -> Result<(), Error> {
+        for registered in &self.dbs {
+            let db = registered.name.clone();
+            if let Some(statistics) = &registered.statistics {
+                for ticker in Ticker::iter() {
+                    ::metrics::counter!(ticker_metric_name(*ticker), "db" => db.clone())
+                        .absolute(statistics.get_ticker_count(*ticker));
+                }
+                for histogram in Histogram::iter() {
+                    let name = histogram_metric_name(*histogram);
+                    let data = statistics.get_histogram_data(*histogram);
+                    for (quantile, value) in [
+                        ("0.5", data.median()),
+                        ("0.95", data.p95()),
+                        ("0.99", data.p99()),
+                        ("1", data.max()),
+                    ] {
+                        ::metrics::gauge!(name.clone(), "db" => db.clone(), "quantile" => quantile)
+                            .set(value);
+                    }
+                    ::metrics::counter!(format!("{name}_sum"), "db" => db.clone())
+                        .absolute(data.sum());
+                    ::metrics::counter!(format!("{name}_count"), "db" => db.clone())
+                        .absolute(data.count());
+                }
+            }
+            for (property, metric) in CF_PROPERTY_METRICS {
+                for (cf, value) in registered.db.cf_int_property(property)? {
+                    if let Some(value) = value {
+                        ::metrics::gauge!(*metric, "db" => db.clone(), "cf" => cf)
+                            .set(value as f64);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reading of the `OPTIONS-<number>` files RocksDB persists in the DB directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// A section of an OPTIONS file, e.g. `[CFOptions "default"]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Section {
+    /// The section kind, e.g. `CFOptions` or `TableOptions/BlockBasedTable`.
+    pub kind: String,
+    /// The quoted column family name following the kind, if any.
+    pub name: Option<String>,
+    pub values: BTreeMap<String, String>,
+}
+
+/// The parsed content of an OPTIONS file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct OptionsFile {
+    pub sections: Vec<Section>,
+}
+
+impl OptionsFile {
+    /// Loads the most recent OPTIONS file of the DB at `db_path`.
+    pub fn load_latest<P: AsRef<Path>>(db_path: P) -> Result<Self, Error> {
+        Self::load(latest_options_file(db_path)?)
+    }
+
+    /// Loads the OPTIONS file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            Error::new(format!(
+                "Failed to read options file `{}`: {e}",
+                path.display()
+            ))
+        })?;
+        Ok(Self::parse(&content))
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let mut sections: Vec<Section> = vec![];
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let (kind, name) = match header.split_once(' ') {
+                    Some((kind, name)) => (kind, Some(name.trim().trim_matches('"').to_owned())),
+                    None => (header, None),
+                };
+                sections.push(Section {
+                    kind: kind.to_owned(),
+                    name,
+                    values: BTreeMap::new(),
+                });
+            } else if let (Some(section), Some((key, value))) =
+                (sections.last_mut(), line.split_once('='))
+            {
+                section
+                    .values
+                    .insert(key.trim().to_owned(), value.trim().to_owned());
+            }
+        }
+        Self { sections }
+    }
+
+    /// Returns the section of the given kind for column family `cf_name`.
+    pub fn section(&self, kind: &str, cf_name: &str) -> Option<&Section> {
+        self.sections
+            .iter()
+            .find(|s| s.kind == kind && s.name.as_deref() == Some(cf_name))
+    }
+
+    /// Returns the column family options of `cf_name`.
+    pub fn cf_options(&self, cf_name: &str) -> Option<&BTreeMap<String, String>> {
+        self.section("CFOptions", cf_name).map(|s| &s.values)
+    }
+}
+
+/// Returns the path of the OPTIONS file with the highest number in `db_path`.
+pub(crate) fn latest_options_file<P: AsRef<Path>>(db_path: P) -> Result<PathBuf, Error> {
+    let db_path = db_path.as_ref();
+    options_files(db_path)?.pop().ok_or_else(|| {
+        Error::new(format!(
+            "Invalid argument: no OPTIONS file found in `{}`",
+            db_path.display()
+        ))
+    })
+}
+
+/// Returns the paths of the OPTIONS files in `db_path`, oldest first.
+pub(crate) fn options_files<P: AsRef<Path>>(db_path: P) -> Result<Vec<PathBuf>, Error> {
+    let db_path = db_path.as_ref();
+    let entries = fs::read_dir(db_path).map_err(|e| {
+        Error::new(format!(
+            "Failed to list DB directory `{}`: {e}",
+            db_path.display()
+        ))
+    })?;
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let number = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("OPTIONS-")?
+                .parse::<u64>()
+                .ok()?;
+            Some((number, path))
+        })
+        .collect();
+    files.sort_unstable_by_key(|(number, _)| *number);
+    Ok(files.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Extracts the name of a pluggable object (comparator, merge operator, ...)
+/// from its serialized value, which is either the bare name or a struct like
+/// `{id=name;...}`. Returns `None` when no object is configured.
+pub(crate) fn object_id(value: &str) -> Option<String> {
+    let value = value.trim();
+    let id = match value.strip_prefix('{').and_then(|v| v.strip_suffix('}')) {
+        Some(fields) => fields
+            .split(';')
+            .find_map(|field| field.trim().strip_prefix("id="))?
+            .trim(),
+        None => value,
+    };
+    if id.is_empty() || id == "nullptr" {
+        None
+    } else {
+        Some(id.to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{object_id, OptionsFile};
+
+    #[test]
+    fn parse_options_file() {
+        let file = OptionsFile::parse(
+            r#"
+# This is a RocksDB option file.
+[Version]
+  rocksdb_version=9.0.0
+
+[DBOptions]
+  max_open_files=-1
+
+[CFOptions "default"]
+  comparator=leveldb.BytewiseComparator
+  merge_operator=nullptr
+
+[CFOptions "cf 1"]
+  merge_operator={id=sum;}
+"#,
+        );
+        assert_eq!(file.sections.len(), 4);
+        let default = file.cf_options("default").unwrap();
+        assert_eq!(default["comparator"], "leveldb.BytewiseComparator");
+        assert_eq!(object_id(&default["merge_operator"]), None);
+        let cf1 = file.cf_options("cf 1").unwrap();
+        assert_eq!(object_id(&cf1["merge_operator"]).as_deref(), Some("sum"));
+        assert!(file.cf_options("missing").is_none());
+    }
+}
@@ -0,0 +1,150 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Inspection of the `OPTIONS-<number>` files RocksDB writes to the DB
+//! directory each time the DB is opened or its options are changed.
+//!
+//! The C API only loads the latest OPTIONS file into [`Options`], so the files
+//! are read as text here, and the checks of `CheckOptionsCompatibility` are
+//! done on the names of the pluggable objects the options carry.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    options_file::{self, OptionsFile},
+    ColumnFamilyDescriptor, Error, Options, DEFAULT_COLUMN_FAMILY_NAME,
+};
+
+/// A value that differs between two OPTIONS files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptionDiff {
+    /// The kind of the section holding the value, e.g. `DBOptions`,
+    /// `CFOptions` or `TableOptions/BlockBasedTable`.
+    pub section: String,
+    /// The column family of the section, `None` for sections of the DB.
+    pub cf: Option<String>,
+    pub key: String,
+    /// The value in the first file, `None` if it is missing there.
+    pub old: Option<String>,
+    /// The value in the second file, `None` if it is missing there.
+    pub new: Option<String>,
+}
+
+/// Returns the paths of the OPTIONS files of the DB at `db_path`, oldest
+/// first. RocksDB keeps the two most recent ones.
+pub fn options_files<P: AsRef<Path>>(db_path: P) -> Result<Vec<PathBuf>, Error> {
+    options_file::options_files(db_path)
+}
+
+/// Compares two OPTIONS files value by value, returning the values that were
+/// added, removed or changed from `path_a` to `path_b`, ordered by section,
+/// column family and key. The order of the sections and values in the files
+/// does not matter.
+pub fn diff_options_files<P: AsRef<Path>, Q: AsRef<Path>>(
+    path_a: P,
+    path_b: Q,
+) -> Result<Vec<OptionDiff>, Error> {
+    let a = sections(OptionsFile::load(path_a)?);
+    let b = sections(OptionsFile::load(path_b)?);
+    let empty = BTreeMap::new();
+
+    let mut diffs = vec![];
+    for section in a.keys().chain(b.keys()).collect::<BTreeSet<_>>() {
+        let old = a.get(section).unwrap_or(&empty);
+        let new = b.get(section).unwrap_or(&empty);
+        for key in old.keys().chain(new.keys()).collect::<BTreeSet<_>>() {
+            let (old, new) = (old.get(key), new.get(key));
+            if old != new {
+                diffs.push(OptionDiff {
+                    section: section.0.clone(),
+                    cf: section.1.clone(),
+                    key: key.clone(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                });
+            }
+        }
+    }
+    Ok(diffs)
+}
+
+type SectionKey = (String, Option<String>);
+
+fn sections(file: OptionsFile) -> BTreeMap<SectionKey, BTreeMap<String, String>> {
+    file.sections
+        .into_iter()
+        .map(|section| ((section.kind, section.name), section.values))
+        .collect()
+}
+
+/// Checks whether the DB at `db_path` can be opened with `opts` and the
+/// column families `cfs`, without opening it.
+///
+/// Like `CheckOptionsCompatibility`, this fails with an invalid argument error
+/// if the column families do not match the ones in the latest OPTIONS file, or
+/// if one of them uses a different comparator, or a different merge operator
+/// than the one it was persisted with. The plugins are compared like
+/// [`Options::validate_cf_against_existing`] does, but a merge operator added
+/// to a column family persisted without one, or a changed prefix extractor,
+/// pass. The default column family uses `opts` unless it is part of `cfs`.
+pub fn check_options_compatibility<P: AsRef<Path>>(
+    db_path: P,
+    opts: &Options,
+    cfs: &[ColumnFamilyDescriptor],
+) -> Result<(), Error> {
+    let file = OptionsFile::load_latest(db_path)?;
+
+    let mut specified: BTreeMap<&str, &Options> = cfs
+        .iter()
+        .map(|cf| (cf.name.as_str(), &cf.options))
+        .collect();
+    specified.entry(DEFAULT_COLUMN_FAMILY_NAME).or_insert(opts);
+    let persisted: BTreeSet<&str> = file
+        .sections
+        .iter()
+        .filter(|section| section.kind == "CFOptions")
+        .filter_map(|section| section.name.as_deref())
+        .collect();
+    if !specified.keys().copied().eq(persisted.iter().copied()) {
+        return Err(Error::new(format!(
+            "Invalid argument: [RocksDBOptionsParser Error] The persisted column families {:?} \
+             do not match the specified ones {:?}",
+            persisted,
+            specified.keys().collect::<Vec<_>>()
+        )));
+    }
+
+    for (name, cf_opts) in specified {
+        // like RocksDB, a merge operator may be added to a column family persisted
+        // without one, and the prefix extractor may change
+        let mismatch = cf_opts
+            .mismatches(&file, name)?
+            .into_iter()
+            .find(|mismatch| match mismatch.field {
+                "merge_operator" => mismatch.existing.is_some(),
+                "prefix_extractor" => false,
+                _ => true,
+            });
+        if let Some(mismatch) = mismatch {
+            return Err(Error::new(format!(
+                "Invalid argument: [RocksDBOptionsParser]: failed the verification on \
+                 ColumnFamilyOptions::{} of column family `{name}` --- The specified one is \
+                 {} while the persisted one is {}",
+                mismatch.field,
+                mismatch.configured.as_deref().unwrap_or("nullptr"),
+                mismatch.existing.as_deref().unwrap_or("nullptr"),
+            )));
+        }
+    }
+    Ok(())
+}
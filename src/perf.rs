@@ -165,6 +165,18 @@ impl PerfContext {
     pub fn metric(&self, id: PerfMetric) -> u64 {
         unsafe { ffi::rocksdb_perfcontext_metric(self.inner, id as c_int) }
     }
+
+    /// Returns the number of merge operands passed to the merge operator by the
+    /// iterators of this thread.
+    pub fn internal_merge_count(&self) -> u64 {
+        self.metric(PerfMetric::InternalMergeCount)
+    }
+
+    /// Returns the nanoseconds spent in the merge operator by this thread, which
+    /// needs the perf stats level to measure time.
+    pub fn merge_operator_time_nanos(&self) -> u64 {
+        self.metric(PerfMetric::MergeOperatorTimeNanos)
+    }
 }
 
 /// Memory usage stats
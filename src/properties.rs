@@ -221,6 +221,27 @@ pub const BLOCK_CACHE_USAGE: &PropName = property!("block-cache-usage");
 /// entries being pinned.
 pub const BLOCK_CACHE_PINNED_USAGE: &PropName = property!("block-cache-pinned-usage");
 
+/// "rocksdb.block-cache-entry-stats" - returns a multi-line string with the
+/// number and total charge of the entries in the block cache by role, e.g.
+/// data, index or filter blocks.
+pub const BLOCK_CACHE_ENTRY_STATS: &PropName = property!("block-cache-entry-stats");
+
 /// "rocksdb.options-statistics" - returns multi-line string
 /// of options.statistics
 pub const OPTIONS_STATISTICS: &PropName = property!("options-statistics");
+
+/// "rocksdb.num-blob-files" - returns number of blob files in the current
+/// version.
+pub const NUM_BLOB_FILES: &PropName = property!("num-blob-files");
+
+/// "rocksdb.total-blob-file-size" - returns the total size of all blob
+/// files over all versions.
+pub const TOTAL_BLOB_FILE_SIZE: &PropName = property!("total-blob-file-size");
+
+/// "rocksdb.live-blob-file-size" - returns the total size of all blob
+/// files in the current version.
+pub const LIVE_BLOB_FILE_SIZE: &PropName = property!("live-blob-file-size");
+
+/// "rocksdb.live-blob-file-garbage-size" - returns the total amount of
+/// garbage in the blob files in the current version.
+pub const LIVE_BLOB_FILE_GARBAGE_SIZE: &PropName = property!("live-blob-file-garbage-size");
@@ -0,0 +1,246 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retrying operations that fail with transient errors, such as a busy lock
+//! or a write stall, with exponential backoff.
+//!
+//! ```
+//! use rocksdb::{retry::{self, RetryPolicy}, DB};
+//! use std::time::Duration;
+//!
+//! let tempdir = tempfile::Builder::new()
+//!     .prefix("_path_for_retry")
+//!     .tempdir()
+//!     .expect("Failed to create temporary path for the _path_for_retry");
+//! let db = DB::open_default(tempdir.path()).unwrap();
+//! let mut policy = RetryPolicy::new();
+//! policy.set_max_attempts(3);
+//! policy.set_base_delay(Duration::from_millis(1));
+//! retry::with_backoff(&policy, || db.put(b"key", b"value")).unwrap();
+//! ```
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Error, ErrorKind};
+
+/// Returns whether an error of the given kind is transient, so that the
+/// operation that failed may succeed if tried again.
+///
+/// This is the default predicate of [`RetryPolicy`].
+pub fn is_retryable(kind: &ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::Busy | ErrorKind::TryAgain | ErrorKind::TimedOut
+    )
+}
+
+/// How [`with_backoff`] retries a failing operation.
+///
+/// The delay before the n-th retry is `base_delay * multiplier^(n - 1)`,
+/// capped at `max_delay` and shortened by a random fraction of at most
+/// `jitter`.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    multiplier: f64,
+    max_delay: Duration,
+    jitter: f64,
+    retryable: fn(&ErrorKind) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(10),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(1),
+            jitter: 0.5,
+            retryable: is_retryable,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of times the operation is invoked, including the first
+    /// one. A value of 0 is treated as 1.
+    ///
+    /// Default: 5
+    pub fn set_max_attempts(&mut self, attempts: u32) {
+        self.max_attempts = attempts.max(1);
+    }
+
+    /// Sets the delay before the first retry.
+    ///
+    /// Default: 10ms
+    pub fn set_base_delay(&mut self, delay: Duration) {
+        self.base_delay = delay;
+    }
+
+    /// Sets the factor the delay grows by after each retry. Values below 1
+    /// are treated as 1.
+    ///
+    /// Default: 2.0
+    pub fn set_multiplier(&mut self, multiplier: f64) {
+        self.multiplier = multiplier.max(1.0);
+    }
+
+    /// Sets the upper bound of the delay between two attempts.
+    ///
+    /// Default: 1s
+    pub fn set_max_delay(&mut self, delay: Duration) {
+        self.max_delay = delay;
+    }
+
+    /// Sets the largest fraction of each delay that is randomly cut off, so
+    /// that concurrent writers retrying the same conflict spread out. The
+    /// value is clamped to `[0, 1]`, 0 disables jitter.
+    ///
+    /// Default: 0.5
+    pub fn set_jitter(&mut self, jitter: f64) {
+        self.jitter = jitter.clamp(0.0, 1.0);
+    }
+
+    /// Sets the predicate deciding which errors are retried. Other errors are
+    /// returned as they are on the first failure.
+    ///
+    /// Default: [`is_retryable`]
+    pub fn set_retryable(&mut self, retryable: fn(&ErrorKind) -> bool) {
+        self.retryable = retryable;
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = (self.base_delay.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        Duration::from_secs_f64(delay * (1.0 - self.jitter * random()))
+    }
+}
+
+// State of the xorshift generator behind `random`, shared by all threads so
+// their retries spread out. 0 until it is seeded from the clock on first use.
+static RANDOM_STATE: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a pseudo-random number in `[0, 1]`.
+fn random() -> f64 {
+    let mut state = RANDOM_STATE.load(Ordering::Relaxed);
+    loop {
+        let mut x = if state == 0 {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_nanos() as u64)
+                | 1
+        } else {
+            state
+        };
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        match RANDOM_STATE.compare_exchange_weak(state, x, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return f64::from((x >> 32) as u32) / f64::from(u32::MAX),
+            Err(current) => state = current,
+        }
+    }
+}
+
+/// Invokes `op` until it succeeds, fails with an error `policy` does not
+/// retry, or has been invoked the maximum number of attempts, sleeping
+/// between attempts.
+///
+/// When the attempts run out, the last error is returned with the number of
+/// attempts appended to its message. Its [`kind`](Error::kind) is unchanged.
+pub fn with_backoff<T, F>(policy: &RetryPolicy, mut op: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(err) if !(policy.retryable)(&err.kind()) => return Err(err),
+            Err(err) if attempt >= policy.max_attempts => {
+                return Err(Error::new(format!(
+                    "{} (gave up after {attempt} attempts)",
+                    err.into_string()
+                )));
+            }
+            Err(_) => {
+                thread::sleep(policy.delay(attempt));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{with_backoff, RetryPolicy};
+    use crate::{Error, ErrorKind};
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        let mut policy = RetryPolicy::new();
+        policy.set_max_attempts(max_attempts);
+        policy.set_base_delay(Duration::from_millis(1));
+        policy
+    }
+
+    #[test]
+    fn retries_busy_until_success() {
+        let mut calls = 0;
+        let result = with_backoff(&policy(5), || {
+            calls += 1;
+            if calls <= 2 {
+                Err(Error::new("Resource busy: ".to_owned()))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn gives_up_after_max_attempts() {
+        let mut calls = 0;
+        let err = with_backoff(&policy(3), || -> Result<(), Error> {
+            calls += 1;
+            Err(Error::new("Operation timed out: lock".to_owned()))
+        })
+        .unwrap_err();
+        assert_eq!(calls, 3);
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(err.as_ref().ends_with("(gave up after 3 attempts)"));
+    }
+
+    #[test]
+    fn passes_through_non_retryable_errors() {
+        let mut calls = 0;
+        let err = with_backoff(&policy(5), || -> Result<(), Error> {
+            calls += 1;
+            Err(Error::new("Corruption: bad block".to_owned()))
+        })
+        .unwrap_err();
+        assert_eq!(calls, 1);
+        assert_eq!(err.kind(), ErrorKind::Corruption);
+        assert_eq!(err.as_ref(), "Corruption: bad block");
+    }
+}
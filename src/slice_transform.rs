@@ -12,12 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::BTreeMap;
 use std::ffi::CString;
 use std::slice;
+use std::sync::Mutex;
 
 use libc::{c_char, c_uchar, c_void, size_t};
 
-use crate::{ffi, ffi_util::CStrLike};
+use crate::{
+    ffi,
+    ffi_util::{abort_on_panic, CStrLike},
+};
 
 /// A `SliceTransform` is a generic pluggable way of transforming one string
 /// to another. Its primary use-case is in configuring rocksdb
@@ -27,6 +32,12 @@ pub struct SliceTransform {
     pub inner: *mut ffi::rocksdb_slicetransform_t,
 }
 
+// The names RocksDB records in the OPTIONS file for the transforms created by
+// the constructors below, keyed by the address of the transform, until they
+// are handed to `Options::set_prefix_extractor`. The C API offers no way to
+// read them back.
+static NAMES: Mutex<BTreeMap<usize, String>> = Mutex::new(BTreeMap::new());
+
 // NB we intentionally don't implement a Drop that passes
 // through to rocksdb_slicetransform_destroy because
 // this is currently only used (to my knowledge)
@@ -39,8 +50,10 @@ impl SliceTransform {
         transform_fn: TransformFn,
         in_domain_fn: Option<InDomainFn>,
     ) -> SliceTransform {
+        let name = name.into_c_string().unwrap();
+        let name_str = name.to_string_lossy().into_owned();
         let cb = Box::into_raw(Box::new(TransformCallback {
-            name: name.into_c_string().unwrap(),
+            name,
             transform_fn,
             in_domain_fn,
         }));
@@ -57,19 +70,33 @@ impl SliceTransform {
             )
         };
 
-        SliceTransform { inner: st }
+        SliceTransform::with_name(st, name_str)
     }
 
     pub fn create_fixed_prefix(len: size_t) -> SliceTransform {
-        SliceTransform {
-            inner: unsafe { ffi::rocksdb_slicetransform_create_fixed_prefix(len) },
-        }
+        SliceTransform::with_name(
+            unsafe { ffi::rocksdb_slicetransform_create_fixed_prefix(len) },
+            format!("rocksdb.FixedPrefix.{len}"),
+        )
     }
 
     pub fn create_noop() -> SliceTransform {
-        SliceTransform {
-            inner: unsafe { ffi::rocksdb_slicetransform_create_noop() },
-        }
+        SliceTransform::with_name(
+            unsafe { ffi::rocksdb_slicetransform_create_noop() },
+            "rocksdb.Noop".to_owned(),
+        )
+    }
+
+    fn with_name(inner: *mut ffi::rocksdb_slicetransform_t, name: String) -> SliceTransform {
+        NAMES.lock().unwrap().insert(inner as usize, name);
+        SliceTransform { inner }
+    }
+
+    /// Returns the name of a transform made by one of the constructors, `None`
+    /// for one built from a raw `inner`. Forgets the name, as the transform is
+    /// handed over to the options.
+    pub(crate) fn take_name(&self) -> Option<String> {
+        NAMES.lock().unwrap().remove(&(self.inner as usize))
     }
 }
 
@@ -97,11 +124,13 @@ pub unsafe extern "C" fn transform_callback(
     key_len: size_t,
     dst_length: *mut size_t,
 ) -> *mut c_char {
-    let cb = &mut *(raw_cb as *mut TransformCallback);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_len);
-    let prefix = (cb.transform_fn)(key);
-    *dst_length = prefix.len() as size_t;
-    prefix.as_ptr() as *mut c_char
+    abort_on_panic("slice transform", || {
+        let cb = &mut *(raw_cb as *mut TransformCallback);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_len);
+        let prefix = (cb.transform_fn)(key);
+        *dst_length = prefix.len() as size_t;
+        prefix.as_ptr() as *mut c_char
+    })
 }
 
 pub unsafe extern "C" fn in_domain_callback(
@@ -109,7 +138,9 @@ pub unsafe extern "C" fn in_domain_callback(
     raw_key: *const c_char,
     key_len: size_t,
 ) -> c_uchar {
-    let cb = &mut *(raw_cb as *mut TransformCallback);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_len);
-    c_uchar::from(cb.in_domain_fn.map_or(true, |in_domain| in_domain(key)))
+    abort_on_panic("slice transform", || {
+        let cb = &mut *(raw_cb as *mut TransformCallback);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_len);
+        c_uchar::from(cb.in_domain_fn.map_or(true, |in_domain| in_domain(key)))
+    })
 }
@@ -51,6 +51,15 @@ impl<'a, D: DBAccess> SnapshotWithThreadMode<'a, D> {
         }
     }
 
+    /// Returns the sequence number of the last write visible in this snapshot.
+    ///
+    /// Pass `sequence_number() + 1` to
+    /// [`get_updates_since`](crate::DBCommon::get_updates_since) to read the writes
+    /// made after the snapshot was taken.
+    pub fn sequence_number(&self) -> u64 {
+        unsafe { ffi::rocksdb_snapshot_get_sequence_number(self.inner) }
+    }
+
     /// Creates an iterator over the data in this snapshot, using the default read options.
     pub fn iterator(&self, mode: IteratorMode) -> DBIteratorWithThreadMode<'a, D> {
         let readopts = ReadOptions::default();
@@ -87,7 +96,7 @@ impl<'a, D: DBAccess> SnapshotWithThreadMode<'a, D> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<D> {
         readopts.set_snapshot(self);
-        DBIteratorWithThreadMode::new_cf(self.db, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self.db, cf_handle, readopts, mode)
     }
 
     /// Creates a raw iterator over the data in this snapshot, using the default read options.
@@ -120,7 +129,7 @@ impl<'a, D: DBAccess> SnapshotWithThreadMode<'a, D> {
         mut readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<D> {
         readopts.set_snapshot(self);
-        DBRawIteratorWithThreadMode::new_cf(self.db, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self.db, cf_handle, readopts)
     }
 
     /// Returns the bytes associated with a key value with default read options.
@@ -13,10 +13,27 @@
 // limitations under the License.
 //`
 
-use crate::{ffi, ffi_util::to_cpath, Error, Options};
+use crate::{ffi, ffi_util::to_cpath, Env, Error, Options};
 
 use libc::{self, c_char, size_t};
-use std::{ffi::CString, marker::PhantomData, path::Path};
+use std::{
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Describes an SST file written for ingestion, such as the one written by
+/// [`DBCommon::export_range_to_sst_cf`](crate::DBCommon::export_range_to_sst_cf).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalSstFileInfo {
+    pub file_path: PathBuf,
+    /// The first key in the file.
+    pub smallest_key: Vec<u8>,
+    /// The last key in the file.
+    pub largest_key: Vec<u8>,
+    pub num_entries: u64,
+    pub file_size: u64,
+}
 
 /// SstFileWriter is used to create sst files that can be added to database later
 /// All keys in files generated by SstFileWriter will have sequence number = 0.
@@ -24,7 +41,7 @@ pub struct SstFileWriter<'a> {
     pub(crate) inner: *mut ffi::rocksdb_sstfilewriter_t,
     // Options are needed to be alive when calling open(),
     // so let's make sure it doesn't get, dropped for the lifetime of SstFileWriter
-    phantom: PhantomData<&'a Options>,
+    opts: &'a Options,
 }
 
 unsafe impl<'a> Send for SstFileWriter<'a> {}
@@ -58,7 +75,7 @@ impl<'a> SstFileWriter<'a> {
 
         Self {
             inner: writer,
-            phantom: PhantomData,
+            opts,
         }
     }
 
@@ -91,6 +108,33 @@ impl<'a> SstFileWriter<'a> {
         }
     }
 
+    /// Finalize writing to the sst file and return its contents, removing the file.
+    ///
+    /// `path` must be the path given to [`open`](Self::open). The file is read
+    /// back through the local filesystem, so the writer must not use
+    /// [`Env::mem_env`](crate::Env::mem_env): the C API offers no way to read
+    /// files back from it, and the call fails with an invalid argument error
+    /// without finishing the file. The returned bytes can be loaded with
+    /// [`DBCommon::ingest_external_file_from_buffers`].
+    ///
+    /// [`DBCommon::ingest_external_file_from_buffers`]: crate::DBCommon::ingest_external_file_from_buffers
+    pub fn finish_to_buffer<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<u8>, Error> {
+        if self.opts.outlive.env().is_some_and(Env::is_in_memory) {
+            return Err(Error::new(
+                "Invalid argument: SstFileWriter::finish_to_buffer cannot read files \
+                 written through an in-memory Env"
+                    .to_owned(),
+            ));
+        }
+        let path = path.as_ref();
+        let result = self.finish().and_then(|()| {
+            fs::read(path)
+                .map_err(|e| Error::new(format!("Failed to read `{}`: {e}", path.display())))
+        });
+        let _ = fs::remove_file(path);
+        result
+    }
+
     /// returns the current file size
     pub fn file_size(&self) -> u64 {
         let mut file_size: u64 = 0;
@@ -158,6 +202,27 @@ impl<'a> SstFileWriter<'a> {
             Ok(())
         }
     }
+
+    /// Adds a range deletion of the keys in `[begin_key, end_key)` to currently
+    /// opened file. Once the file is ingested, the range tombstone hides the keys
+    /// of the range written to the DB before, e.g. to clear a whole shard.
+    ///
+    /// Unlike the other entries, range deletions may be added in any order.
+    pub fn delete_range<K: AsRef<[u8]>>(&mut self, begin_key: K, end_key: K) -> Result<(), Error> {
+        let begin_key = begin_key.as_ref();
+        let end_key = end_key.as_ref();
+
+        unsafe {
+            ffi_try!(ffi::rocksdb_sstfilewriter_delete_range(
+                self.inner,
+                begin_key.as_ptr() as *const c_char,
+                begin_key.len() as size_t,
+                end_key.as_ptr() as *const c_char,
+                end_key.len() as size_t,
+            ));
+            Ok(())
+        }
+    }
 }
 
 impl<'a> Drop for SstFileWriter<'a> {
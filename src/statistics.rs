@@ -1,4 +1,6 @@
-use crate::ffi;
+use std::sync::{Arc, Mutex};
+
+use crate::{ffi, Options};
 
 #[derive(Debug, Clone)]
 pub struct NameParseError;
@@ -32,7 +34,6 @@ macro_rules! iterable_named_enum {
             )+
         }
 
-        #[automatically_derived]
         impl $typename {
             #[doc = "The corresponding rocksdb string identifier for this variant"]
             pub const fn name(&self) -> &'static str {
@@ -792,6 +793,95 @@ impl Drop for HistogramData {
     }
 }
 
+/// A statistics object that can be shared by several DBs, so that their tickers
+/// and histograms are aggregated into a single view.
+///
+/// RocksDB's C API only creates statistics through the options they are
+/// attached to, so a `Statistics` keeps a set of options owning the object.
+/// Options created with [`Options::with_statistics`] and their clones report
+/// into it.
+///
+/// # Examples
+///
+/// ```
+/// use rocksdb::{statistics::{Statistics, Ticker}, Options};
+///
+/// let statistics = Statistics::new();
+/// let mut opts = Options::with_statistics(&statistics);
+/// opts.create_if_missing(true);
+/// // open any number of DBs with `opts` or clones of it, then
+/// let bytes_written = statistics.get_ticker_count(Ticker::BytesWritten);
+/// ```
+///
+/// [`Options::with_statistics`]: crate::Options::with_statistics
+#[derive(Clone)]
+pub struct Statistics {
+    pub(crate) options: Options,
+    // Ticker counts at the last `reset`, indexed by ticker.
+    baseline: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Statistics {
+    pub fn new() -> Self {
+        let mut options = Options::default();
+        options.enable_statistics();
+        Self::from_options(options)
+    }
+
+    pub(crate) fn from_options(options: Options) -> Self {
+        Self {
+            options,
+            baseline: Arc::new(Mutex::new(vec![0; Ticker::iter().count()])),
+        }
+    }
+
+    /// Zeroes the ticker counts returned by [`get_ticker_count`](Self::get_ticker_count)
+    /// of this object and its clones, e.g. to measure one phase of a benchmark.
+    ///
+    /// RocksDB's C API cannot reset the underlying statistics, so the counts at the
+    /// time of the call are remembered and subtracted from later reads. Histograms
+    /// and the dump written by `Display` are not affected.
+    pub fn reset(&self) {
+        let mut baseline = self.baseline.lock().unwrap();
+        for ticker in Ticker::iter() {
+            baseline[*ticker as usize] = self.options.get_ticker_count(*ticker);
+        }
+    }
+
+    /// StatsLevel can be used to reduce statistics overhead by skipping certain
+    /// types of stats in the stats collection process.
+    pub fn set_statistics_level(&self, level: StatsLevel) {
+        self.options.set_statistics_level(level);
+    }
+
+    /// Returns the value of cumulative counters of all DBs sharing this object
+    /// since it was created or last [`reset`](Self::reset).
+    pub fn get_ticker_count(&self, ticker: Ticker) -> u64 {
+        let baseline = self.baseline.lock().unwrap()[ticker as usize];
+        self.options
+            .get_ticker_count(ticker)
+            .saturating_sub(baseline)
+    }
+
+    /// Gets Histogram data collected from all DBs sharing this object.
+    pub fn get_histogram_data(&self, histogram: Histogram) -> HistogramData {
+        self.options.get_histogram_data(histogram)
+    }
+}
+
+impl core::fmt::Display for Statistics {
+    /// Writes a human readable dump of all the statistics.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&self.options.get_statistics().unwrap_or_default())
+    }
+}
+
+impl Default for Statistics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[test]
 fn sanity_checks() {
     let want = "rocksdb.async.read.bytes";
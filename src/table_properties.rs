@@ -10,12 +10,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::ffi;
+use crate::{ffi, ffi_util::abort_on_panic};
 use std::{
     collections::BTreeMap,
+    convert::TryInto,
     ffi::{c_char, c_int, c_void, CStr},
     ptr::addr_of_mut,
     slice,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 pub trait TablePropertiesCollectorFactory {
@@ -30,15 +33,19 @@ unsafe extern "C" fn factory_destructor_callback<F>(raw_self: *mut c_void)
 where
     F: TablePropertiesCollectorFactory,
 {
-    drop(Box::from_raw(raw_self as *mut F));
+    abort_on_panic("table properties collector factory", || {
+        drop(Box::from_raw(raw_self as *mut Mutex<F>));
+    });
 }
 
 unsafe extern "C" fn factory_name_callback<F>(raw_self: *mut c_void) -> *const c_char
 where
     F: TablePropertiesCollectorFactory,
 {
-    let self_ = &*(raw_self.cast_const() as *const F);
-    self_.name().as_ptr()
+    abort_on_panic("table properties collector factory", || {
+        let self_ = &*(raw_self.cast_const() as *const Mutex<F>);
+        self_.lock().unwrap().name().as_ptr()
+    })
 }
 
 unsafe extern "C" fn create_table_properties_collector_callback<F>(
@@ -48,19 +55,23 @@ unsafe extern "C" fn create_table_properties_collector_callback<F>(
 where
     F: TablePropertiesCollectorFactory,
 {
-    let self_ = &mut *(raw_self as *mut F);
-    let context = TablePropertiesCollectorFactoryContext::from_raw(context);
-    let collector = Box::new(self_.create(context));
-
-    ffi::rocksdb_table_properties_collector_create(
-        Box::into_raw(collector).cast::<c_void>(),
-        Some(collector_destructor_callback::<F::Collector>),
-        Some(collector_name_callback::<F::Collector>),
-        Some(collector_add_user_key_callback::<F::Collector>),
-        Some(collector_block_add_callback::<F::Collector>),
-        Some(collector_finish_properties_callback::<F::Collector>),
-        Some(collector_get_readable_properties_callback::<F::Collector>),
-    )
+    abort_on_panic("table properties collector factory", || {
+        // concurrent flushes and compactions, also of DBs opened with cloned
+        // options, share the factory
+        let self_ = &*(raw_self.cast_const() as *const Mutex<F>);
+        let context = TablePropertiesCollectorFactoryContext::from_raw(context);
+        let collector = Box::new(self_.lock().unwrap().create(context));
+
+        ffi::rocksdb_table_properties_collector_create(
+            Box::into_raw(collector).cast::<c_void>(),
+            Some(collector_destructor_callback::<F::Collector>),
+            Some(collector_name_callback::<F::Collector>),
+            Some(collector_add_user_key_callback::<F::Collector>),
+            Some(collector_block_add_callback::<F::Collector>),
+            Some(collector_finish_properties_callback::<F::Collector>),
+            Some(collector_get_readable_properties_callback::<F::Collector>),
+        )
+    })
 }
 
 pub(crate) unsafe fn create_table_properties_collector_factory<F>(
@@ -69,7 +80,7 @@ pub(crate) unsafe fn create_table_properties_collector_factory<F>(
 where
     F: TablePropertiesCollectorFactory,
 {
-    let factory = Box::new(factory);
+    let factory = Box::new(Mutex::new(factory));
     ffi::rocksdb_table_properties_collector_factory_create(
         Box::into_raw(factory).cast::<c_void>(),
         Some(factory_destructor_callback::<F>),
@@ -93,7 +104,7 @@ impl TablePropertiesCollectorFactoryContext {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum EntryType {
     Put,
     Delete,
@@ -160,19 +171,94 @@ pub trait TablePropertiesCollector {
     }
 }
 
+/// Wraps a [`TablePropertiesCollector`], forwarding to it only the entries of
+/// the chosen [`EntryType`]s. Other calls are forwarded unchanged.
+///
+/// ```
+/// use rocksdb::table_properties::{EntryType, FilteredCollector, RangeTombstoneCollector};
+///
+/// // the inner collector only sees range deletions
+/// let collector =
+///     FilteredCollector::new(RangeTombstoneCollector::default(), [EntryType::RangeDeletion]);
+/// ```
+#[derive(Debug)]
+pub struct FilteredCollector<C> {
+    inner: C,
+    entry_types: Vec<EntryType>,
+}
+
+impl<C: TablePropertiesCollector> FilteredCollector<C> {
+    pub fn new(inner: C, entry_types: impl IntoIterator<Item = EntryType>) -> Self {
+        Self {
+            inner,
+            entry_types: entry_types.into_iter().collect(),
+        }
+    }
+
+    /// Returns the wrapped collector.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+impl<C: TablePropertiesCollector> TablePropertiesCollector for FilteredCollector<C> {
+    fn name(&self) -> &CStr {
+        self.inner.name()
+    }
+
+    fn add_user_key(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        entry_type: EntryType,
+        seq: u64,
+        file_size: u64,
+    ) {
+        if self.entry_types.contains(&entry_type) {
+            self.inner
+                .add_user_key(key, value, entry_type, seq, file_size);
+        }
+    }
+
+    fn block_add(
+        &mut self,
+        block_uncomp_bytes: u64,
+        block_compressed_bytes_fast: u64,
+        block_compressed_bytes_slow: u64,
+    ) {
+        self.inner.block_add(
+            block_uncomp_bytes,
+            block_compressed_bytes_fast,
+            block_compressed_bytes_slow,
+        );
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        self.inner.finish_properties()
+    }
+
+    fn get_readable_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        self.inner.get_readable_properties()
+    }
+}
+
 unsafe extern "C" fn collector_destructor_callback<C>(raw_self: *mut c_void)
 where
     C: TablePropertiesCollector,
 {
-    drop(Box::from_raw(raw_self as *mut C));
+    abort_on_panic("table properties collector", || {
+        drop(Box::from_raw(raw_self as *mut C));
+    });
 }
 
 unsafe extern "C" fn collector_name_callback<C>(raw_self: *mut c_void) -> *const c_char
 where
     C: TablePropertiesCollector,
 {
-    let self_ = &*(raw_self.cast_const() as *const C);
-    self_.name().as_ptr()
+    abort_on_panic("table properties collector", || {
+        let self_ = &*(raw_self.cast_const() as *const C);
+        self_.name().as_ptr()
+    })
 }
 
 unsafe extern "C" fn collector_add_user_key_callback<C>(
@@ -187,11 +273,13 @@ unsafe extern "C" fn collector_add_user_key_callback<C>(
 ) where
     C: TablePropertiesCollector,
 {
-    let self_ = &mut *(raw_self as *mut C);
-    let key = slice::from_raw_parts(raw_key as *const u8, key_len);
-    let value = slice::from_raw_parts(raw_value as *const u8, value_len);
-    let entry_type = EntryType::from_raw(entry_type).unwrap();
-    self_.add_user_key(key, value, entry_type, seq, file_size);
+    abort_on_panic("table properties collector", || {
+        let self_ = &mut *(raw_self as *mut C);
+        let key = slice::from_raw_parts(raw_key as *const u8, key_len);
+        let value = slice::from_raw_parts(raw_value as *const u8, value_len);
+        let entry_type = EntryType::from_raw(entry_type).unwrap();
+        self_.add_user_key(key, value, entry_type, seq, file_size);
+    });
 }
 
 unsafe extern "C" fn collector_block_add_callback<C>(
@@ -202,12 +290,14 @@ unsafe extern "C" fn collector_block_add_callback<C>(
 ) where
     C: TablePropertiesCollector,
 {
-    let self_ = &mut *(raw_self as *mut C);
-    self_.block_add(
-        block_uncomp_bytes,
-        block_compressed_bytes_fast,
-        block_compressed_bytes_slow,
-    );
+    abort_on_panic("table properties collector", || {
+        let self_ = &mut *(raw_self as *mut C);
+        self_.block_add(
+            block_uncomp_bytes,
+            block_compressed_bytes_fast,
+            block_compressed_bytes_slow,
+        );
+    });
 }
 
 unsafe extern "C" fn collector_finish_properties_callback<C>(
@@ -217,18 +307,20 @@ unsafe extern "C" fn collector_finish_properties_callback<C>(
 ) where
     C: TablePropertiesCollector,
 {
-    if let Some(callback) = add_user_properties_callback {
-        let self_ = &mut *(raw_self as *mut C);
-        for (key, value) in &self_.finish_properties() {
-            callback(
-                properties,
-                key.as_ptr() as _,
-                key.len(),
-                value.as_ptr() as _,
-                value.len(),
-            );
+    abort_on_panic("table properties collector", || {
+        if let Some(callback) = add_user_properties_callback {
+            let self_ = &mut *(raw_self as *mut C);
+            for (key, value) in &self_.finish_properties() {
+                callback(
+                    properties,
+                    key.as_ptr() as _,
+                    key.len(),
+                    value.as_ptr() as _,
+                    value.len(),
+                );
+            }
         }
-    }
+    });
 }
 
 unsafe extern "C" fn collector_get_readable_properties_callback<C>(
@@ -238,18 +330,20 @@ unsafe extern "C" fn collector_get_readable_properties_callback<C>(
 ) where
     C: TablePropertiesCollector,
 {
-    if let Some(callback) = add_user_properties_callback {
-        let self_ = &mut *(raw_self as *mut C);
-        for (key, value) in &self_.get_readable_properties() {
-            callback(
-                properties,
-                key.as_ptr() as _,
-                key.len(),
-                value.as_ptr() as _,
-                value.len(),
-            );
+    abort_on_panic("table properties collector", || {
+        if let Some(callback) = add_user_properties_callback {
+            let self_ = &mut *(raw_self as *mut C);
+            for (key, value) in &self_.get_readable_properties() {
+                callback(
+                    properties,
+                    key.as_ptr() as _,
+                    key.len(),
+                    value.as_ptr() as _,
+                    value.len(),
+                );
+            }
         }
-    }
+    });
 }
 
 pub struct TablePropertiesCollection {
@@ -311,6 +405,30 @@ impl TableProperties {
             map
         }
     }
+
+    /// Returns the time the flush or compaction writing the file started, as
+    /// recorded by [`WriteTimesCollector`]. `None` for files written without it.
+    ///
+    /// This is not RocksDB's `creation_time` table property, which the C API
+    /// does not expose: a compaction output records the start of the
+    /// compaction, not the age of the oldest data of its inputs.
+    pub fn recorded_write_start_time(&self) -> Option<SystemTime> {
+        self.write_times()
+            .and_then(|times| from_unix_secs(times.start))
+    }
+
+    /// Returns the time the file was finished, as recorded by
+    /// [`WriteTimesCollector`]. `None` for files written without it.
+    pub fn recorded_write_finish_time(&self) -> Option<SystemTime> {
+        self.write_times()
+            .and_then(|times| from_unix_secs(times.finish))
+    }
+
+    fn write_times(&self) -> Option<WriteTimes> {
+        self.user_collected_properties()
+            .get(WRITE_TIMES_PROPERTY)
+            .and_then(|data| WriteTimes::decode(data))
+    }
 }
 
 impl Drop for TableProperties {
@@ -332,3 +450,591 @@ unsafe extern "C" fn table_property_reader(
 
     map.insert(key.to_vec().into(), value.to_vec().into());
 }
+
+/// The user-collected property key under which [`RangeTombstoneCollector`]
+/// stores the range tombstones of an SST file.
+pub const RANGE_TOMBSTONES_PROPERTY: &[u8] = b"rust-rocksdb.range-tombstones";
+
+/// A range deletion `[start, end)` written at sequence number `seq`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RangeTombstone {
+    pub start: Box<[u8]>,
+    pub end: Box<[u8]>,
+    pub seq: u64,
+}
+
+/// A factory of [`RangeTombstoneCollector`]s.
+///
+/// Register it with [`Options::add_table_properties_collector_factory`] to make
+/// the range tombstones of a column family observable through
+/// [`DBCommon::range_tombstones_cf`].
+///
+/// [`Options::add_table_properties_collector_factory`]: crate::Options::add_table_properties_collector_factory
+/// [`DBCommon::range_tombstones_cf`]: crate::DBCommon::range_tombstones_cf
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RangeTombstoneCollectorFactory;
+
+impl TablePropertiesCollectorFactory for RangeTombstoneCollectorFactory {
+    type Collector = RangeTombstoneCollector;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        RangeTombstoneCollector::default()
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"RangeTombstoneCollectorFactory\0").unwrap()
+    }
+}
+
+/// Records every range deletion of an SST file into the
+/// [`RANGE_TOMBSTONES_PROPERTY`] user-collected property.
+///
+/// Each tombstone is encoded as the little-endian `u32` length of the start key,
+/// the start key, the `u32` length of the end key, the end key and the `u64`
+/// sequence number.
+#[derive(Debug, Default)]
+pub struct RangeTombstoneCollector {
+    encoded: Vec<u8>,
+}
+
+impl TablePropertiesCollector for RangeTombstoneCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"RangeTombstoneCollector\0").unwrap()
+    }
+
+    fn add_user_key(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        entry_type: EntryType,
+        seq: u64,
+        _file_size: u64,
+    ) {
+        if entry_type == EntryType::RangeDeletion {
+            self.encoded
+                .extend_from_slice(&(key.len() as u32).to_le_bytes());
+            self.encoded.extend_from_slice(key);
+            self.encoded
+                .extend_from_slice(&(value.len() as u32).to_le_bytes());
+            self.encoded.extend_from_slice(value);
+            self.encoded.extend_from_slice(&seq.to_le_bytes());
+        }
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            RANGE_TOMBSTONES_PROPERTY.into(),
+            std::mem::take(&mut self.encoded).into_boxed_slice(),
+        );
+        map
+    }
+}
+
+/// Decodes the value of a [`RANGE_TOMBSTONES_PROPERTY`] property. Decoding stops
+/// at the first truncated entry.
+pub(crate) fn decode_range_tombstones(mut data: &[u8]) -> Vec<RangeTombstone> {
+    let mut tombstones = vec![];
+    while !data.is_empty() {
+        let next = (|| {
+            let start = take_slice(&mut data)?;
+            let end = take_slice(&mut data)?;
+            let seq = u64::from_le_bytes(take(&mut data, 8)?.try_into().ok()?);
+            Some(RangeTombstone {
+                start: start.into(),
+                end: end.into(),
+                seq,
+            })
+        })();
+        match next {
+            Some(tombstone) => tombstones.push(tombstone),
+            None => break,
+        }
+    }
+    tombstones
+}
+
+/// The user-collected property key under which [`SeqnoRangeCollector`] stores
+/// the key range and largest sequence number of an SST file.
+pub const SEQNO_RANGE_PROPERTY: &[u8] = b"rust-rocksdb.seqno-range";
+
+/// The inclusive range `[smallest_key, largest_key]` of keys written up to
+/// sequence number `largest_seqno`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyRange {
+    pub smallest_key: Box<[u8]>,
+    pub largest_key: Box<[u8]>,
+    pub largest_seqno: u64,
+}
+
+/// A factory of [`SeqnoRangeCollector`]s.
+///
+/// Register it with [`Options::add_table_properties_collector_factory`] to make
+/// the column family usable with [`DBCommon::changed_key_ranges_since_cf`].
+///
+/// [`Options::add_table_properties_collector_factory`]: crate::Options::add_table_properties_collector_factory
+/// [`DBCommon::changed_key_ranges_since_cf`]: crate::DBCommon::changed_key_ranges_since_cf
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SeqnoRangeCollectorFactory;
+
+impl TablePropertiesCollectorFactory for SeqnoRangeCollectorFactory {
+    type Collector = SeqnoRangeCollector;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        SeqnoRangeCollector::default()
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"SeqnoRangeCollectorFactory\0").unwrap()
+    }
+}
+
+/// Records the bytewise smallest and largest key and the largest sequence
+/// number of an SST file into the [`SEQNO_RANGE_PROPERTY`] user-collected
+/// property. The end key of a range deletion counts as a key of the file.
+///
+/// The range is encoded as the little-endian `u64` largest sequence number,
+/// followed by the `u32` length of the smallest key, the smallest key, the `u32`
+/// length of the largest key and the largest key.
+#[derive(Debug, Default)]
+pub struct SeqnoRangeCollector {
+    range: Option<KeyRange>,
+}
+
+impl SeqnoRangeCollector {
+    fn add_key(&mut self, key: &[u8], seq: u64) {
+        match &mut self.range {
+            Some(range) => {
+                if key < &*range.smallest_key {
+                    range.smallest_key = key.into();
+                }
+                if key > &*range.largest_key {
+                    range.largest_key = key.into();
+                }
+                range.largest_seqno = range.largest_seqno.max(seq);
+            }
+            None => {
+                self.range = Some(KeyRange {
+                    smallest_key: key.into(),
+                    largest_key: key.into(),
+                    largest_seqno: seq,
+                });
+            }
+        }
+    }
+}
+
+impl TablePropertiesCollector for SeqnoRangeCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"SeqnoRangeCollector\0").unwrap()
+    }
+
+    fn add_user_key(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        entry_type: EntryType,
+        seq: u64,
+        _file_size: u64,
+    ) {
+        self.add_key(key, seq);
+        if entry_type == EntryType::RangeDeletion {
+            self.add_key(value, seq);
+        }
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let mut map = BTreeMap::new();
+        if let Some(range) = self.range.take() {
+            let mut encoded = range.largest_seqno.to_le_bytes().to_vec();
+            encoded.extend_from_slice(&(range.smallest_key.len() as u32).to_le_bytes());
+            encoded.extend_from_slice(&range.smallest_key);
+            encoded.extend_from_slice(&(range.largest_key.len() as u32).to_le_bytes());
+            encoded.extend_from_slice(&range.largest_key);
+            map.insert(SEQNO_RANGE_PROPERTY.into(), encoded.into_boxed_slice());
+        }
+        map
+    }
+}
+
+/// Decodes the value of a [`SEQNO_RANGE_PROPERTY`] property.
+pub(crate) fn decode_seqno_range(mut data: &[u8]) -> Option<KeyRange> {
+    let largest_seqno = u64::from_le_bytes(take(&mut data, 8)?.try_into().ok()?);
+    let smallest_key = take_slice(&mut data)?;
+    let largest_key = take_slice(&mut data)?;
+    Some(KeyRange {
+        smallest_key: smallest_key.into(),
+        largest_key: largest_key.into(),
+        largest_seqno,
+    })
+}
+
+fn take<'a>(data: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if data.len() < n {
+        return None;
+    }
+    let (head, tail) = data.split_at(n);
+    *data = tail;
+    Some(head)
+}
+
+fn take_slice<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let len = u32::from_le_bytes(take(data, 4)?.try_into().ok()?);
+    take(data, len as usize)
+}
+
+/// The user-collected property key under which [`WriteTimesCollector`]
+/// stores the times an SST file was written.
+pub const WRITE_TIMES_PROPERTY: &[u8] = b"rust-rocksdb.write-times";
+
+/// A factory of [`WriteTimesCollector`]s.
+///
+/// Register it with [`Options::add_table_properties_collector_factory`] to make
+/// the times returned by [`TableProperties::recorded_write_start_time`] and
+/// [`TableProperties::recorded_write_finish_time`] known, e.g. for
+/// [`DBCommon::files_written_before_cf`].
+///
+/// [`Options::add_table_properties_collector_factory`]: crate::Options::add_table_properties_collector_factory
+/// [`DBCommon::files_written_before_cf`]: crate::DBCommon::files_written_before_cf
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WriteTimesCollectorFactory;
+
+impl TablePropertiesCollectorFactory for WriteTimesCollectorFactory {
+    type Collector = WriteTimesCollector;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        WriteTimesCollector {
+            start: unix_secs_now(),
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"WriteTimesCollectorFactory\0").unwrap()
+    }
+}
+
+/// Records the wall-clock times, in seconds since the Unix epoch, at which an
+/// SST file was started and finished into the [`WRITE_TIMES_PROPERTY`]
+/// user-collected property.
+///
+/// These are the times of the flush or compaction writing the file, so a file
+/// rewritten by a compaction gets new ones. They are unrelated to the creation
+/// times RocksDB keeps in its own table properties, e.g. for
+/// [`Options::set_periodic_compaction_seconds`], which the C API does not
+/// expose.
+///
+/// The times are encoded as two little-endian `u64`s: the start and the finish
+/// time.
+///
+/// [`Options::set_periodic_compaction_seconds`]: crate::Options::set_periodic_compaction_seconds
+#[derive(Debug, Default)]
+pub struct WriteTimesCollector {
+    start: u64,
+}
+
+impl TablePropertiesCollector for WriteTimesCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"WriteTimesCollector\0").unwrap()
+    }
+
+    fn add_user_key(
+        &mut self,
+        _key: &[u8],
+        _value: &[u8],
+        _entry_type: EntryType,
+        _seq: u64,
+        _file_size: u64,
+    ) {
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let times = WriteTimes {
+            start: self.start,
+            finish: unix_secs_now(),
+        };
+        let mut map = BTreeMap::new();
+        map.insert(
+            WRITE_TIMES_PROPERTY.into(),
+            times.encode().into_boxed_slice(),
+        );
+        map
+    }
+}
+
+struct WriteTimes {
+    start: u64,
+    finish: u64,
+}
+
+impl WriteTimes {
+    fn encode(&self) -> Vec<u8> {
+        let mut encoded = self.start.to_le_bytes().to_vec();
+        encoded.extend_from_slice(&self.finish.to_le_bytes());
+        encoded
+    }
+
+    fn decode(mut data: &[u8]) -> Option<Self> {
+        let mut next = || take(&mut data, 8)?.try_into().ok().map(u64::from_le_bytes);
+        Some(WriteTimes {
+            start: next()?,
+            finish: next()?,
+        })
+    }
+}
+
+fn unix_secs_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// 0 stands for an unknown time, which is not taken as the epoch.
+fn from_unix_secs(secs: u64) -> Option<SystemTime> {
+    (secs != 0).then(|| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// The user-collected property key under which [`HllKeyCountCollector`] stores
+/// the HyperLogLog sketch of the keys of an SST file.
+pub const HLL_KEY_COUNT_PROPERTY: &[u8] = b"rust-rocksdb.hll-key-count";
+
+/// A HyperLogLog sketch, estimating the number of distinct keys inserted into it
+/// with a relative standard error of about `1.04 / sqrt(2^precision)`, e.g. 1.6%
+/// at the default precision of 12, in `2^precision` bytes.
+///
+/// Keys are hashed with a hash that does not depend on the process or platform,
+/// so the sketches persisted by [`HllKeyCountCollector`] can be merged later on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub const MIN_PRECISION: u8 = 4;
+    pub const MAX_PRECISION: u8 = 18;
+    pub const DEFAULT_PRECISION: u8 = 12;
+
+    /// Creates an empty sketch of `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not within `MIN_PRECISION..=MAX_PRECISION`.
+    pub fn new(precision: u8) -> Self {
+        assert!(
+            (Self::MIN_PRECISION..=Self::MAX_PRECISION).contains(&precision),
+            "HyperLogLog precision {precision} is not within {}..={}",
+            Self::MIN_PRECISION,
+            Self::MAX_PRECISION
+        );
+        HyperLogLog {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn insert(&mut self, key: &[u8]) {
+        let hash = stable_hash(key);
+        let index = (hash >> (64 - self.precision)) as usize;
+        // the position of the first set bit after the index bits, bounded by a
+        // sentinel bit for the hashes having none
+        let rank = ((hash << self.precision) | (1 << (self.precision - 1))).leading_zeros() + 1;
+        let register = &mut self.registers[index];
+        *register = (*register).max(rank as u8);
+    }
+
+    /// Adds the keys of `other` to the sketch. Sketches of different precisions
+    /// are merged at the lower one, which the sketch is reduced to if needed, as
+    /// if all the keys had been inserted at that precision.
+    pub fn merge(&mut self, other: &HyperLogLog) {
+        if other.precision < self.precision {
+            *self = self.reduce(other.precision);
+        }
+        let reduced;
+        let other = if other.precision > self.precision {
+            reduced = other.reduce(self.precision);
+            &reduced
+        } else {
+            other
+        };
+        for (register, &value) in self.registers.iter_mut().zip(&other.registers) {
+            *register = (*register).max(value);
+        }
+    }
+
+    /// Returns the estimated number of distinct keys inserted.
+    pub fn estimate(&self) -> f64 {
+        let m = f64::from(1_u32 << self.precision);
+        let alpha = match self.precision {
+            4 => 0.673,
+            5 => 0.697,
+            6 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|&value| 2_f64.powi(-i32::from(value)))
+            .sum();
+        let raw = alpha * m * m / sum;
+        #[allow(clippy::naive_bytecount)]
+        let zeros = self.registers.iter().filter(|&&value| value == 0).count() as u32;
+        if raw <= 2.5 * m && zeros > 0 {
+            // linear counting is more accurate for the small cardinalities
+            m * (m / f64::from(zeros)).ln()
+        } else {
+            raw
+        }
+    }
+
+    /// Returns the sketch it would be if its keys had been inserted at the lower
+    /// `precision`: the index bits dropped become the leading bits of the rest.
+    fn reduce(&self, precision: u8) -> HyperLogLog {
+        let shift = u32::from(self.precision - precision);
+        let mut reduced = HyperLogLog::new(precision);
+        for (index, &value) in self.registers.iter().enumerate() {
+            if value == 0 {
+                continue;
+            }
+            let dropped = index & ((1 << shift) - 1);
+            let rank = if dropped == 0 {
+                value + shift as u8
+            } else {
+                (dropped.leading_zeros() - (usize::BITS - shift)) as u8 + 1
+            };
+            let register = &mut reduced.registers[index >> shift];
+            *register = (*register).max(rank);
+        }
+        reduced
+    }
+
+    /// Encodes the sketch as its precision followed by its registers, a byte each.
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut encoded = Vec::with_capacity(1 + self.registers.len());
+        encoded.push(self.precision);
+        encoded.extend_from_slice(&self.registers);
+        encoded
+    }
+
+    /// Decodes the value of a [`HLL_KEY_COUNT_PROPERTY`] property.
+    pub(crate) fn decode(data: &[u8]) -> Option<Self> {
+        let (&precision, registers) = data.split_first()?;
+        if !(Self::MIN_PRECISION..=Self::MAX_PRECISION).contains(&precision)
+            || registers.len() != 1 << precision
+            // the rank is at most one more than the number of bits after the index
+            || registers.iter().any(|&value| value > 65 - precision)
+        {
+            return None;
+        }
+        Some(HyperLogLog {
+            precision,
+            registers: registers.to_vec(),
+        })
+    }
+}
+
+/// FNV-1a, finalized with the mixer of MurmurHash3 to spread its bits.
+fn stable_hash(key: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for &byte in key {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+/// A factory of [`HllKeyCountCollector`]s of the given precision.
+///
+/// Register it with [`Options::add_table_properties_collector_factory`] to make
+/// the number of distinct keys of a column family estimable with
+/// [`DBCommon::estimate_unique_keys_cf`]. The precision can be changed when
+/// reopening the DB: the sketches of the files written before are merged at the
+/// lowest precision.
+///
+/// [`Options::add_table_properties_collector_factory`]: crate::Options::add_table_properties_collector_factory
+/// [`DBCommon::estimate_unique_keys_cf`]: crate::DBCommon::estimate_unique_keys_cf
+#[derive(Debug, Clone, Copy)]
+pub struct HllKeyCountCollectorFactory {
+    precision: u8,
+}
+
+impl HllKeyCountCollectorFactory {
+    /// Creates a factory of collectors with sketches of `2^precision` registers,
+    /// see [`HyperLogLog`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not within the bounds of [`HyperLogLog::new`].
+    pub fn new(precision: u8) -> Self {
+        HyperLogLog::new(precision);
+        HllKeyCountCollectorFactory { precision }
+    }
+}
+
+impl Default for HllKeyCountCollectorFactory {
+    fn default() -> Self {
+        HllKeyCountCollectorFactory {
+            precision: HyperLogLog::DEFAULT_PRECISION,
+        }
+    }
+}
+
+impl TablePropertiesCollectorFactory for HllKeyCountCollectorFactory {
+    type Collector = HllKeyCountCollector;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        HllKeyCountCollector {
+            sketch: HyperLogLog::new(self.precision),
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"HllKeyCountCollectorFactory\0").unwrap()
+    }
+}
+
+/// Records a [`HyperLogLog`] sketch of the keys written with a value in an SST
+/// file into the [`HLL_KEY_COUNT_PROPERTY`] user-collected property. The keys
+/// of deletions and range deletions are not inserted.
+#[derive(Debug)]
+pub struct HllKeyCountCollector {
+    sketch: HyperLogLog,
+}
+
+impl TablePropertiesCollector for HllKeyCountCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"HllKeyCountCollector\0").unwrap()
+    }
+
+    fn add_user_key(
+        &mut self,
+        key: &[u8],
+        _value: &[u8],
+        entry_type: EntryType,
+        _seq: u64,
+        _file_size: u64,
+    ) {
+        if matches!(
+            entry_type,
+            EntryType::Put | EntryType::Merge | EntryType::WideColumnEntity | EntryType::TimedPut
+        ) {
+            self.sketch.insert(key);
+        }
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            HLL_KEY_COUNT_PROPERTY.into(),
+            self.sketch.encode().into_boxed_slice(),
+        );
+        map
+    }
+}
@@ -0,0 +1,134 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for the tests of applications using RocksDB. Enabled by the
+//! `test-util` feature.
+//!
+//! ```
+//! use rocksdb::test_util::{DeterministicDb, TempDb};
+//!
+//! let dir = TempDb::new("_path_for_deterministic_db");
+//! let db = DeterministicDb::open(&dir).unwrap();
+//! db.put(b"k1", b"v1").unwrap();
+//! db.flush_and_compact_all().unwrap();
+//! db.assert_quiesced();
+//! ```
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    properties, ColumnFamilyDescriptor, CompactOptions, DBWithThreadMode, Error, Options,
+    SingleThreaded, DB,
+};
+
+// Shared with the tests of this crate, which are built without this module.
+include!("test_util/temp_db.rs");
+
+/// A DB whose flushes and compactions only happen when asked for, so tests
+/// do not race with background jobs.
+///
+/// All column families are opened with automatic compactions disabled and a
+/// single background job. Memtables that fill up are still flushed in the
+/// background, so tests writing more than a memtable holds should call
+/// [`flush_and_compact_all`](Self::flush_and_compact_all) before asserting on
+/// the files of the DB.
+pub struct DeterministicDb {
+    db: DBWithThreadMode<SingleThreaded>,
+}
+
+impl DeterministicDb {
+    /// The options the DB and its column families are opened with.
+    pub fn options() -> Options {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        opts.set_max_background_jobs(1);
+        opts.set_disable_auto_compactions(true);
+        opts
+    }
+
+    /// Opens the DB at `path` with [`options`](Self::options), creating it if
+    /// missing.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::open_cf(path, Vec::<&str>::new())
+    }
+
+    /// Opens the DB at `path` with the column families `cfs`, creating the ones
+    /// missing.
+    pub fn open_cf<P, I, N>(path: P, cfs: I) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = N>,
+        N: AsRef<str>,
+    {
+        Self::open_cf_opt(&Self::options(), path, cfs)
+    }
+
+    /// Same as `open_cf`, but opens the DB and all column families with `opts`,
+    /// which should be built on top of [`options`](Self::options).
+    pub fn open_cf_opt<P, I, N>(opts: &Options, path: P, cfs: I) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        I: IntoIterator<Item = N>,
+        N: AsRef<str>,
+    {
+        let cfs = cfs
+            .into_iter()
+            .map(|name| ColumnFamilyDescriptor::new(name.as_ref(), opts.clone()));
+        let db = DBWithThreadMode::open_cf_descriptors(opts, path, cfs)?;
+        Ok(Self { db })
+    }
+
+    /// Flushes the memtables of all column families and compacts all their
+    /// files, returning once done.
+    pub fn flush_and_compact_all(&self) -> Result<(), Error> {
+        let compact_opts = CompactOptions::default();
+        for (_, cf) in self.db.cf_handles() {
+            self.db.flush_cf(cf)?;
+            self.db
+                .try_compact_range_cf_opt(cf, None::<&[u8]>, None::<&[u8]>, &compact_opts)?;
+        }
+        Ok(())
+    }
+
+    /// Panics if a flush or compaction is pending or running in a column family.
+    #[track_caller]
+    pub fn assert_quiesced(&self) {
+        for (name, cf) in self.db.cf_handles() {
+            for property in [
+                properties::MEM_TABLE_FLUSH_PENDING,
+                properties::NUM_RUNNING_FLUSHES,
+                properties::COMPACTION_PENDING,
+                properties::NUM_RUNNING_COMPACTIONS,
+            ] {
+                let value = self
+                    .db
+                    .property_int_value_cf(cf, property)
+                    .unwrap()
+                    .unwrap_or(0);
+                assert_eq!(
+                    value, 0,
+                    "column family `{name}` is not quiesced: {property} is {value}"
+                );
+            }
+        }
+    }
+}
+
+impl Deref for DeterministicDb {
+    type Target = DBWithThreadMode<SingleThreaded>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.db
+    }
+}
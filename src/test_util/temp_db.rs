@@ -0,0 +1,39 @@
+/// A temporary directory for a DB, destroying the DB and removing the
+/// directory when dropped.
+pub struct TempDb {
+    _dir: tempfile::TempDir, // kept for cleaning up during drop
+    path: PathBuf,
+}
+
+impl TempDb {
+    /// Creates a temporary directory named after `prefix`. The DB path inside
+    /// it does not exist yet.
+    pub fn new(prefix: &str) -> Self {
+        let dir = tempfile::Builder::new()
+            .prefix(prefix)
+            .tempdir()
+            .expect("Failed to create temporary path for db.");
+        let path = dir.path().join("db");
+
+        Self { _dir: dir, path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        let opts = Options::default();
+        DB::destroy(&opts, &self.path).expect("Failed to destroy temporary DB");
+    }
+}
+
+/// Not implemented for `TempDb` values, so the directory is not removed by
+/// passing it to a function taking a path.
+impl AsRef<Path> for &TempDb {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
@@ -18,9 +18,11 @@ use std::{collections::BTreeMap, ffi::CString, fs, iter, marker::PhantomData, pa
 use libc::{c_char, c_int, size_t};
 
 use crate::{
+    column_family::{column_family_id, DEFAULT_COLUMN_FAMILY_ID},
     db::{DBCommon, DBInner},
     ffi,
     ffi_util::to_cpath,
+    transactions::transaction::{compare_and_put_in, retry_on_conflict, update_in},
     write_batch::WriteBatchWithTransaction,
     AsColumnFamilyRef, ColumnFamilyDescriptor, Error, OptimisticTransactionOptions, Options,
     ThreadMode, Transaction, WriteOptions, DEFAULT_COLUMN_FAMILY_NAME,
@@ -146,9 +148,11 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
 
         let db: *mut ffi::rocksdb_optimistictransactiondb_t;
         let mut cf_map = BTreeMap::new();
+        let mut cf_plugin_names = BTreeMap::new();
 
         if cfs.is_empty() {
             db = Self::open_raw(opts, &cpath)?;
+            cf_plugin_names.insert(DEFAULT_COLUMN_FAMILY_ID, opts.plugin_names.clone());
         } else {
             let mut cfs_v = cfs;
             // Always open the default column family.
@@ -186,6 +190,10 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
             }
 
             for (cf_desc, inner) in cfs_v.iter().zip(cfhandles) {
+                cf_plugin_names.insert(
+                    column_family_id(inner),
+                    cf_desc.options.plugin_names.clone(),
+                );
                 cf_map.insert(cf_desc.name.clone(), inner);
             }
         }
@@ -207,7 +215,10 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
             inner,
             T::new_cf_map_internal(cf_map),
             path.as_ref().to_path_buf(),
+            opts.write_validation,
             outlive,
+            cf_plugin_names,
+            opts.effective_max_open_files(),
         ))
     }
 
@@ -268,6 +279,7 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
                     std::ptr::null_mut(),
                 )
             },
+            write_validation: self.write_validation,
             _marker: PhantomData,
         }
     }
@@ -277,6 +289,10 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
         batch: WriteBatchWithTransaction<true>,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        if let Some(validation) = &self.write_validation {
+            validation.check_batch(batch.data())?;
+        }
+
         unsafe {
             ffi_try!(ffi::rocksdb_optimistictransactiondb_write(
                 self.inner.db,
@@ -307,6 +323,10 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
     ) -> Result<(), Error> {
         let from = from.as_ref();
         let to = to.as_ref();
+        if let Some(validation) = &self.write_validation {
+            validation.check_key(from.len())?;
+            validation.check_key(to.len())?;
+        }
 
         unsafe {
             ffi_try!(ffi::rocksdb_delete_range_cf(
@@ -331,4 +351,51 @@ impl<T: ThreadMode> OptimisticTransactionDB<T> {
     ) -> Result<(), Error> {
         self.delete_range_cf_opt(cf, from, to, &WriteOptions::default())
     }
+
+    /// Replaces the value of `key` in the column family with the one `f` returns
+    /// for its current value, or deletes the key if `f` returns `None`, and
+    /// returns the value written.
+    ///
+    /// The key is read and written in a transaction, which is retried from the
+    /// read with backoff if another writer changed the key before it committed,
+    /// so concurrent updates are never lost. `f` is called once per attempt.
+    /// After 10 attempts the last [`ErrorKind::Busy`] error is returned.
+    ///
+    /// [`ErrorKind::Busy`]: crate::ErrorKind::Busy
+    pub fn update_cf<K, F>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        mut f: F,
+    ) -> Result<Option<Vec<u8>>, Error>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    {
+        let key = key.as_ref();
+        retry_on_conflict(|| self.transaction(), |txn| update_in(txn, cf, key, &mut f))
+    }
+
+    /// Writes `new` to `key` in the column family if its current value is
+    /// `expected`, `None` standing for a missing key, and returns whether it did.
+    ///
+    /// The comparison and the write are made in a transaction, retried like the
+    /// one of [`update_cf`](Self::update_cf).
+    pub fn compare_and_put_cf<K, V>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        expected: Option<&[u8]>,
+        new: V,
+    ) -> Result<bool, Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key, new) = (key.as_ref(), new.as_ref());
+        retry_on_conflict(
+            || self.transaction(),
+            |txn| compare_and_put_in(txn, cf, key, expected, new),
+        )
+    }
 }
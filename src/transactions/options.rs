@@ -136,6 +136,11 @@ impl Drop for TransactionOptions {
     }
 }
 
+/// Options of a [`TransactionDB`](crate::TransactionDB), such as the sizing of
+/// its lock table.
+///
+/// Transactions are always written with the `WRITE_COMMITTED` policy, as the C
+/// API does not expose the write policy.
 pub struct TransactionDBOptions {
     pub(crate) inner: *mut ffi::rocksdb_transactiondb_options_t,
 }
@@ -13,12 +13,16 @@
 // limitations under the License.
 //
 
-use std::{marker::PhantomData, ptr};
+use std::{marker::PhantomData, ptr, time::Duration};
 
 use crate::{
     db::{convert_values, DBAccess},
-    ffi, AsColumnFamilyRef, DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode,
-    Direction, Error, IteratorMode, ReadOptions, SnapshotWithThreadMode, WriteBatchWithTransaction,
+    db_options::WriteValidationOptions,
+    ffi,
+    retry::{self, RetryPolicy},
+    AsColumnFamilyRef, DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode,
+    Direction, Error, ErrorKind, IteratorMode, ReadOptions, SnapshotWithThreadMode,
+    WriteBatchWithTransaction,
 };
 use libc::{c_char, c_void, size_t};
 
@@ -30,6 +34,9 @@ use libc::{c_char, c_void, size_t};
 /// [`OptimisticTransactionDB`]: crate::OptimisticTransactionDB
 pub struct Transaction<'db, DB> {
     pub(crate) inner: *mut ffi::rocksdb_transaction_t,
+    // Limits of the DB checked before each write, see
+    // `Options::set_write_validation`.
+    pub(crate) write_validation: Option<WriteValidationOptions>,
     pub(crate) _marker: PhantomData<&'db DB>,
 }
 
@@ -585,6 +592,8 @@ impl<'db, DB> Transaction<'db, DB> {
     ///
     /// [`put_cf`]: Self::put_cf
     pub fn put<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), Error> {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_put(
                 self.inner,
@@ -618,6 +627,8 @@ impl<'db, DB> Transaction<'db, DB> {
         key: K,
         value: V,
     ) -> Result<(), Error> {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_put_cf(
                 self.inner,
@@ -637,6 +648,8 @@ impl<'db, DB> Transaction<'db, DB> {
     ///
     /// [`merge_cf`]: Self::merge_cf
     pub fn merge<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), Error> {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_merge(
                 self.inner,
@@ -670,6 +683,8 @@ impl<'db, DB> Transaction<'db, DB> {
         key: K,
         value: V,
     ) -> Result<(), Error> {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_merge_cf(
                 self.inner,
@@ -689,6 +704,7 @@ impl<'db, DB> Transaction<'db, DB> {
     ///
     /// [`delete_cf`]: Self::delete_cf
     pub fn delete<K: AsRef<[u8]>>(&self, key: K) -> Result<(), Error> {
+        self.check_delete(key.as_ref())?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_delete(
                 self.inner,
@@ -718,6 +734,7 @@ impl<'db, DB> Transaction<'db, DB> {
         cf: &impl AsColumnFamilyRef,
         key: K,
     ) -> Result<(), Error> {
+        self.check_delete(key.as_ref())?;
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_delete_cf(
                 self.inner,
@@ -729,6 +746,41 @@ impl<'db, DB> Transaction<'db, DB> {
         Ok(())
     }
 
+    /// Deletes the keys in the range `[from, to)` of the default column family.
+    ///
+    /// See [`delete_keys_in_range_cf`] for details.
+    ///
+    /// [`delete_keys_in_range_cf`]: Self::delete_keys_in_range_cf
+    pub fn delete_keys_in_range<K: AsRef<[u8]>>(&self, from: K, to: K) -> Result<(), Error> {
+        let mut readopts = ReadOptions::default();
+        readopts.set_iterate_upper_bound(to.as_ref());
+        let keys = keys_from(self.raw_iterator_opt(readopts), from.as_ref())?;
+        keys.iter().try_for_each(|key| self.delete(key))
+    }
+
+    /// Deletes the keys in the range `[from, to)` of the column family.
+    ///
+    /// This is not a range deletion, which RocksDB transactions do not support:
+    /// the keys of the range the transaction sees when it is called, i.e. the
+    /// keys committed by then and the ones the transaction wrote itself, are
+    /// read and deleted one by one, so the cost grows with the number of keys.
+    /// Keys written to the range by others afterwards are not deleted on
+    /// commit. Each deletion is like a [`delete_cf`], so it locks the key or
+    /// checks it for conflicts, and fails the same way.
+    ///
+    /// [`delete_cf`]: Self::delete_cf
+    pub fn delete_keys_in_range_cf<K: AsRef<[u8]>>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        from: K,
+        to: K,
+    ) -> Result<(), Error> {
+        let mut readopts = ReadOptions::default();
+        readopts.set_iterate_upper_bound(to.as_ref());
+        let keys = keys_from(self.raw_iterator_cf_opt(cf, readopts), from.as_ref())?;
+        keys.iter().try_for_each(|key| self.delete_cf(cf, key))
+    }
+
     pub fn iterator<'a: 'b, 'b>(
         &'a self,
         mode: IteratorMode,
@@ -753,7 +805,7 @@ impl<'db, DB> Transaction<'db, DB> {
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -787,7 +839,7 @@ impl<'db, DB> Transaction<'db, DB> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -797,7 +849,7 @@ impl<'db, DB> Transaction<'db, DB> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -809,7 +861,7 @@ impl<'db, DB> Transaction<'db, DB> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
@@ -827,7 +879,7 @@ impl<'db, DB> Transaction<'db, DB> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -844,7 +896,20 @@ impl<'db, DB> Transaction<'db, DB> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
+    }
+
+    /// Checks a put or merge against the limits set with `Options::set_write_validation`.
+    fn check_write(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_write(key, value))
+    }
+
+    /// Checks the key of a delete against the limits set with
+    /// `Options::set_write_validation`.
+    fn check_delete(&self, key: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_key(key.len()))
     }
 
     pub fn get_writebatch(&self) -> WriteBatchWithTransaction<true> {
@@ -854,7 +919,7 @@ impl<'db, DB> Transaction<'db, DB> {
             let ptr = ffi::rocksdb_writebatch_wi_data(wi, &mut len as _);
             let writebatch = ffi::rocksdb_writebatch_create_from(ptr, len);
             ffi::rocksdb_free(wi as *mut c_void);
-            WriteBatchWithTransaction { inner: writebatch }
+            WriteBatchWithTransaction::from_raw(writebatch)
         }
     }
 
@@ -862,6 +927,10 @@ impl<'db, DB> Transaction<'db, DB> {
         &self,
         writebatch: &WriteBatchWithTransaction<true>,
     ) -> Result<(), Error> {
+        if let Some(validation) = &self.write_validation {
+            validation.check_batch(writebatch.data())?;
+        }
+
         unsafe {
             ffi_try!(ffi::rocksdb_transaction_rebuild_from_writebatch(
                 self.inner,
@@ -872,6 +941,80 @@ impl<'db, DB> Transaction<'db, DB> {
     }
 }
 
+/// Collects the keys of `iter` from `from` on. The keys are deleted once the
+/// iterator is done, as the iterator reads the batch of the transaction too.
+fn keys_from<D: DBAccess>(
+    mut iter: DBRawIteratorWithThreadMode<D>,
+    from: &[u8],
+) -> Result<Vec<Vec<u8>>, Error> {
+    let mut keys = vec![];
+    iter.seek(from);
+    while let Some(key) = iter.key() {
+        keys.push(key.to_vec());
+        iter.next();
+    }
+    iter.status()?;
+    Ok(keys)
+}
+
+/// Number of transactions `retry_on_conflict` starts before giving up.
+const MAX_CONFLICT_ATTEMPTS: u32 = 10;
+
+/// Runs `body` in a transaction started by `begin` and commits it, starting over
+/// in a new transaction with backoff while it conflicts with another writer, at
+/// most `MAX_CONFLICT_ATTEMPTS` times.
+pub(crate) fn retry_on_conflict<'db, DB: 'db, R>(
+    mut begin: impl FnMut() -> Transaction<'db, DB>,
+    mut body: impl FnMut(&Transaction<'db, DB>) -> Result<R, Error>,
+) -> Result<R, Error> {
+    let mut policy = RetryPolicy::new();
+    policy.set_max_attempts(MAX_CONFLICT_ATTEMPTS);
+    policy.set_base_delay(Duration::from_millis(1));
+    policy.set_max_delay(Duration::from_millis(100));
+    policy.set_retryable(|kind| matches!(kind, ErrorKind::Busy | ErrorKind::TryAgain));
+    retry::with_backoff(&policy, || {
+        let txn = begin();
+        body(&txn).and_then(|value| txn.commit().map(|()| value))
+    })
+}
+
+/// Writes the value `f` returns for the current value of `key` in `txn`, or
+/// deletes the key if it returns `None`.
+pub(crate) fn update_in<DB, F>(
+    txn: &Transaction<DB>,
+    cf: &impl AsColumnFamilyRef,
+    key: &[u8],
+    f: &mut F,
+) -> Result<Option<Vec<u8>>, Error>
+where
+    F: FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+{
+    let current = txn.get_for_update_cf(cf, key, true)?;
+    let new = f(current.as_deref());
+    match &new {
+        Some(value) => txn.put_cf(cf, key, value)?,
+        None => txn.delete_cf(cf, key)?,
+    }
+    Ok(new)
+}
+
+/// Writes `new` to `key` in `txn` if its current value is `expected`, returning
+/// whether it did.
+pub(crate) fn compare_and_put_in<DB>(
+    txn: &Transaction<DB>,
+    cf: &impl AsColumnFamilyRef,
+    key: &[u8],
+    expected: Option<&[u8]>,
+    new: &[u8],
+) -> Result<bool, Error> {
+    let current = txn.get_for_update_cf(cf, key, true)?;
+    if current.as_deref() != expected {
+        return Ok(false);
+    }
+    txn.put_cf(cf, key, new)?;
+    Ok(true)
+}
+
 impl<'db, DB> Drop for Transaction<'db, DB> {
     fn drop(&mut self) {
         unsafe {
@@ -24,11 +24,12 @@ use std::{
 };
 
 use crate::{
-    column_family::UnboundColumnFamily,
+    column_family::{column_family_id, UnboundColumnFamily},
     db::{convert_values, DBAccess},
-    db_options::OptionsMustOutliveDB,
+    db_options::{OptionsMustOutliveDB, WriteValidationOptions},
     ffi,
     ffi_util::to_cpath,
+    transactions::transaction::{compare_and_put_in, retry_on_conflict, update_in},
     AsColumnFamilyRef, BoundColumnFamily, ColumnFamily, ColumnFamilyDescriptor,
     DBIteratorWithThreadMode, DBPinnableSlice, DBRawIteratorWithThreadMode, Direction, Error,
     IteratorMode, MultiThreaded, Options, ReadOptions, SingleThreaded, SnapshotWithThreadMode,
@@ -74,7 +75,14 @@ pub struct TransactionDB<T: ThreadMode = DefaultThreadMode> {
     path: PathBuf,
     // prepared 2pc transactions.
     prepared: Mutex<Vec<*mut rocksdb_transaction_t>>,
+    // Limits checked before each write, see `Options::set_write_validation`.
+    write_validation: Option<WriteValidationOptions>,
+    // Keeps what RocksDB borrows from the options the DB was opened with, e.g.
+    // comparators.
     _outlive: Vec<OptionsMustOutliveDB>,
+    // The same for the column families created since, by column family id,
+    // released when they are dropped.
+    cf_outlive: Mutex<BTreeMap<u32, OptionsMustOutliveDB>>,
 }
 
 unsafe impl<T: ThreadMode> Send for TransactionDB<T> {}
@@ -312,7 +320,9 @@ impl<T: ThreadMode> TransactionDB<T> {
             cfs: T::new_cf_map_internal(cf_map),
             path: path.as_ref().to_path_buf(),
             prepared: Mutex::new(prepared),
+            write_validation: opts.write_validation,
             _outlive: outlive,
+            cf_outlive: Mutex::new(BTreeMap::new()),
         })
     }
 
@@ -363,13 +373,18 @@ impl<T: ThreadMode> TransactionDB<T> {
             Error::new("Failed to convert path to CString when creating cf".to_owned())
         })?;
 
-        Ok(unsafe {
+        let handle = unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_create_column_family(
                 self.inner,
                 opts.inner,
                 cf_name.as_ptr(),
             ))
-        })
+        };
+        self.cf_outlive
+            .lock()
+            .unwrap()
+            .insert(column_family_id(handle), opts.outlive.clone());
+        Ok(handle)
     }
 
     pub fn list_cf<P: AsRef<Path>>(opts: &Options, path: P) -> Result<Vec<String>, Error> {
@@ -408,10 +423,61 @@ impl<T: ThreadMode> TransactionDB<T> {
                     std::ptr::null_mut(),
                 )
             },
+            write_validation: self.write_validation,
             _marker: PhantomData,
         }
     }
 
+    /// Replaces the value of `key` in the column family with the one `f` returns
+    /// for its current value, or deletes the key if `f` returns `None`, and
+    /// returns the value written.
+    ///
+    /// The key is read and written in a transaction, holding the lock of the key
+    /// from the read until the commit, so concurrent updates wait for each other
+    /// and are never lost. The transaction is retried with backoff, at most 10
+    /// times, if it fails with [`ErrorKind::Busy`], e.g. on a detected deadlock,
+    /// so `f` may be called more than once. It fails with [`ErrorKind::TimedOut`] if the lock cannot be
+    /// acquired within the lock timeout.
+    ///
+    /// [`ErrorKind::Busy`]: crate::ErrorKind::Busy
+    /// [`ErrorKind::TimedOut`]: crate::ErrorKind::TimedOut
+    pub fn update_cf<K, F>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        mut f: F,
+    ) -> Result<Option<Vec<u8>>, Error>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(Option<&[u8]>) -> Option<Vec<u8>>,
+    {
+        let key = key.as_ref();
+        retry_on_conflict(|| self.transaction(), |txn| update_in(txn, cf, key, &mut f))
+    }
+
+    /// Writes `new` to `key` in the column family if its current value is
+    /// `expected`, `None` standing for a missing key, and returns whether it did.
+    ///
+    /// The comparison and the write are made in a transaction holding the lock
+    /// of the key, see [`update_cf`](Self::update_cf).
+    pub fn compare_and_put_cf<K, V>(
+        &self,
+        cf: &impl AsColumnFamilyRef,
+        key: K,
+        expected: Option<&[u8]>,
+        new: V,
+    ) -> Result<bool, Error>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key, new) = (key.as_ref(), new.as_ref());
+        retry_on_conflict(
+            || self.transaction(),
+            |txn| compare_and_put_in(txn, cf, key, expected, new),
+        )
+    }
+
     /// Get all prepared transactions for recovery.
     ///
     /// This function is expected to call once after open database.
@@ -423,11 +489,25 @@ impl<T: ThreadMode> TransactionDB<T> {
             .drain(0..)
             .map(|inner| Transaction {
                 inner,
+                write_validation: self.write_validation,
                 _marker: PhantomData,
             })
             .collect()
     }
 
+    /// Checks a put or merge against the limits set with `Options::set_write_validation`.
+    fn check_write(&self, key: &[u8], value: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_write(key, value))
+    }
+
+    /// Checks the key of a delete against the limits set with
+    /// `Options::set_write_validation`.
+    fn check_delete(&self, key: &[u8]) -> Result<(), Error> {
+        self.write_validation
+            .map_or(Ok(()), |validation| validation.check_key(key.len()))
+    }
+
     /// Returns the bytes associated with a key value.
     pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<Vec<u8>>, Error> {
         self.get_pinned(key).map(|x| x.map(|v| v.as_ref().to_vec()))
@@ -644,6 +724,8 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_put(
                 self.inner,
@@ -668,6 +750,8 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_put_cf(
                 self.inner,
@@ -691,6 +775,10 @@ impl<T: ThreadMode> TransactionDB<T> {
         batch: WriteBatchWithTransaction<true>,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        if let Some(validation) = &self.write_validation {
+            validation.check_batch(batch.data())?;
+        }
+
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_write(
                 self.inner,
@@ -722,6 +810,8 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_merge(
                 self.inner,
@@ -746,6 +836,8 @@ impl<T: ThreadMode> TransactionDB<T> {
         K: AsRef<[u8]>,
         V: AsRef<[u8]>,
     {
+        self.check_write(key.as_ref(), value.as_ref())?;
+
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_merge_cf(
                 self.inner,
@@ -777,6 +869,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         key: K,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        self.check_delete(key.as_ref())?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_delete(
                 self.inner,
@@ -794,6 +887,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         key: K,
         writeopts: &WriteOptions,
     ) -> Result<(), Error> {
+        self.check_delete(key.as_ref())?;
         unsafe {
             ffi_try!(ffi::rocksdb_transactiondb_delete_cf(
                 self.inner,
@@ -830,7 +924,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         readopts: ReadOptions,
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, readopts, mode)
     }
 
     /// Opens an iterator with `set_total_order_seek` enabled.
@@ -864,7 +958,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         mode: IteratorMode,
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn full_iterator_cf<'a: 'b, 'b>(
@@ -874,7 +968,7 @@ impl<T: ThreadMode> TransactionDB<T> {
     ) -> DBIteratorWithThreadMode<'b, Self> {
         let mut opts = ReadOptions::default();
         opts.set_total_order_seek(true);
-        DBIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts, mode)
+        DBIteratorWithThreadMode::new_cf(self, cf_handle, opts, mode)
     }
 
     pub fn prefix_iterator_cf<'a, P: AsRef<[u8]>>(
@@ -886,7 +980,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         opts.set_prefix_same_as_start(true);
         DBIteratorWithThreadMode::<'a, Self>::new_cf(
             self,
-            cf_handle.inner(),
+            cf_handle,
             opts,
             IteratorMode::From(prefix.as_ref(), Direction::Forward),
         )
@@ -904,7 +998,7 @@ impl<T: ThreadMode> TransactionDB<T> {
         cf_handle: &impl AsColumnFamilyRef,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
         let opts = ReadOptions::default();
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), opts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, opts)
     }
 
     /// Opens a raw iterator over the database, using the given read options
@@ -921,18 +1015,21 @@ impl<T: ThreadMode> TransactionDB<T> {
         cf_handle: &impl AsColumnFamilyRef,
         readopts: ReadOptions,
     ) -> DBRawIteratorWithThreadMode<'b, Self> {
-        DBRawIteratorWithThreadMode::new_cf(self, cf_handle.inner(), readopts)
+        DBRawIteratorWithThreadMode::new_cf(self, cf_handle, readopts)
     }
 
     pub fn snapshot(&self) -> SnapshotWithThreadMode<Self> {
         SnapshotWithThreadMode::<Self>::new(self)
     }
 
+    /// Drops the column family and destroys its handle `cf`, returning what RocksDB
+    /// borrows from its options if it was created after the DB was opened, for the
+    /// caller to keep as long as other handles to it are alive.
     fn drop_column_family<C>(
         &self,
         cf_inner: *mut ffi::rocksdb_column_family_handle_t,
-        _cf: C,
-    ) -> Result<(), Error> {
+        cf: C,
+    ) -> Result<Option<OptionsMustOutliveDB>, Error> {
         unsafe {
             // first mark the column family as dropped
             ffi_try!(ffi::rocksdb_drop_column_family(
@@ -940,9 +1037,15 @@ impl<T: ThreadMode> TransactionDB<T> {
                 cf_inner
             ));
         }
-        // Since `_cf` is dropped here, the column family handle is destroyed
+        let outlive = self
+            .cf_outlive
+            .lock()
+            .unwrap()
+            .remove(&column_family_id(cf_inner));
+        // Since `cf` is dropped here, the column family handle is destroyed
         // and any resources (mem, files) are reclaimed.
-        Ok(())
+        drop(cf);
+        Ok(outlive)
     }
 }
 
@@ -964,7 +1067,8 @@ impl TransactionDB<SingleThreaded> {
     /// Drops the column family with the given name
     pub fn drop_cf(&mut self, name: &str) -> Result<(), Error> {
         if let Some(cf) = self.cfs.cfs.remove(name) {
-            self.drop_column_family(cf.inner, cf)
+            // no iterator can be alive, so the options are released with the handle
+            self.drop_column_family(cf.inner, cf).map(drop)
         } else {
             Err(Error::new(format!("Invalid column family: {name}")))
         }
@@ -977,7 +1081,7 @@ impl TransactionDB<MultiThreaded> {
         let inner = self.create_inner_cf_handle(name.as_ref(), opts)?;
         self.cfs.cfs.write().unwrap().insert(
             name.as_ref().to_string(),
-            Arc::new(UnboundColumnFamily { inner }),
+            Arc::new(UnboundColumnFamily::new(inner)),
         );
         Ok(())
     }
@@ -997,7 +1101,9 @@ impl TransactionDB<MultiThreaded> {
     /// family map. This avoids needing `&mut self` reference
     pub fn drop_cf(&self, name: &str) -> Result<(), Error> {
         if let Some(cf) = self.cfs.cfs.write().unwrap().remove(name) {
-            self.drop_column_family(cf.inner, cf)
+            let outlive = self.drop_column_family(cf.inner, Arc::clone(&cf))?;
+            cf.retain_options(outlive);
+            Ok(())
         } else {
             Err(Error::new(format!("Invalid column family: {name}")))
         }
@@ -0,0 +1,214 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A typed layer over a column family, storing keys and values encoded with
+//! serde. Enabled by the `typed` feature.
+//!
+//! ```
+//! use rocksdb::{typed::TypedCf, DB, Options, DEFAULT_COLUMN_FAMILY_NAME};
+//! # use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Debug, PartialEq)]
+//! struct User {
+//!     name: String,
+//! }
+//!
+//! let tempdir = tempfile::Builder::new()
+//!     .prefix("_path_for_typed_cf")
+//!     .tempdir()
+//!     .expect("Failed to create temporary path for the _path_for_typed_cf");
+//! let path = tempdir.path();
+//! {
+//!     let db = DB::open_default(path).unwrap();
+//!     let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+//!     let users: TypedCf<u64, User, _, _> = TypedCf::new(&db, cf);
+//!     users.put(&1, &User { name: "alice".to_owned() }).unwrap();
+//!     assert_eq!(users.get(&1).unwrap().unwrap().name, "alice");
+//! }
+//! let _ = DB::destroy(&Options::default(), path);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    AsColumnFamilyRef, DBIteratorWithThreadMode, DBWithThreadMode, Direction, Error, IteratorMode,
+    ReadOptions, ThreadMode, WriteBatch,
+};
+
+/// Encodes keys and values to bytes and back.
+///
+/// Keys are compared by RocksDB as bytes, so range scans over a [`TypedCf`] only
+/// follow the natural order of the keys if the codec preserves it.
+pub trait Codec {
+    type Error: fmt::Display;
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Self::Error>;
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Self::Error>;
+}
+
+/// A [`Codec`] using bincode with big-endian fixed-size integers.
+///
+/// Unsigned integers, and tuples and structs of them, are encoded in their
+/// natural order. Signed integers are not, see [`encode_i64`]. Strings and
+/// other variable-length fields are prefixed by their length, so they are
+/// ordered by length first.
+pub struct BincodeCodec;
+
+impl BincodeCodec {
+    fn options() -> impl bincode::Options {
+        use bincode::Options;
+
+        bincode::DefaultOptions::new()
+            .with_big_endian()
+            .with_fixint_encoding()
+    }
+}
+
+impl Codec for BincodeCodec {
+    type Error = bincode::Error;
+
+    fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Self::Error> {
+        bincode::Options::serialize(Self::options(), value)
+    }
+
+    fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Self::Error> {
+        bincode::Options::deserialize(Self::options(), bytes)
+    }
+}
+
+/// Maps an `i64` to a `u64` with the same order, so that signed keys can be
+/// stored with an order-preserving codec such as [`BincodeCodec`].
+pub fn encode_i64(value: i64) -> u64 {
+    (value as u64) ^ (1 << 63)
+}
+
+/// Reverses [`encode_i64`].
+pub fn decode_i64(value: u64) -> i64 {
+    (value ^ (1 << 63)) as i64
+}
+
+/// A column family storing keys of type `K` and values of type `V`, encoded
+/// with the codec `C`.
+pub struct TypedCf<'a, K, V, T: ThreadMode, CF: AsColumnFamilyRef, C: Codec = BincodeCodec> {
+    db: &'a DBWithThreadMode<T>,
+    cf: CF,
+    _marker: PhantomData<fn() -> (K, V, C)>,
+}
+
+impl<'a, K, V, T, CF, C> TypedCf<'a, K, V, T, CF, C>
+where
+    K: Serialize + DeserializeOwned,
+    V: Serialize + DeserializeOwned,
+    T: ThreadMode,
+    CF: AsColumnFamilyRef,
+    C: Codec,
+{
+    pub fn new(db: &'a DBWithThreadMode<T>, cf: CF) -> Self {
+        Self {
+            db,
+            cf,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn put(&self, key: &K, value: &V) -> Result<(), Error> {
+        self.db
+            .put_cf(&self.cf, encode::<C, _>(key)?, encode::<C, _>(value)?)
+    }
+
+    /// Returns the value stored under `key`, failing with a corruption error if
+    /// it cannot be decoded as a `V`.
+    pub fn get(&self, key: &K) -> Result<Option<V>, Error> {
+        self.db
+            .get_pinned_cf(&self.cf, encode::<C, _>(key)?)?
+            .map(|value| decode::<C, _>(&value))
+            .transpose()
+    }
+
+    pub fn delete(&self, key: &K) -> Result<(), Error> {
+        self.db.delete_cf(&self.cf, encode::<C, _>(key)?)
+    }
+
+    /// Adds a put of `key` to `batch`, to be written with other updates.
+    pub fn put_in_batch(&self, batch: &mut WriteBatch, key: &K, value: &V) -> Result<(), Error> {
+        batch.put_cf(&self.cf, encode::<C, _>(key)?, encode::<C, _>(value)?);
+        Ok(())
+    }
+
+    /// Adds a delete of `key` to `batch`, to be written with other updates.
+    pub fn delete_in_batch(&self, batch: &mut WriteBatch, key: &K) -> Result<(), Error> {
+        batch.delete_cf(&self.cf, encode::<C, _>(key)?);
+        Ok(())
+    }
+
+    /// Iterates over all entries in the order of their encoded keys.
+    pub fn iter(&self) -> TypedIterator<'a, K, V, T, C> {
+        TypedIterator::new(self.db.iterator_cf(&self.cf, IteratorMode::Start))
+    }
+
+    /// Iterates over the entries with keys in `[from, to)`, comparing the
+    /// encoded keys.
+    pub fn range(&self, from: &K, to: &K) -> Result<TypedIterator<'a, K, V, T, C>, Error> {
+        let from = encode::<C, _>(from)?;
+        let mut readopts = ReadOptions::default();
+        readopts.set_iterate_upper_bound(encode::<C, _>(to)?);
+        Ok(TypedIterator::new(self.db.iterator_cf_opt(
+            &self.cf,
+            readopts,
+            IteratorMode::From(&from, Direction::Forward),
+        )))
+    }
+}
+
+/// An iterator over the decoded entries of a [`TypedCf`].
+pub struct TypedIterator<'a, K, V, T: ThreadMode, C: Codec> {
+    inner: DBIteratorWithThreadMode<'a, DBWithThreadMode<T>>,
+    _marker: PhantomData<fn() -> (K, V, C)>,
+}
+
+impl<'a, K, V, T: ThreadMode, C: Codec> TypedIterator<'a, K, V, T, C> {
+    fn new(inner: DBIteratorWithThreadMode<'a, DBWithThreadMode<T>>) -> Self {
+        Self {
+            inner,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, K, V, T, C> Iterator for TypedIterator<'a, K, V, T, C>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    T: ThreadMode,
+    C: Codec,
+{
+    type Item = Result<(K, V), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|item| {
+            let (key, value) = item?;
+            Ok((decode::<C, _>(&key)?, decode::<C, _>(&value)?))
+        })
+    }
+}
+
+fn encode<C: Codec, T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    C::encode(value).map_err(|err| Error::new(format!("Invalid argument: failed to encode: {err}")))
+}
+
+fn decode<C: Codec, T: DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
+    C::decode(bytes).map_err(|err| Error::new(format!("Corruption: failed to decode: {err}")))
+}
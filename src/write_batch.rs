@@ -12,8 +12,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{ffi, AsColumnFamilyRef};
+use crate::{
+    column_family::{column_family_name, DynColumnFamily},
+    ffi,
+    ffi_util::abort_on_panic,
+    AsColumnFamilyRef, Error,
+};
 use libc::{c_char, c_void, size_t};
+use std::collections::BTreeMap;
 use std::slice;
 
 /// A type alias to keep compatibility. See [`WriteBatchWithTransaction`] for details
@@ -60,6 +66,21 @@ pub trait WriteBatchIterator {
     fn delete(&mut self, key: Box<[u8]>);
 }
 
+/// Receives the operations of a write batch along with the id of their column
+/// family, see [`WriteBatchWithTransaction::iterate_cf`].
+pub trait WriteBatchIteratorCf {
+    /// Called with a key and value that were put into the column family.
+    fn put_cf(&mut self, cf_id: u32, key: &[u8], value: &[u8]);
+    /// Called with a key that was deleted, or single deleted, from the column
+    /// family.
+    fn delete_cf(&mut self, cf_id: u32, key: &[u8]);
+    /// Called with a key and value that were merged into the column family.
+    fn merge_cf(&mut self, cf_id: u32, key: &[u8], value: &[u8]);
+    /// Called with the range `[from, to)` that was deleted from the column
+    /// family.
+    fn delete_range_cf(&mut self, cf_id: u32, from: &[u8], to: &[u8]);
+}
+
 unsafe extern "C" fn writebatch_put_callback(
     state: *mut c_void,
     k: *const c_char,
@@ -67,25 +88,29 @@ unsafe extern "C" fn writebatch_put_callback(
     v: *const c_char,
     vlen: usize,
 ) {
-    // coerce the raw pointer back into a box, but "leak" it so we prevent
-    // freeing the resource before we are done with it
-    let boxed_cb = Box::from_raw(state as *mut &mut dyn WriteBatchIterator);
-    let leaked_cb = Box::leak(boxed_cb);
-    let key = slice::from_raw_parts(k as *const u8, klen);
-    let value = slice::from_raw_parts(v as *const u8, vlen);
-    leaked_cb.put(
-        key.to_vec().into_boxed_slice(),
-        value.to_vec().into_boxed_slice(),
-    );
+    abort_on_panic("write batch iterator", || {
+        // coerce the raw pointer back into a box, but "leak" it so we prevent
+        // freeing the resource before we are done with it
+        let boxed_cb = Box::from_raw(state as *mut &mut dyn WriteBatchIterator);
+        let leaked_cb = Box::leak(boxed_cb);
+        let key = slice::from_raw_parts(k as *const u8, klen);
+        let value = slice::from_raw_parts(v as *const u8, vlen);
+        leaked_cb.put(
+            key.to_vec().into_boxed_slice(),
+            value.to_vec().into_boxed_slice(),
+        );
+    });
 }
 
 unsafe extern "C" fn writebatch_delete_callback(state: *mut c_void, k: *const c_char, klen: usize) {
-    // coerce the raw pointer back into a box, but "leak" it so we prevent
-    // freeing the resource before we are done with it
-    let boxed_cb = Box::from_raw(state as *mut &mut dyn WriteBatchIterator);
-    let leaked_cb = Box::leak(boxed_cb);
-    let key = slice::from_raw_parts(k as *const u8, klen);
-    leaked_cb.delete(key.to_vec().into_boxed_slice());
+    abort_on_panic("write batch iterator", || {
+        // coerce the raw pointer back into a box, but "leak" it so we prevent
+        // freeing the resource before we are done with it
+        let boxed_cb = Box::from_raw(state as *mut &mut dyn WriteBatchIterator);
+        let leaked_cb = Box::leak(boxed_cb);
+        let key = slice::from_raw_parts(k as *const u8, klen);
+        leaked_cb.delete(key.to_vec().into_boxed_slice());
+    });
 }
 
 impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
@@ -94,15 +119,23 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
         unsafe {
             let ptr = data.as_ptr();
             let len = data.len();
-            Self {
-                inner: ffi::rocksdb_writebatch_create_from(
-                    ptr as *const libc::c_char,
-                    len as size_t,
-                ),
-            }
+            Self::from_raw(ffi::rocksdb_writebatch_create_from(
+                ptr as *const libc::c_char,
+                len as size_t,
+            ))
         }
     }
 
+    /// Wraps a batch created by RocksDB.
+    pub(crate) fn from_raw(inner: *mut ffi::rocksdb_writebatch_t) -> Self {
+        Self { inner }
+    }
+
+    /// Copies the operations of the batch.
+    pub(crate) fn duplicate(&self) -> Self {
+        Self::from_data(self.data())
+    }
+
     pub fn len(&self) -> usize {
         unsafe { ffi::rocksdb_writebatch_count(self.inner) as usize }
     }
@@ -148,6 +181,39 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
         }
     }
 
+    /// Passes the operations within this write batch, in order, to `callbacks`
+    /// along with the id of their column family, e.g. to apply a replicated
+    /// batch to the handles returned by
+    /// [`DBCommon::cf_handle_by_id`](crate::DBCommon::cf_handle_by_id).
+    ///
+    /// Fails with an invalid argument error if the batch cannot be decoded, and
+    /// with a not supported error if it holds blob indexes or wide-column
+    /// entities, before calling `callbacks`.
+    pub fn iterate_cf(&self, callbacks: &mut dyn WriteBatchIteratorCf) -> Result<(), Error> {
+        let ops = decode_batch_ops(self.data()).ok_or_else(|| {
+            Error::new("Invalid argument: the write batch cannot be decoded".to_owned())
+        })?;
+        if ops
+            .iter()
+            .any(|(_, op)| matches!(op, BatchOp::Other { .. }))
+        {
+            return Err(Error::new(
+                "Not implemented: the write batch holds blob indexes or wide-column entities"
+                    .to_owned(),
+            ));
+        }
+        for (cf_id, op) in ops {
+            match op {
+                BatchOp::Put { key, value } => callbacks.put_cf(cf_id, key, value),
+                BatchOp::Delete { key } => callbacks.delete_cf(cf_id, key),
+                BatchOp::Merge { key, value } => callbacks.merge_cf(cf_id, key, value),
+                BatchOp::DeleteRange { from, to } => callbacks.delete_range_cf(cf_id, from, to),
+                BatchOp::Other { .. } => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+
     /// Insert a value into the database under the given key.
     pub fn put<K, V>(&mut self, key: K, value: V)
     where
@@ -259,6 +325,33 @@ impl<const TRANSACTION: bool> WriteBatchWithTransaction<TRANSACTION> {
             ffi::rocksdb_writebatch_clear(self.inner);
         }
     }
+
+    /// Appends the operations of `other` to this batch, so that both are written
+    /// atomically by a single write.
+    ///
+    /// The batch is rebuilt from the concatenation of the serialized batches, so
+    /// this takes time linear in the size of both.
+    pub fn append(&mut self, other: &Self) {
+        // the serialized batch starts with an 8-byte sequence number and a 4-byte count
+        const HEADER_LEN: usize = 12;
+
+        if other.is_empty() {
+            return;
+        }
+        let mut data = Vec::with_capacity(self.size_in_bytes() + other.size_in_bytes());
+        data.extend_from_slice(self.data());
+        data.extend_from_slice(&other.data()[HEADER_LEN..]);
+        let count = (self.len() + other.len()) as u32;
+        data[8..HEADER_LEN].copy_from_slice(&count.to_le_bytes());
+        unsafe {
+            let inner = ffi::rocksdb_writebatch_create_from(
+                data.as_ptr() as *const c_char,
+                data.len() as size_t,
+            );
+            ffi::rocksdb_writebatch_destroy(self.inner);
+            self.inner = inner;
+        }
+    }
 }
 
 impl WriteBatchWithTransaction<false> {
@@ -302,11 +395,226 @@ impl WriteBatchWithTransaction<false> {
     }
 }
 
+/// Builds a [`WriteBatch`] spanning several column families, handing out a
+/// [`CfBatch`] view per column family so that each part of an application can
+/// add its operations without repeating the column family.
+///
+/// Builders filled separately, e.g. on different threads, can be combined with
+/// [`append`](Self::append) before the batch is [built](Self::build) and
+/// written atomically. The number of operations and the bytes of the keys and
+/// values added to each column family are counted, for metrics.
+///
+/// ```
+/// use rocksdb::{Options, WriteBatchBuilder, DB};
+///
+/// let path = "_path_for_write_batch_builder";
+/// {
+///     let mut opts = Options::default();
+///     opts.create_if_missing(true);
+///     opts.create_missing_column_families(true);
+///     let db = DB::open_cf(&opts, path, ["users", "index"]).unwrap();
+///     let users = db.cf_handle("users").unwrap();
+///     let index = db.cf_handle("index").unwrap();
+///
+///     let mut builder = WriteBatchBuilder::new();
+///     builder.cf(&users).put(b"1", b"alice");
+///     builder.cf(&index).put(b"alice", b"1");
+///     assert_eq!(builder.stats()["users"].ops, 1);
+///     db.write(builder.build()).unwrap();
+/// }
+/// let _ = DB::destroy(&Options::default(), path);
+/// ```
+#[derive(Default)]
+pub struct WriteBatchBuilder {
+    batch: WriteBatch,
+    stats: BTreeMap<String, CfBatchStats>,
+}
+
+/// The operations added to a column family through a [`WriteBatchBuilder`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CfBatchStats {
+    /// The number of operations.
+    pub ops: usize,
+    /// The total size of the keys and values of the operations.
+    pub bytes: usize,
+}
+
+impl CfBatchStats {
+    fn record(&mut self, bytes: usize) {
+        self.ops += 1;
+        self.bytes += bytes;
+    }
+}
+
+impl WriteBatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a view adding operations to the column family `cf`.
+    pub fn cf<'a>(&'a mut self, cf: &'a impl AsColumnFamilyRef) -> CfBatch<'a> {
+        let stats = self
+            .stats
+            .entry(column_family_name(cf.inner()))
+            .or_default();
+        CfBatch {
+            batch: &mut self.batch,
+            cf,
+            stats,
+        }
+    }
+
+    /// Appends the operations of `other`, adding up the counts of both.
+    pub fn append(&mut self, other: WriteBatchBuilder) {
+        self.batch.append(&other.batch);
+        for (name, stats) in other.stats {
+            let total = self.stats.entry(name).or_default();
+            total.ops += stats.ops;
+            total.bytes += stats.bytes;
+        }
+    }
+
+    /// Returns the counts of the operations added so far, by column family name.
+    pub fn stats(&self) -> &BTreeMap<String, CfBatchStats> {
+        &self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.batch.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batch.is_empty()
+    }
+
+    /// Returns the batch holding all the operations added.
+    pub fn build(self) -> WriteBatch {
+        self.batch
+    }
+}
+
+/// Adds operations on one column family to a [`WriteBatchBuilder`].
+pub struct CfBatch<'a> {
+    batch: &'a mut WriteBatch,
+    cf: &'a dyn AsColumnFamilyRef,
+    stats: &'a mut CfBatchStats,
+}
+
+impl<'a> CfBatch<'a> {
+    pub fn put<K, V>(&mut self, key: K, value: V)
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        self.batch.put_cf(&DynColumnFamily(self.cf), key, value);
+        self.stats.record(key.len() + value.len());
+    }
+
+    pub fn merge<K, V>(&mut self, key: K, value: V)
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let (key, value) = (key.as_ref(), value.as_ref());
+        self.batch.merge_cf(&DynColumnFamily(self.cf), key, value);
+        self.stats.record(key.len() + value.len());
+    }
+
+    pub fn delete<K: AsRef<[u8]>>(&mut self, key: K) {
+        let key = key.as_ref();
+        self.batch.delete_cf(&DynColumnFamily(self.cf), key);
+        self.stats.record(key.len());
+    }
+
+    /// Removes the entries in the range `[from, to)`.
+    pub fn delete_range<K: AsRef<[u8]>>(&mut self, from: K, to: K) {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        self.batch
+            .delete_range_cf(&DynColumnFamily(self.cf), from, to);
+        self.stats.record(from.len() + to.len());
+    }
+
+    /// Returns the counts of the operations added to the column family, including
+    /// the ones added through other views of it.
+    pub fn stats(&self) -> CfBatchStats {
+        *self.stats
+    }
+}
+
+/// The operations of a write batch by column family, see
+/// [`DBCommon::validate_write_batch`](crate::DBCommon::validate_write_batch).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// The operations on the column families of the DB, by name.
+    pub column_families: BTreeMap<String, BatchOpCounts>,
+    /// The operations on column family ids the DB has no column family for,
+    /// e.g. because the batch was built against another DB or the column family
+    /// was dropped, by id.
+    pub unknown_cf_ids: BTreeMap<u32, BatchOpCounts>,
+    /// The size of the serialized batch.
+    pub size_in_bytes: usize,
+}
+
+impl BatchSummary {
+    /// Returns `true` if all the operations are on column families of the DB.
+    pub fn is_valid(&self) -> bool {
+        self.unknown_cf_ids.is_empty()
+    }
+}
+
+/// The number of operations of each type in a column family of a write batch.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BatchOpCounts {
+    pub puts: usize,
+    /// Deletes and single deletes.
+    pub deletes: usize,
+    pub merges: usize,
+    pub delete_ranges: usize,
+    /// Writes of values RocksDB stores elsewhere or in another format, e.g. blob
+    /// indexes or wide-column entities.
+    pub others: usize,
+}
+
+impl BatchOpCounts {
+    /// The number of operations of all types.
+    pub fn total(&self) -> usize {
+        self.puts + self.deletes + self.merges + self.delete_ranges + self.others
+    }
+}
+
+/// Counts the operations of `batch` by column family, looking up the name of
+/// each column family id in `cf_names`.
+pub(crate) fn summarize_batch<const TRANSACTION: bool>(
+    batch: &WriteBatchWithTransaction<TRANSACTION>,
+    cf_names: &BTreeMap<u32, String>,
+) -> Result<BatchSummary, Error> {
+    let ops = decode_batch_ops(batch.data()).ok_or_else(|| {
+        Error::new("Invalid argument: the write batch cannot be decoded".to_owned())
+    })?;
+    let mut summary = BatchSummary {
+        size_in_bytes: batch.size_in_bytes(),
+        ..BatchSummary::default()
+    };
+    for (cf_id, op) in ops {
+        let counts = match cf_names.get(&cf_id) {
+            Some(name) => summary.column_families.entry(name.clone()).or_default(),
+            None => summary.unknown_cf_ids.entry(cf_id).or_default(),
+        };
+        match op {
+            BatchOp::Put { .. } => counts.puts += 1,
+            BatchOp::Delete { .. } => counts.deletes += 1,
+            BatchOp::Merge { .. } => counts.merges += 1,
+            BatchOp::DeleteRange { .. } => counts.delete_ranges += 1,
+            BatchOp::Other { .. } => counts.others += 1,
+        }
+    }
+    Ok(summary)
+}
+
 impl<const TRANSACTION: bool> Default for WriteBatchWithTransaction<TRANSACTION> {
     fn default() -> Self {
-        Self {
-            inner: unsafe { ffi::rocksdb_writebatch_create() },
-        }
+        Self::from_raw(unsafe { ffi::rocksdb_writebatch_create() })
     }
 }
 
@@ -319,3 +627,121 @@ impl<const TRANSACTION: bool> Drop for WriteBatchWithTransaction<TRANSACTION> {
 }
 
 unsafe impl<const TRANSACTION: bool> Send for WriteBatchWithTransaction<TRANSACTION> {}
+
+/// An operation of a serialized write batch, see [`decode_batch_ops`].
+pub(crate) enum BatchOp<'a> {
+    Put {
+        key: &'a [u8],
+        value: &'a [u8],
+    },
+    /// A delete or a single delete.
+    Delete {
+        key: &'a [u8],
+    },
+    Merge {
+        key: &'a [u8],
+        value: &'a [u8],
+    },
+    DeleteRange {
+        from: &'a [u8],
+        to: &'a [u8],
+    },
+    /// A write of `key` with a value RocksDB stores elsewhere or in another
+    /// format, e.g. a blob index or a wide-column entity.
+    Other {
+        key: &'a [u8],
+    },
+}
+
+/// Decodes the serialized write batch `data` into the column family id and the
+/// operation of each entry that is assigned a sequence number, in order, so the
+/// `n`th operation is written at the sequence number of the batch plus `n`.
+/// Markers such as log data and the ones of two-phase commit are skipped.
+///
+/// The C API has no way to decode a batch by column family, so this parses
+/// RocksDB's internal `WriteBatch` format as of the version bundled with
+/// `librocksdb-sys` (9.0), and handles every record tag of that version. Check
+/// the tags again when upgrading RocksDB.
+///
+/// Returns `None` if `data` is malformed or holds a record tag unknown here.
+pub(crate) fn decode_batch_ops(data: &[u8]) -> Option<Vec<(u32, BatchOp<'_>)>> {
+    // the serialized batch starts with an 8-byte sequence number and a 4-byte count
+    const HEADER_LEN: usize = 12;
+
+    fn varint32(data: &mut &[u8]) -> Option<u32> {
+        let mut value = 0_u32;
+        for shift in (0..35).step_by(7) {
+            let (&byte, rest) = data.split_first()?;
+            *data = rest;
+            value |= u32::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn slice<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+        let len = varint32(data)? as usize;
+        if data.len() < len {
+            return None;
+        }
+        let (head, tail) = data.split_at(len);
+        *data = tail;
+        Some(head)
+    }
+
+    let mut data = data.get(HEADER_LEN..)?;
+    let mut ops = vec![];
+    while let Some((&tag, rest)) = data.split_first() {
+        data = rest;
+        // the tags of the column family variants carry the id of the column family
+        let cf = match tag {
+            0x4 | 0x5 | 0x6 | 0x8 | 0xE | 0x10 | 0x17 => varint32(&mut data)?,
+            _ => 0,
+        };
+        let op = match tag {
+            // a value
+            0x1 | 0x5 => BatchOp::Put {
+                key: slice(&mut data)?,
+                value: slice(&mut data)?,
+            },
+            // a deletion or single deletion
+            0x0 | 0x4 | 0x7 | 0x8 => BatchOp::Delete {
+                key: slice(&mut data)?,
+            },
+            // a merge
+            0x2 | 0x6 => BatchOp::Merge {
+                key: slice(&mut data)?,
+                value: slice(&mut data)?,
+            },
+            // a range deletion
+            0xE | 0xF => BatchOp::DeleteRange {
+                from: slice(&mut data)?,
+                to: slice(&mut data)?,
+            },
+            // a blob index or a wide-column entity
+            0x10 | 0x11 | 0x16 | 0x17 => {
+                let key = slice(&mut data)?;
+                slice(&mut data)?;
+                BatchOp::Other { key }
+            }
+            // log data, and the end, commit and rollback markers of a transaction
+            0x3 | 0xA | 0xB | 0xC => {
+                slice(&mut data)?;
+                continue;
+            }
+            // the commit marker of a transaction with a commit timestamp
+            0x15 => {
+                slice(&mut data)?;
+                slice(&mut data)?;
+                continue;
+            }
+            // a no-op, and the begin markers of a transaction
+            0x9 | 0xD | 0x12 | 0x13 => continue,
+            _ => return None,
+        };
+        ops.push((cf, op));
+    }
+    Some(ops)
+}
@@ -0,0 +1,120 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "bench-util")]
+
+mod util;
+
+use std::time::Duration;
+
+use rocksdb::{
+    bench_util::{Workload, WorkloadRunner},
+    statistics::{Statistics, Ticker},
+    ErrorKind, IteratorMode, Options, DB,
+};
+use util::DBPath;
+
+fn runner(workload: Workload, num_keys: u64, threads: usize) -> WorkloadRunner {
+    let mut runner = WorkloadRunner::new(workload);
+    runner.set_num_keys(num_keys);
+    runner.set_threads(threads);
+    runner.set_key_size(8);
+    runner.set_value_size(32);
+    runner
+}
+
+#[test]
+fn test_fill_workloads() {
+    let path = DBPath::new("_rust_rocksdb_test_fill_workloads");
+    let statistics = Statistics::new();
+    let mut opts = Options::with_statistics(&statistics);
+    opts.create_if_missing(true);
+    let db = DB::open(&opts, &path).unwrap();
+
+    let mut fill_seq = runner(Workload::FillSeq, 1000, 3);
+    fill_seq.set_statistics(&statistics);
+    let report = fill_seq.run(&db).unwrap();
+    assert_eq!(report.workload, Workload::FillSeq);
+    assert_eq!(report.ops, 1000);
+    assert_eq!(report.ticker(Ticker::NumberKeysWritten), 1000);
+    assert!(report.latency.p50 <= report.latency.p99);
+    assert!(report.latency.p99 <= report.latency.max);
+    assert!(report.ops_per_sec() > 0.0);
+    assert!(report.to_string().starts_with("fillseq"));
+
+    // every key of the key space was written once
+    let keys: Vec<_> = db
+        .iterator(IteratorMode::Start)
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(keys.len(), 1000);
+    assert_eq!(&*keys[0], b"00000000");
+    assert_eq!(&*keys[999], b"00000999");
+    assert_eq!(db.get(b"00000500").unwrap().unwrap().len(), 32);
+
+    let mut fill_random = runner(Workload::FillRandom, 100_000, 2);
+    fill_random.set_num_ops(500);
+    fill_random.set_disable_wal(true);
+    fill_random.set_statistics(&statistics);
+    let report = fill_random.run(&db).unwrap();
+    assert_eq!(report.ops, 500);
+    assert_eq!(report.ticker(Ticker::NumberKeysWritten), 500);
+    let count = db.iterator(IteratorMode::Start).count();
+    assert!(count > 1000 && count <= 1500, "{count} keys");
+
+    // the key space does not fit in the keys
+    let err = runner(Workload::FillSeq, 1_000_000_000, 1)
+        .run(&db)
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn test_read_workloads() {
+    let path = DBPath::new("_rust_rocksdb_test_read_workloads");
+    let db = DB::open_default(&path).unwrap();
+
+    // nothing to find yet
+    let report = runner(Workload::ReadRandom, 1000, 2).run(&db).unwrap();
+    assert_eq!(report.ops, 1000);
+    assert_eq!(report.found, 0);
+
+    runner(Workload::FillSeq, 1000, 1).run(&db).unwrap();
+    let mut read_random = runner(Workload::ReadRandom, 1000, 4);
+    read_random.set_num_ops(2001);
+    let report = read_random.run(&db).unwrap();
+    assert_eq!(report.ops, 2001);
+    assert_eq!(report.found, 2001);
+    assert_eq!(report.background_writes, 0);
+
+    let mut read_while_writing = runner(Workload::ReadWhileWriting, 1000, 2);
+    read_while_writing.set_write_rate_limit(10_000);
+    let report = read_while_writing.run(&db).unwrap();
+    assert_eq!(report.ops, 1000);
+    assert_eq!(report.found, 1000);
+    assert!(report.background_writes > 0);
+    assert_eq!(db.iterator(IteratorMode::Start).count(), 1000);
+}
+
+#[test]
+fn test_workload_rate_limit() {
+    let path = DBPath::new("_rust_rocksdb_test_workload_rate_limit");
+    let db = DB::open_default(&path).unwrap();
+
+    let mut fill_seq = runner(Workload::FillSeq, 100, 2);
+    fill_seq.set_rate_limit(1000);
+    let report = fill_seq.run(&db).unwrap();
+    assert_eq!(report.ops, 100);
+    // each thread writes 500 keys per second, the last of its 50 writes is due
+    // after 98ms
+    assert!(report.elapsed >= Duration::from_millis(98), "{report}");
+}
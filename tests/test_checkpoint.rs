@@ -16,7 +16,10 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{checkpoint::Checkpoint, Options, DB};
+use rocksdb::{
+    checkpoint::Checkpoint, BlockBasedOptions, Cache, ErrorKind, ImportColumnFamilyOptions,
+    IteratorMode, Options, DB,
+};
 use util::DBPath;
 
 #[test]
@@ -100,6 +103,128 @@ pub fn test_multi_checkpoints() {
     assert_eq!(cp.get(b"k6").unwrap().unwrap(), b"v6");
 }
 
+#[test]
+fn test_dump_column_family() {
+    const PATH_PREFIX: &str = "_rust_rocksdb_cp_dump_cf_";
+
+    let db_path = DBPath::new(&format!("{PATH_PREFIX}db"));
+    let dump_path = DBPath::new(&format!("{PATH_PREFIX}dump"));
+    let empty_dump_path = DBPath::new(&format!("{PATH_PREFIX}empty_dump"));
+    let target_path = DBPath::new(&format!("{PATH_PREFIX}target"));
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &db_path, ["data", "empty"]).unwrap();
+    let cf = db.cf_handle("data").unwrap();
+    for i in 0..50 {
+        db.put_cf(&cf, format!("k{i:02}"), format!("v{i}")).unwrap();
+    }
+    db.delete_cf(&cf, b"k10").unwrap();
+
+    let cp = Checkpoint::new(&db).unwrap();
+    let metadata = cp.dump_column_family(&cf, &dump_path).unwrap();
+    assert_eq!(metadata.db_comparator_name, "leveldb.BytewiseComparator");
+    assert_eq!(metadata.files.len(), 1);
+    for file in &metadata.files {
+        assert!(file.starts_with(&dump_path), "{}", file.display());
+        assert!(file.is_file(), "{}", file.display());
+    }
+
+    // the dump directory must not exist
+    let err = cp.dump_column_family(&cf, &dump_path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    let empty = db.cf_handle("empty").unwrap();
+    let empty_metadata = cp.dump_column_family(&empty, &empty_dump_path).unwrap();
+    assert!(empty_metadata.files.is_empty());
+
+    // the dumped files recreate the column family in another DB
+    #[cfg(feature = "multi-threaded-cf")]
+    let target = DB::open(&opts, &target_path).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut target = DB::open(&opts, &target_path).unwrap();
+    target
+        .create_cf_with_import(
+            "imported",
+            &Options::default(),
+            &ImportColumnFamilyOptions::default(),
+            &metadata,
+        )
+        .unwrap();
+    let imported = target.cf_handle("imported").unwrap();
+    assert_eq!(
+        target
+            .iterator_cf(&imported, IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>(),
+        db.iterator_cf(&cf, IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn test_open_checkpoint_read_only() {
+    const PATH_PREFIX: &str = "_rust_rocksdb_cp_read_only_";
+
+    let db_path = DBPath::new(&format!("{PATH_PREFIX}db"));
+    let cp_path = DBPath::new(&format!("{PATH_PREFIX}cp"));
+
+    let cache = Cache::new_lru_cache(8 << 20);
+    let mut block_based_opts = BlockBasedOptions::default();
+    block_based_opts.set_block_cache(&cache);
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_disable_auto_compactions(true);
+    opts.set_block_based_table_factory(&block_based_opts);
+    let db = DB::open(&opts, &db_path).unwrap();
+    for i in 0..1000 {
+        db.put(format!("key{i:04}"), [b'v'; 100]).unwrap();
+    }
+    db.flush().unwrap();
+    Checkpoint::new(&db)
+        .unwrap()
+        .create_checkpoint(&cp_path)
+        .unwrap();
+
+    let cp = db.open_checkpoint_read_only(&cp_path).unwrap();
+    // both DBs use the one cache
+    assert_eq!(
+        cp.block_cache_entry_stats().unwrap().cache_id,
+        db.block_cache_entry_stats().unwrap().cache_id
+    );
+
+    // the primary keeps taking writes, the checkpoint does not see them
+    db.put(b"key1000", b"new").unwrap();
+    assert_eq!(db.get(b"key1000").unwrap().unwrap(), b"new");
+    assert!(cp.get(b"key1000").unwrap().is_none());
+    assert!(cp.put(b"key1000", b"new").is_err());
+
+    // scans with the analytical read options do not fill the cache
+    let usage = cache.get_usage();
+    assert_eq!(
+        cp.iterator_opt(IteratorMode::Start, DB::analytical_read_options())
+            .count(),
+        1000
+    );
+    assert_eq!(cache.get_usage(), usage);
+
+    // point lookups do
+    for i in (0..1000).step_by(100) {
+        assert_eq!(cp.get(format!("key{i:04}")).unwrap().unwrap(), [b'v'; 100]);
+    }
+    assert!(cache.get_usage() > usage);
+    drop(cp);
+    assert_eq!(db.get(b"key0000").unwrap().unwrap(), [b'v'; 100]);
+
+    // there is no cache to share without one set in the block based options
+    let other_path = DBPath::new(&format!("{PATH_PREFIX}other"));
+    let other = DB::open_default(&other_path).unwrap();
+    let err = other.open_checkpoint_read_only(&cp_path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+}
+
 #[test]
 fn test_checkpoint_outlive_db() {
     let t = trybuild::TestCases::new();
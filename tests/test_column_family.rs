@@ -16,7 +16,11 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{ColumnFamilyDescriptor, MergeOperands, Options, DB, DEFAULT_COLUMN_FAMILY_NAME};
+use rocksdb::{
+    ColumnFamilyDescriptor, DBCompressionType, DBWithThreadMode, Durability, ErrorKind,
+    IteratorMode, MergeOperands, Options, SstFileWriter, WriteBatch, WriteBatchIteratorCf,
+    WriteOptions, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
 use rocksdb::{TransactionDB, TransactionDBOptions};
 use util::DBPath;
 
@@ -488,8 +492,8 @@ fn test_no_leaked_column_family() {
         {
             let outlived_cf = outlived_cf.unwrap();
             assert_eq!(
-                &db.get_cf(&outlived_cf, "k0").unwrap().unwrap(),
-                &large_blob
+                db.get_cf(&outlived_cf, "k0").unwrap_err().kind(),
+                ErrorKind::ColumnFamilyDropped
             );
             drop(outlived_cf);
         }
@@ -498,3 +502,435 @@ fn test_no_leaked_column_family() {
         drop(db);
     }
 }
+
+#[test]
+fn test_drop_cf_while_iterating() {
+    let n = DBPath::new("_rust_rocksdb_drop_cf_while_iterating");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let db = DBWithThreadMode::<rocksdb::MultiThreaded>::open(&opts, &n).unwrap();
+        db.create_cf("cf1", &Options::default()).unwrap();
+        let cf = db.cf_handle("cf1").unwrap();
+        for i in 0..100 {
+            db.put_cf(&cf, format!("k{i:03}"), format!("v{i}")).unwrap();
+        }
+
+        let mut iter = db.iterator_cf(&cf, IteratorMode::Start);
+        for _ in 0..10 {
+            iter.next().unwrap().unwrap();
+        }
+        db.drop_cf("cf1").unwrap();
+        assert!(db.cf_handle("cf1").is_none());
+
+        // the iterator keeps reading over its implicit snapshot
+        assert_eq!(iter.map(Result::unwrap).count(), 90);
+
+        // other operations through the outlived handle fail
+        let err = db.get_cf(&cf, "k000").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+        let err = db.put_cf(&cf, "k000", "v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+        let err = db.delete_cf(&cf, "k000").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ColumnFamilyDropped);
+    }
+
+    let cfs = DB::list_cf(&Options::default(), &n).unwrap();
+    assert_eq!(cfs, vec![DEFAULT_COLUMN_FAMILY_NAME]);
+}
+
+#[test]
+fn test_create_and_drop_cfs() {
+    let n = DBPath::new("_rust_rocksdb_create_and_drop_cfs");
+    let names: Vec<_> = (0..50).map(|i| format!("cf{i:02}")).collect();
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+
+        #[cfg(feature = "multi-threaded-cf")]
+        let db = DB::open(&opts, &n).unwrap();
+        #[cfg(not(feature = "multi-threaded-cf"))]
+        let mut db = DB::open(&opts, &n).unwrap();
+
+        db.create_cfs(&names, &Options::default()).unwrap();
+        for name in &names {
+            let cf = db.cf_handle(name).unwrap();
+            db.put_cf(&cf, name, "v").unwrap();
+            assert_eq!(db.get_cf(&cf, name).unwrap().unwrap(), b"v");
+        }
+
+        // creation stops at the existing column family
+        let err = db
+            .create_cfs(&["new1", "cf00", "new2"], &Options::default())
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("not created: cf00, new2"),
+            "{}",
+            err
+        );
+        assert!(db.cf_handle("new1").is_some());
+        assert!(db.cf_handle("new2").is_none());
+
+        db.drop_cfs(&names[..25]).unwrap();
+        let err = db.drop_cfs(&["cf00", "cf49"]).unwrap_err();
+        assert!(err.to_string().contains("cf00"), "{}", err);
+        assert!(db.cf_handle("cf49").is_none());
+    }
+
+    let mut cfs = DB::list_cf(&Options::default(), &n).unwrap();
+    cfs.sort();
+    let mut expected: Vec<_> = names[25..49].to_vec();
+    expected.push("new1".to_owned());
+    expected.push(DEFAULT_COLUMN_FAMILY_NAME.to_owned());
+    expected.sort();
+    assert_eq!(cfs, expected);
+}
+
+#[test]
+fn test_copy_cf() {
+    let n = DBPath::new("_rust_rocksdb_copy_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+
+    #[cfg(feature = "multi-threaded-cf")]
+    let db = DB::open_cf(&opts, &n, ["data"]).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut db = DB::open_cf(&opts, &n, ["data"]).unwrap();
+
+    {
+        let data = db.cf_handle("data").unwrap();
+        db.put_cf(&data, b"k1", b"flushed").unwrap();
+        db.put_cf(&data, b"k2", b"flushed").unwrap();
+        db.flush_cf(&data).unwrap();
+        db.put_cf(&data, b"k2", b"unflushed").unwrap();
+        db.put_cf(&data, b"k3", b"unflushed").unwrap();
+        db.delete_cf(&data, b"k1").unwrap();
+    }
+
+    db.copy_cf("data", "data_v2", &Options::default()).unwrap();
+    assert!(db
+        .copy_cf("missing", "data_v3", &Options::default())
+        .is_err());
+
+    let data = db.cf_handle("data").unwrap();
+    let data_v2 = db.cf_handle("data_v2").unwrap();
+    let entries = |cf| {
+        db.iterator_cf(cf, IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entries(&data), entries(&data_v2));
+    assert_eq!(db.get_cf(&data_v2, b"k1").unwrap(), None);
+    assert_eq!(db.get_cf(&data_v2, b"k2").unwrap().unwrap(), b"unflushed");
+
+    db.put_cf(&data, b"k4", b"source").unwrap();
+    db.put_cf(&data_v2, b"k2", b"copy").unwrap();
+    assert_eq!(db.get_cf(&data_v2, b"k4").unwrap(), None);
+    assert_eq!(db.get_cf(&data, b"k2").unwrap().unwrap(), b"unflushed");
+    assert_eq!(db.get_cf(&data_v2, b"k2").unwrap().unwrap(), b"copy");
+}
+
+#[test]
+fn test_cf_durability_policy() {
+    let n = DBPath::new("_rust_rocksdb_cf_durability_policy");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &n, ["durable", "scratch"]).unwrap();
+    let durable = db.cf_handle("durable").unwrap();
+    let scratch = db.cf_handle("scratch").unwrap();
+    let default = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    let mut no_wal = WriteOptions::default();
+    no_wal.disable_wal(true);
+    let wal = WriteOptions::default();
+
+    db.set_cf_durability_policy(&durable, Durability::WalRequired);
+    db.set_cf_durability_policy(&scratch, Durability::WalForbidden);
+    db.set_cf_durability_policy(&default, Durability::WalRequired);
+
+    // direct writes
+    db.put_cf_opt(&durable, b"k", b"v", &wal).unwrap();
+    let err = db.put_cf_opt(&durable, b"k", b"v", &no_wal).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.delete_cf_opt(&durable, b"k", &no_wal).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    db.put_cf_opt(&scratch, b"k", b"v", &no_wal).unwrap();
+    let err = db.merge_cf_opt(&scratch, b"k", b"v", &wal).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    // the default column family with and without a handle
+    let err = db.put_cf_opt(&default, b"k", b"v", &no_wal).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.put_opt(b"k", b"v", &no_wal).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    db.put(b"k", b"v").unwrap();
+
+    // batches are checked against every column family they write to
+    let mut batch = WriteBatch::default();
+    batch.put_cf(&scratch, b"k1", b"v1");
+    db.write_without_wal(batch).unwrap();
+
+    let mut batch = WriteBatch::default();
+    batch.put_cf(&scratch, b"k2", b"v2");
+    batch.delete_cf(&durable, b"k");
+    let err = db.write_without_wal(batch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert_eq!(db.get_cf(&scratch, b"k2").unwrap(), None);
+
+    let mut batch = WriteBatch::default();
+    batch.put(b"k2", b"v2");
+    let data = batch.data().to_vec();
+    let err = db.write_without_wal(batch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // a batch built from serialized data is decoded and checked the same way
+    let err = db
+        .write_without_wal(WriteBatch::from_data(&data))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // removing the policies accepts any write again
+    db.set_cf_durability_policy(&scratch, Durability::DontCare);
+    db.write(WriteBatch::from_data(&data)).unwrap();
+    db.put_cf_opt(&scratch, b"k", b"v", &wal).unwrap();
+    assert_eq!(db.get(b"k2").unwrap(), Some(b"v2".to_vec()));
+}
+
+#[test]
+fn test_freeze_cf() {
+    let n = DBPath::new("_rust_rocksdb_freeze_cf");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_freeze_cf")
+        .tempdir()
+        .expect("Failed to create temporary path for the SST file.");
+    let sst_path = dir.path().join("ingest.sst");
+    {
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(&sst_path).unwrap();
+        writer.put(b"i", b"v").unwrap();
+        writer.finish().unwrap();
+    }
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &n, ["migrated", "live"]).unwrap();
+    let migrated = db.cf_handle("migrated").unwrap();
+    let live = db.cf_handle("live").unwrap();
+    db.put_cf(&migrated, b"k", b"v").unwrap();
+
+    db.freeze_cf(&migrated);
+    assert!(db.is_cf_frozen(&migrated));
+    assert!(!db.is_cf_frozen(&live));
+
+    // direct writes and ingestions fail
+    let err = db.put_cf(&migrated, b"k", b"v2").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.delete_cf(&migrated, b"k").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.delete_range_cf(&migrated, b"a", b"z").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db
+        .ingest_external_file_cf(&migrated, vec![&sst_path])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    db.put_cf(&live, b"k", b"v").unwrap();
+
+    // a batch touching the frozen column family is rejected as a whole
+    let mut batch = WriteBatch::default();
+    batch.put_cf(&live, b"k1", b"v1");
+    batch.delete_cf(&migrated, b"k");
+    let data = batch.data().to_vec();
+    let err = db.write(batch).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert_eq!(db.get_cf(&live, b"k1").unwrap(), None);
+    let err = db.write(WriteBatch::from_data(&data)).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // reads, flushes and compactions proceed
+    assert_eq!(db.get_cf(&migrated, b"k").unwrap(), Some(b"v".to_vec()));
+    db.flush_cf(&migrated).unwrap();
+    db.compact_range_cf(&migrated, None::<&[u8]>, None::<&[u8]>);
+    assert_eq!(db.get_cf(&migrated, b"k").unwrap(), Some(b"v".to_vec()));
+
+    db.unfreeze_cf(&migrated);
+    assert!(!db.is_cf_frozen(&migrated));
+    db.write(WriteBatch::from_data(&data)).unwrap();
+    assert_eq!(db.get_cf(&migrated, b"k").unwrap(), None);
+    db.ingest_external_file_cf(&migrated, vec![&sst_path])
+        .unwrap();
+    assert_eq!(db.get_cf(&migrated, b"i").unwrap(), Some(b"v".to_vec()));
+}
+
+#[test]
+fn test_cf_stats_snapshot() {
+    let n = DBPath::new("_rust_rocksdb_cf_stats_snapshot");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    opts.set_compression_type(DBCompressionType::None);
+    let db = DB::open_cf(&opts, &n, ["metrics"]).unwrap();
+    let cf = db.cf_handle("metrics").unwrap();
+
+    let before = db.cf_stats_snapshot(&cf).unwrap();
+    assert_eq!(before.num_files.first().copied().unwrap_or(0), 0);
+
+    for i in 0..4000 {
+        db.put_cf(&cf, format!("key{i:05}"), vec![b'v'; 1000])
+            .unwrap();
+    }
+    // flushing other column families leaves the memtables of `cf` alone
+    db.put(b"other", vec![b'v'; 1000]).unwrap();
+    db.flush().unwrap();
+    let written = db.cf_stats_snapshot(&cf).unwrap();
+    assert!(written.memtable_bytes > before.memtable_bytes);
+    assert_eq!(written.num_files.first().copied().unwrap_or(0), 0);
+    db.flush_cf(&cf).unwrap();
+
+    let flushed = db.cf_stats_snapshot(&cf).unwrap();
+    assert_eq!(flushed.num_files[0], 1);
+    assert_eq!(flushed.num_files.len(), flushed.level_bytes.len());
+    assert!(flushed.level_bytes[0] > 0);
+    assert!(flushed.memtable_bytes < written.memtable_bytes);
+
+    // a compaction moves the file down
+    db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+    let compacted = db.cf_stats_snapshot(&cf).unwrap();
+    assert_eq!(compacted.num_files[0], 0);
+    assert_eq!(compacted.num_files.iter().sum::<u64>(), 1);
+    assert_eq!(compacted.pending_compaction_bytes, 0);
+}
+
+#[test]
+fn test_cf_handles() {
+    let n = DBPath::new("_rust_rocksdb_cf_handles");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &n, ["cf1", "cf2", "cf3"]).unwrap();
+
+    let names: Vec<_> = db.cf_handles().map(|(name, _)| name.to_string()).collect();
+    assert_eq!(names, ["cf1", "cf2", "cf3", DEFAULT_COLUMN_FAMILY_NAME]);
+
+    // the handles can be used for bulk operations
+    for (name, cf) in db.cf_handles() {
+        db.put_cf(&cf, b"k", name.as_bytes()).unwrap();
+        db.flush_cf(&cf).unwrap();
+    }
+    assert_eq!(db.live_files().unwrap().len(), 4);
+    let cf2 = db.cf_handle("cf2").unwrap();
+    assert_eq!(db.get_cf(&cf2, b"k").unwrap().unwrap(), b"cf2");
+}
+
+/// Rebuilds a received batch against the column families of `db`, by id.
+struct Replayer<'a> {
+    db: &'a DB,
+    batch: WriteBatch,
+    skipped: usize,
+}
+
+impl<'a> Replayer<'a> {
+    fn new(db: &'a DB) -> Self {
+        Self {
+            db,
+            batch: WriteBatch::default(),
+            skipped: 0,
+        }
+    }
+}
+
+impl<'a> WriteBatchIteratorCf for Replayer<'a> {
+    fn put_cf(&mut self, cf_id: u32, key: &[u8], value: &[u8]) {
+        match self.db.cf_handle_by_id(cf_id) {
+            Some(cf) => self.batch.put_cf(&cf, key, value),
+            None => self.skipped += 1,
+        }
+    }
+
+    fn delete_cf(&mut self, cf_id: u32, key: &[u8]) {
+        match self.db.cf_handle_by_id(cf_id) {
+            Some(cf) => self.batch.delete_cf(&cf, key),
+            None => self.skipped += 1,
+        }
+    }
+
+    fn merge_cf(&mut self, cf_id: u32, key: &[u8], value: &[u8]) {
+        match self.db.cf_handle_by_id(cf_id) {
+            Some(cf) => self.batch.merge_cf(&cf, key, value),
+            None => self.skipped += 1,
+        }
+    }
+
+    fn delete_range_cf(&mut self, cf_id: u32, from: &[u8], to: &[u8]) {
+        match self.db.cf_handle_by_id(cf_id) {
+            Some(cf) => self.batch.delete_range_cf(&cf, from, to),
+            None => self.skipped += 1,
+        }
+    }
+}
+
+#[test]
+fn test_cf_handle_by_id() {
+    let n = DBPath::new("_rust_rocksdb_cf_handle_by_id");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+
+    #[cfg(feature = "multi-threaded-cf")]
+    let db = DB::open_cf(&opts, &n, ["a", "b"]).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut db = DB::open_cf(&opts, &n, ["a", "b"]).unwrap();
+
+    let a_id = db.cf_handle("a").unwrap().id();
+    let b_id = db.cf_handle("b").unwrap().id();
+    assert_eq!(db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap().id(), 0);
+    assert!(a_id != 0 && b_id != 0 && a_id != b_id);
+    for id in [0, a_id, b_id] {
+        assert_eq!(db.cf_handle_by_id(id).unwrap().id(), id);
+    }
+    assert!(db.cf_handle_by_id(a_id.max(b_id) + 1).is_none());
+
+    // a batch received from elsewhere, e.g. a node with the same column families
+    let mut batch = WriteBatch::default();
+    {
+        let a = db.cf_handle("a").unwrap();
+        let b = db.cf_handle("b").unwrap();
+        db.put_cf(&b, b"k3", b"old").unwrap();
+        batch.put(b"k0", b"v0");
+        batch.put_cf(&a, b"k1", b"v1");
+        batch.delete_range_cf(&b, b"k", b"l");
+        batch.put_cf(&b, b"k2", b"v2");
+    }
+
+    let mut replayer = Replayer::new(&db);
+    batch.iterate_cf(&mut replayer).unwrap();
+    assert_eq!(replayer.skipped, 0);
+    assert_eq!(replayer.batch.data(), batch.data());
+    db.write(replayer.batch).unwrap();
+    {
+        let a = db.cf_handle("a").unwrap();
+        let b = db.cf_handle("b").unwrap();
+        assert_eq!(db.get(b"k0").unwrap().unwrap(), b"v0");
+        assert_eq!(db.get_cf(&a, b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get_cf(&b, b"k2").unwrap().unwrap(), b"v2");
+        assert!(db.get_cf(&b, b"k3").unwrap().is_none());
+    }
+
+    // a column family created again under the same name gets a new id
+    db.drop_cf("b").unwrap();
+    assert!(db.cf_handle_by_id(b_id).is_none());
+    db.create_cf("b", &Options::default()).unwrap();
+    let new_b_id = db.cf_handle("b").unwrap().id();
+    assert_ne!(new_b_id, b_id);
+    assert_eq!(db.cf_handle_by_id(new_b_id).unwrap().id(), new_b_id);
+    assert_eq!(db.cf_handle_by_id(a_id).unwrap().id(), a_id);
+
+    // so the operations on the old one no longer resolve
+    let mut replayer = Replayer::new(&db);
+    batch.iterate_cf(&mut replayer).unwrap();
+    assert_eq!(replayer.skipped, 2);
+    assert_eq!(replayer.batch.len(), 2);
+}
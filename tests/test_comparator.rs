@@ -1,6 +1,12 @@
-use rocksdb::{Options, DB};
+mod util;
+
+use rocksdb::{
+    ColumnFamilyDescriptor, ErrorKind, MergeOperands, Options, OptionsMismatch, ReadOptions,
+    U64Comparator, U64Timestamp, WriteValidationOptions, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
 use std::cmp::Ordering;
 use std::iter::FromIterator;
+use util::DBPath;
 
 /// This function is for ensuring test of backwards compatibility
 pub fn rocks_old_compare(one: &[u8], two: &[u8]) -> Ordering {
@@ -72,3 +78,244 @@ fn test_comparator() {
     );
     assert_eq!(vec!["b-key", "a-key"], res_closure_reverse);
 }
+
+fn concat_merge(_key: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    let mut result = existing.map(<[u8]>::to_vec).unwrap_or_default();
+    operands.iter().for_each(|op| result.extend_from_slice(op));
+    Some(result)
+}
+
+#[test]
+fn test_validate_against_existing() {
+    let path = DBPath::new("_rust_rocksdb_validate_against_existing");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_comparator("cmp-a", Box::new(|a: &[u8], b: &[u8]| a.cmp(b)));
+        opts.set_merge_operator_associative("concat", concat_merge);
+        let db = DB::open_cf_descriptors(
+            &opts,
+            &path,
+            [ColumnFamilyDescriptor::new(
+                DEFAULT_COLUMN_FAMILY_NAME,
+                opts.clone(),
+            )],
+        )
+        .unwrap();
+        let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        assert_eq!(db.comparator_name_cf(&cf).unwrap(), "cmp-a");
+        assert_eq!(
+            db.merge_operator_name_cf(&cf).unwrap().as_deref(),
+            Some("concat")
+        );
+        assert!(opts.validate_against_existing(&path).unwrap().is_empty());
+    }
+
+    let mut opts = Options::default();
+    opts.set_comparator("cmp-b", Box::new(|a: &[u8], b: &[u8]| a.cmp(b)));
+    let mismatches = opts.validate_against_existing(&path).unwrap();
+    assert_eq!(
+        mismatches,
+        vec![
+            OptionsMismatch {
+                column_family: "default".to_owned(),
+                field: "comparator",
+                configured: Some("cmp-b".to_owned()),
+                existing: Some("cmp-a".to_owned()),
+            },
+            OptionsMismatch {
+                column_family: "default".to_owned(),
+                field: "merge_operator",
+                configured: None,
+                existing: Some("concat".to_owned()),
+            },
+        ]
+    );
+    // RocksDB itself only notices the comparator mismatch when opening.
+    assert!(DB::open(&opts, &path).is_err());
+}
+
+#[test]
+fn test_plugin_names_of_created_cf() {
+    let path = DBPath::new("_rust_rocksdb_plugin_names_of_created_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    let mut db = DB::open_cf(&opts, &path, [DEFAULT_COLUMN_FAMILY_NAME]).unwrap();
+
+    let mut cf_opts = Options::default();
+    cf_opts.set_comparator("cmp-a", Box::new(|a: &[u8], b: &[u8]| a.cmp(b)));
+    cf_opts.set_merge_operator_associative("concat", concat_merge);
+    db.create_cf("cf", &cf_opts).unwrap();
+
+    let cf = db.cf_handle("cf").unwrap();
+    assert_eq!(db.comparator_name_cf(&cf).unwrap(), "cmp-a");
+    assert_eq!(
+        db.merge_operator_name_cf(&cf).unwrap().as_deref(),
+        Some("concat")
+    );
+    let default = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    assert_eq!(
+        db.comparator_name_cf(&default).unwrap(),
+        "leveldb.BytewiseComparator"
+    );
+    assert_eq!(db.merge_operator_name_cf(&default).unwrap(), None);
+}
+
+#[test]
+fn test_comparator_with_ts() {
+    let path = DBPath::new("_rust_rocksdb_comparator_with_ts");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_comparator_with_ts(
+        U64Comparator::NAME,
+        U64Timestamp::SIZE,
+        Box::new(U64Comparator::compare),
+        Box::new(U64Comparator::compare_ts),
+        Box::new(U64Comparator::compare_without_ts),
+    );
+    let db = DB::open(&opts, &path).unwrap();
+    db.put_with_ts(b"b", U64Timestamp::new(1), b"b1").unwrap();
+    db.put_with_ts(b"a", U64Timestamp::new(1), b"a1").unwrap();
+    db.put_with_ts(b"a", U64Timestamp::new(2), b"a2").unwrap();
+
+    let read_at = |ts: u64| {
+        let mut readopts = ReadOptions::default();
+        readopts.set_timestamp(U64Timestamp::new(ts).as_ref());
+        db.get_opt(b"a", &readopts).unwrap()
+    };
+    assert_eq!(read_at(1).unwrap(), b"a1");
+    assert_eq!(read_at(2).unwrap(), b"a2");
+
+    // Iterate over every version: keys in order, newest version first.
+    let mut readopts = ReadOptions::default();
+    readopts.set_timestamp(U64Timestamp::new(u64::MAX).as_ref());
+    readopts.set_iter_start_ts(U64Timestamp::new(0).as_ref());
+    let mut iter = db.raw_iterator_opt(readopts);
+    iter.seek_to_first();
+    let mut versions = vec![];
+    while iter.valid() {
+        // With iter_start_ts set, keys are internal keys: the user key followed by
+        // the timestamp and the 8-byte sequence number and type.
+        let key = iter.key().unwrap();
+        versions.push((
+            key[..key.len() - U64Timestamp::SIZE - 8].to_vec(),
+            U64Timestamp::decode(iter.timestamp().unwrap()).unwrap(),
+            iter.value().unwrap().to_vec(),
+        ));
+        iter.next();
+    }
+    assert_eq!(
+        versions,
+        vec![
+            (b"a".to_vec(), 2, b"a2".to_vec()),
+            (b"a".to_vec(), 1, b"a1".to_vec()),
+            (b"b".to_vec(), 1, b"b1".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_comparator_with_ts_write_validation() {
+    let path = DBPath::new("_rust_rocksdb_comparator_with_ts_write_validation");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_comparator_with_ts(
+        U64Comparator::NAME,
+        U64Timestamp::SIZE,
+        Box::new(U64Comparator::compare),
+        Box::new(U64Comparator::compare_ts),
+        Box::new(U64Comparator::compare_without_ts),
+    );
+    opts.set_write_validation(WriteValidationOptions {
+        max_value_bytes: Some(8),
+        reject_empty_keys: true,
+        ..WriteValidationOptions::default()
+    });
+    let db = DB::open(&opts, &path).unwrap();
+    let ts = U64Timestamp::new(1);
+
+    let err = db.put_with_ts(b"k", ts, vec![b'v'; 16]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.put_with_ts(b"", ts, b"v").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    let err = db.delete_with_ts(b"", ts).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    db.put_with_ts(b"k", ts, b"v").unwrap();
+    db.delete_with_ts(b"k", U64Timestamp::new(2)).unwrap();
+}
+
+#[test]
+fn test_u64_comparator_short_keys() {
+    let ts = U64Timestamp::new(1);
+    let key = [b"a".as_ref(), ts.as_ref()].concat();
+    // keys too short to carry a timestamp are compared bytewise, not a panic
+    assert_eq!(U64Comparator::compare(b"", b"a"), Ordering::Less);
+    assert_eq!(U64Comparator::compare(b"zz", &key), Ordering::Greater);
+    assert_eq!(
+        U64Comparator::compare_without_ts(b"a", true, &key, true),
+        Ordering::Equal
+    );
+}
+
+#[test]
+fn test_full_history_ts_low() {
+    let path = DBPath::new("_rust_rocksdb_full_history_ts_low");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_comparator_with_ts(
+        U64Comparator::NAME,
+        U64Timestamp::SIZE,
+        Box::new(U64Comparator::compare),
+        Box::new(U64Comparator::compare_ts),
+        Box::new(U64Comparator::compare_without_ts),
+    );
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    for ts in 1..=3 {
+        db.put_with_ts(b"a", U64Timestamp::new(ts), format!("a{ts}"))
+            .unwrap();
+        db.flush().unwrap();
+    }
+    assert_eq!(db.get_full_history_ts_low_cf(&cf).unwrap(), b"");
+
+    let read_at = |ts: u64| {
+        let mut readopts = ReadOptions::default();
+        readopts.set_timestamp(U64Timestamp::new(ts).as_ref());
+        db.get_opt(b"a", &readopts)
+    };
+    let versions = || {
+        let mut readopts = ReadOptions::default();
+        readopts.set_timestamp(U64Timestamp::new(u64::MAX).as_ref());
+        readopts.set_iter_start_ts(U64Timestamp::new(0).as_ref());
+        let mut iter = db.raw_iterator_opt(readopts);
+        iter.seek_to_first();
+        let mut values = vec![];
+        while iter.valid() {
+            values.push(iter.value().unwrap().to_vec());
+            iter.next();
+        }
+        values
+    };
+    assert_eq!(read_at(1).unwrap().unwrap(), b"a1");
+    assert_eq!(
+        versions(),
+        vec![b"a3".to_vec(), b"a2".to_vec(), b"a1".to_vec()]
+    );
+
+    db.increase_full_history_ts_low_cf(&cf, U64Timestamp::new(4))
+        .unwrap();
+    assert_eq!(
+        db.get_full_history_ts_low_cf(&cf).unwrap(),
+        U64Timestamp::new(4).as_ref()
+    );
+    let err = db
+        .increase_full_history_ts_low_cf(&cf, U64Timestamp::new(2))
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // the older versions are collected by the next compaction
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    assert_eq!(versions(), vec![b"a3".to_vec()]);
+    assert_eq!(read_at(1).unwrap_err().kind(), ErrorKind::InvalidArgument);
+    assert_eq!(read_at(4).unwrap().unwrap(), b"a3");
+}
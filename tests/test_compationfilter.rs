@@ -14,9 +14,27 @@
 
 mod util;
 
+use std::{
+    collections::BTreeMap,
+    ffi::{CStr, CString},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
 use pretty_assertions::assert_eq;
 
-use rocksdb::{CompactionDecision, Options, DB};
+use rocksdb::{
+    compaction_filter::CompactionFilter,
+    compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory},
+    properties,
+    table_properties::{
+        EntryType, TablePropertiesCollector, TablePropertiesCollectorFactory,
+        TablePropertiesCollectorFactoryContext,
+    },
+    CompactionDecision, Options, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
 use util::DBPath;
 
 #[cfg(test)]
@@ -47,3 +65,179 @@ fn compaction_filter_test() {
         assert_eq!(&*db.get(b"%k").unwrap().unwrap(), b"secret");
     }
 }
+
+fn expire_filter(_level: u32, key: &[u8], _value: &[u8]) -> CompactionDecision {
+    if key.starts_with(b"expired") {
+        CompactionDecision::Remove
+    } else {
+        CompactionDecision::Keep
+    }
+}
+
+#[test]
+fn compaction_filter_blob_files_test() {
+    let path = DBPath::new("_rust_rocksdb_filter_blob_files_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_enable_blob_files(true);
+    opts.set_min_blob_size(0);
+    opts.set_enable_blob_gc(true);
+    opts.set_blob_gc_age_cutoff(1.0);
+    opts.set_compaction_filter("expire", expire_filter);
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        let value = vec![0x42; 1024];
+        for i in 0..100 {
+            db.put(format!("expired{i:03}"), &value).unwrap();
+        }
+        db.flush().unwrap();
+        db.put(b"live", b"value").unwrap();
+        db.flush().unwrap();
+
+        let blob_files = |db: &DB| db.property_int_value(properties::NUM_BLOB_FILES).unwrap();
+        let blob_size = |db: &DB| {
+            db.property_int_value(properties::LIVE_BLOB_FILE_SIZE)
+                .unwrap()
+                .unwrap()
+        };
+        assert_eq!(blob_files(&db), Some(2));
+        let size_before = blob_size(&db);
+
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+        assert!(db.get(b"expired000").unwrap().is_none());
+        assert!(db.get(b"expired099").unwrap().is_none());
+        assert_eq!(&*db.get(b"live").unwrap().unwrap(), b"value");
+
+        // the blob file holding only expired values has been dropped
+        assert_eq!(blob_files(&db), Some(1));
+        assert!(blob_size(&db) < size_before);
+    }
+}
+
+/// Marks a table file as "all-fresh" if all of its keys start with `fresh:`.
+struct FreshnessCollector {
+    all_fresh: bool,
+}
+
+impl TablePropertiesCollector for FreshnessCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"freshness-collector\0").unwrap()
+    }
+
+    fn add_user_key(&mut self, key: &[u8], _: &[u8], _: EntryType, _: u64, _: u64) {
+        self.all_fresh &= key.starts_with(b"fresh:");
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            b"all-fresh".to_vec().into_boxed_slice(),
+            vec![u8::from(self.all_fresh)].into_boxed_slice(),
+        );
+        map
+    }
+}
+
+struct FreshnessCollectorFactory;
+
+impl TablePropertiesCollectorFactory for FreshnessCollectorFactory {
+    type Collector = FreshnessCollector;
+
+    fn create(&mut self, _: TablePropertiesCollectorFactoryContext) -> FreshnessCollector {
+        FreshnessCollector { all_fresh: true }
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"freshness-collector-factory\0").unwrap()
+    }
+}
+
+/// Removes every key, unless told to skip the compaction.
+struct RemoveAllFilter {
+    skip: bool,
+    name: CString,
+}
+
+impl CompactionFilter for RemoveAllFilter {
+    fn filter(&mut self, _level: u32, _key: &[u8], _value: &[u8]) -> CompactionDecision {
+        if self.skip {
+            CompactionDecision::Keep
+        } else {
+            CompactionDecision::Remove
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        &self.name
+    }
+}
+
+struct SkipFreshFactory {
+    skip: Arc<AtomicBool>,
+    name: CString,
+}
+
+impl CompactionFilterFactory for SkipFreshFactory {
+    type Filter = RemoveAllFilter;
+
+    fn create(&mut self, _context: CompactionFilterContext) -> RemoveAllFilter {
+        RemoveAllFilter {
+            skip: self.skip.load(Ordering::SeqCst),
+            name: CString::new("RemoveAllFilter").unwrap(),
+        }
+    }
+
+    fn name(&self) -> &CStr {
+        &self.name
+    }
+}
+
+#[test]
+fn compaction_filter_factory_input_properties_test() {
+    let path = DBPath::new("_rust_rocksdb_filter_factory_input_properties_test");
+    let skip = Arc::new(AtomicBool::new(false));
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.add_table_properties_collector_factory(FreshnessCollectorFactory);
+    opts.set_compaction_filter_factory(SkipFreshFactory {
+        skip: Arc::clone(&skip),
+        name: CString::new("SkipFreshFactory").unwrap(),
+    });
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    // decide about the next full compaction from the properties of its inputs
+    let prepare_compaction = || {
+        let names: Vec<_> = db
+            .live_files()
+            .unwrap()
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        let names: Vec<_> = names.iter().map(String::as_str).collect();
+        let properties = db.properties_for_files(&cf, &names).unwrap();
+        assert_eq!(properties.len(), names.len());
+        let all_fresh = properties
+            .values()
+            .all(|table| table.user_collected_properties()[&b"all-fresh"[..]][..] == [1]);
+        skip.store(all_fresh, Ordering::SeqCst);
+    };
+
+    db.put(b"fresh:a", b"1").unwrap();
+    db.flush().unwrap();
+    db.put(b"fresh:b", b"2").unwrap();
+    db.flush().unwrap();
+    prepare_compaction();
+    assert!(skip.load(Ordering::SeqCst));
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    assert_eq!(db.get(b"fresh:a").unwrap().unwrap(), b"1");
+    assert_eq!(db.get(b"fresh:b").unwrap().unwrap(), b"2");
+
+    db.put(b"stale:c", b"3").unwrap();
+    db.flush().unwrap();
+    prepare_compaction();
+    assert!(!skip.load(Ordering::SeqCst));
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    assert_eq!(db.get(b"fresh:a").unwrap(), None);
+    assert_eq!(db.get(b"stale:c").unwrap(), None);
+}
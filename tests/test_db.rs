@@ -15,18 +15,26 @@
 mod util;
 
 use std::convert::TryInto;
-use std::{mem, sync::Arc, thread, time::Duration};
+use std::{
+    collections::HashMap,
+    mem,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::statistics::{Histogram, StatsLevel, Ticker};
+use rocksdb::retry::RetryPolicy;
+use rocksdb::statistics::{Histogram, Statistics, StatsLevel, Ticker};
 use rocksdb::{
     perf::get_memory_usage_stats, BlockBasedOptions, BottommostLevelCompaction, Cache,
-    ColumnFamilyDescriptor, CompactOptions, CuckooTableOptions, DBAccess, DBCompactionStyle,
-    DBWithThreadMode, Env, Error, ErrorKind, FifoCompactOptions, IteratorMode, MultiThreaded,
-    Options, PerfContext, PerfMetric, ReadOptions, SingleThreaded, SliceTransform, Snapshot,
-    UniversalCompactOptions, UniversalCompactionStopStyle, WaitForCompactOptions, WriteBatch, DB,
-    DEFAULT_COLUMN_FAMILY_NAME,
+    ColumnFamilyDescriptor, CompactOptions, CompactionDecision, CuckooTableOptions, DBAccess,
+    DBCompactionStyle, DBWithThreadMode, Env, Error, ErrorKind, FifoCompactOptions, IteratorMode,
+    MultiThreaded, OpenError, Options, PerfContext, PerfMetric, ReadOptions, ReadTier,
+    SingleThreaded, SizeApproximationOptions, SliceTransform, Snapshot, TickTasks,
+    UniversalCompactOptions, UniversalCompactionStopStyle, WaitForCompactOptions, WriteBatch,
+    WriteOptions, WriteValidationOptions, DB, DEFAULT_COLUMN_FAMILY_NAME,
 };
 use util::{assert_iter, pair, DBPath};
 
@@ -405,6 +413,66 @@ fn get_statistics_test() {
     }
 }
 
+#[test]
+fn test_shared_statistics() {
+    let path1 = DBPath::new("_rust_rocksdb_test_shared_statistics_1");
+    let path2 = DBPath::new("_rust_rocksdb_test_shared_statistics_2");
+    {
+        let statistics = Statistics::new();
+        let mut opts = Options::with_statistics(&statistics);
+        opts.create_if_missing(true);
+        let db1 = DB::open(&opts, &path1).unwrap();
+        let db2 = DB::open(&opts.clone(), &path2).unwrap();
+
+        db1.put(b"key1", b"value").unwrap();
+        assert_eq!(statistics.get_ticker_count(Ticker::NumberKeysWritten), 1);
+        db2.put(b"key1", b"value").unwrap();
+        db2.put(b"key2", b"value").unwrap();
+        assert_eq!(statistics.get_ticker_count(Ticker::NumberKeysWritten), 3);
+        assert_eq!(
+            opts.statistics()
+                .unwrap()
+                .get_ticker_count(Ticker::NumberKeysWritten),
+            3
+        );
+        assert!(statistics
+            .to_string()
+            .contains("rocksdb.number.keys.written COUNT : 3"));
+
+        // options without statistics don't share anything
+        assert!(Options::default().statistics().is_none());
+    }
+}
+
+#[test]
+fn test_statistics_reset() {
+    let path = DBPath::new("_rust_rocksdb_test_statistics_reset");
+    {
+        let statistics = Statistics::new();
+        let mut opts = Options::with_statistics(&statistics);
+        opts.create_if_missing(true);
+        let db = DB::open(&opts, &path).unwrap();
+
+        for i in 0..10 {
+            db.put(format!("key{i}"), b"value").unwrap();
+        }
+        assert_eq!(statistics.get_ticker_count(Ticker::NumberKeysWritten), 10);
+
+        statistics.reset();
+        assert_eq!(statistics.get_ticker_count(Ticker::NumberKeysWritten), 0);
+        db.put(b"key10", b"value").unwrap();
+        db.put(b"key11", b"value").unwrap();
+        assert_eq!(statistics.get_ticker_count(Ticker::NumberKeysWritten), 2);
+        // clones share the reset
+        assert_eq!(
+            statistics
+                .clone()
+                .get_ticker_count(Ticker::NumberKeysWritten),
+            2
+        );
+    }
+}
+
 #[test]
 fn set_column_family_metadata_test() {
     let path = DBPath::new("_set_column_family_metadata_test");
@@ -446,6 +514,85 @@ fn test_sequence_number() {
     }
 }
 
+#[test]
+fn test_write_with_callback() {
+    let path = DBPath::new("_rust_rocksdb_test_write_with_callback");
+    let db = Arc::new(DB::open_default(&path).unwrap());
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    // plain writes run concurrently and take sequence numbers in between
+    let plain = {
+        let db = db.clone();
+        thread::spawn(move || {
+            for i in 0..200 {
+                db.put(format!("plain-k{i}"), b"v").unwrap();
+            }
+        })
+    };
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let db = db.clone();
+            let written = written.clone();
+            thread::spawn(move || {
+                let mut last = 0;
+                for i in 0..50 {
+                    let mut batch = WriteBatch::default();
+                    batch.put(format!("t{t}-k{i}-a"), b"v");
+                    batch.put(format!("t{t}-k{i}-b"), b"v");
+                    let data = batch.data()[12..].to_vec();
+                    let mut calls = 0;
+                    db.write_with_callback(batch, |seq| {
+                        calls += 1;
+                        assert!(seq > last);
+                        last = seq;
+                        written.lock().unwrap().push((seq, data));
+                    })
+                    .unwrap();
+                    assert_eq!(calls, 1);
+                }
+            })
+        })
+        .chain(std::iter::once(plain))
+        .collect();
+    for h in handles {
+        h.join().unwrap();
+    }
+    assert_eq!(db.latest_sequence_number(), 600);
+
+    // each reported sequence number is the one the WAL holds the batch at
+    let wal: HashMap<_, _> = db
+        .get_updates_since(0)
+        .unwrap()
+        .map(|update| {
+            let (seq, batch) = update.unwrap();
+            (seq, batch.data()[12..].to_vec())
+        })
+        .collect();
+    let written = written.lock().unwrap();
+    assert_eq!(written.len(), 200);
+    for (seq, data) in written.iter() {
+        assert_eq!(wal.get(seq), Some(data));
+    }
+
+    // sync writes report their sequence number, failed writes do not
+    let mut wo = rocksdb::WriteOptions::default();
+    wo.set_sync(true);
+    let mut batch = WriteBatch::default();
+    batch.put(b"k", b"v");
+    let mut reported = None;
+    db.write_with_callback_opt(batch, &wo, |seq| reported = Some(seq))
+        .unwrap();
+    assert_eq!(reported, Some(601));
+    wo.disable_wal(true);
+    let mut batch = WriteBatch::default();
+    batch.put(b"k", b"v");
+    let mut called = false;
+    assert!(db
+        .write_with_callback_opt(batch, &wo, |_| called = true)
+        .is_err());
+    assert!(!called);
+}
+
 struct OperationCounts {
     puts: usize,
     deletes: usize,
@@ -460,6 +607,170 @@ impl rocksdb::WriteBatchIterator for OperationCounts {
     }
 }
 
+#[test]
+fn test_snapshot_sequence_number() {
+    let path = DBPath::new("_rust_rocksdb_test_snapshot_sequence_number");
+    let db = DB::open_default(&path).unwrap();
+    db.put(b"key1", b"value1").unwrap();
+    db.put(b"key2", b"value2").unwrap();
+
+    let snapshot = db.snapshot();
+    let seq = snapshot.sequence_number();
+    assert_eq!(seq, db.latest_sequence_number());
+
+    db.put(b"key3", b"value3").unwrap();
+    let mut batch = WriteBatch::default();
+    batch.put(b"key4", b"value4");
+    batch.delete(b"key1");
+    db.write(batch).unwrap();
+    assert_eq!(snapshot.sequence_number(), seq);
+
+    // resume right after the snapshot
+    let updates: Vec<_> = db
+        .get_updates_since(seq)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(updates.len(), 2);
+    assert_eq!(updates[0].0, seq + 1);
+    assert_eq!(updates[1].0, seq + 2);
+    let mut counts = OperationCounts {
+        puts: 0,
+        deletes: 0,
+    };
+    for (_, batch) in &updates {
+        batch.iterate(&mut counts);
+    }
+    assert_eq!(counts.puts, 2);
+    assert_eq!(counts.deletes, 1);
+}
+
+#[test]
+fn test_get_at_seqno() {
+    let path = DBPath::new("_rust_rocksdb_test_get_at_seqno");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["cf1"]).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let cf1 = db.cf_handle("cf1").unwrap();
+
+    db.put(b"k", b"v0").unwrap();
+    let before_snapshot = db.latest_sequence_number();
+    db.put(b"other", b"x").unwrap();
+    let snapshot = db.snapshot();
+
+    let mut versions = vec![(snapshot.sequence_number(), Some(b"v0".to_vec()))];
+    for i in 1..=3 {
+        db.put(b"k", format!("v{i}")).unwrap();
+        // writes to other keys and column families are not replayed on `k`
+        db.put_cf(&cf1, b"k", b"cf1").unwrap();
+        versions.push((
+            db.latest_sequence_number() - 1,
+            Some(format!("v{i}").into_bytes()),
+        ));
+        versions.push((
+            db.latest_sequence_number(),
+            Some(format!("v{i}").into_bytes()),
+        ));
+    }
+    let mut batch = WriteBatch::default();
+    batch.put(b"other", b"y");
+    batch.delete(b"k");
+    db.write(batch).unwrap();
+    versions.push((db.latest_sequence_number() - 1, Some(b"v3".to_vec())));
+    versions.push((db.latest_sequence_number(), None));
+    db.put(b"k", b"v4").unwrap();
+    versions.push((db.latest_sequence_number(), Some(b"v4".to_vec())));
+
+    for (seqno, expected) in &versions {
+        assert_eq!(
+            &db.get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", *seqno)
+                .unwrap(),
+            expected
+        );
+    }
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &cf1, b"k", versions[1].0)
+            .unwrap(),
+        None
+    );
+
+    // the history before the snapshot is gone
+    let err = db
+        .get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", before_snapshot)
+        .unwrap_err();
+    assert!(err.is_history_unavailable());
+    assert_eq!(err.kind(), ErrorKind::Incomplete);
+    let latest = db.latest_sequence_number();
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", latest + 1)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::InvalidArgument
+    );
+}
+
+#[test]
+fn test_get_at_seqno_after_batch() {
+    let path = DBPath::new("_rust_rocksdb_test_get_at_seqno_after_batch");
+    let db = DB::open_default(&path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    // the snapshot sees the last write of a batch of two
+    let mut batch = WriteBatch::default();
+    batch.put(b"k", b"v0");
+    batch.put(b"other", b"x");
+    db.write(batch).unwrap();
+    let snapshot = db.snapshot();
+    assert_eq!(snapshot.sequence_number(), db.latest_sequence_number());
+
+    db.put(b"k", b"v1").unwrap();
+    let seqno = db.latest_sequence_number();
+    db.put(b"k", b"v2").unwrap();
+
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", snapshot.sequence_number())
+            .unwrap(),
+        Some(b"v0".to_vec())
+    );
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", seqno)
+            .unwrap(),
+        Some(b"v1".to_vec())
+    );
+}
+
+#[test]
+fn test_get_at_seqno_range_deletion() {
+    let path = DBPath::new("_rust_rocksdb_test_get_at_seqno_range_deletion");
+    let mut db = DB::open_default(&path).unwrap();
+    let mut reversed_opts = Options::default();
+    reversed_opts.set_comparator("reversed", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+    db.create_cf("reversed", &reversed_opts).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let reversed = db.cf_handle("reversed").unwrap();
+
+    db.put(b"k", b"v").unwrap();
+    db.put_cf(&reversed, b"k", b"v").unwrap();
+    let snapshot = db.snapshot();
+    db.delete_range_cf(&cf, b"a", b"z").unwrap();
+    db.delete_range_cf(&reversed, b"z", b"a").unwrap();
+    let seqno = db.latest_sequence_number();
+
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &cf, b"k", seqno)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        db.get_at_seqno_cf_with_snapshot(&snapshot, &reversed, b"k", seqno)
+            .unwrap_err()
+            .kind(),
+        ErrorKind::NotSupported
+    );
+}
+
 #[test]
 fn test_get_updates_since_empty() {
     let path = DBPath::new("_rust_rocksdb_test_get_updates_since_empty");
@@ -710,6 +1021,353 @@ fn test_open_cf_with_ttl() {
     assert!(db.get_cf(&cf, b"key1").unwrap().is_none());
 }
 
+fn rewrite_filter(_level: u32, key: &[u8], _value: &[u8]) -> CompactionDecision {
+    if key.starts_with(b"rewrite") {
+        CompactionDecision::Change(b"rewritten")
+    } else {
+        CompactionDecision::Keep
+    }
+}
+
+#[test]
+fn test_open_with_ttl_and_compaction_filter() {
+    let path = DBPath::new("_rust_rocksdb_test_open_with_ttl_and_compaction_filter");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_compaction_filter("rewrite", rewrite_filter);
+    let db = DB::open_with_ttl(&opts, &path, Duration::from_secs(1)).unwrap();
+    assert_eq!(db.ttl(), Some(Duration::from_secs(1)));
+    db.put(b"expired", b"value").unwrap();
+
+    thread::sleep(Duration::from_secs(2));
+    db.put(b"rewrite1", b"value").unwrap();
+    db.put(b"keep1", b"value").unwrap();
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+    // the TTL filter and the user filter both ran
+    assert!(db.get(b"expired").unwrap().is_none());
+    assert_eq!(db.get(b"rewrite1").unwrap().unwrap(), b"rewritten");
+    assert_eq!(db.get(b"keep1").unwrap().unwrap(), b"value");
+}
+
+#[test]
+fn test_open_cf_with_ttls() {
+    let path = DBPath::new("_rust_rocksdb_test_open_cf_with_ttls");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf_with_ttls(
+        &opts,
+        &path,
+        [
+            ("cache", Duration::from_secs(1)),
+            ("meta", Duration::from_secs(3600)),
+        ],
+    )
+    .unwrap();
+    let cache = db.cf_handle("cache").unwrap();
+    let meta = db.cf_handle("meta").unwrap();
+    assert_eq!(db.ttl_cf(&cache), Some(Duration::from_secs(1)));
+    assert_eq!(db.ttl_cf(&meta), Some(Duration::from_secs(3600)));
+    assert_eq!(db.ttl(), None);
+    db.put_cf(&cache, b"key1", b"value1").unwrap();
+    db.put_cf(&meta, b"key1", b"value1").unwrap();
+    db.put(b"key1", b"value1").unwrap();
+
+    thread::sleep(Duration::from_secs(2));
+    db.compact_range_cf(&cache, None::<&[u8]>, None::<&[u8]>);
+    db.compact_range_cf(&meta, None::<&[u8]>, None::<&[u8]>);
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+    // only the column family with the shorter TTL expired
+    assert!(db.get_cf(&cache, b"key1").unwrap().is_none());
+    assert_eq!(db.get_cf(&meta, b"key1").unwrap().unwrap(), b"value1");
+    assert_eq!(db.get(b"key1").unwrap().unwrap(), b"value1");
+}
+
+#[test]
+fn test_compact_range_with_info() {
+    let path = DBPath::new("_rust_rocksdb_test_compact_range_with_info");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    // two overlapping L0 files
+    for round in 0..2 {
+        for i in 0..100 {
+            db.put(format!("k{i:03}"), format!("v{round}")).unwrap();
+        }
+        db.flush().unwrap();
+    }
+    let mut l0_files: Vec<_> = db
+        .live_files()
+        .unwrap()
+        .into_iter()
+        .map(|f| f.name)
+        .collect();
+    l0_files.sort();
+    assert_eq!(l0_files.len(), 2);
+
+    let info = db
+        .compact_range_cf_opt_with_info(
+            &cf,
+            None::<&[u8]>,
+            None::<&[u8]>,
+            &CompactOptions::default(),
+        )
+        .unwrap();
+    assert_eq!(info.column_family_name, DEFAULT_COLUMN_FAMILY_NAME);
+    let mut input_files = info.input_files.clone();
+    input_files.sort();
+    assert_eq!(input_files, l0_files);
+    assert!(!info.output_files.is_empty());
+    assert!(info.output_level.unwrap() > 0);
+    assert!(info.bytes_read > 0);
+    assert!(info.bytes_written > 0);
+    assert_eq!(db.get(b"k000").unwrap().unwrap(), b"v1");
+}
+
+#[test]
+fn test_promote_l0_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_promote_l0_cf");
+
+    let statistics = Statistics::new();
+    let mut opts = Options::with_statistics(&statistics);
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, ["disjoint", "overlapping"]).unwrap();
+    let level_files = |cf_name: &str, level: i32| -> Vec<String> {
+        let mut files: Vec<_> = db
+            .live_files()
+            .unwrap()
+            .into_iter()
+            .filter(|f| f.column_family_name == cf_name && f.level == level)
+            .map(|f| f.name)
+            .collect();
+        files.sort();
+        files
+    };
+
+    // sorted, disjoint batches are moved without being rewritten
+    let cf = db.cf_handle("disjoint").unwrap();
+    for batch in 0..4 {
+        for i in 0..100 {
+            db.put_cf(cf, format!("k{batch}{i:03}"), b"value").unwrap();
+        }
+        db.flush_cf(cf).unwrap();
+    }
+    let l0_files = level_files("disjoint", 0);
+    assert_eq!(l0_files.len(), 4);
+
+    db.promote_l0_cf(cf, 2).unwrap();
+    assert!(level_files("disjoint", 0).is_empty());
+    assert_eq!(level_files("disjoint", 2), l0_files);
+    assert_eq!(statistics.get_ticker_count(Ticker::CompactWriteBytes), 0);
+    assert_eq!(db.get_cf(cf, b"k3099").unwrap().unwrap(), b"value");
+
+    // the files of the other levels would be moved along
+    db.put_cf(cf, b"k4000", b"value").unwrap();
+    db.flush_cf(cf).unwrap();
+    for target_level in [0, 1, 3, 7] {
+        let err = db.promote_l0_cf(cf, target_level).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    }
+    assert_eq!(level_files("disjoint", 0).len(), 1);
+    assert_eq!(level_files("disjoint", 2), l0_files);
+
+    // overlapping files stay in L0
+    let cf = db.cf_handle("overlapping").unwrap();
+    for round in 0..2 {
+        for i in 0..100 {
+            db.put_cf(cf, format!("k{i:03}"), format!("v{round}"))
+                .unwrap();
+        }
+        db.flush_cf(cf).unwrap();
+    }
+    let err = db.promote_l0_cf(cf, 2).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert_eq!(level_files("overlapping", 0).len(), 2);
+    assert_eq!(statistics.get_ticker_count(Ticker::CompactWriteBytes), 0);
+}
+
+#[test]
+fn test_spawn_wal_flusher() {
+    fn wal_size(path: &DBPath) -> u64 {
+        std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+            .map(|path| std::fs::metadata(path).unwrap().len())
+            .sum()
+    }
+
+    let path = DBPath::new("_rust_rocksdb_test_spawn_wal_flusher");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_manual_wal_flush(true);
+    let db = Arc::new(DB::open(&opts, &path).unwrap());
+    let interval = Duration::from_millis(50);
+    let flusher = db.spawn_wal_flusher(interval);
+
+    let before = wal_size(&path);
+    db.put(b"k1", vec![b'v'; 4096]).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    thread::sleep(interval);
+    while wal_size(&path) < before + 4096 {
+        assert!(Instant::now() < deadline, "the WAL was not flushed");
+        thread::sleep(interval);
+    }
+
+    // dropping the handle joins the thread
+    drop(flusher);
+    assert_eq!(Arc::strong_count(&db), 1);
+}
+
+#[test]
+fn test_tick() {
+    fn wal_size(path: &DBPath) -> u64 {
+        std::fs::read_dir(path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "log"))
+            .map(|path| std::fs::metadata(path).unwrap().len())
+            .sum()
+    }
+
+    let path = DBPath::new("_rust_rocksdb_test_tick");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_manual_wal_flush(true);
+    opts.enable_statistics();
+    // the jobs are only run by `tick`
+    opts.set_stats_dump_period_sec(0);
+    opts.set_stats_persist_period_sec(0);
+    let db = DB::open(&opts, &path).unwrap();
+
+    let before = wal_size(&path);
+    for i in 0..10 {
+        db.put(format!("k{i}"), vec![b'v'; 1024]).unwrap();
+    }
+    assert_eq!(wal_size(&path), before);
+    assert!(db.stats_history().is_empty());
+
+    let report = db
+        .tick(TickTasks::FLUSH_WAL | TickTasks::RECORD_STATS)
+        .unwrap();
+    assert!(report.stats_dump.is_none());
+    assert!(wal_size(&path) >= before + 10 * 1024);
+    let history = db.stats_history();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].tickers["rocksdb.number.keys.written"], 10);
+
+    // the next entry holds the increase since the previous one
+    for i in 0..5 {
+        db.put(format!("k{i}"), b"v").unwrap();
+    }
+    let report = db
+        .tick(TickTasks::DUMP_STATS | TickTasks::RECORD_STATS)
+        .unwrap();
+    assert!(report.stats_dump.unwrap().contains("DB Stats"));
+    let history = db.stats_history();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].tickers["rocksdb.number.keys.written"], 5);
+    assert!(history[0].time <= history[1].time);
+}
+
+#[test]
+fn test_flush_default_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_flush_default_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        // without the WAL, the write only survives a reopen once flushed
+        let mut write_opts = WriteOptions::default();
+        write_opts.disable_wal(true);
+        db.put_opt(b"k1", b"v1", &write_opts).unwrap();
+        db.flush().unwrap();
+        assert_eq!(db.live_files().unwrap().len(), 1);
+    }
+    let db = DB::open(&opts, &path).unwrap();
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+}
+
+#[test]
+fn test_flush_with_info() {
+    let path = DBPath::new("_rust_rocksdb_test_flush_with_info");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    for i in 10..100 {
+        db.put(format!("k{i:03}"), b"v").unwrap();
+    }
+    let info = db.flush_cf_with_info(&cf).unwrap();
+    assert_eq!(info.column_family_name, DEFAULT_COLUMN_FAMILY_NAME);
+    assert_eq!(info.output_files.len(), 1);
+    assert_eq!(info.smallest_key.as_deref(), Some(&b"k010"[..]));
+    assert_eq!(info.largest_key.as_deref(), Some(&b"k099"[..]));
+    assert!(info.bytes_written > 0);
+
+    // nothing left to flush
+    let info = db.flush_cf_with_info(&cf).unwrap();
+    assert!(info.output_files.is_empty());
+    assert_eq!(info.smallest_key, None);
+}
+
+#[test]
+fn test_flush_cf_and_get_files() {
+    let path = DBPath::new("_rust_rocksdb_test_flush_cf_and_get_files");
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    let db = DB::open_cf(&opts, &path, ["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    let flush_opts = rocksdb::FlushOptions::default();
+
+    for i in 10..100 {
+        db.put_cf(&cf, format!("k{i:03}"), b"v").unwrap();
+    }
+    // writes to other column families are not flushed
+    db.put(b"other", b"v").unwrap();
+    let files = db.flush_cf_and_get_files(&cf, &flush_opts).unwrap();
+    assert_eq!(files.len(), 1);
+    let file = &files[0];
+    assert_eq!(file.column_family_name, "cf");
+    assert_eq!(file.level, 0);
+    assert_eq!(file.start_key.as_deref(), Some(&b"k010"[..]));
+    assert_eq!(file.end_key.as_deref(), Some(&b"k099"[..]));
+    assert_eq!(file.num_entries, 90);
+    assert!(file.size > 0);
+    let live: Vec<_> = db
+        .live_files()
+        .unwrap()
+        .into_iter()
+        .map(|f| f.name)
+        .collect();
+    assert_eq!(live, [file.name.clone()]);
+    let file_path = path.path().join(file.name.trim_start_matches('/'));
+    assert_eq!(
+        std::fs::metadata(file_path).unwrap().len(),
+        file.size as u64
+    );
+
+    // nothing left to flush
+    let files = db.flush_cf_and_get_files(&cf, &flush_opts).unwrap();
+    assert!(files.is_empty());
+}
+
 #[test]
 fn test_open_as_single_threaded() {
     let primary_path = DBPath::new("_rust_rocksdb_test_open_as_single_threaded");
@@ -804,6 +1462,60 @@ fn compact_range_test() {
     }
 }
 
+#[test]
+fn universal_compaction_test() {
+    let path = DBPath::new("_rust_rocksdb_universal_compaction_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_compaction_style(DBCompactionStyle::Universal);
+    opts.set_level_zero_file_num_compaction_trigger(2);
+    let mut uni_co_opts = UniversalCompactOptions::default();
+    uni_co_opts.set_size_ratio(1);
+    uni_co_opts.set_min_merge_width(2);
+    uni_co_opts.set_max_merge_width(4);
+    uni_co_opts.set_max_size_amplification_percent(25);
+    uni_co_opts.set_stop_style(UniversalCompactionStopStyle::Total);
+    opts.set_universal_compaction_options(&uni_co_opts);
+    let db = DB::open(&opts, &path).unwrap();
+
+    // each round overwrites all keys, so all runs but the oldest are redundant
+    for round in 0..10u8 {
+        for i in 0..2000 {
+            db.put(format!("k{i:04}"), vec![round; 100]).unwrap();
+        }
+        db.flush().unwrap();
+        db.wait_for_compact(&WaitForCompactOptions::default())
+            .unwrap();
+
+        // the sorted runs from the newest to the oldest
+        let files = db.live_files().unwrap();
+        let mut l0: Vec<_> = files.iter().filter(|f| f.level == 0).collect();
+        l0.sort_by(|a, b| b.name.cmp(&a.name));
+        let mut runs: Vec<usize> = l0.iter().map(|f| f.size).collect();
+        for level in 1..7 {
+            let size: usize = files
+                .iter()
+                .filter(|f| f.level == level)
+                .map(|f| f.size)
+                .sum();
+            if size > 0 {
+                runs.push(size);
+            }
+        }
+
+        let oldest = runs.pop().unwrap();
+        let newer: usize = runs.iter().sum();
+        assert!(
+            newer * 100 <= oldest * 25,
+            "round {}: {} bytes in newer runs for {} in the oldest",
+            round,
+            newer,
+            oldest
+        );
+    }
+    assert_eq!(db.get(b"k1999").unwrap().unwrap(), vec![9; 100]);
+}
+
 #[test]
 fn fifo_compaction_test() {
     let path = DBPath::new("_rust_rocksdb_fifo_compaction_test");
@@ -888,6 +1600,171 @@ fn wait_for_compact_test() {
     }
 }
 
+#[test]
+fn num_running_compactions_test() {
+    fn slow_filter(_level: u32, _key: &[u8], _value: &[u8]) -> CompactionDecision {
+        thread::sleep(Duration::from_millis(2));
+        CompactionDecision::Keep
+    }
+
+    let path = DBPath::new("_rust_rocksdb_num_running_compactions_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.set_level_zero_file_num_compaction_trigger(4);
+        opts.set_compaction_filter("slow", slow_filter);
+        let db = DB::open(&opts, &path).unwrap();
+        for file in 0..4 {
+            for i in 0..50 {
+                db.put(format!("k{i:02}"), format!("v{file}")).unwrap();
+            }
+            db.flush().unwrap();
+        }
+        assert_eq!(db.num_running_compactions().unwrap(), 0);
+        assert_eq!(db.num_running_flushes().unwrap(), 0);
+
+        // the compaction filter keeps the compaction running for a while
+        db.set_options(&[("disable_auto_compactions", "false")])
+            .unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while db.num_running_compactions().unwrap() == 0 {
+            assert!(Instant::now() < deadline, "no compaction started");
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        db.wait_for_compact(&WaitForCompactOptions::default())
+            .unwrap();
+        assert_eq!(db.num_running_compactions().unwrap(), 0);
+        assert_eq!(db.num_running_flushes().unwrap(), 0);
+        assert_eq!(db.get(b"k00").unwrap(), Some(b"v3".to_vec()));
+    }
+}
+
+#[test]
+fn disable_manual_compaction_test() {
+    fn slow_filter(_level: u32, _key: &[u8], _value: &[u8]) -> CompactionDecision {
+        thread::sleep(Duration::from_millis(5));
+        CompactionDecision::Keep
+    }
+
+    let path = DBPath::new("_rust_rocksdb_disable_manual_compaction_test");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.set_compaction_filter("slow", slow_filter);
+        let db = DB::open(&opts, &path).unwrap();
+        for file in 0..4 {
+            for i in 0..1000 {
+                db.put(format!("k{i:04}"), format!("v{file}")).unwrap();
+            }
+            db.flush().unwrap();
+        }
+        let compact_opts = CompactOptions::default();
+
+        // the compaction would take several seconds without the cancellation
+        let elapsed = thread::scope(|scope| {
+            let compaction = scope.spawn(|| {
+                let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+                db.try_compact_range_cf_opt(&cf, None::<&[u8]>, None::<&[u8]>, &compact_opts)
+            });
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while db.num_running_compactions().unwrap() == 0 {
+                assert!(Instant::now() < deadline, "no compaction started");
+                thread::sleep(Duration::from_millis(1));
+            }
+            let cancelled = Instant::now();
+            db.disable_manual_compaction();
+            let err = compaction.join().unwrap().unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Incomplete);
+            cancelled.elapsed()
+        });
+        assert!(elapsed < Duration::from_secs(2), "took {:?}", elapsed);
+
+        // new manual compactions fail right away while disabled
+        let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        let err = db
+            .try_compact_range_cf_opt(&cf, None::<&[u8]>, None::<&[u8]>, &compact_opts)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Incomplete);
+
+        // the DB remains usable
+        db.enable_manual_compaction();
+        db.put(b"k0000", b"v4").unwrap();
+        assert_eq!(db.get(b"k0999").unwrap(), Some(b"v3".to_vec()));
+        assert_eq!(db.get(b"k0000").unwrap(), Some(b"v4".to_vec()));
+    }
+}
+
+#[test]
+fn open_locked_db_test() {
+    let path = DBPath::new("_rust_rocksdb_open_locked_db_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    let db = DB::open(&opts, &path).unwrap();
+
+    let err = DB::open(&opts, &path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::IOError);
+    assert!(err.is_lock_held());
+    assert!(matches!(
+        DB::try_open(&opts, &path),
+        Err(OpenError::Locked { holder_pid: Some(pid), error })
+            if pid == std::process::id() && error.is_lock_held()
+    ));
+    assert!(matches!(
+        DB::open_with_lock_wait(&opts, &path, Duration::from_millis(50)),
+        Err(OpenError::Locked { .. })
+    ));
+
+    // other errors are passed through
+    let missing = DBPath::new("_rust_rocksdb_open_locked_db_test_missing");
+    let err = DB::try_open(&Options::default(), &missing).unwrap_err();
+    assert!(matches!(&err, OpenError::Other(e) if e.kind() == ErrorKind::InvalidArgument));
+
+    // the retrying open succeeds once the DB is closed
+    let closer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        drop(db);
+    });
+    let db = DB::open_with_lock_wait(&opts, &path, Duration::from_secs(10)).unwrap();
+    closer.join().unwrap();
+    db.put(b"k", b"v").unwrap();
+}
+
+#[test]
+fn cache_occupancy_count_test() {
+    let path = DBPath::new("_rust_rocksdb_cache_occupancy_count_test");
+    for cache in [
+        Cache::new_lru_cache(1 << 20),
+        Cache::new_hyper_clock_cache(1 << 20, 4 << 10),
+    ] {
+        assert_eq!(cache.get_occupancy_count(), 0);
+        assert!(cache.get_table_address_count() > 0);
+
+        let mut block_based_opts = BlockBasedOptions::default();
+        block_based_opts.set_block_cache(&cache);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_block_based_table_factory(&block_based_opts);
+        {
+            let db = DB::open(&opts, &path).unwrap();
+            for i in 0..1000 {
+                db.put(format!("k{i:04}"), b"value").unwrap();
+            }
+            db.flush().unwrap();
+            for i in 0..1000 {
+                db.get(format!("k{i:04}")).unwrap();
+            }
+            assert!(cache.get_occupancy_count() > 0);
+            assert!(cache.get_usage() > 0);
+        }
+    }
+
+    let cache = Cache::new_lru_cache(1 << 20);
+    cache.disown_data();
+}
+
 #[test]
 fn env_and_dbpaths_test() {
     let path = DBPath::new("_rust_rocksdb_dbpath_test");
@@ -1457,6 +2334,37 @@ fn key_may_exist_cf() {
     }
 }
 
+#[test]
+fn get_into_cf() {
+    let path = DBPath::new("_rust_rocksdb_get_into_cf");
+
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, &path, ["cf"]).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+
+        for i in 0..100 {
+            db.put_cf(&cf, format!("k{i}"), "v".repeat(i)).unwrap();
+        }
+
+        let mut buf = Vec::new();
+        for i in (0..100).rev() {
+            let len = db.get_into_cf(&cf, format!("k{i}"), &mut buf).unwrap();
+            assert_eq!(len, Some(i));
+            assert_eq!(buf, "v".repeat(i).as_bytes());
+        }
+        // The buffer grew once to the largest value and was reused afterwards.
+        assert!(buf.capacity() >= 99);
+
+        buf.clear();
+        buf.extend_from_slice(b"untouched");
+        assert_eq!(db.get_into_cf(&cf, "nonexistent", &mut buf).unwrap(), None);
+        assert_eq!(buf, b"untouched");
+    }
+}
+
 #[test]
 fn key_may_exist_cf_value() {
     let path = DBPath::new("_rust_key_may_exist_cf_value");
@@ -1592,3 +2500,220 @@ fn test_get_approximate_sizes() {
         assert!(result[0] > 0, "get size {}", result[0]);
     }
 }
+
+#[test]
+fn test_get_approximate_sizes_opt() {
+    let path = DBPath::new("_rust_rocksdb_get_approximate_sizes_opt");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+
+        let db = DB::open_cf(&opts, &path, ["cf"]).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+        for i in 0..1000 {
+            db.put_cf(&cf, format!("k{i:04}"), [b'v'; 100]).unwrap();
+        }
+
+        // nothing is flushed yet
+        let ranges = [("k0000", "k9999")];
+        assert_eq!(db.get_approximate_sizes(&cf, &ranges).unwrap(), vec![0]);
+        let with_memtables = SizeApproximationOptions {
+            include_memtables: true,
+            ..SizeApproximationOptions::default()
+        };
+        let result = db
+            .get_approximate_sizes_opt(&cf, &ranges, &with_memtables)
+            .unwrap();
+        assert!(result[0] > 0, "get size {}", result[0]);
+
+        let nothing = SizeApproximationOptions {
+            include_memtables: false,
+            include_files: false,
+        };
+        let err = db
+            .get_approximate_sizes_opt(&cf, &ranges, &nothing)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_write_validation() {
+    let path = DBPath::new("_rust_rocksdb_write_validation");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_write_validation(WriteValidationOptions {
+            max_key_bytes: Some(1024),
+            max_value_bytes: Some(1024 * 1024),
+            reject_empty_keys: true,
+        });
+        let db = DB::open(&opts, &path).unwrap();
+
+        // boundary sizes are accepted
+        let key = vec![b'k'; 1024];
+        let value = vec![b'v'; 1024 * 1024];
+        db.put(&key, &value).unwrap();
+        assert_eq!(db.get(&key).unwrap(), Some(value.clone()));
+
+        let err = db.put(vec![b'k'; 1025], b"v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(err.to_string().contains("1025"), "{}", err);
+
+        let err = db.put(b"k", vec![b'v'; 1024 * 1024 + 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(err.to_string().contains("1048577"), "{}", err);
+
+        let err = db.put(b"", b"v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"k1", b"v1");
+        batch.put(vec![b'k'; 2048], b"v2");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(err.to_string().contains("2048"), "{}", err);
+        assert_eq!(db.get(b"k1").unwrap(), None);
+
+        let mut batch = WriteBatch::default();
+        batch.put(&key, b"v1");
+        batch.put(b"k2", &value);
+        db.write(batch).unwrap();
+        assert_eq!(db.get(b"k2").unwrap(), Some(value));
+
+        // an empty batch passes even with empty keys rejected
+        db.write(WriteBatch::default()).unwrap();
+
+        // batches built from serialized data are decoded and checked too
+        let mut batch = WriteBatch::default();
+        batch.put(vec![b'k'; 2048], b"v");
+        let err = db.write(WriteBatch::from_data(batch.data())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        // deletes have their keys checked like the ones of batches
+        let err = db.delete(b"").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        let err = db.delete(vec![b'k'; 1025]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        let err = db.delete_range_cf(&cf, "", "k").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        db.delete(&key).unwrap();
+        assert_eq!(db.get(&key).unwrap(), None);
+    }
+}
+
+#[test]
+fn test_path_and_name() {
+    let path = DBPath::new("_rust_rocksdb_test_path_and_name");
+    {
+        let db = DB::open_default(&path).unwrap();
+        assert_eq!(db.path(), (&path).as_ref());
+        assert_eq!(db.name(), "db");
+    }
+}
+
+#[test]
+fn test_write_with_retry() {
+    let path = DBPath::new("_rust_rocksdb_test_write_with_retry");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_write_validation(WriteValidationOptions {
+            max_key_bytes: Some(8),
+            ..WriteValidationOptions::default()
+        });
+        let db = DB::open(&opts, &path).unwrap();
+
+        let mut policy = RetryPolicy::new();
+        policy.set_base_delay(Duration::from_millis(1));
+        db.put_with_retry(&policy, b"k1", b"v1").unwrap();
+        assert_eq!(db.get(b"k1").unwrap(), Some(b"v1".to_vec()));
+
+        let mut batch = WriteBatch::default();
+        batch.put(b"k2", b"v2");
+        db.write_opt_with_retry(&policy, &batch, &WriteOptions::default())
+            .unwrap();
+        assert_eq!(db.get(b"k2").unwrap(), Some(b"v2".to_vec()));
+
+        // invalid arguments are not retried and keep their message
+        let err = db
+            .put_with_retry(&policy, vec![b'k'; 16], b"v3")
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        assert!(!err.to_string().contains("attempts"), "{}", err);
+    }
+}
+
+#[test]
+fn test_read_tier_block_cache() {
+    let path = DBPath::new("_rust_rocksdb_test_read_tier_block_cache");
+    let open = || {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(&Cache::new_lru_cache(1 << 20));
+        opts.set_block_based_table_factory(&block_opts);
+        DB::open(&opts, &path).unwrap()
+    };
+    {
+        let db = open();
+        db.put(b"k1", b"v1").unwrap();
+        db.flush().unwrap();
+    }
+
+    // after a reopen with an empty block cache, the key is only on the disk
+    let db = open();
+    let mut cache_only = ReadOptions::default();
+    cache_only.set_read_tier(ReadTier::BlockCache);
+    let err = db.get_opt(b"k1", &cache_only).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Incomplete);
+
+    // the fallback reads the key from the disk, into the block cache
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    assert_eq!(db.get_opt(b"k1", &cache_only).unwrap().unwrap(), b"v1");
+
+    // keys in the memtable are served too
+    db.put(b"k2", b"v2").unwrap();
+    assert_eq!(db.get_opt(b"k2", &cache_only).unwrap().unwrap(), b"v2");
+}
+
+#[test]
+fn test_ignore_range_deletions() {
+    let path = DBPath::new("_rust_rocksdb_test_ignore_range_deletions");
+    let db = DB::open_default(&path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    db.put(b"k1", b"v1").unwrap();
+    db.flush().unwrap();
+    db.delete_range_cf(&cf, b"k0", b"k2").unwrap();
+    assert!(db.get(b"k1").unwrap().is_none());
+
+    let mut readopts = ReadOptions::default();
+    readopts.set_ignore_range_deletions(true);
+    assert_eq!(db.get_opt(b"k1", &readopts).unwrap().unwrap(), b"v1");
+
+    // compactions drop the keys covered by the tombstone
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    assert!(db.get_opt(b"k1", &readopts).unwrap().is_none());
+}
+
+#[test]
+fn test_update_cf() {
+    let path = DBPath::new("_rust_rocksdb_test_update_cf");
+    let db = DB::open_default(&path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    let append = |value: Option<&[u8]>| {
+        let mut value = value.map(<[u8]>::to_vec).unwrap_or_default();
+        value.push(b'a');
+        Some(value)
+    };
+    assert_eq!(db.update_cf(&cf, b"k1", append).unwrap().unwrap(), b"a");
+    assert_eq!(db.update_cf(&cf, b"k1", append).unwrap().unwrap(), b"aa");
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"aa");
+
+    // returning `None` deletes the key
+    assert_eq!(db.update_cf(&cf, b"k1", |_| None).unwrap(), None);
+    assert!(db.get(b"k1").unwrap().is_none());
+}
@@ -16,8 +16,11 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{Direction, IteratorMode, MemtableFactory, Options, DB};
-use util::{assert_iter, assert_iter_reversed, pair, DBPath};
+use rocksdb::{
+    DBWithThreadMode, Direction, Error, IterateBounds, IteratorMode, MemtableFactory,
+    MultiThreaded, Options, ReadOptions, ScanOptions, DB, DEFAULT_COLUMN_FAMILY_NAME,
+};
+use util::{assert_iter, assert_iter_reversed, pair, DBPath, Pair};
 
 #[test]
 #[allow(clippy::cognitive_complexity)]
@@ -226,6 +229,39 @@ fn test_full_iterator() {
     }
 }
 
+#[test]
+fn test_prefix_iterator_cf_opt_total_order() {
+    let path = DBPath::new("_rust_rocksdb_prefix_iterator_cf_opt_total_order");
+    {
+        const A1: &[u8] = b"aaa1";
+        const A2: &[u8] = b"aaa2";
+        const B1: &[u8] = b"bbb1";
+        const B2: &[u8] = b"bbb2";
+
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_prefix_extractor(rocksdb::SliceTransform::create_fixed_prefix(3));
+        let db = DB::open(&opts, &path).unwrap();
+        let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+        for key in [A1, A2, B1, B2] {
+            db.put_cf(&cf, key, key).unwrap();
+        }
+        db.flush_cf(&cf).unwrap();
+
+        // prefix mode stops at the end of the prefix
+        assert_iter(
+            db.prefix_iterator_cf_opt(&cf, b"aaa", ReadOptions::default()),
+            &[pair(A1, A1), pair(A2, A2)],
+        );
+
+        let all = [pair(A1, A1), pair(A2, A2), pair(B1, B1), pair(B2, B2)];
+        let mut readopts = ReadOptions::default();
+        readopts.set_total_order_seek(true);
+        assert!(readopts.get_total_order_seek());
+        assert_iter(db.prefix_iterator_cf_opt(&cf, b"aaa", readopts), &all);
+    }
+}
+
 fn custom_iter(db: &'_ DB) -> impl Iterator<Item = usize> + '_ {
     db.iterator(IteratorMode::Start)
         .map(Result::unwrap)
@@ -379,3 +415,262 @@ fn test_iter_range() {
         check(&db, b"b0", prefix(b"\xff"), 0..0);
     }
 }
+
+#[test]
+fn test_iter_bounds_matrix() {
+    const KEYS: [&[u8]; 5] = [b"a", b"c", b"e", b"g", b"i"];
+
+    #[derive(Debug, Clone, Copy)]
+    struct Bounds<'a>(Option<&'a [u8]>, Option<&'a [u8]>);
+
+    impl IterateBounds for Bounds<'_> {
+        fn into_bounds(self) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
+            (self.0.map(<[u8]>::to_vec), self.1.map(<[u8]>::to_vec))
+        }
+    }
+
+    fn keys(iter: impl Iterator<Item = Result<Pair, Error>>) -> Vec<Vec<u8>> {
+        iter.map(|item| item.unwrap().0.to_vec()).collect()
+    }
+
+    let path = DBPath::new("_rust_rocksdb_iter_bounds_matrix");
+    let db = DB::open_default(&path).unwrap();
+    for key in KEYS {
+        db.put(key, key).unwrap();
+    }
+
+    // bounds exactly on existing keys, and between keys
+    for lower in [None, Some(&b"c"[..]), Some(&b"b"[..])] {
+        for upper in [None, Some(&b"g"[..]), Some(&b"h"[..])] {
+            let bounds = Bounds(lower, upper);
+            let forward: Vec<Vec<u8>> = KEYS
+                .iter()
+                .filter(|key| lower.map_or(true, |lower| **key >= lower))
+                .filter(|key| upper.map_or(true, |upper| **key < upper))
+                .map(|key| key.to_vec())
+                .collect();
+            let reverse: Vec<Vec<u8>> = forward.iter().rev().cloned().collect();
+
+            // the keys of the `From` modes lie outside of the bounds
+            for (mode, want) in [
+                (IteratorMode::Start, &forward),
+                (IteratorMode::From(b"", Direction::Forward), &forward),
+                (IteratorMode::End, &reverse),
+                (IteratorMode::From(b"z", Direction::Reverse), &reverse),
+            ] {
+                let mut opts = ReadOptions::default();
+                opts.set_iterate_range(bounds);
+                let mut iter = db.iterator_opt(mode, opts);
+                assert_eq!(&keys(&mut iter), want, "{bounds:?}");
+
+                // repositioning an exhausted iterator checks the bounds again
+                iter.set_mode(mode);
+                assert_eq!(&keys(iter), want, "{bounds:?}");
+
+                // and so does changing them
+                let mut iter = db.iterator(IteratorMode::Start);
+                iter.set_bounds_and_mode(bounds, mode);
+                assert_eq!(&keys(iter), want, "{bounds:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_set_bounds_and_mode_after_drop_cf() {
+    let path = DBPath::new("_rust_rocksdb_set_bounds_and_mode_after_drop_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DBWithThreadMode::<MultiThreaded>::open_cf(&opts, &path, ["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    for key in [b"a", b"b", b"c", b"d"] {
+        db.put_cf(&cf, key, key).unwrap();
+    }
+    let mut iter = db.iterator_cf(&cf, IteratorMode::Start);
+    db.drop_cf("cf").unwrap();
+    drop(cf);
+
+    iter.set_bounds_and_mode(b"b".as_slice()..b"d".as_slice(), IteratorMode::End);
+    let keys: Vec<_> = iter.map(|item| item.unwrap().0.to_vec()).collect();
+    assert_eq!(keys, [b"c".to_vec(), b"b".to_vec()]);
+}
+
+#[test]
+fn test_scan_cf() {
+    let path = DBPath::new("_rust_rocksdb_scan_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    for i in 0..10_000 {
+        db.put_cf(&cf, format!("key{i:05}"), format!("value{i}"))
+            .unwrap();
+    }
+    db.flush_cf(&cf).unwrap();
+
+    let scan_opts = ScanOptions {
+        batch_size: 100,
+        ..ScanOptions::default()
+    };
+    let mut scanned = vec![];
+    for (n, item) in db.scan_cf(&cf, .., scan_opts).enumerate() {
+        let (key, value) = item.unwrap();
+        scanned.push((key, value));
+        if n % 250 == 0 {
+            // Concurrent writes ahead of, behind and exactly at the scan position
+            // must not show up in a snapshot scan.
+            db.put_cf(&cf, format!("key{n:05}a"), "new").unwrap();
+            db.delete_cf(&cf, format!("key{:05}", n + 1)).unwrap();
+            db.put_cf(&cf, format!("key{:05}", n), "overwritten")
+                .unwrap();
+        }
+    }
+    assert_eq!(scanned.len(), 10_000);
+    for (i, (key, value)) in scanned.iter().enumerate() {
+        assert_eq!(&**key, format!("key{i:05}").as_bytes());
+        assert_eq!(&**value, format!("value{i}").as_bytes());
+    }
+
+    // Bounds are honoured across batches.
+    let keys: Vec<_> = db
+        .scan_cf(&cf, "key00300".."key00450", scan_opts)
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(keys.len(), 150);
+    assert_eq!(&*keys[0], b"key00300");
+    assert_eq!(&*keys[149], b"key00449");
+}
+
+#[test]
+fn test_range_cf() {
+    use std::ops::{Bound, RangeBounds};
+
+    let path = DBPath::new("_rust_rocksdb_test_range_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+    let keys: [&[u8]; 5] = [b"a", b"b", b"b\0", b"c", b"d"];
+    for key in keys.iter() {
+        db.put_cf(&cf, key, b"v").unwrap();
+    }
+
+    let bounds = |key: &[u8]| {
+        vec![
+            Bound::Included(key.to_vec()),
+            Bound::Excluded(key.to_vec()),
+            Bound::Unbounded,
+        ]
+    };
+    for lower in bounds(b"b") {
+        for upper in bounds(b"c") {
+            let range = (lower.clone(), upper);
+            let expected: Vec<Box<[u8]>> = keys
+                .iter()
+                .filter(|key| range.contains(&key.to_vec()))
+                .map(|key| Box::from(*key))
+                .collect();
+
+            let forward: Vec<_> = db
+                .range_cf(&cf, range.clone(), Direction::Forward)
+                .map(|item| item.unwrap().0)
+                .collect();
+            assert_eq!(forward, expected, "{:?}", range);
+
+            let mut reverse: Vec<_> = db
+                .range_cf(&cf, range.clone(), Direction::Reverse)
+                .map(|item| item.unwrap().0)
+                .collect();
+            reverse.reverse();
+            assert_eq!(reverse, expected, "{:?}", range);
+        }
+    }
+
+    // ranges over borrowed keys work the same way
+    let items: Vec<_> = db
+        .range_cf(&cf, &b"b"[..]..=&b"c"[..], Direction::Forward)
+        .map(|item| item.unwrap().0)
+        .collect();
+    assert_eq!(items.len(), 3);
+}
+
+#[test]
+fn test_iterators_for_ranges() {
+    let path = DBPath::new("_rust_rocksdb_iterators_for_ranges");
+    let db = DB::open_default(&path).unwrap();
+    for i in 0..1000u32 {
+        db.put(i.to_be_bytes(), i.to_string()).unwrap();
+    }
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let snapshot = db.snapshot();
+    // writes after the snapshot are seen by none of the iterators
+    db.put(250u32.to_be_bytes(), "changed").unwrap();
+    db.put(1000u32.to_be_bytes(), "added").unwrap();
+
+    let bounds = [0u32, 250, 500, 750, 1000].map(u32::to_be_bytes);
+    let ranges: Vec<(&[u8], &[u8])> = bounds.windows(2).map(|w| (&w[0][..], &w[1][..])).collect();
+    let iters = db.iterators_for_ranges_cf(&cf, &ranges, &snapshot);
+    assert_eq!(iters.len(), 4);
+
+    let scanned: Vec<_> = std::thread::scope(|scope| {
+        let workers: Vec<_> = iters
+            .into_iter()
+            .map(|iter| scope.spawn(move || iter.map(Result::unwrap).collect::<Vec<_>>()))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    let full: Vec<_> = snapshot
+        .iterator(IteratorMode::Start)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(full.len(), 1000);
+    assert_eq!(scanned, full);
+}
+
+#[test]
+fn test_iterator_bytes_read() {
+    let path = DBPath::new("_rust_rocksdb_iterator_bytes_read");
+    let db = DB::open_default(&path).unwrap();
+    let mut expected = 0;
+    for i in 0..500u32 {
+        let (key, value) = (format!("key{i:03}"), "v".repeat(i as usize % 17));
+        expected += (key.len() + value.len()) as u64;
+        db.put(key, value).unwrap();
+    }
+    db.flush().unwrap();
+
+    let mut iter = db.iterator(IteratorMode::Start);
+    assert_eq!(iter.bytes_read(), 0);
+    let yielded: u64 = iter
+        .by_ref()
+        .map(|item| {
+            let (key, value) = item.unwrap();
+            (key.len() + value.len()) as u64
+        })
+        .sum();
+    assert_eq!(yielded, expected);
+    assert_eq!(iter.bytes_read(), expected);
+    // an exhausted iterator reads nothing more
+    assert!(iter.next().is_none());
+    assert_eq!(iter.bytes_read(), expected);
+
+    // repositioning keeps counting
+    iter.set_mode(IteratorMode::From(b"key499", Direction::Reverse));
+    let (key, value) = iter.next().unwrap().unwrap();
+    assert_eq!(&*key, b"key499");
+    assert_eq!(
+        iter.bytes_read(),
+        expected + (key.len() + value.len()) as u64
+    );
+
+    // only the returned items are counted
+    let mut iter = db.iterator(IteratorMode::From(b"key100", Direction::Forward));
+    iter.next().unwrap().unwrap();
+    assert_eq!(iter.bytes_read(), 6 + 100 % 17);
+}
@@ -15,7 +15,11 @@
 mod util;
 
 use pretty_assertions::assert_eq;
-use rocksdb::{merge_operator::MergeFn, DBCompactionStyle, MergeOperands, Options, DB};
+use rocksdb::{
+    merge_operator::{decode_timestamped, encode_timestamped, last_write_wins, MergeFn},
+    perf::{set_perf_stats, PerfStatsLevel},
+    DBCompactionStyle, ErrorKind, IteratorMode, MergeOperands, Options, PerfContext, DB,
+};
 use serde::{Deserialize, Serialize};
 use util::DBPath;
 
@@ -345,3 +349,137 @@ fn test_merge_state() {
     }
     assert!(DB::destroy(&opts, path).is_ok());
 }
+
+#[test]
+fn test_max_successive_merges() {
+    use std::convert::TryInto;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static MAX_OPERANDS: AtomicUsize = AtomicUsize::new(0);
+
+    fn sum_merge(
+        _new_key: &[u8],
+        existing_val: Option<&[u8]>,
+        operands: &MergeOperands,
+    ) -> Option<Vec<u8>> {
+        MAX_OPERANDS.fetch_max(operands.len(), Ordering::SeqCst);
+        let to_u64 = |v: &[u8]| u64::from_le_bytes(v.try_into().unwrap());
+        let sum = operands
+            .iter()
+            .map(to_u64)
+            .fold(existing_val.map_or(0, to_u64), |acc, v| acc + v);
+        Some(sum.to_le_bytes().to_vec())
+    }
+
+    let path = DBPath::new("_rust_rocksdb_max_successive_merges");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_max_successive_merges(3);
+    opts.set_merge_operator_associative("sum", sum_merge);
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(b"counter", 0u64.to_le_bytes()).unwrap();
+        for _ in 0..100 {
+            db.merge(b"counter", 1u64.to_le_bytes()).unwrap();
+        }
+        let value = db.get(b"counter").unwrap().unwrap();
+        assert_eq!(u64::from_le_bytes(value.try_into().unwrap()), 100);
+        // The memtable never holds more than 3 successive merge operands
+        // (plus the one being added when the limit is hit).
+        assert!(MAX_OPERANDS.load(Ordering::SeqCst) <= 4);
+    }
+}
+
+#[test]
+fn last_write_wins_merge_test() {
+    let path = DBPath::new("_rust_rocksdb_last_write_wins_merge_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_merge_operator_associative("last_write_wins", last_write_wins);
+    let db = DB::open(&opts, &path).unwrap();
+
+    // newer first, then older
+    db.merge(b"k1", encode_timestamped(20, b"new")).unwrap();
+    db.merge(b"k1", encode_timestamped(10, b"old")).unwrap();
+    // older first, then newer, on top of an existing value
+    db.put(b"k2", encode_timestamped(5, b"base")).unwrap();
+    db.merge(b"k2", encode_timestamped(10, b"old")).unwrap();
+    db.merge(b"k2", encode_timestamped(20, b"new")).unwrap();
+    // the existing value is the newest
+    db.put(b"k3", encode_timestamped(30, b"base")).unwrap();
+    db.merge(b"k3", encode_timestamped(20, b"new")).unwrap();
+
+    for (key, expected) in [
+        (b"k1", (20, &b"new"[..])),
+        (b"k2", (20, &b"new"[..])),
+        (b"k3", (30, &b"base"[..])),
+    ] {
+        let value = db.get(key).unwrap().unwrap();
+        assert_eq!(decode_timestamped(&value), Some(expected));
+    }
+    // the same after the operands are merged by a compaction
+    db.compact_range(None::<&[u8]>, None::<&[u8]>);
+    let value = db.get(b"k1").unwrap().unwrap();
+    assert_eq!(decode_timestamped(&value), Some((20, &b"new"[..])));
+
+    // values without a timestamp cannot be merged
+    db.put(b"k4", b"short").unwrap();
+    db.merge(b"k4", encode_timestamped(1, b"v")).unwrap();
+    assert_eq!(db.get(b"k4").unwrap_err().kind(), ErrorKind::Corruption);
+}
+
+fn sum_merge(_key: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+    use std::convert::TryInto;
+
+    let mut sum = match existing {
+        Some(v) => u64::from_le_bytes(v.try_into().ok()?),
+        None => 0,
+    };
+    for op in operands {
+        sum += u64::from_le_bytes(op.try_into().ok()?);
+    }
+    Some(sum.to_le_bytes().to_vec())
+}
+
+#[test]
+fn get_with_merge_limit_test() {
+    let path = DBPath::new("_rust_rocksdb_get_with_merge_limit_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_merge_operator_associative("sum", sum_merge);
+    let db = DB::open(&opts, &path).unwrap();
+
+    for _ in 0..1000 {
+        db.merge(b"counter", 1u64.to_le_bytes()).unwrap();
+    }
+    db.put(b"other", 7u64.to_le_bytes()).unwrap();
+    let expected = 1000u64.to_le_bytes().to_vec();
+
+    let err = db.get_with_merge_limit(b"counter", 100).unwrap_err();
+    assert!(err.is_merge_operand_threshold_exceeded());
+    assert_eq!(err.kind(), ErrorKind::Incomplete);
+    // keys without operands, and reads without a limit, are not affected
+    assert_eq!(
+        db.get_with_merge_limit(b"other", 100).unwrap(),
+        Some(7u64.to_le_bytes().to_vec())
+    );
+    assert_eq!(db.get(b"counter").unwrap(), Some(expected.clone()));
+    assert_eq!(
+        db.get_with_merge_limit(b"counter", 1000).unwrap(),
+        Some(expected.clone())
+    );
+
+    set_perf_stats(PerfStatsLevel::EnableCount);
+    let mut ctx = PerfContext::default();
+    ctx.reset();
+    let (_, value) = db.iterator(IteratorMode::Start).next().unwrap().unwrap();
+    assert_eq!(&value[..], &expected[..]);
+    assert!(ctx.internal_merge_count() > 0);
+
+    // the compaction folds the operands into a single value
+    db.compact_range(Some(b"counter"), Some(b"counter\0"));
+    assert_eq!(
+        db.get_with_merge_limit(b"counter", 100).unwrap(),
+        Some(expected)
+    );
+}
@@ -0,0 +1,86 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "metrics")]
+
+mod util;
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use rocksdb::{
+    metrics::MetricsCollector,
+    statistics::{Statistics, Ticker},
+    Options, DB,
+};
+use util::DBPath;
+
+#[test]
+fn test_metrics_collect() {
+    let path = DBPath::new("_rust_rocksdb_test_metrics_collect");
+    let statistics = Statistics::new();
+    let mut opts = Options::with_statistics(&statistics);
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["cf1"]).unwrap();
+    for i in 0..100 {
+        db.put(format!("key{i}"), b"value").unwrap();
+    }
+    db.flush().unwrap();
+
+    let mut collector = MetricsCollector::new();
+    collector.register("main", &db, Some(statistics.clone()));
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || collector.collect()).unwrap();
+
+    let metrics = snapshotter.snapshot().into_vec();
+    let find = |name: &str, labels: &[(&str, &str)]| {
+        metrics
+            .iter()
+            .find(|(key, _, _, _)| {
+                let key = key.key();
+                key.name() == name
+                    && labels.iter().all(|(k, v)| {
+                        key.labels()
+                            .any(|label| label.key() == *k && label.value() == *v)
+                    })
+            })
+            .map(|(_, _, _, value)| value.clone())
+            .unwrap_or_else(|| panic!("{name} {labels:?} was not emitted"))
+    };
+
+    match find(
+        "rocksdb_estimate_num_keys",
+        &[("db", "main"), ("cf", "default")],
+    ) {
+        DebugValue::Gauge(value) => assert_eq!(value.into_inner(), 100.0),
+        value => panic!("unexpected {value:?}"),
+    }
+    match find(
+        "rocksdb_estimate_num_keys",
+        &[("db", "main"), ("cf", "cf1")],
+    ) {
+        DebugValue::Gauge(value) => assert_eq!(value.into_inner(), 0.0),
+        value => panic!("unexpected {value:?}"),
+    }
+    match find("rocksdb_bytes_written", &[("db", "main")]) {
+        DebugValue::Counter(value) => {
+            assert!(value > 0);
+            assert_eq!(value, statistics.get_ticker_count(Ticker::BytesWritten));
+        }
+        value => panic!("unexpected {value:?}"),
+    }
+    find(
+        "rocksdb_db_write_micros",
+        &[("db", "main"), ("quantile", "0.99")],
+    );
+    find("rocksdb_db_write_micros_count", &[("db", "main")]);
+}
@@ -18,7 +18,8 @@ mod util;
 use rocksdb::{
     CuckooTableOptions, DBAccess, Direction, Error, ErrorKind, IteratorMode,
     OptimisticTransactionDB, OptimisticTransactionOptions, Options, ReadOptions, SingleThreaded,
-    SliceTransform, SnapshotWithThreadMode, WriteBatchWithTransaction, WriteOptions, DB,
+    SliceTransform, SnapshotWithThreadMode, WriteBatchWithTransaction, WriteOptions,
+    WriteValidationOptions, DB,
 };
 use util::DBPath;
 
@@ -609,3 +610,109 @@ fn delete_range_test() {
         assert!(db.get_cf(&cf1, b"k3").unwrap().is_none());
     }
 }
+
+#[test]
+fn transaction_delete_keys_in_range() {
+    let path =
+        DBPath::new("_rust_rocksdb_optimistic_transaction_db_transaction_delete_keys_in_range");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db: OptimisticTransactionDB =
+            OptimisticTransactionDB::open_cf(&opts, &path, ["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        for key in [b"k1", b"k2", b"k3", b"k4"] {
+            db.put_cf(&cf1, key, b"v").unwrap();
+        }
+
+        let txn = db.transaction();
+        txn.delete_keys_in_range_cf(&cf1, b"k2", b"k4").unwrap();
+        txn.commit().unwrap();
+        assert!(db.get_cf(&cf1, b"k1").unwrap().is_some());
+        assert!(db.get_cf(&cf1, b"k2").unwrap().is_none());
+        assert!(db.get_cf(&cf1, b"k3").unwrap().is_none());
+        assert!(db.get_cf(&cf1, b"k4").unwrap().is_some());
+
+        // a key of the range written meanwhile is a conflict
+        let txn = db.transaction();
+        txn.delete_keys_in_range_cf(&cf1, b"k1", b"k5").unwrap();
+        db.put_cf(&cf1, b"k4", b"v2").unwrap();
+        assert_eq!(txn.commit().unwrap_err().kind(), ErrorKind::Busy);
+    }
+}
+
+#[test]
+fn update_cf() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_update_cf");
+    let db: OptimisticTransactionDB = OptimisticTransactionDB::open_default(&path).unwrap();
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                // a `&ColumnFamily` cannot be shared between threads, so each looks it up
+                let cf = db.cf_handle("default").unwrap();
+                for _ in 0..1000 {
+                    db.update_cf(&cf, b"counter", |value| {
+                        let count: u64 =
+                            value.map_or(0, |v| std::str::from_utf8(v).unwrap().parse().unwrap());
+                        Some((count + 1).to_string().into_bytes())
+                    })
+                    .unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(db.get(b"counter").unwrap().unwrap(), b"8000");
+
+    // returning `None` deletes the key
+    let cf = db.cf_handle("default").unwrap();
+    assert_eq!(db.update_cf(&cf, b"counter", |_| None).unwrap(), None);
+    assert!(db.get(b"counter").unwrap().is_none());
+}
+
+#[test]
+fn compare_and_put_cf() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_compare_and_put_cf");
+    let db: OptimisticTransactionDB = OptimisticTransactionDB::open_default(&path).unwrap();
+    let cf = db.cf_handle("default").unwrap();
+
+    assert!(db.compare_and_put_cf(&cf, b"k1", None, b"v1").unwrap());
+    assert!(!db.compare_and_put_cf(&cf, b"k1", None, b"v2").unwrap());
+    assert!(!db
+        .compare_and_put_cf(&cf, b"k1", Some(&b"v0"[..]), b"v2")
+        .unwrap());
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    assert!(db
+        .compare_and_put_cf(&cf, b"k1", Some(&b"v1"[..]), b"v2")
+        .unwrap());
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v2");
+}
+
+#[test]
+fn write_validation() {
+    let path = DBPath::new("_rust_rocksdb_optimistic_transaction_db_write_validation");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_write_validation(WriteValidationOptions {
+            max_key_bytes: Some(8),
+            ..WriteValidationOptions::default()
+        });
+        let db: OptimisticTransactionDB = OptimisticTransactionDB::open(&opts, &path).unwrap();
+
+        let err = db.put(vec![b'k'; 16], b"v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        batch.put(vec![b'k'; 16], b"v");
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        let err = txn.merge(vec![b'k'; 16], b"v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap(), Some(b"v1".to_vec()));
+    }
+}
@@ -16,7 +16,7 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{properties, Options, DB};
+use rocksdb::{properties, BlockBasedOptions, Cache, CacheEntryRoleStats, ErrorKind, Options, DB};
 use util::DBPath;
 
 #[test]
@@ -95,3 +95,142 @@ fn property_int_cf_test() {
         assert_eq!(total_keys, Some(0));
     }
 }
+
+#[test]
+fn table_cache_test() {
+    let n = DBPath::new("_rust_rocksdb_table_cache_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_disable_auto_compactions(true);
+    assert_eq!(opts.get_max_open_files(), -1);
+    {
+        let db = DB::open(&opts, &n).unwrap();
+        for file in 0..60 {
+            for i in 0..100 {
+                db.put(format!("k{file:02}-{i:03}"), b"value").unwrap();
+            }
+            db.flush().unwrap();
+        }
+        assert_eq!(db.num_files_at_level(0).unwrap(), 60);
+        assert_eq!(db.num_files_at_level(1).unwrap(), 0);
+    }
+
+    let check_reads = |db: &DB| {
+        for file in 0..60 {
+            let key = format!("k{file:02}-050");
+            assert_eq!(db.get(key).unwrap(), Some(b"value".to_vec()));
+        }
+    };
+
+    // all files are opened with the DB
+    let all_open_mem = {
+        let db = DB::open(&opts, &n).unwrap();
+        assert_eq!(db.max_open_files(), -1);
+        assert_eq!(db.table_cache_capacity(), 4_194_304);
+        let mem = db.estimate_table_readers_mem().unwrap();
+        assert!(mem > 0);
+        check_reads(&db);
+        mem
+    };
+
+    opts.set_max_open_files(50);
+    opts.set_max_file_opening_threads(2);
+    assert_eq!(opts.get_max_open_files(), 50);
+    assert_eq!(opts.get_max_file_opening_threads(), 2);
+    {
+        let db = DB::open(&opts, &n).unwrap();
+        assert_eq!(db.max_open_files(), 50);
+        assert_eq!(db.table_cache_capacity(), 40);
+        check_reads(&db);
+        // at most 40 of the 60 readers are cached
+        let mem = db.estimate_table_readers_mem().unwrap();
+        assert!(mem < all_open_mem, "{} >= {}", mem, all_open_mem);
+    }
+
+    // RocksDB raises the limit to 20
+    opts.set_max_open_files(5);
+    {
+        let db = DB::open(&opts, &n).unwrap();
+        assert_eq!(db.max_open_files(), 20);
+        assert_eq!(db.table_cache_capacity(), 10);
+        check_reads(&db);
+    }
+}
+
+#[test]
+fn block_cache_entry_stats_test() {
+    let n = DBPath::new("_rust_rocksdb_block_cache_entry_stats_test");
+    {
+        let cache = Cache::new_lru_cache(8 << 20);
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(&cache);
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_block_based_table_factory(&block_opts);
+        let db = DB::open(&opts, &n).unwrap();
+        for i in 0..1000 {
+            db.put(format!("k{i:04}"), vec![b'v'; 100]).unwrap();
+        }
+        db.flush().unwrap();
+        for i in 0..1000 {
+            assert!(db.get(format!("k{i:04}")).unwrap().is_some());
+        }
+
+        let stats = db.block_cache_entry_stats().unwrap();
+        assert_eq!(stats.capacity, 8 << 20);
+        assert!(stats.data_block().count > 0);
+        assert!(stats.data_block().charge > 0);
+        assert!(stats.usage >= stats.data_block().charge);
+        assert_eq!(stats.role("NoSuchRole"), CacheEntryRoleStats::default());
+    }
+
+    {
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.disable_cache();
+        let mut opts = Options::default();
+        opts.set_block_based_table_factory(&block_opts);
+        let db = DB::open(&opts, &n).unwrap();
+        let err = db.block_cache_entry_stats().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NotSupported);
+    }
+}
+
+#[test]
+fn level_stats_test() {
+    let n = DBPath::new("_rust_rocksdb_level_stats_test");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    opts.set_disable_auto_compactions(true);
+    opts.set_level_zero_file_num_compaction_trigger(2);
+    let db = DB::open_cf(&opts, &n, ["cf"]).unwrap();
+    let cf = db.cf_handle("cf").unwrap();
+
+    let stats = db.level_stats_cf(&cf).unwrap();
+    assert_eq!(stats.len(), 7);
+    for (i, level) in stats.iter().enumerate() {
+        assert_eq!(level.level, i as i32);
+        assert_eq!(level.num_files, 0);
+        assert_eq!(level.size_bytes, 0);
+    }
+
+    for i in 0..5 {
+        db.put_cf(&cf, format!("k{i}"), "v").unwrap();
+        db.flush_cf(&cf).unwrap();
+    }
+    let stats = db.level_stats_cf(&cf).unwrap();
+    let l0 = &stats[0];
+    assert_eq!(l0.num_files, 5);
+    assert!(l0.size_bytes > 0);
+    // 5 files against a trigger of 2, with nothing compacting them
+    assert!(l0.score > 1.0, "{:?}", l0);
+    assert!(l0.score >= 2.5, "{:?}", l0);
+    assert_eq!(l0.being_compacted_files, 0);
+    assert!(stats[1..].iter().all(|level| level.num_files == 0));
+
+    db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+    let stats = db.level_stats_cf(&cf).unwrap();
+    assert_eq!(stats[0].num_files, 0);
+    assert!(stats[0].score < 1.0, "{:?}", stats[0]);
+    assert_eq!(stats.iter().map(|level| level.num_files).sum::<usize>(), 1);
+}
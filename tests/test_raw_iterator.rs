@@ -16,7 +16,10 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{DBAccess, DBRawIteratorWithThreadMode, DB};
+use rocksdb::{
+    DBAccess, DBRawIteratorWithThreadMode, DBWithThreadMode, MultiThreaded, Options, PrefixRange,
+    ReadOptions, DB,
+};
 use util::DBPath;
 
 fn assert_item<D: DBAccess>(iter: &DBRawIteratorWithThreadMode<'_, D>, key: &[u8], value: &[u8]) {
@@ -150,3 +153,104 @@ pub fn test_next_without_seek() {
         iter.next();
     }
 }
+
+fn collect_keys<D: DBAccess>(iter: &mut DBRawIteratorWithThreadMode<'_, D>) -> Vec<Vec<u8>> {
+    let mut keys = vec![];
+    iter.seek_to_first();
+    while let Some(key) = iter.key() {
+        keys.push(key.to_vec());
+        iter.next();
+    }
+    iter.status().unwrap();
+    keys
+}
+
+#[test]
+pub fn test_bounds_outlive_caller_buffers() {
+    let n = DBPath::new("bounds_outlive_caller_buffers");
+    {
+        let db = DB::open_default(&n).unwrap();
+        for i in 0..10 {
+            db.put(format!("k{i}"), b"v").unwrap();
+        }
+
+        let mut iter = {
+            let lower = b"k2".to_vec();
+            let upper = String::from("k5");
+            let mut readopts = ReadOptions::default();
+            readopts.set_iterate_lower_bound(lower);
+            readopts.set_iterate_upper_bound(upper);
+            db.raw_iterator_opt(readopts)
+        };
+        // overwrite freed memory the bounds could still point to
+        let _garbage = vec![vec![0xffu8; 2]; 64];
+        assert_eq!(collect_keys(&mut iter), [b"k2", b"k3", b"k4"]);
+
+        iter.seek_to_last();
+        assert_item(&iter, b"k4", b"v");
+    }
+}
+
+#[test]
+pub fn test_set_bounds() {
+    let n = DBPath::new("set_bounds");
+    {
+        let db = DB::open_default(&n).unwrap();
+        for i in 0..10 {
+            db.put(format!("k{i}"), b"v").unwrap();
+        }
+
+        let mut readopts = ReadOptions::default();
+        readopts.set_iterate_range(b"k1".to_vec()..b"k3".to_vec());
+        let mut iter = db.raw_iterator_opt(readopts);
+        assert_eq!(collect_keys(&mut iter), [b"k1", b"k2"]);
+
+        // retarget both bounds in place
+        iter.set_bounds(format!("k{}", 6)..format!("k{}", 8));
+        assert_eq!(collect_keys(&mut iter), [b"k6", b"k7"]);
+        iter.seek(b"k0");
+        assert_item(&iter, b"k6", b"v");
+        iter.seek_for_prev(b"k9");
+        assert_item(&iter, b"k7", b"v");
+
+        // drop the lower bound
+        iter.set_bounds(..b"k2".as_slice());
+        assert_eq!(collect_keys(&mut iter), [b"k0", b"k1"]);
+
+        // add a lower bound and drop the upper bound
+        iter.set_bounds(b"k8".as_slice()..);
+        assert_eq!(collect_keys(&mut iter), [b"k8", b"k9"]);
+
+        iter.set_bounds(..);
+        assert_eq!(collect_keys(&mut iter).len(), 10);
+
+        // a prefix range sets both bounds again
+        db.put(b"k10", b"v").unwrap();
+        iter.set_bounds(PrefixRange(b"k1".as_slice()));
+        assert_eq!(collect_keys(&mut iter), [b"k1".to_vec(), b"k10".to_vec()]);
+    }
+}
+
+#[test]
+pub fn test_set_bounds_after_drop_cf() {
+    let n = DBPath::new("set_bounds_after_drop_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let db = DBWithThreadMode::<MultiThreaded>::open_cf(&opts, &n, ["cf"]).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+        for i in 0..4 {
+            db.put_cf(&cf, format!("k{i}"), b"v").unwrap();
+        }
+        let mut iter = db.raw_iterator_cf(&cf);
+
+        // the iterator keeps the handle it recreates itself from alive
+        db.drop_cf("cf").unwrap();
+        drop(cf);
+        iter.set_bounds(b"k1".as_slice()..b"k3".as_slice());
+        assert_eq!(collect_keys(&mut iter), [b"k1", b"k2"]);
+        iter.set_bounds(..);
+        assert_eq!(collect_keys(&mut iter).len(), 4);
+    }
+}
@@ -14,10 +14,34 @@
 
 mod util;
 
-use std::{fs, io::Read as _};
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::CStr,
+    fs,
+    io::Read as _,
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use rocksdb::{
-    BlockBasedOptions, Cache, DBCompressionType, DataBlockIndexType, Env, Options, ReadOptions, DB,
+    compaction_filter::CompactionFilter,
+    compaction_filter_factory::{CompactionFilterContext, CompactionFilterFactory},
+    options_util::{self, OptionDiff},
+    perf::{set_perf_stats, PerfStatsLevel},
+    statistics::Ticker,
+    table_properties::{
+        EntryType, TablePropertiesCollector, TablePropertiesCollectorFactory,
+        TablePropertiesCollectorFactoryContext,
+    },
+    BlockBasedIndexType, BlockBasedOptions, Cache, ColumnFamilyDescriptor, CompactionDecision,
+    DBCompressionType, DataBlockIndexType, Env, ErrorKind, IteratorMode, MergeOperands, Options,
+    PerfContext, PerfMetric, Priority, ReadOptions, WaitForCompactOptions, DB,
+    DEFAULT_COLUMN_FAMILY_NAME,
 };
 use util::DBPath;
 
@@ -66,13 +90,40 @@ fn test_increase_parallelism() {
 
 #[test]
 fn test_set_level_compaction_dynamic_level_bytes() {
-    let n = DBPath::new("_rust_rocksdb_test_set_level_compaction_dynamic_level_bytes");
-    {
+    // the levels holding data after a full compaction
+    fn compacted_levels(dynamic: bool) -> Vec<i32> {
+        let n = DBPath::new(&format!(
+            "_rust_rocksdb_test_set_level_compaction_dynamic_level_bytes_{dynamic}"
+        ));
         let mut opts = Options::default();
         opts.create_if_missing(true);
-        opts.set_level_compaction_dynamic_level_bytes(true);
-        let _db = DB::open(&opts, &n).unwrap();
+        opts.set_num_levels(7);
+        opts.set_level_compaction_dynamic_level_bytes(dynamic);
+        let db = DB::open(&opts, &n).unwrap();
+        for i in 0..4 {
+            for j in 0..100 {
+                db.put(format!("key{j:03}"), format!("value{i}")).unwrap();
+            }
+            db.flush().unwrap();
+        }
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+
+        let metadata = db.get_column_family_metadata();
+        assert_eq!(metadata.levels.len(), 7);
+        assert_eq!(
+            metadata.levels.iter().map(|l| l.file_count).sum::<usize>(),
+            metadata.file_count
+        );
+        metadata
+            .levels
+            .iter()
+            .filter(|level| level.file_count > 0)
+            .map(|level| level.level)
+            .collect()
     }
+
+    assert_eq!(compacted_levels(true), vec![6]);
+    assert_eq!(compacted_levels(false), vec![1]);
 }
 
 #[test]
@@ -106,6 +157,199 @@ fn test_block_based_options() {
     }
 }
 
+#[test]
+fn test_pin_l0_filter_and_index_blocks() {
+    let n = DBPath::new("_rust_rocksdb_test_pin_l0_filter_and_index_blocks");
+    let cache = Cache::new_lru_cache(1 << 20);
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_block_cache(&cache);
+        block_opts.set_bloom_filter(10.0, false);
+        block_opts.set_cache_index_and_filter_blocks(true);
+        block_opts.set_pin_l0_filter_and_index_blocks_in_cache(true);
+        block_opts.set_pin_top_level_index_and_filter(true);
+        opts.set_block_based_table_factory(&block_opts);
+
+        let db = DB::open(&opts, &n).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.flush().unwrap();
+        assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get(b"k2").unwrap(), None);
+
+        // the blocks of the level 0 file are held by its table reader
+        assert!(cache.get_pinned_usage() > 0);
+
+        let log = fs::read_to_string(format!("{}/LOG", (&n).as_ref().to_str().unwrap())).unwrap();
+        assert!(log.contains("pin_l0_filter_and_index_blocks_in_cache: 1"));
+        assert!(log.contains("pin_top_level_index_and_filter: 1"));
+    }
+}
+
+#[test]
+fn test_partitioned_bloom_filter() {
+    let n = DBPath::new("_rust_rocksdb_test_partitioned_bloom_filter");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.enable_statistics();
+
+    let mut block_opts = BlockBasedOptions::default();
+    block_opts.set_bloom_filter(10.0, false);
+    block_opts.set_partition_filters(true);
+    block_opts.set_index_type(BlockBasedIndexType::TwoLevelIndexSearch);
+    // small partitions, so the filter of the file is split into several of them
+    block_opts.set_metadata_block_size(256);
+    opts.set_block_based_table_factory(&block_opts);
+
+    let db = DB::open(&opts, &n).unwrap();
+    for i in 0..10_000 {
+        db.put(format!("key{i:05}"), format!("value{i}")).unwrap();
+    }
+    db.flush().unwrap();
+
+    for i in (0..10_000).step_by(7) {
+        assert_eq!(
+            db.get(format!("key{i:05}")).unwrap().unwrap(),
+            format!("value{i}").as_bytes()
+        );
+        assert_eq!(db.get(format!("key{i:05}-missing")).unwrap(), None);
+    }
+    // most of the missing keys were ruled out by the filter
+    assert!(opts.get_ticker_count(Ticker::BloomFilterUseful) > 1000);
+
+    let log = fs::read_to_string(format!("{}/LOG", (&n).as_ref().to_str().unwrap())).unwrap();
+    assert!(log.contains("partition_filters: 1"));
+}
+
+#[test]
+fn test_index_types() {
+    // the LOG prints the index type as its value in RocksDB's enum
+    for (index_type, code) in [
+        (BlockBasedIndexType::TwoLevelIndexSearch, 2),
+        (BlockBasedIndexType::BinarySearchWithFirstKey, 3),
+    ] {
+        let n = DBPath::new(&format!("_rust_rocksdb_test_index_type_{code}"));
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let mut block_opts = BlockBasedOptions::default();
+        block_opts.set_index_type(index_type);
+        // small blocks and index partitions, so the index spans many blocks
+        block_opts.set_block_size(256);
+        block_opts.set_metadata_block_size(256);
+        opts.set_block_based_table_factory(&block_opts);
+
+        let db = DB::open(&opts, &n).unwrap();
+        for i in 0..20_000 {
+            db.put(format!("key{i:05}"), format!("value{i}")).unwrap();
+        }
+        db.flush().unwrap();
+
+        for i in (0..20_000).step_by(13) {
+            assert_eq!(
+                db.get(format!("key{i:05}")).unwrap().unwrap(),
+                format!("value{i}").as_bytes()
+            );
+        }
+        assert!(db.get("key20000").unwrap().is_none());
+        let mut iter = db.raw_iterator();
+        iter.seek(b"key12345");
+        assert_eq!(iter.value().unwrap(), b"value12345");
+        assert_eq!(db.iterator(IteratorMode::Start).count(), 20_000);
+
+        let log = fs::read_to_string(format!("{}/LOG", (&n).as_ref().to_str().unwrap())).unwrap();
+        assert!(log.contains(&format!("index_type: {code}")));
+    }
+}
+
+#[test]
+fn test_background_jobs() {
+    let n = DBPath::new("_rust_rocksdb_test_background_jobs");
+    let mut env = Env::new().unwrap();
+    env.set_background_threads_with_priority(8, Priority::Low);
+    env.set_background_threads_with_priority(2, Priority::High);
+    env.set_background_threads_with_priority(1, Priority::Bottom);
+    assert_eq!(env.get_background_threads(Priority::Low), 8);
+    assert_eq!(env.get_background_threads(Priority::High), 2);
+    assert_eq!(env.get_background_threads(Priority::Bottom), 1);
+
+    let mut opts = Options::default();
+    assert_eq!(opts.get_max_background_jobs(), 2);
+    opts.create_if_missing(true);
+    opts.set_env(&env);
+    opts.set_max_background_jobs(10);
+    #[allow(deprecated)]
+    {
+        opts.set_max_background_flushes(2);
+        opts.set_max_background_compactions(8);
+    }
+    assert_eq!(opts.get_max_background_jobs(), 10);
+    assert_eq!(opts.get_max_background_flushes(), 2);
+    assert_eq!(opts.get_max_background_compactions(), 8);
+    opts.enable_statistics();
+    opts.set_write_buffer_size(64 << 10);
+    opts.set_level_zero_file_num_compaction_trigger(2);
+
+    {
+        let db = DB::open(&opts, &n).unwrap();
+        for i in 0..20_000 {
+            db.put(format!("k{i:05}"), [b'v'; 100]).unwrap();
+        }
+        db.flush().unwrap();
+        db.wait_for_compact(&WaitForCompactOptions::default())
+            .unwrap();
+        assert_eq!(db.get(b"k12345").unwrap().unwrap(), [b'v'; 100]);
+    }
+    assert!(opts.get_ticker_count(Ticker::FlushWriteBytes) > 0);
+    assert!(opts.get_ticker_count(Ticker::CompactWriteBytes) > 0);
+
+    let log = fs::read_to_string(format!("{}/LOG", (&n).as_ref().to_str().unwrap())).unwrap();
+    assert!(log.contains("Options.max_background_jobs: 10"));
+    assert!(log.contains("Options.max_background_flushes: 2"));
+    assert!(log.contains("Options.max_background_compactions: 8"));
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+fn test_memtable_huge_page_size() {
+    let n = DBPath::new("_rust_rocksdb_test_memtable_huge_page_size");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_memtable_huge_page_size(2 << 20);
+    assert_eq!(opts.get_memtable_huge_page_size(), 2 << 20);
+
+    // falls back to malloc when no huge pages are reserved
+    let db = DB::open(&opts, &n).unwrap();
+    for i in 0..1000 {
+        db.put(format!("k{i}"), b"value").unwrap();
+    }
+    assert_eq!(db.get(b"k999").unwrap().unwrap(), b"value");
+}
+
+#[test]
+fn test_db_log_dir() {
+    let path = DBPath::new("_rust_rocksdb_test_db_log_dir");
+    let log_dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_test_db_log_dir_logs")
+        .tempdir()
+        .unwrap();
+    let log_path = log_dir.path().join("logs");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_db_log_dir(&log_path);
+    {
+        let _db = DB::open(&opts, &path).unwrap();
+    }
+
+    let logs: Vec<String> = fs::read_dir(&log_path)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(logs.iter().any(|name| name.ends_with("_LOG")), "{:?}", logs);
+    assert!(!(&path).as_ref().join("LOG").exists());
+}
+
 #[test]
 fn test_read_options() {
     let mut read_opts = ReadOptions::default();
@@ -276,6 +520,23 @@ fn test_set_avoid_unnecessary_blocking_io() {
         let db = DB::open(&opts, &path).unwrap();
         let _ = db.put(b"k1", b"a");
         assert_eq!(&*db.get(b"k1").unwrap().unwrap(), b"a");
+
+        // iterators pinning files made obsolete by compaction still read correct data
+        db.flush().unwrap();
+        let iter = db.iterator(IteratorMode::Start);
+        db.put(b"k2", b"b").unwrap();
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+        let items: Vec<_> = iter.map(Result::unwrap).collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(&*items[0].0, b"k1");
+        assert_eq!(&*items[0].1, b"a");
+
+        let items: Vec<_> = db
+            .iterator(IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!(&*items[1].0, b"k2");
     }
 }
 
@@ -313,3 +574,675 @@ fn test_set_ratelimiter() {
         assert_eq!(&*db.get(b"k2").unwrap().unwrap(), b"a");
     }
 }
+
+#[test]
+fn test_set_pending_compaction_bytes_limit() {
+    let path = DBPath::new("_set_pending_compaction_bytes_limit");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.enable_statistics();
+        opts.set_write_buffer_size(64 * 1024);
+        opts.set_level_zero_file_num_compaction_trigger(1);
+        opts.set_max_bytes_for_level_base(64 * 1024);
+        opts.set_soft_pending_compaction_bytes_limit(1);
+        opts.set_hard_pending_compaction_bytes_limit(1 << 30);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // writing faster than compaction keeps up with triggers the soft limit
+        let value = vec![0x42; 16 * 1024];
+        for i in 0..10_000 {
+            db.put(format!("k{i:05}"), &value).unwrap();
+            if opts.get_ticker_count(Ticker::StallMicros) > 0 {
+                break;
+            }
+        }
+        assert!(opts.get_ticker_count(Ticker::StallMicros) > 0);
+    }
+}
+
+#[test]
+fn test_set_max_sequential_skip_in_iterations() {
+    let path = DBPath::new("_set_max_sequential_skip_in_iterations");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.enable_statistics();
+        opts.set_max_sequential_skip_in_iterations(2);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // the memtable keeps every version of the overwritten key
+        for i in 0..100 {
+            db.put(b"a", format!("v{i}")).unwrap();
+        }
+        db.put(b"b", b"v").unwrap();
+
+        let items: Vec<_> = db
+            .iterator(IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items.len(), 2);
+        assert_eq!((&*items[0].0, &*items[0].1), (&b"a"[..], &b"v99"[..]));
+        assert_eq!((&*items[1].0, &*items[1].1), (&b"b"[..], &b"v"[..]));
+        assert!(opts.get_ticker_count(Ticker::NumberOfReseeksInIteration) > 0);
+    }
+}
+
+#[test]
+fn test_set_unordered_write() {
+    let path = DBPath::new("_rust_rocksdb_test_set_unordered_write");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_unordered_write(true);
+    let db = Arc::new(DB::open(&opts, &path).unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    db.put(format!("{t}-{i:04}"), b"v").unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(db.iterator(IteratorMode::Start).count(), 4000);
+    for t in 0..4 {
+        for i in 0..1000 {
+            assert!(db.get(format!("{t}-{i:04}")).unwrap().is_some());
+        }
+    }
+
+    // unordered_write cannot be combined with pipelined writes
+    drop(db);
+    opts.set_enable_pipelined_write(true);
+    assert!(DB::open(&opts, &path).is_err());
+}
+
+#[test]
+fn test_set_two_write_queues() {
+    let path = DBPath::new("_rust_rocksdb_test_set_two_write_queues");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_two_write_queues(true);
+    let db = Arc::new(DB::open(&opts, &path).unwrap());
+
+    let handles: Vec<_> = (0..4)
+        .map(|t| {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                for i in 0..1000 {
+                    db.put(format!("{t}-{i:04}"), format!("{i}")).unwrap();
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    for t in 0..4 {
+        for i in 0..1000 {
+            assert_eq!(
+                db.get(format!("{t}-{i:04}")).unwrap().unwrap(),
+                format!("{i}").as_bytes()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_shared_row_and_blob_cache() {
+    let path = DBPath::new("_rust_rocksdb_test_shared_row_and_blob_cache");
+    let cache = Cache::new_lru_cache(8 << 20);
+    let mut block_opts = BlockBasedOptions::default();
+    block_opts.set_block_cache(&cache);
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.enable_statistics();
+    opts.set_block_based_table_factory(&block_opts);
+    opts.set_row_cache(&cache);
+    opts.set_enable_blob_files(true);
+    opts.set_blob_cache(&cache);
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(b"key", b"value").unwrap();
+        db.flush().unwrap();
+
+        set_perf_stats(PerfStatsLevel::EnableCount);
+        let mut ctx = PerfContext::default();
+        assert_eq!(db.get(b"key").unwrap().unwrap(), b"value");
+        assert_eq!(opts.get_ticker_count(Ticker::RowCacheMiss), 1);
+        assert!(
+            ctx.metric(PerfMetric::BlockCacheHitCount) + ctx.metric(PerfMetric::BlockReadCount) > 0
+        );
+
+        for i in 1..=10 {
+            ctx.reset();
+            assert_eq!(db.get(b"key").unwrap().unwrap(), b"value");
+            assert_eq!(opts.get_ticker_count(Ticker::RowCacheHit), i);
+            assert_eq!(ctx.metric(PerfMetric::BlockCacheHitCount), 0);
+            assert_eq!(ctx.metric(PerfMetric::BlockReadCount), 0);
+        }
+        set_perf_stats(PerfStatsLevel::Disable);
+
+        // the cached row and blocks share one memory pool
+        assert!(cache.get_usage() > 0);
+    }
+}
+
+#[test]
+fn test_set_keep_and_recycle_log_file_num() {
+    let path = DBPath::new("_set_keep_and_recycle_log_file_num");
+    let info_logs = || {
+        fs::read_dir(&path)
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("LOG")
+            })
+            .count()
+    };
+
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_keep_log_file_num(2);
+    opts.set_recycle_log_file_num(2);
+    for i in 0..5 {
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(format!("k{i}"), b"v").unwrap();
+        assert!(info_logs() <= 2, "{} info log files", info_logs());
+    }
+
+    let db = DB::open(&opts, &path).unwrap();
+    assert_eq!(info_logs(), 2);
+    for i in 0..5 {
+        assert_eq!(db.get(format!("k{i}")).unwrap(), Some(b"v".to_vec()));
+    }
+}
+
+#[test]
+fn test_set_writable_file_max_buffer_size() {
+    let path = DBPath::new("_set_writable_file_max_buffer_size");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_writable_file_max_buffer_size(64 << 10);
+        let db = DB::open(&opts, &path).unwrap();
+
+        // write files several times larger than the buffer
+        let value = vec![b'v'; 1024];
+        for i in 0..1000 {
+            db.put(format!("k{i:04}"), &value).unwrap();
+        }
+        db.flush().unwrap();
+        db.compact_range(None::<&[u8]>, None::<&[u8]>);
+        for i in 0..1000 {
+            assert_eq!(db.get(format!("k{i:04}")).unwrap(), Some(value.clone()));
+        }
+
+        let options_file = fs::read_dir(&path)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .starts_with("OPTIONS-")
+            })
+            .max()
+            .unwrap();
+        let persisted = fs::read_to_string(options_file).unwrap();
+        assert!(persisted.contains("writable_file_max_buffer_size=65536"));
+    }
+}
+
+#[test]
+fn test_diff_options_files() {
+    let path = DBPath::new("_diff_options_files");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    drop(DB::open(&opts, &path).unwrap());
+    opts.set_write_buffer_size(16 << 20);
+    drop(DB::open(&opts, &path).unwrap());
+
+    let files = options_util::options_files(&path).unwrap();
+    assert!(files.len() >= 2, "{:?}", files);
+    let diffs =
+        options_util::diff_options_files(&files[files.len() - 2], &files[files.len() - 1]).unwrap();
+    assert_eq!(
+        diffs,
+        vec![OptionDiff {
+            section: "CFOptions".to_owned(),
+            cf: Some("default".to_owned()),
+            key: "write_buffer_size".to_owned(),
+            old: Some((64 << 20).to_string()),
+            new: Some((16 << 20).to_string()),
+        }]
+    );
+    let last = &files[files.len() - 1];
+    assert!(options_util::diff_options_files(last, last)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn test_check_options_compatibility() {
+    let path = DBPath::new("_check_options_compatibility");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    drop(DB::open_cf(&opts, &path, ["cf1"]).unwrap());
+
+    let cf1 = || ColumnFamilyDescriptor::new("cf1", Options::default());
+    options_util::check_options_compatibility(&path, &opts, &[cf1()]).unwrap();
+
+    // a missing column family
+    let err = options_util::check_options_compatibility(&path, &opts, &[]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    // a different comparator
+    let mut reverse = Options::default();
+    reverse.set_comparator("reverse", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+    let err = options_util::check_options_compatibility(
+        &path,
+        &opts,
+        &[ColumnFamilyDescriptor::new("cf1", reverse)],
+    )
+    .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert!(err.to_string().contains("comparator"), "{}", err);
+
+    // adding a merge operator is compatible
+    fn concat_merge(
+        _key: &[u8],
+        existing: Option<&[u8]>,
+        operands: &MergeOperands,
+    ) -> Option<Vec<u8>> {
+        let mut value = existing.map(<[u8]>::to_vec).unwrap_or_default();
+        for operand in operands {
+            value.extend_from_slice(operand);
+        }
+        Some(value)
+    }
+    let mut merge = Options::default();
+    merge.set_merge_operator_associative("concat", concat_merge);
+    options_util::check_options_compatibility(
+        &path,
+        &opts,
+        &[ColumnFamilyDescriptor::new("cf1", merge)],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_background_error_options() {
+    let path = DBPath::new("_background_error_options");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_comparator("reverse", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+    opts.set_paranoid_checks(false);
+    opts.set_flush_verify_memtable_count(false).unwrap();
+    opts.set_verify_sst_unique_id_in_manifest(false).unwrap();
+    opts.set_max_bgerror_resume_count(3).unwrap();
+    opts.set_bgerror_resume_retry_interval(500_000).unwrap();
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        db.put(b"k2", b"v2").unwrap();
+        db.flush().unwrap();
+        // the options set before the ones set from strings are kept
+        let keys: Vec<_> = db
+            .iterator(IteratorMode::Start)
+            .map(|item| item.unwrap().0.to_vec())
+            .collect();
+        assert_eq!(keys, [b"k2".to_vec(), b"k1".to_vec()]);
+    }
+
+    let files = options_util::options_files(&path).unwrap();
+    let persisted = fs::read_to_string(files.last().unwrap()).unwrap();
+    for option in [
+        "paranoid_checks=false",
+        "flush_verify_memtable_count=false",
+        "verify_sst_unique_id_in_manifest=false",
+        "max_bgerror_resume_count=3",
+        "bgerror_resume_retry_interval=500000",
+    ] {
+        assert!(
+            persisted.contains(option),
+            "{} not in {}",
+            option,
+            persisted
+        );
+    }
+}
+
+#[test]
+fn test_write_thread_adaptive_yield() {
+    let path = DBPath::new("_rust_rocksdb_test_write_thread_adaptive_yield");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_enable_write_thread_adaptive_yield(true);
+    opts.set_write_thread_max_yield_usec(500).unwrap();
+    let db = DB::open(&opts, &path).unwrap();
+
+    thread::scope(|scope| {
+        for t in 0..16 {
+            let db = &db;
+            scope.spawn(move || {
+                for i in 0..500 {
+                    db.put(format!("t{t:02}-k{i:03}"), "v").unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(db.iterator(IteratorMode::Start).count(), 16 * 500);
+    assert_eq!(db.get("t15-k499").unwrap().unwrap(), b"v");
+
+    let options_file = options_util::options_files(&path).unwrap().pop().unwrap();
+    let persisted = fs::read_to_string(options_file).unwrap();
+    assert!(persisted.contains("enable_write_thread_adaptive_yield=true"));
+    assert!(persisted.contains("write_thread_max_yield_usec=500"));
+}
+
+#[test]
+fn test_set_max_file_opening_threads() {
+    let path = DBPath::new("_rust_rocksdb_test_set_max_file_opening_threads");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_max_open_files(-1);
+    opts.set_disable_auto_compactions(true);
+    opts.set_max_file_opening_threads(4);
+    {
+        let db = DB::open(&opts, &path).unwrap();
+        for i in 0..8 {
+            db.put(format!("k{i}"), format!("v{i}")).unwrap();
+            db.flush().unwrap();
+        }
+    }
+
+    // all the files are opened by the threads on open
+    let db = DB::open(&opts, &path).unwrap();
+    for i in 0..8 {
+        assert_eq!(
+            db.get(format!("k{i}")).unwrap().unwrap(),
+            format!("v{i}").as_bytes()
+        );
+    }
+    let options_file = options_util::options_files(&path).unwrap().pop().unwrap();
+    let persisted = fs::read_to_string(options_file).unwrap();
+    assert!(persisted.contains("max_file_opening_threads=4"));
+}
+
+#[test]
+fn test_avoid_flush_during_recovery() {
+    // the writes are made by a child process crashing before closing the DB
+    const DB_PATH_ENV: &str = "_RUST_ROCKSDB_CRASHING_WRITER_DB";
+    if let Some(path) = env::var_os(DB_PATH_ENV) {
+        let db = DB::open_default(path).unwrap();
+        for i in 0..100 {
+            db.put(format!("k{i}"), format!("v{i}")).unwrap();
+        }
+        std::process::abort();
+    }
+
+    let path = DBPath::new("_rust_rocksdb_test_avoid_flush_during_recovery");
+    let status = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "test_avoid_flush_during_recovery"])
+        .env(DB_PATH_ENV, (&path).as_ref())
+        .status()
+        .unwrap();
+    assert!(!status.success());
+
+    let mut opts = Options::default();
+    opts.set_avoid_flush_during_recovery(true);
+    for _ in 0..2 {
+        // the writes are replayed from the WAL on each open, without being flushed
+        let db = DB::open(&opts, &path).unwrap();
+        assert!(db.live_files().unwrap().is_empty());
+        for i in 0..100 {
+            assert_eq!(
+                db.get(format!("k{i}")).unwrap().unwrap(),
+                format!("v{i}").as_bytes()
+            );
+        }
+    }
+}
+
+/// Counts its live instances, to check the callbacks of cloned options are
+/// dropped exactly once.
+struct Tracked(Arc<AtomicUsize>);
+
+impl Tracked {
+    fn new(alive: &Arc<AtomicUsize>) -> Self {
+        alive.fetch_add(1, Ordering::SeqCst);
+        Self(alive.clone())
+    }
+}
+
+impl Clone for Tracked {
+    fn clone(&self) -> Self {
+        Self::new(&self.0)
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn remove_tmp(key: &[u8]) -> CompactionDecision {
+    if key.starts_with(b"tmp") {
+        CompactionDecision::Remove
+    } else {
+        CompactionDecision::Keep
+    }
+}
+
+struct TmpRemover;
+
+impl CompactionFilter for TmpRemover {
+    fn filter(&mut self, _level: u32, key: &[u8], _value: &[u8]) -> CompactionDecision {
+        remove_tmp(key)
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"tmp-remover\0").unwrap()
+    }
+}
+
+// The field is only held to be dropped with the factory.
+struct TmpRemoverFactory(#[allow(dead_code)] Tracked);
+
+impl CompactionFilterFactory for TmpRemoverFactory {
+    type Filter = TmpRemover;
+
+    fn create(&mut self, _context: CompactionFilterContext) -> Self::Filter {
+        TmpRemover
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"tmp-remover-factory\0").unwrap()
+    }
+}
+
+struct PutCounter(usize);
+
+impl TablePropertiesCollector for PutCounter {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"put-counter\0").unwrap()
+    }
+
+    fn add_user_key(
+        &mut self,
+        _key: &[u8],
+        _value: &[u8],
+        entry_type: EntryType,
+        _seq: u64,
+        _file_size: u64,
+    ) {
+        if entry_type == EntryType::Put {
+            self.0 += 1;
+        }
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        BTreeMap::from([(
+            b"puts".as_slice().into(),
+            self.0.to_string().into_bytes().into(),
+        )])
+    }
+}
+
+// The field is only held to be dropped with the factory.
+struct PutCounterFactory(#[allow(dead_code)] Tracked);
+
+impl TablePropertiesCollectorFactory for PutCounterFactory {
+    type Collector = PutCounter;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        PutCounter(0)
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"put-counter-factory\0").unwrap()
+    }
+}
+
+fn options_with_callbacks(alive: &Arc<AtomicUsize>, filter_factory: bool) -> Options {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    let tracked = Tracked::new(alive);
+    opts.set_comparator(
+        "tracked-bytewise",
+        Box::new(move |a: &[u8], b: &[u8]| {
+            let _ = &tracked;
+            a.cmp(b)
+        }),
+    );
+    let tracked = Tracked::new(alive);
+    opts.set_merge_operator_associative(
+        "tracked-concat",
+        move |_key: &[u8], existing: Option<&[u8]>, operands: &MergeOperands| {
+            let _ = &tracked;
+            let mut result = existing.map(<[u8]>::to_vec).unwrap_or_default();
+            for operand in operands {
+                result.extend_from_slice(operand);
+            }
+            Some(result)
+        },
+    );
+    if filter_factory {
+        opts.set_compaction_filter_factory(TmpRemoverFactory(Tracked::new(alive)));
+    } else {
+        let tracked = Tracked::new(alive);
+        opts.set_compaction_filter(
+            "tmp-remover",
+            move |_level: u32, key: &[u8], _value: &[u8]| {
+                let _ = &tracked;
+                remove_tmp(key)
+            },
+        );
+    }
+    opts.add_table_properties_collector_factory(PutCounterFactory(Tracked::new(alive)));
+    opts
+}
+
+#[test]
+fn test_clone_options_with_callbacks() {
+    for filter_factory in [false, true] {
+        for drop_clone_first in [false, true] {
+            let alive = Arc::new(AtomicUsize::new(0));
+            let path1 = DBPath::new(&format!(
+                "_rust_rocksdb_clone_options_{filter_factory}_{drop_clone_first}_1"
+            ));
+            let path2 = DBPath::new(&format!(
+                "_rust_rocksdb_clone_options_{filter_factory}_{drop_clone_first}_2"
+            ));
+
+            let opts = options_with_callbacks(&alive, filter_factory);
+            let registered = alive.load(Ordering::SeqCst);
+            // the clone shares the callbacks
+            let cloned = opts.clone();
+            assert_eq!(alive.load(Ordering::SeqCst), registered);
+
+            let db1 = DB::open(&opts, &path1).unwrap();
+            let db2 = DB::open(&cloned, &path2).unwrap();
+            for (db, n) in [(&db1, 10), (&db2, 20)] {
+                for i in 0..n {
+                    db.put(format!("k{i:02}"), "v").unwrap();
+                }
+                db.put("tmp", "v").unwrap();
+                db.merge("m", "a").unwrap();
+                db.merge("m", "b").unwrap();
+                db.flush().unwrap();
+            }
+            // each DB collects the properties of its own files
+            for (db, n) in [(&db1, 10), (&db2, 20)] {
+                let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+                let tables = db.get_properties_of_all_range(&cf).unwrap().tables;
+                assert_eq!(tables.len(), 1);
+                let properties = tables[0].user_collected_properties();
+                assert_eq!(
+                    &*properties[b"puts".as_slice()],
+                    (n + 1).to_string().as_bytes()
+                );
+            }
+
+            let (first, second) = if drop_clone_first {
+                ((cloned, db2), (opts, db1))
+            } else {
+                ((opts, db1), (cloned, db2))
+            };
+            drop(first);
+
+            // the callbacks outlive the options and the DB dropped first
+            let (opts, db) = second;
+            db.compact_range(None::<&[u8]>, None::<&[u8]>);
+            assert!(db.get("tmp").unwrap().is_none());
+            assert_eq!(db.get("k00").unwrap().unwrap(), b"v");
+            assert_eq!(db.get("m").unwrap().unwrap(), b"ab");
+            assert_eq!(alive.load(Ordering::SeqCst), registered);
+            drop(db);
+            assert_eq!(alive.load(Ordering::SeqCst), registered);
+            drop(opts);
+            assert_eq!(alive.load(Ordering::SeqCst), 0);
+        }
+    }
+
+    // a column family keeps the callbacks of the options it was created with
+    let alive = Arc::new(AtomicUsize::new(0));
+    let path = DBPath::new("_rust_rocksdb_clone_options_cf");
+    {
+        #[cfg(feature = "multi-threaded-cf")]
+        let db = DB::open_default(&path).unwrap();
+        #[cfg(not(feature = "multi-threaded-cf"))]
+        let mut db = DB::open_default(&path).unwrap();
+
+        db.create_cf("cf", &options_with_callbacks(&alive, false))
+            .unwrap();
+        let registered = alive.load(Ordering::SeqCst);
+        assert!(registered > 0);
+        {
+            let cf = db.cf_handle("cf").unwrap();
+            db.put_cf(&cf, "tmp", "v").unwrap();
+            db.merge_cf(&cf, "m", "a").unwrap();
+            db.flush_cf(&cf).unwrap();
+            db.merge_cf(&cf, "m", "b").unwrap();
+            db.compact_range_cf(&cf, None::<&[u8]>, None::<&[u8]>);
+            assert!(db.get_cf(&cf, "tmp").unwrap().is_none());
+            assert_eq!(db.get_cf(&cf, "m").unwrap().unwrap(), b"ab");
+            assert_eq!(alive.load(Ordering::SeqCst), registered);
+        }
+
+        // and releases them once dropped, while the DB stays open
+        db.drop_cf("cf").unwrap();
+        assert_eq!(alive.load(Ordering::SeqCst), 0);
+    }
+}
@@ -16,7 +16,11 @@ mod util;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{Error, Options, SstFileWriter, DB};
+use rocksdb::{
+    checkpoint::ExportImportFilesMetaData, Env, Error, ErrorKind, ImportColumnFamilyOptions,
+    IngestExternalFileOptions, IteratorMode, Options, ReadOptions, SstFileWriter, DB,
+    DEFAULT_COLUMN_FAMILY_NAME,
+};
 use util::DBPath;
 
 #[test]
@@ -50,3 +54,315 @@ fn sst_file_writer_works() {
         assert!(db.get(b"k3").unwrap().is_none());
     }
 }
+
+#[test]
+fn sst_file_writer_ingest_from_buffers() {
+    let db_path = DBPath::new("_rust_rocksdb_sstfilewriter_buffers");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_buffers")
+        .tempdir()
+        .expect("Failed to create temporary path for file writer.");
+    let opts = Options::default();
+    let mut buffers = vec![];
+    for (i, keys) in [["k1", "k2"], ["k3", "k4"]].iter().enumerate() {
+        let writer_path = dir.path().join(format!("filewriter{i}"));
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(&writer_path).unwrap();
+        for key in keys {
+            writer.put(key, format!("v-{key}")).unwrap();
+        }
+        buffers.push(writer.finish_to_buffer(&writer_path).unwrap());
+        assert!(!writer_path.exists());
+    }
+    {
+        let db = DB::open_default(&db_path).unwrap();
+        let buffers: Vec<&[u8]> = buffers.iter().map(|b| b.as_slice()).collect();
+        let mut ingest_opts = IngestExternalFileOptions::default();
+        ingest_opts.set_move_files(true);
+        db.ingest_external_file_from_buffers(&ingest_opts, &buffers)
+            .unwrap();
+        for key in ["k1", "k2", "k3", "k4"] {
+            assert_eq!(db.get(key).unwrap().unwrap(), format!("v-{key}").as_bytes());
+        }
+
+        // A corrupt buffer fails the ingestion without leaving temporary files behind.
+        let entries = || std::fs::read_dir(&db_path).unwrap().count();
+        let before = entries();
+        assert!(db
+            .ingest_external_file_from_buffers(&ingest_opts, &[b"not an sst file".as_slice()])
+            .is_err());
+        assert_eq!(entries(), before);
+        let staging_prefix = format!("rocksdb-ingest-{}-", std::process::id());
+        assert!(!std::fs::read_dir(std::env::temp_dir())
+            .unwrap()
+            .any(|entry| entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&staging_prefix)));
+    }
+}
+
+#[test]
+fn sst_file_writer_buffers_reject_mem_env() {
+    let db_path = DBPath::new("_rust_rocksdb_sstfilewriter_buffers_mem_env");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_env(&Env::mem_env().unwrap());
+
+    let writer_path = db_path.path().join("buffer.sst");
+    let mut writer = SstFileWriter::create(&opts);
+    writer.open(&writer_path).unwrap();
+    writer.put(b"k1", b"v1").unwrap();
+    let err = writer.finish_to_buffer(&writer_path).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+    let db = DB::open(&opts, &db_path).unwrap();
+    let err = db
+        .ingest_external_file_from_buffers(&IngestExternalFileOptions::default(), &[b"".as_slice()])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn sst_file_writer_create_cf_with_ingest() {
+    let db_path = DBPath::new("_rust_rocksdb_sstfilewriter_create_cf_with_ingest");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_create_cf_with_ingest")
+        .tempdir()
+        .expect("Failed to create temporary path for file writer.");
+    let writer_path = dir.path().join("filewriter");
+    let corrupt_path = dir.path().join("corrupt");
+    {
+        let opts = Options::default();
+        let mut writer = SstFileWriter::create(&opts);
+        writer.open(&writer_path).unwrap();
+        writer.put(b"k1", b"v1").unwrap();
+        writer.put(b"k2", b"v2").unwrap();
+        writer.finish().unwrap();
+        std::fs::write(&corrupt_path, b"not an sst file").unwrap();
+    }
+    {
+        #[cfg(feature = "multi-threaded-cf")]
+        let db = DB::open_default(&db_path).unwrap();
+        #[cfg(not(feature = "multi-threaded-cf"))]
+        let mut db = DB::open_default(&db_path).unwrap();
+        let ingest_opts = IngestExternalFileOptions::default();
+        db.create_cf_with_ingest(
+            "loaded",
+            &Options::default(),
+            vec![&writer_path],
+            &ingest_opts,
+        )
+        .unwrap();
+        let cf = db.cf_handle("loaded").unwrap();
+        assert_eq!(db.get_cf(&cf, b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get_cf(&cf, b"k2").unwrap().unwrap(), b"v2");
+
+        assert!(db
+            .create_cf_with_ingest(
+                "broken",
+                &Options::default(),
+                vec![&corrupt_path],
+                &ingest_opts
+            )
+            .is_err());
+        assert!(db.cf_handle("broken").is_none());
+    }
+    let mut cfs = DB::list_cf(&Options::default(), &db_path).unwrap();
+    cfs.sort();
+    assert_eq!(cfs, vec!["default", "loaded"]);
+}
+
+#[test]
+fn sst_file_writer_create_cf_with_import() {
+    let source_path = DBPath::new("_rust_rocksdb_sstfilewriter_import_source");
+    let target_path = DBPath::new("_rust_rocksdb_sstfilewriter_import_target");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_import")
+        .tempdir()
+        .expect("Failed to create temporary path for file writer.");
+
+    // export the column family of the source DB into SST files
+    let mut expected = vec![];
+    let mut files = vec![];
+    {
+        let db = DB::open_default(&source_path).unwrap();
+        for i in 0..100 {
+            db.put(format!("k{i:03}"), format!("v{i}")).unwrap();
+        }
+        let opts = Options::default();
+        for (n, chunk) in db
+            .iterator(rocksdb::IteratorMode::Start)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .chunks(30)
+            .enumerate()
+        {
+            let file = dir.path().join(format!("{n}.sst"));
+            let mut writer = SstFileWriter::create(&opts);
+            writer.open(&file).unwrap();
+            for (key, value) in chunk {
+                writer.put(key, value).unwrap();
+                expected.push((key.clone(), value.clone()));
+            }
+            writer.finish().unwrap();
+            files.push(file);
+        }
+    }
+    let metadata = ExportImportFilesMetaData {
+        db_comparator_name: "leveldb.BytewiseComparator".to_owned(),
+        files,
+    };
+
+    #[cfg(feature = "multi-threaded-cf")]
+    let db = DB::open_default(&target_path).unwrap();
+    #[cfg(not(feature = "multi-threaded-cf"))]
+    let mut db = DB::open_default(&target_path).unwrap();
+
+    // files sorted with another comparator are rejected
+    let mut reverse = Options::default();
+    reverse.set_comparator("reverse", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+    let err = db
+        .create_cf_with_import(
+            "reversed",
+            &reverse,
+            &ImportColumnFamilyOptions::default(),
+            &metadata,
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert!(db.cf_handle("reversed").is_none());
+
+    db.create_cf_with_import(
+        "imported",
+        &Options::default(),
+        &ImportColumnFamilyOptions::default(),
+        &metadata,
+    )
+    .unwrap();
+    let cf = db.cf_handle("imported").unwrap();
+    let imported: Vec<_> = db
+        .iterator_cf(&cf, rocksdb::IteratorMode::Start)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(imported, expected);
+    // the files were copied
+    assert!(metadata.files.iter().all(|file| file.exists()));
+}
+
+#[test]
+fn sst_file_writer_export_range() {
+    let db_path = DBPath::new("_rust_rocksdb_sstfilewriter_export_range");
+    let target_path = DBPath::new("_rust_rocksdb_sstfilewriter_export_range_target");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_export_range")
+        .tempdir()
+        .expect("Failed to create temporary path for the export.");
+    let sst_path = dir.path().join("range.sst");
+    let empty_path = dir.path().join("empty.sst");
+
+    let db = DB::open_default(&db_path).unwrap();
+    for i in 0..100 {
+        db.put(format!("k{i:03}"), format!("v{i}")).unwrap();
+    }
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    let mut readopts = ReadOptions::default();
+    readopts.set_readahead_size(1 << 20);
+    let info = db
+        .export_range_to_sst_cf(
+            &cf,
+            "k010",
+            "k020",
+            &sst_path,
+            &Options::default(),
+            readopts,
+        )
+        .unwrap();
+    assert_eq!(info.file_path, sst_path);
+    assert_eq!(info.smallest_key, b"k010");
+    assert_eq!(info.largest_key, b"k019");
+    assert_eq!(info.num_entries, 10);
+    assert_eq!(info.file_size, std::fs::metadata(&sst_path).unwrap().len());
+
+    let target = DB::open_default(&target_path).unwrap();
+    target.ingest_external_file(vec![&sst_path]).unwrap();
+    let imported: Vec<_> = target
+        .iterator(IteratorMode::Start)
+        .map(Result::unwrap)
+        .collect();
+    let expected: Vec<_> = (10..20)
+        .map(|i| {
+            (
+                format!("k{i:03}").into_bytes().into_boxed_slice(),
+                format!("v{i}").into_bytes().into_boxed_slice(),
+            )
+        })
+        .collect();
+    assert_eq!(imported, expected);
+
+    // an empty range fails without creating a file
+    let err = db
+        .export_range_to_sst_cf(
+            &cf,
+            "x",
+            "y",
+            &empty_path,
+            &Options::default(),
+            ReadOptions::default(),
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    assert!(!empty_path.exists());
+
+    // the writer must sort keys like the column family
+    let mut reverse = Options::default();
+    reverse.set_comparator("reverse", Box::new(|a: &[u8], b: &[u8]| b.cmp(a)));
+    let err = db
+        .export_range_to_sst_cf(
+            &cf,
+            "k010",
+            "k020",
+            &empty_path,
+            &reverse,
+            ReadOptions::default(),
+        )
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+}
+
+#[test]
+fn sst_file_writer_delete_range() {
+    let db_path = DBPath::new("_rust_rocksdb_sstfilewriter_delete_range");
+    let dir = tempfile::Builder::new()
+        .prefix("_rust_rocksdb_sstfilewriter_delete_range")
+        .tempdir()
+        .unwrap();
+    let sst_path = dir.path().join("reset.sst");
+    let writer_opts = Options::default();
+    let mut writer = SstFileWriter::create(&writer_opts);
+    writer.open(&sst_path).unwrap();
+    writer.put(b"shard1/k9", b"new").unwrap();
+    writer.delete_range("shard1/", "shard1/k5").unwrap();
+    writer.finish().unwrap();
+
+    let db = DB::open_default(&db_path).unwrap();
+    for key in [
+        "shard0/k1",
+        "shard1/k1",
+        "shard1/k4",
+        "shard1/k5",
+        "shard2/k1",
+    ] {
+        db.put(key, b"old").unwrap();
+    }
+    db.ingest_external_file(vec![&sst_path]).unwrap();
+
+    assert!(db.get(b"shard1/k1").unwrap().is_none());
+    assert!(db.get(b"shard1/k4").unwrap().is_none());
+    assert_eq!(db.get(b"shard1/k5").unwrap().unwrap(), b"old");
+    assert_eq!(db.get(b"shard1/k9").unwrap().unwrap(), b"new");
+    assert_eq!(db.get(b"shard0/k1").unwrap().unwrap(), b"old");
+    assert_eq!(db.get(b"shard2/k1").unwrap().unwrap(), b"old");
+}
@@ -14,15 +14,20 @@ mod util;
 
 use std::{
     collections::BTreeMap,
+    env,
     ffi::{CStr, CString},
+    process::Command,
+    time::{Duration, SystemTime},
 };
 
 use rocksdb::{
     table_properties::{
-        EntryType, TablePropertiesCollector, TablePropertiesCollectorFactory,
-        TablePropertiesCollectorFactoryContext,
+        EntryType, FilteredCollector, HllKeyCountCollectorFactory, HyperLogLog,
+        RangeTombstoneCollectorFactory, SeqnoRangeCollectorFactory, TablePropertiesCollector,
+        TablePropertiesCollectorFactory, TablePropertiesCollectorFactoryContext,
+        WriteTimesCollectorFactory,
     },
-    Options, DB,
+    ErrorKind, Options, DB, DEFAULT_COLUMN_FAMILY_NAME,
 };
 use util::DBPath;
 
@@ -125,3 +130,362 @@ fn test_table_properties_collector() {
         }
     }
 }
+
+#[test]
+fn test_range_tombstones_cf() {
+    let path = DBPath::new("_range_tombstones_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.add_table_properties_collector_factory(RangeTombstoneCollectorFactory);
+        let mut db = DB::open(&opts, &path).unwrap();
+        db.create_cf("cf", &opts).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+        db.put_cf(&cf, "k1", "a").unwrap();
+        db.put_cf(&cf, "k5", "a").unwrap();
+        db.delete_range_cf(&cf, "k2", "k4").unwrap();
+        let seq = db.latest_sequence_number();
+
+        // Unflushed tombstones are not visible yet.
+        assert!(db.range_tombstones_cf(&cf).unwrap().is_empty());
+
+        db.flush_cf(&cf).unwrap();
+        let tombstones = db.range_tombstones_cf(&cf).unwrap();
+        assert_eq!(tombstones.len(), 1);
+        assert_eq!(&*tombstones[0].start, b"k2");
+        assert_eq!(&*tombstones[0].end, b"k4");
+        assert_eq!(tombstones[0].seq, seq);
+
+        // files written without the collector are reported instead of skipped
+        db.create_cf("plain", &Options::default()).unwrap();
+        let plain = db.cf_handle("plain").unwrap();
+        assert!(db.range_tombstones_cf(&plain).unwrap().is_empty());
+        db.delete_range_cf(&plain, "k2", "k4").unwrap();
+        db.flush_cf(&plain).unwrap();
+        let err = db.range_tombstones_cf(&plain).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+    }
+}
+
+#[test]
+fn test_changed_key_ranges_since_cf() {
+    let path = DBPath::new("_changed_key_ranges_since_cf");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.add_table_properties_collector_factory(SeqnoRangeCollectorFactory);
+        let mut db = DB::open(&opts, &path).unwrap();
+        db.create_cf("cf", &opts).unwrap();
+        let cf = db.cf_handle("cf").unwrap();
+
+        for i in 0..100 {
+            db.put_cf(&cf, format!("a{i:03}"), "v").unwrap();
+        }
+        db.flush_cf(&cf).unwrap();
+        let seq = db.latest_sequence_number();
+
+        for i in 0..100 {
+            db.put_cf(&cf, format!("b{i:03}"), "v").unwrap();
+        }
+        db.delete_range_cf(&cf, "c", "d").unwrap();
+        db.flush_cf(&cf).unwrap();
+
+        let ranges = db.changed_key_ranges_since_cf(&cf, seq).unwrap();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(&*ranges[0].smallest_key, b"b000");
+        assert_eq!(&*ranges[0].largest_key, b"d");
+        assert_eq!(ranges[0].largest_seqno, db.latest_sequence_number());
+
+        let ranges = db.changed_key_ranges_since_cf(&cf, 0).unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(&*ranges[0].smallest_key, b"a000");
+        assert_eq!(&*ranges[0].largest_key, b"a099");
+
+        assert!(db
+            .changed_key_ranges_since_cf(&cf, db.latest_sequence_number())
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[test]
+fn test_estimate_unique_keys_cf() {
+    let path = DBPath::new("_estimate_unique_keys_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.set_disable_auto_compactions(true);
+    opts.add_table_properties_collector_factory(HllKeyCountCollectorFactory::default());
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    assert_eq!(db.estimate_unique_keys_cf(&cf).unwrap(), 0);
+
+    // 7000 distinct keys, the last 3000 writes overwriting keys of older files
+    for i in 0..10_000 {
+        db.put(format!("key{:05}", i % 7000), "value").unwrap();
+        if i % 2000 == 1999 {
+            db.flush().unwrap();
+        }
+    }
+    assert_eq!(db.live_files().unwrap().len(), 5);
+
+    let estimate = db.estimate_unique_keys_cf(&cf).unwrap();
+    assert!(estimate.abs_diff(7000) < 350, "estimated {} keys", estimate);
+}
+
+#[test]
+fn test_estimate_unique_keys_cf_mixed_precisions() {
+    let path = DBPath::new("_estimate_unique_keys_cf_mixed_precisions");
+    let open = |precision| {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_disable_auto_compactions(true);
+        opts.add_table_properties_collector_factory(HllKeyCountCollectorFactory::new(precision));
+        DB::open(&opts, &path).unwrap()
+    };
+    {
+        let db = open(14);
+        for i in 0..3000 {
+            db.put(format!("key{i:05}"), "value").unwrap();
+        }
+        db.flush().unwrap();
+    }
+    let db = open(12);
+    for i in 2000..6000 {
+        db.put(format!("key{i:05}"), "value").unwrap();
+    }
+    db.flush().unwrap();
+
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let estimate = db.estimate_unique_keys_cf(&cf).unwrap();
+    assert!(estimate.abs_diff(6000) < 300, "estimated {} keys", estimate);
+}
+
+#[test]
+fn test_hyperloglog_merge_reduces_precision() {
+    let mut high = HyperLogLog::new(14);
+    let mut low = HyperLogLog::new(12);
+    for i in 0..5000 {
+        high.insert(format!("key{i}").as_bytes());
+        low.insert(format!("key{i}").as_bytes());
+    }
+
+    // a sketch reduced to a lower precision is the one of the same keys at it
+    let mut merged = HyperLogLog::new(12);
+    merged.merge(&high);
+    assert_eq!(merged, low);
+    high.merge(&HyperLogLog::new(12));
+    assert_eq!(high.precision(), 12);
+    assert_eq!(high, low);
+}
+
+#[test]
+fn test_aggregate_user_properties() {
+    let path = DBPath::new("_aggregate_user_properties");
+    let factory = TablePropertiesCollectorFactoryImpl {
+        name: CString::new("table-properties-collector-factory").unwrap(),
+    };
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.add_table_properties_collector_factory(factory);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    for (flush, keys) in [3, 5, 7].iter().enumerate() {
+        for i in 0..*keys {
+            db.put(format!("{flush}-{i}"), "value").unwrap();
+        }
+        db.flush().unwrap();
+    }
+
+    let total = db
+        .aggregate_user_properties_cf(&cf, |total, properties| {
+            let parse =
+                |value: &[u8]| -> usize { std::str::from_utf8(value).unwrap().parse().unwrap() };
+            let key: Box<[u8]> = b"num-keys".to_vec().into_boxed_slice();
+            let sum = total.get(&key).map_or(0, |value| parse(value)) + parse(&properties[&key]);
+            total.insert(key, sum.to_string().into_bytes().into_boxed_slice());
+        })
+        .unwrap();
+    assert_eq!(&total[&b"num-keys"[..]][..], b"15");
+}
+
+/// Counts every entry it is given, whatever its type.
+#[derive(Default)]
+struct EntryCounter {
+    entries: usize,
+}
+
+impl TablePropertiesCollector for EntryCounter {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"entry-counter\0").unwrap()
+    }
+
+    fn add_user_key(&mut self, _: &[u8], _: &[u8], _: EntryType, _: u64, _: u64) {
+        self.entries += 1;
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        let mut map = BTreeMap::new();
+        map.insert(
+            b"entries".to_vec().into_boxed_slice(),
+            self.entries.to_string().into_bytes().into_boxed_slice(),
+        );
+        map
+    }
+}
+
+struct PutCounterFactory;
+
+impl TablePropertiesCollectorFactory for PutCounterFactory {
+    type Collector = FilteredCollector<EntryCounter>;
+
+    fn create(&mut self, _ctx: TablePropertiesCollectorFactoryContext) -> Self::Collector {
+        FilteredCollector::new(EntryCounter::default(), [EntryType::Put])
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"put-counter-factory\0").unwrap()
+    }
+}
+
+#[test]
+fn test_filtered_collector() {
+    let path = DBPath::new("_filtered_collector");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.add_table_properties_collector_factory(PutCounterFactory);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+
+    for i in 0..5 {
+        db.put(format!("k{i}"), "value").unwrap();
+    }
+    db.delete("k7").unwrap();
+    db.delete("k8").unwrap();
+    db.flush().unwrap();
+
+    // the deletes are written to the file but not counted
+    assert_eq!(db.live_files().unwrap()[0].num_deletions, 2);
+    let collection = db.get_properties_of_all_range(&cf).unwrap();
+    assert_eq!(collection.tables.len(), 1);
+    let properties = collection.tables[0].user_collected_properties();
+    assert_eq!(&properties[&b"entries"[..]][..], b"5");
+}
+
+#[test]
+fn test_files_written_before_cf() {
+    let path = DBPath::new("_files_written_before_cf");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let hour = Duration::from_secs(3600);
+    let future = SystemTime::now() + hour;
+
+    // files written without the collector have unknown times
+    db.put("k0", "value").unwrap();
+    db.flush().unwrap();
+    let collection = db.get_properties_of_all_range(&cf).unwrap();
+    assert_eq!(collection.tables[0].recorded_write_start_time(), None);
+    assert_eq!(collection.tables[0].recorded_write_finish_time(), None);
+    assert!(db.files_written_before_cf(&cf, future).unwrap().is_empty());
+    drop(collection);
+    drop(db);
+
+    opts.add_table_properties_collector_factory(WriteTimesCollectorFactory);
+    let db = DB::open(&opts, &path).unwrap();
+    let cf = db.cf_handle(DEFAULT_COLUMN_FAMILY_NAME).unwrap();
+    let before = SystemTime::now() - hour;
+    db.put("k1", "value").unwrap();
+    db.flush().unwrap();
+
+    let collection = db.get_properties_of_all_range(&cf).unwrap();
+    let table = collection
+        .tables
+        .iter()
+        .find(|table| table.recorded_write_start_time().is_some())
+        .unwrap();
+    let started = table.recorded_write_start_time().unwrap();
+    assert!(before < started && started < future);
+    assert!(table.recorded_write_finish_time().unwrap() >= started);
+
+    assert!(db.files_written_before_cf(&cf, before).unwrap().is_empty());
+    let old_files = db.files_written_before_cf(&cf, future).unwrap();
+    assert_eq!(old_files.len(), 1);
+    let live_files = db.live_files().unwrap();
+    assert_eq!(live_files.len(), 2);
+    assert!(live_files.iter().any(|f| f.name == old_files[0]));
+}
+
+struct PanickingCollector;
+
+impl TablePropertiesCollector for PanickingCollector {
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"panicking-collector\0").unwrap()
+    }
+
+    fn add_user_key(&mut self, _: &[u8], _: &[u8], _: EntryType, _: u64, _: u64) {
+        panic!("add_user_key failed");
+    }
+
+    fn finish_properties(&mut self) -> BTreeMap<Box<[u8]>, Box<[u8]>> {
+        BTreeMap::new()
+    }
+}
+
+struct PanickingCollectorFactory;
+
+impl TablePropertiesCollectorFactory for PanickingCollectorFactory {
+    type Collector = PanickingCollector;
+
+    fn create(&mut self, _: TablePropertiesCollectorFactoryContext) -> PanickingCollector {
+        PanickingCollector
+    }
+
+    fn name(&self) -> &CStr {
+        CStr::from_bytes_with_nul(b"panicking-collector-factory\0").unwrap()
+    }
+}
+
+#[test]
+fn test_collector_panic_aborts() {
+    // the panicking flush runs in a child process, as it aborts the process
+    const DB_PATH_ENV: &str = "_RUST_ROCKSDB_PANICKING_COLLECTOR_DB";
+    if let Some(path) = env::var_os(DB_PATH_ENV) {
+        rocksdb::set_callback_panic_handler(|callback, message| {
+            eprintln!("handled panic in {callback}: {message}");
+        });
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.add_table_properties_collector_factory(PanickingCollectorFactory);
+        let db = DB::open(&opts, path).unwrap();
+        db.put(b"k1", b"v1").unwrap();
+        let _ = db.flush();
+        unreachable!("the flush must abort the process");
+    }
+
+    let path = DBPath::new("_panicking_collector");
+    let output = Command::new(env::current_exe().unwrap())
+        .args(["--exact", "test_collector_panic_aborts", "--nocapture"])
+        .env(DB_PATH_ENV, (&path).as_ref())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("handled panic in table properties collector: add_user_key failed"),
+        "{}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("the flush must abort the process"),
+        "{}",
+        stderr
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(output.status.signal(), Some(6 /* SIGABRT */));
+    }
+    assert!(!output.status.success());
+}
@@ -0,0 +1,62 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "test-util")]
+
+use std::thread;
+use std::time::Duration;
+
+use rocksdb::{
+    test_util::{DeterministicDb, TempDb},
+    FlushOptions, MergeOperands,
+};
+
+#[test]
+fn test_temp_db_removes_dir() {
+    for _ in 0..3 {
+        let dir = TempDb::new("_rust_rocksdb_test_temp_db_removes_dir");
+        let parent = dir.path().parent().unwrap().to_path_buf();
+        {
+            let db = DeterministicDb::open_cf(&dir, ["cf1"]).unwrap();
+            db.put(b"k1", b"v1").unwrap();
+            db.put_cf(db.cf_handle("cf1").unwrap(), b"k2", b"v2")
+                .unwrap();
+            db.flush_and_compact_all().unwrap();
+            db.assert_quiesced();
+        }
+        assert!(dir.path().exists());
+        drop(dir);
+        assert!(!parent.exists());
+    }
+}
+
+#[test]
+#[should_panic(expected = "is not quiesced")]
+fn test_assert_quiesced_fails_on_pending_flush() {
+    // keeps the flush running, as flushes merge the operands of a key on top of a put
+    fn slow_merge(_: &[u8], existing: Option<&[u8]>, operands: &MergeOperands) -> Option<Vec<u8>> {
+        thread::sleep(Duration::from_secs(1));
+        operands.iter().last().or(existing).map(<[u8]>::to_vec)
+    }
+
+    let dir = TempDb::new("_rust_rocksdb_test_assert_quiesced_fails");
+    let mut opts = DeterministicDb::options();
+    opts.set_merge_operator_associative("slow merge", slow_merge);
+    let db = DeterministicDb::open_cf_opt(&opts, &dir, Vec::<&str>::new()).unwrap();
+    db.put(b"k1", b"v1").unwrap();
+    db.merge(b"k1", b"v2").unwrap();
+
+    let mut flush_opts = FlushOptions::default();
+    flush_opts.set_wait(false);
+    db.flush_opt(&flush_opts).unwrap();
+    db.assert_quiesced();
+}
@@ -18,9 +18,9 @@ mod util;
 use pretty_assertions::assert_eq;
 
 use rocksdb::{
-    CuckooTableOptions, DBAccess, Direction, Error, ErrorKind, IteratorMode, Options, ReadOptions,
-    SliceTransform, TransactionDB, TransactionDBOptions, TransactionOptions,
-    WriteBatchWithTransaction, WriteOptions, DB,
+    ColumnFamilyDescriptor, CuckooTableOptions, DBAccess, Direction, Error, ErrorKind,
+    IteratorMode, Options, ReadOptions, SliceTransform, TransactionDB, TransactionDBOptions,
+    TransactionOptions, WriteBatchWithTransaction, WriteOptions, WriteValidationOptions, DB,
 };
 use util::DBPath;
 
@@ -574,6 +574,76 @@ fn transaction_cf() {
     }
 }
 
+#[test]
+fn transaction_cf_atomic() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_cf_atomic");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut txn_db_opts = TransactionDBOptions::default();
+        txn_db_opts.set_num_stripes(32);
+        let db: TransactionDB = TransactionDB::open_cf_descriptors(
+            &opts,
+            &txn_db_opts,
+            &path,
+            vec![
+                ColumnFamilyDescriptor::new("cf1", Options::default()),
+                ColumnFamilyDescriptor::new("cf2", Options::default()),
+            ],
+        )
+        .unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+        let cf2 = db.cf_handle("cf2").unwrap();
+
+        let txn = db.transaction();
+        txn.put_cf(&cf1, b"k1", b"v1").unwrap();
+        txn.put_cf(&cf2, b"k2", b"v2").unwrap();
+        txn.rollback().unwrap();
+        txn.commit().unwrap();
+        assert!(db.get_cf(&cf1, b"k1").unwrap().is_none());
+        assert!(db.get_cf(&cf2, b"k2").unwrap().is_none());
+
+        let txn = db.transaction();
+        txn.put_cf(&cf1, b"k1", b"v1").unwrap();
+        txn.put_cf(&cf2, b"k2", b"v2").unwrap();
+        assert!(db.get_cf(&cf1, b"k1").unwrap().is_none());
+        txn.commit().unwrap();
+        assert_eq!(db.get_cf(&cf1, b"k1").unwrap().unwrap(), b"v1");
+        assert_eq!(db.get_cf(&cf2, b"k2").unwrap().unwrap(), b"v2");
+    }
+}
+
+#[test]
+fn transaction_max_num_locks() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_max_num_locks");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.create_missing_column_families(true);
+        let mut txn_db_opts = TransactionDBOptions::default();
+        txn_db_opts.set_max_num_locks(2);
+        let db: TransactionDB =
+            TransactionDB::open_cf(&opts, &txn_db_opts, &path, ["cf1"]).unwrap();
+        let cf1 = db.cf_handle("cf1").unwrap();
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        txn.put(b"k2", b"v2").unwrap();
+        // the limit applies per column family
+        txn.put_cf(&cf1, b"k1", b"v1").unwrap();
+        let err = txn.put(b"k3", b"v3").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Busy);
+
+        // the locks are released by the commit
+        txn.commit().unwrap();
+        let txn = db.transaction();
+        txn.put(b"k3", b"v3").unwrap();
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k3").unwrap().unwrap(), b"v3");
+    }
+}
+
 #[test]
 fn transaction_snapshot() {
     let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_snapshot");
@@ -676,6 +746,41 @@ fn two_phase_commit() {
     }
 }
 
+#[test]
+fn transaction_delete_keys_in_range() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_transaction_delete_keys_in_range");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        let mut txn_db_opts = TransactionDBOptions::default();
+        txn_db_opts.set_txn_lock_timeout(10);
+        let db: TransactionDB = TransactionDB::open(&opts, &txn_db_opts, &path).unwrap();
+        for key in [b"k1", b"k2", b"k3", b"k4", b"k5"] {
+            db.put(key, b"v").unwrap();
+        }
+
+        let txn = db.transaction();
+        txn.put(b"k3a", b"v").unwrap();
+        txn.delete_keys_in_range(b"k2", b"k4").unwrap();
+        assert!(txn.get(b"k3a").unwrap().is_none());
+        // nothing is deleted before the commit
+        assert!(db.get(b"k2").unwrap().is_some());
+
+        // the deleted keys are locked
+        let err = db.put(b"k3", b"v").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        txn.commit().unwrap();
+        for key in [b"k2", b"k3"] {
+            assert!(db.get(key).unwrap().is_none());
+        }
+        assert!(db.get(b"k3a").unwrap().is_none());
+        for key in [b"k1", b"k4", b"k5"] {
+            assert!(db.get(key).unwrap().is_some());
+        }
+    }
+}
+
 #[test]
 fn test_snapshot_outlive_transaction_db() {
     let t = trybuild::TestCases::new();
@@ -693,3 +798,80 @@ fn test_snapshot_outlive_txn() {
     let t = trybuild::TestCases::new();
     t.compile_fail("tests/fail/snapshot_outlive_transaction.rs");
 }
+
+#[test]
+fn update_cf() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_update_cf");
+    let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+    std::thread::scope(|scope| {
+        for _ in 0..8 {
+            scope.spawn(|| {
+                // a `&ColumnFamily` cannot be shared between threads, so each looks it up
+                let cf = db.cf_handle("default").unwrap();
+                for _ in 0..1000 {
+                    db.update_cf(&cf, b"counter", |value| {
+                        let count: u64 =
+                            value.map_or(0, |v| std::str::from_utf8(v).unwrap().parse().unwrap());
+                        Some((count + 1).to_string().into_bytes())
+                    })
+                    .unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(db.get(b"counter").unwrap().unwrap(), b"8000");
+
+    // returning `None` deletes the key
+    let cf = db.cf_handle("default").unwrap();
+    assert_eq!(db.update_cf(&cf, b"counter", |_| None).unwrap(), None);
+    assert!(db.get(b"counter").unwrap().is_none());
+}
+
+#[test]
+fn compare_and_put_cf() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_compare_and_put_cf");
+    let db: TransactionDB = TransactionDB::open_default(&path).unwrap();
+    let cf = db.cf_handle("default").unwrap();
+
+    assert!(db.compare_and_put_cf(&cf, b"k1", None, b"v1").unwrap());
+    assert!(!db.compare_and_put_cf(&cf, b"k1", None, b"v2").unwrap());
+    assert!(!db
+        .compare_and_put_cf(&cf, b"k1", Some(&b"v0"[..]), b"v2")
+        .unwrap());
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v1");
+    assert!(db
+        .compare_and_put_cf(&cf, b"k1", Some(&b"v1"[..]), b"v2")
+        .unwrap());
+    assert_eq!(db.get(b"k1").unwrap().unwrap(), b"v2");
+}
+
+#[test]
+fn write_validation() {
+    let path = DBPath::new("_rust_rocksdb_transaction_db_write_validation");
+    {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        opts.set_write_validation(WriteValidationOptions {
+            max_value_bytes: Some(8),
+            ..WriteValidationOptions::default()
+        });
+        let db: TransactionDB =
+            TransactionDB::open(&opts, &TransactionDBOptions::default(), &path).unwrap();
+
+        let err = db.put(b"k", vec![b'v'; 16]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        let mut batch = WriteBatchWithTransaction::<true>::default();
+        batch.put(b"k", vec![b'v'; 16]);
+        let err = db.write(batch).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+
+        let txn = db.transaction();
+        txn.put(b"k1", b"v1").unwrap();
+        let err = txn.put(b"k2", vec![b'v'; 16]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidArgument);
+        txn.commit().unwrap();
+        assert_eq!(db.get(b"k1").unwrap(), Some(b"v1".to_vec()));
+        assert_eq!(db.get(b"k2").unwrap(), None);
+    }
+}
@@ -0,0 +1,132 @@
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(feature = "typed")]
+
+mod util;
+
+use pretty_assertions::assert_eq;
+use serde::{Deserialize, Serialize};
+
+use rocksdb::{
+    typed::{decode_i64, encode_i64, TypedCf},
+    ErrorKind, Options, WriteBatch, DB,
+};
+use util::DBPath;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct User {
+    name: String,
+    age: u32,
+    tags: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Other {
+    id: u64,
+}
+
+fn open(path: &DBPath) -> DB {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    DB::open_cf(&opts, path, ["users", "numbers"]).unwrap()
+}
+
+#[test]
+fn test_typed_round_trip() {
+    let path = DBPath::new("_rust_rocksdb_typed_round_trip");
+    let db = open(&path);
+    let users: TypedCf<String, User, _, _> = TypedCf::new(&db, db.cf_handle("users").unwrap());
+
+    let alice = User {
+        name: "alice".to_owned(),
+        age: 30,
+        tags: vec!["admin".to_owned()],
+    };
+    users.put(&"alice".to_owned(), &alice).unwrap();
+    assert_eq!(users.get(&"alice".to_owned()).unwrap(), Some(alice.clone()));
+    assert_eq!(users.get(&"bob".to_owned()).unwrap(), None);
+
+    let bob = User {
+        name: "bob".to_owned(),
+        age: 25,
+        tags: vec![],
+    };
+    let mut batch = WriteBatch::default();
+    users
+        .put_in_batch(&mut batch, &"bob".to_owned(), &bob)
+        .unwrap();
+    users
+        .delete_in_batch(&mut batch, &"alice".to_owned())
+        .unwrap();
+    db.write(batch).unwrap();
+    assert_eq!(users.get(&"alice".to_owned()).unwrap(), None);
+    assert_eq!(
+        users.iter().collect::<Result<Vec<_>, _>>().unwrap(),
+        vec![("bob".to_owned(), bob)]
+    );
+
+    users.delete(&"bob".to_owned()).unwrap();
+    assert_eq!(users.iter().count(), 0);
+}
+
+#[test]
+fn test_typed_range_in_numeric_order() {
+    let path = DBPath::new("_rust_rocksdb_typed_range");
+    let db = open(&path);
+    let numbers: TypedCf<u64, String, _, _> = TypedCf::new(&db, db.cf_handle("numbers").unwrap());
+
+    for n in [1000_u64, 2, 300, 10, 256, 1, 70000] {
+        numbers.put(&n, &n.to_string()).unwrap();
+    }
+    let keys: Vec<u64> = numbers.iter().map(|item| item.unwrap().0).collect();
+    assert_eq!(keys, vec![1, 2, 10, 256, 300, 1000, 70000]);
+
+    let range: Vec<(u64, String)> = numbers
+        .range(&10, &1000)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        range,
+        vec![
+            (10, "10".to_owned()),
+            (256, "256".to_owned()),
+            (300, "300".to_owned())
+        ]
+    );
+
+    let mut signed = vec![-5_i64, 3, i64::MIN, 0, -1, i64::MAX];
+    let mut encoded: Vec<u64> = signed.iter().map(|&n| encode_i64(n)).collect();
+    signed.sort_unstable();
+    encoded.sort_unstable();
+    assert_eq!(
+        encoded.into_iter().map(decode_i64).collect::<Vec<_>>(),
+        signed
+    );
+}
+
+#[test]
+fn test_typed_decode_error() {
+    let path = DBPath::new("_rust_rocksdb_typed_decode_error");
+    let db = open(&path);
+    let others: TypedCf<String, Other, _, _> = TypedCf::new(&db, db.cf_handle("users").unwrap());
+    others.put(&"key".to_owned(), &Other { id: 7 }).unwrap();
+
+    // the value was written with a different type
+    let users: TypedCf<String, User, _, _> = TypedCf::new(&db, db.cf_handle("users").unwrap());
+    let err = users.get(&"key".to_owned()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Corruption);
+    let err = users.iter().next().unwrap().unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Corruption);
+}
@@ -12,11 +12,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod util;
+
 use std::collections::HashMap;
 
 use pretty_assertions::assert_eq;
 
-use rocksdb::{WriteBatch, WriteBatchIterator};
+use rocksdb::{
+    AsColumnFamilyRef, BatchOpCounts, CfBatchStats, Options, WriteBatch, WriteBatchBuilder,
+    WriteBatchIterator, DB,
+};
+use util::DBPath;
 
 #[test]
 fn test_write_batch_clear() {
@@ -66,3 +72,195 @@ fn test_write_batch_with_serialized_data() {
     let mut it = Iterator { data: kvs };
     b2.iterate(&mut it);
 }
+
+#[test]
+fn test_write_batch_append() {
+    let mut batch = WriteBatch::default();
+    batch.put(b"k1", b"v1");
+    let mut other = WriteBatch::default();
+    other.put(b"k2", b"v2");
+    other.delete(b"k3");
+    batch.append(&other);
+    batch.append(&WriteBatch::default());
+    assert_eq!(batch.len(), 3);
+
+    struct Collector(Vec<(Vec<u8>, Option<Vec<u8>>)>);
+    impl WriteBatchIterator for Collector {
+        fn put(&mut self, key: Box<[u8]>, value: Box<[u8]>) {
+            self.0.push((key.into_vec(), Some(value.into_vec())));
+        }
+        fn delete(&mut self, key: Box<[u8]>) {
+            self.0.push((key.into_vec(), None));
+        }
+    }
+    let mut collector = Collector(vec![]);
+    batch.iterate(&mut collector);
+    assert_eq!(
+        collector.0,
+        vec![
+            (b"k1".to_vec(), Some(b"v1".to_vec())),
+            (b"k2".to_vec(), Some(b"v2".to_vec())),
+            (b"k3".to_vec(), None),
+        ]
+    );
+}
+
+#[test]
+fn test_write_batch_builder() {
+    // independent modules, each writing to its own column family
+    fn add_users(builder: &mut WriteBatchBuilder, cf: &impl AsColumnFamilyRef) {
+        let mut users = builder.cf(cf);
+        users.put(b"u1", b"alice");
+        users.put(b"u2", b"bob");
+        users.delete(b"u3");
+    }
+    fn add_index(builder: &mut WriteBatchBuilder, cf: &impl AsColumnFamilyRef) {
+        let mut index = builder.cf(cf);
+        index.put(b"alice", b"u1");
+        index.put(b"bob", b"u2");
+    }
+
+    let path = DBPath::new("_rust_rocksdb_write_batch_builder");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["users", "orders", "index"]).unwrap();
+    let users = db.cf_handle("users").unwrap();
+    let index = db.cf_handle("index").unwrap();
+    db.put_cf(&users, b"u3", b"carol").unwrap();
+
+    let mut builder = WriteBatchBuilder::new();
+    add_users(&mut builder, &users);
+    add_index(&mut builder, &index);
+    // the orders are built on another thread and merged in
+    let orders = std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let orders = db.cf_handle("orders").unwrap();
+                let mut builder = WriteBatchBuilder::new();
+                let mut view = builder.cf(&orders);
+                for i in 0..4 {
+                    view.put(format!("o{i}"), b"u1");
+                }
+                assert_eq!(view.stats().ops, 4);
+                builder
+            })
+            .join()
+            .unwrap()
+    });
+    builder.append(orders);
+
+    let stats: Vec<_> = builder
+        .stats()
+        .iter()
+        .map(|(name, stats)| (name.as_str(), *stats))
+        .collect();
+    assert_eq!(
+        stats,
+        vec![
+            ("index", CfBatchStats { ops: 2, bytes: 12 }),
+            ("orders", CfBatchStats { ops: 4, bytes: 16 }),
+            ("users", CfBatchStats { ops: 3, bytes: 14 }),
+        ]
+    );
+    assert_eq!(builder.len(), 9);
+
+    let snapshot = db.snapshot();
+    let sequence = db.latest_sequence_number();
+    db.write(builder.build()).unwrap();
+    // the batch was written as a whole with consecutive sequence numbers
+    assert_eq!(db.latest_sequence_number(), sequence + 9);
+    assert_eq!(snapshot.get_cf(&index, b"alice").unwrap(), None);
+
+    assert_eq!(db.get_cf(&users, b"u1").unwrap().unwrap(), b"alice");
+    assert_eq!(db.get_cf(&users, b"u3").unwrap(), None);
+    assert_eq!(db.get_cf(&index, b"bob").unwrap().unwrap(), b"u2");
+    let orders = db.cf_handle("orders").unwrap();
+    assert_eq!(db.get_cf(&orders, b"o3").unwrap().unwrap(), b"u1");
+}
+
+#[test]
+fn test_validate_write_batch() {
+    let path = DBPath::new("_rust_rocksdb_validate_write_batch");
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+    let db = DB::open_cf(&opts, &path, ["users"]).unwrap();
+    let users = db.cf_handle("users").unwrap();
+
+    let mut batch = WriteBatch::default();
+    batch.put(b"k1", b"v1");
+    batch.put_cf(&users, b"u1", b"alice");
+    batch.put_cf(&users, b"u2", b"bob");
+    batch.merge_cf(&users, b"u1", b"!");
+    batch.delete_cf(&users, b"u3");
+    batch.delete_range_cf(&users, b"u4", b"u6");
+
+    let summary = db.validate_write_batch(&batch).unwrap();
+    assert!(summary.is_valid());
+    assert_eq!(summary.size_in_bytes, batch.size_in_bytes());
+    let counts: Vec<_> = summary
+        .column_families
+        .iter()
+        .map(|(name, counts)| (name.as_str(), *counts))
+        .collect();
+    assert_eq!(
+        counts,
+        vec![
+            (
+                "default",
+                BatchOpCounts {
+                    puts: 1,
+                    ..BatchOpCounts::default()
+                }
+            ),
+            (
+                "users",
+                BatchOpCounts {
+                    puts: 2,
+                    deletes: 1,
+                    merges: 1,
+                    delete_ranges: 1,
+                    others: 0,
+                }
+            ),
+        ]
+    );
+    assert_eq!(summary.column_families["users"].total(), 5);
+}
+
+#[test]
+fn test_validate_write_batch_unknown_cf() {
+    let mut opts = Options::default();
+    opts.create_if_missing(true);
+    opts.create_missing_column_families(true);
+
+    // the batch is built against the column families of another DB
+    let other_path = DBPath::new("_rust_rocksdb_validate_write_batch_other");
+    let other = DB::open_cf(&opts, &other_path, ["a", "b", "c"]).unwrap();
+    let c = other.cf_handle("c").unwrap();
+    let mut batch = WriteBatch::default();
+    batch.put_cf(&c, b"k1", b"v1");
+    batch.delete_cf(&c, b"k2");
+    batch.put(b"k3", b"v3");
+
+    let path = DBPath::new("_rust_rocksdb_validate_write_batch_unknown_cf");
+    let db = DB::open_cf(&opts, &path, ["a"]).unwrap();
+    let summary = db.validate_write_batch(&batch).unwrap();
+    assert!(!summary.is_valid());
+    // the default column family has id 0, and the created ones the next ids in order
+    let c_id = 3;
+    assert_eq!(
+        summary.unknown_cf_ids.into_iter().collect::<Vec<_>>(),
+        vec![(
+            c_id,
+            BatchOpCounts {
+                puts: 1,
+                deletes: 1,
+                ..BatchOpCounts::default()
+            }
+        )]
+    );
+    assert_eq!(summary.column_families["default"].puts, 1);
+    assert!(db.write(batch).is_err());
+}